@@ -10,6 +10,7 @@ use criterion::{BenchmarkId, Criterion};
 use halo_accumulation::{
     acc::{self, Accumulator},
     pcdl::Instance,
+    split::{self, AccumulationProof},
 };
 
 const PRE: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/.precompute/qs.bin"));
@@ -25,6 +26,19 @@ fn get_cheap_linears(n: usize) -> ([Instance<PallasConfig>; 1], Accumulator<Pall
     ([q_acc.1], q_acc.2)
 }
 
+/// Helper function: Gets a random claim for split accumulation and the accumulator of it.
+fn get_split_linears(
+    n: usize,
+) -> (
+    [split::Accumulator<PallasConfig>; 1],
+    split::Accumulator<PallasConfig>,
+    AccumulationProof<PallasConfig>,
+) {
+    let qs = [split::Accumulator::rand(&mut test_rng(), n)];
+    let (acc, pi) = split::prover(&qs).unwrap();
+    (qs, acc, pi)
+}
+
 const WARMUP: Duration = Duration::from_millis(100);
 const MIN: usize = 2;
 const MAX: usize = 20;
@@ -81,3 +95,55 @@ pub fn acc_verifier(c: &mut Criterion) {
     }
     group.finish();
 }
+
+pub fn split_prover(c: &mut Criterion) {
+    let mut group = c.benchmark_group("split_prover");
+    for size in MIN..MAX + 1 {
+        group.warm_up_time(WARMUP).bench_with_input(
+            BenchmarkId::from_parameter(size),
+            &size,
+            |b, &size| {
+                let n = 2usize.pow(size as u32);
+                let (qs, _, _) = get_split_linears(n);
+
+                b.iter(|| split::prover(&qs));
+            },
+        );
+    }
+    group.finish();
+}
+
+pub fn split_decider(c: &mut Criterion) {
+    let mut group = c.benchmark_group("split_decider");
+    for size in MIN..MAX + 1 {
+        group.warm_up_time(WARMUP).bench_with_input(
+            BenchmarkId::from_parameter(size),
+            &size,
+            |b, &size| {
+                let n = 2usize.pow(size as u32);
+                let (_, acc, _) = get_split_linears(n);
+
+                b.iter(|| acc.decider());
+            },
+        );
+    }
+    group.finish();
+}
+
+pub fn split_verifier(c: &mut Criterion) {
+    let mut group = c.benchmark_group("split_verifier");
+    for size in MIN..MAX + 1 {
+        group.warm_up_time(WARMUP).bench_with_input(
+            BenchmarkId::from_parameter(size),
+            &size,
+            |b, &size| {
+                let n = 2usize.pow(size as u32);
+                let (qs, acc, pi) = get_split_linears(n);
+                let qs = qs.map(|q| q.q);
+
+                b.iter(|| acc.verifier(&qs, &pi));
+            },
+        );
+    }
+    group.finish();
+}
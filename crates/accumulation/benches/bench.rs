@@ -15,6 +15,9 @@ criterion_group! {
         acc_prover,
         acc_decider,
         acc_verifier,
+        split_prover,
+        split_decider,
+        split_verifier,
 }
 
 mod pcdl;
@@ -5,7 +5,7 @@
 use anyhow::{ensure, Context, Result};
 use ark_ec::short_weierstrass::Affine;
 use ark_pallas::PallasConfig;
-use ark_poly::{univariate::DensePolynomial, DenseUVPolynomial, Polynomial};
+use ark_poly::{DenseUVPolynomial, Polynomial};
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use ark_std::{One, UniformRand, Zero};
 use educe::Educe;
@@ -46,24 +46,46 @@ impl<P: PastaConfig> Accumulator<P> {
         }
     }
 
+    /// An accumulator that reveals nothing about `qs`, with the proof π_V the verifier needs
+    pub fn new_hiding<R: Rng>(
+        rng: &mut R,
+        qs: &[Instance<P>],
+    ) -> Result<(Self, AccumulatorHiding<P>)> {
+        prover_hiding(rng, qs)
+    }
+
     pub fn verifier(self, qs: &[Instance<P>]) -> Result<()> {
         verifier(qs, self)
     }
 
+    pub fn verifier_hiding(self, qs: &[Instance<P>], pi_V: &AccumulatorHiding<P>) -> Result<()> {
+        verifier_hiding(qs, self, pi_V)
+    }
+
     pub fn decider(self) -> Result<()> {
         decider(self)
     }
+
+    pub fn decider_with_pp(self, pp: &PublicParams<P>) -> Result<()> {
+        decider_with_pp(pp, self)
+    }
 }
 
-/// pi_V in the paper, used for hiding only
-#[derive(Debug, Clone, CanonicalSerialize, CanonicalDeserialize)]
+/// pi_V in the paper, used for hiding only. The random linear polynomial h_0 masks the h_i of
+/// the accumulated instances, and ω the accumulated commitment.
+#[derive(Educe, CanonicalSerialize, CanonicalDeserialize)]
+#[educe(Debug, Clone, PartialEq, Eq)]
 pub struct AccumulatorHiding<P: PastaConfig> {
+    pub(crate) h_0: Poly<P>,
     pub(crate) U: Point<P>,
+    pub(crate) w: Scalar<P>,
 }
 
 #[derive(Clone, CanonicalSerialize)]
 pub struct AccumulatedHPolys<P: PastaConfig> {
     pub(crate) hs: Vec<pcdl::HPoly<P>>,
+    /// h_0 from π_V, weighted by α^0 ahead of the h_i when hiding
+    h_0: Option<Poly<P>>,
     alpha: Option<Scalar<P>>,
     alphas: Vec<Scalar<P>>,
 }
@@ -72,36 +94,60 @@ impl<P: PastaConfig> AccumulatedHPolys<P> {
     pub(crate) fn with_capacity(capacity: usize) -> Self {
         Self {
             hs: Vec::with_capacity(capacity),
+            h_0: None,
             alphas: Vec::with_capacity(capacity),
             alpha: None,
         }
     }
 
+    fn set_h_0(&mut self, h_0: Poly<P>) {
+        self.h_0 = Some(h_0)
+    }
+
+    /// The weights of the h_i, which start at α^1 if h_0 is present
+    fn hs_alphas(&self) -> &[Scalar<P>] {
+        &self.alphas[self.h_0.is_some() as usize..]
+    }
+
     pub(crate) fn set_alpha(&mut self, alpha: Scalar<P>) {
-        self.alphas = construct_powers::<P>(&alpha, self.alphas.capacity());
+        let n = self.hs.len() + self.h_0.is_some() as usize;
+        self.alphas = construct_powers::<P>(&alpha, n);
         self.alpha = Some(alpha)
     }
 
     // WARNING: This will panic if alphas has not been initialized, but should be fine since this is private
     pub(crate) fn get_poly(&self) -> Poly<P> {
-        let mut h = Poly::<P>::zero();
-        for i in 0..self.hs.len() {
-            h += &(self.hs[i].get_poly() * self.alphas[i]);
+        let mut h = self.h_0.clone().unwrap_or_else(Poly::<P>::zero);
+        let alphas = self.hs_alphas();
+        for (h_i, alpha_i) in self.hs.iter().zip(alphas) {
+            h += &(h_i.get_poly() * *alpha_i);
         }
         h
     }
 
     // WARNING: This will panic if alphas has not been initialized, but should be fine since this is private
     pub(crate) fn eval(&self, z: &Scalar<P>) -> Scalar<P> {
-        let mut v = Scalar::<P>::zero();
-        for i in 0..self.hs.len() {
-            v += self.hs[i].eval(z) * self.alphas[i];
+        let mut v = self
+            .h_0
+            .as_ref()
+            .map_or(Scalar::<P>::zero(), |h_0| h_0.evaluate(z));
+        let alphas = self.hs_alphas();
+        for (h_i, alpha_i) in self.hs.iter().zip(alphas) {
+            v += h_i.eval(z) * alpha_i;
         }
         v
     }
 
     pub(crate) fn get_scalars(&self) -> Vec<Scalar<P>> {
-        let mut vec: Vec<_> = self.hs.iter().flat_map(|x| x.xis.clone()).collect();
+        // Both coefficients of h_0, even if its degree is lower
+        let h_0 = self.h_0.iter().flat_map(|h_0| {
+            let mut coeffs = h_0.coeffs.clone();
+            coeffs.resize(2, Scalar::<P>::zero());
+            coeffs
+        });
+        let mut vec: Vec<_> = h_0
+            .chain(self.hs.iter().flat_map(|x| x.xis.clone()))
+            .collect();
         if let Some(alpha) = self.alpha {
             vec.push(alpha)
         }
@@ -127,6 +173,26 @@ pub fn setup(n: usize) -> Result<()> {
 #[allow(clippy::type_complexity)]
 pub fn common_subroutine<P: PastaConfig>(
     qs: &[Instance<P>],
+) -> Result<(Point<P>, usize, Scalar<P>, AccumulatedHPolys<P>)> {
+    common_subroutine_with_pp(PublicParams::get_pp(), qs)
+}
+
+/// Same as `common_subroutine`, using the public parameters `pp`
+#[allow(clippy::type_complexity)]
+pub fn common_subroutine_with_pp<P: PastaConfig>(
+    pp: &PublicParams<P>,
+    qs: &[Instance<P>],
+) -> Result<(Point<P>, usize, Scalar<P>, AccumulatedHPolys<P>)> {
+    common_subroutine_hiding_with_pp(pp, qs, None)
+}
+
+/// Same as `common_subroutine_with_pp`, hiding with π_V if it is given. Without it, the transcript
+/// is that of the non-hiding scheme, which the in-circuit verifier follows.
+#[allow(clippy::type_complexity)]
+pub fn common_subroutine_hiding_with_pp<P: PastaConfig>(
+    pp: &PublicParams<P>,
+    qs: &[Instance<P>],
+    pi_V: Option<&AccumulatorHiding<P>>,
 ) -> Result<(Point<P>, usize, Scalar<P>, AccumulatedHPolys<P>)> {
     let m = qs.len();
     let d = qs.first().context("No instances given")?.d;
@@ -139,14 +205,26 @@ pub fn common_subroutine<P: PastaConfig>(
     let mut Us = Vec::with_capacity(m);
 
     // (2). Parse π_V as (h_0, U_0, ω), where h_0(X) = aX + b ∈ F_q[X], U_0 ∈ G, and ω ∈ F_q.
-
-    // (3). Check that U_0 is a deterministic commitment to h_0: U_0 = PCDL.Commit_ρ0(ck^(1)_PC, h; ω = ⊥).
+    let w = if let Some(AccumulatorHiding { h_0, U: U_0, w }) = pi_V {
+        ensure!(h_0.degree() <= 1, "h_0 is not linear");
+
+        // (3). Check that U_0 is a deterministic commitment to h_0: U_0 = PCDL.Commit_ρ0(ck^(1)_PC, h; ω = ⊥).
+        ensure!(
+            *U_0 == pcdl::commit_with_pp(pp, h_0, d, None),
+            "U_0 ≠ PCDL.Commit(h_0)"
+        );
+        hs.set_h_0(h_0.clone());
+        Us.push(*U_0);
+        Some(*w)
+    } else {
+        None
+    };
 
     // 4. For each i ∈ [m]:
     for q in qs {
         // 4.a Parse q_i as a tuple ((C_i, d_i, z_i, v_i), π_i).
         // 4.b Compute (h_i(X), U_i) := PCDL.SuccinctCheckρ0(rk, C_i, z_i, v_i, π_i) (see Figure 2).
-        let (h_i, U_i) = q.succinct_check()?;
+        let (h_i, U_i) = q.succinct_check_with_pp(pp)?;
         hs.hs.push(h_i);
         Us.push(U_i);
 
@@ -169,27 +247,78 @@ pub fn common_subroutine<P: PastaConfig>(
     let z = transcript.challenge();
 
     // 10. Randomize C : C_bar := C + ω · S ∈ G.
-    let C_bar = C;
+    let C_bar = match w {
+        Some(w) => C + pp.S * w,
+        None => C,
+    };
 
     // 11. Output (C_bar, d, z, h(X)).
     Ok((C_bar, d, z, hs))
 }
 
 pub fn prover<R: Rng, P: PastaConfig>(rng: &mut R, qs: &[Instance<P>]) -> Result<Accumulator<P>> {
+    prover_with_pp(rng, PublicParams::get_pp(), qs)
+}
+
+/// Same as `prover`, using the public parameters `pp`
+pub fn prover_with_pp<R: Rng, P: PastaConfig>(
+    rng: &mut R,
+    pp: &PublicParams<P>,
+    qs: &[Instance<P>],
+) -> Result<Accumulator<P>> {
+    let (C_bar, d, z, h) = common_subroutine_with_pp(pp, qs)?;
+    accumulate(rng, pp, C_bar, d, z, h, None)
+}
+
+/// The hiding prover, whose accumulator reveals nothing about the instances `qs`. The verifier
+/// additionally needs the returned accumulation proof π_V.
+pub fn prover_hiding<R: Rng, P: PastaConfig>(
+    rng: &mut R,
+    qs: &[Instance<P>],
+) -> Result<(Accumulator<P>, AccumulatorHiding<P>)> {
+    prover_hiding_with_pp(rng, PublicParams::get_pp(), qs)
+}
+
+/// Same as `prover_hiding`, using the public parameters `pp`
+pub fn prover_hiding_with_pp<R: Rng, P: PastaConfig>(
+    rng: &mut R,
+    pp: &PublicParams<P>,
+    qs: &[Instance<P>],
+) -> Result<(Accumulator<P>, AccumulatorHiding<P>)> {
+    let d = qs.first().context("No instances given")?.d;
+
     // 1. Sample a random linear polynomial h_0 ∈ F_q[X],
+    let h_0: Poly<P> = DenseUVPolynomial::rand(1, rng);
 
     // 2. Then compute a deterministic commitment to h_0: U_0 := PCDL.Commit_ρ0(ck_PC, h_0, d; ω = ⊥).
+    let U = pcdl::commit_with_pp(pp, &h_0, d, None);
 
     // 3. Sample commitment randomness ω ∈ Fq, and set π_V := (h_0, U_0, ω).
+    let w = Scalar::<P>::rand(rng);
+    let pi_V = AccumulatorHiding { h_0, U, w };
 
     // 4. Then, compute the tuple (C_bar, d, z, h(X)) := T^ρ(avk, [qi]^n_(i=1), π_V).
-    let (C_bar, d, z, h) = common_subroutine(qs)?;
+    let (C_bar, d, z, h) = common_subroutine_hiding_with_pp(pp, qs, Some(&pi_V))?;
+
+    let acc = accumulate(rng, pp, C_bar, d, z, h, Some(&w))?;
+    Ok((acc, pi_V))
+}
 
+/// Steps 5 to 7 of the prover, opening h(X) at z
+fn accumulate<R: Rng, P: PastaConfig>(
+    rng: &mut R,
+    pp: &PublicParams<P>,
+    C_bar: Point<P>,
+    d: usize,
+    z: Scalar<P>,
+    h: AccumulatedHPolys<P>,
+    w: Option<&Scalar<P>>,
+) -> Result<Accumulator<P>> {
     // 5. Compute the evaluation v := h(z)
     let v = h.eval(&z);
 
     // 6. Generate the hiding evaluation proof π := PCDL.Open_ρ0(ck_PC, h(X), C_bar, d, z; ω).
-    let pi = pcdl::open(rng, h.get_poly(), C_bar, d, &z, None);
+    let pi = pcdl::open_with_pp(rng, pp, h.get_poly(), C_bar, d, &z, w);
 
     // 7. Finally, output the accumulator acc = ((C_bar, d, z, v), π) and the accumulation proof π_V.
     let q = Instance::new(C_bar, d, z, v, pi);
@@ -198,10 +327,47 @@ pub fn prover<R: Rng, P: PastaConfig>(rng: &mut R, qs: &[Instance<P>]) -> Result
 }
 
 pub fn verifier<P: PastaConfig>(qs: &[Instance<P>], acc: Accumulator<P>) -> Result<()> {
+    verifier_with_pp(PublicParams::get_pp(), qs, acc)
+}
+
+/// Same as `verifier`, using the public parameters `pp`
+pub fn verifier_with_pp<P: PastaConfig>(
+    pp: &PublicParams<P>,
+    qs: &[Instance<P>],
+    acc: Accumulator<P>,
+) -> Result<()> {
+    verify_accumulation(pp, qs, acc, None)
+}
+
+/// The verifier of an accumulator from `prover_hiding`, with its accumulation proof π_V
+pub fn verifier_hiding<P: PastaConfig>(
+    qs: &[Instance<P>],
+    acc: Accumulator<P>,
+    pi_V: &AccumulatorHiding<P>,
+) -> Result<()> {
+    verifier_hiding_with_pp(PublicParams::get_pp(), qs, acc, pi_V)
+}
+
+/// Same as `verifier_hiding`, using the public parameters `pp`
+pub fn verifier_hiding_with_pp<P: PastaConfig>(
+    pp: &PublicParams<P>,
+    qs: &[Instance<P>],
+    acc: Accumulator<P>,
+    pi_V: &AccumulatorHiding<P>,
+) -> Result<()> {
+    verify_accumulation(pp, qs, acc, Some(pi_V))
+}
+
+fn verify_accumulation<P: PastaConfig>(
+    pp: &PublicParams<P>,
+    qs: &[Instance<P>],
+    acc: Accumulator<P>,
+    pi_V: Option<&AccumulatorHiding<P>>,
+) -> Result<()> {
     let Instance { C, d, z, v, pi: _ } = acc.q;
 
     // 1. The accumulation verifier V computes (C_bar', d', z', h(X)) := T^ρ(avk, [qi]^n_(i=1), π_V)
-    let (C_bar_prime, d_prime, z_prime, h) = common_subroutine(qs)?;
+    let (C_bar_prime, d_prime, z_prime, h) = common_subroutine_hiding_with_pp(pp, qs, pi_V)?;
 
     // 2. Then checks that C_bar' = C_bar, d' = d, z' = z, and h(z) = v.
     ensure!(C_bar_prime == C, "C_bar' ≠ C_bar");
@@ -213,7 +379,12 @@ pub fn verifier<P: PastaConfig>(qs: &[Instance<P>], acc: Accumulator<P>) -> Resu
 }
 
 pub fn decider<P: PastaConfig>(acc: Accumulator<P>) -> Result<()> {
-    acc.q.check()
+    decider_with_pp(PublicParams::get_pp(), acc)
+}
+
+/// Same as `decider`, using the public parameters `pp`
+pub fn decider_with_pp<P: PastaConfig>(pp: &PublicParams<P>, acc: Accumulator<P>) -> Result<()> {
+    acc.q.check_with_pp(pp)
 }
 
 // -------------------- Tests --------------------
@@ -259,6 +430,45 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_acc_scheme_hiding() -> Result<()> {
+        let mut rng = rand::thread_rng();
+        let n = 2_usize.pow(rng.sample(Uniform::new(2, 5)));
+
+        // Hiding and non-hiding instances, and accumulators of either kind, accumulate together
+        let q = Instance::<PallasConfig>::rand(&mut rng, n);
+        let (acc, pi_V) = prover_hiding(&mut rng, &[q])?;
+        let qs = vec![acc.into(), Instance::rand_without_hiding(&mut rng, n)];
+        let acc = prover(&mut rng, &qs)?;
+        verifier(&qs, acc.clone())?;
+
+        let qs = vec![
+            acc.into(),
+            Instance::rand(&mut rng, n),
+            Instance::rand_without_hiding(&mut rng, n),
+        ];
+        let (acc, pi_V_2) = Accumulator::new_hiding(&mut rng, &qs)?;
+        acc.clone().verifier_hiding(&qs, &pi_V_2)?;
+        decider(acc.clone())?;
+
+        // The accumulator only verifies with its own π_V
+        assert!(verifier(&qs, acc.clone()).is_err());
+        assert!(verifier_hiding(&qs, acc.clone(), &pi_V).is_err());
+        let mut wrong = pi_V_2.clone();
+        wrong.w += Scalar::<PallasConfig>::one();
+        assert!(verifier_hiding(&qs, acc.clone(), &wrong).is_err());
+        let mut wrong = pi_V_2.clone();
+        wrong.h_0 = DenseUVPolynomial::rand(1, &mut rng);
+        assert!(verifier_hiding(&qs, acc.clone(), &wrong).is_err());
+
+        // Accumulating the same instances twice gives unrelated accumulators
+        let (acc_2, _) = prover_hiding(&mut rng, &qs)?;
+        assert_ne!(acc.q.C, acc_2.q.C);
+        assert_ne!(acc.q.v, acc_2.q.v);
+
+        Ok(())
+    }
+
     fn accumulate_random_instance_without_hiding<R: Rng>(
         rng: &mut R,
         n: usize,
@@ -1,3 +1,4 @@
 pub mod acc;
 pub mod pcdl;
 pub mod pedersen;
+pub mod split;
@@ -8,15 +8,13 @@ use std::{
     time::SystemTime,
 };
 
-use acc::Accumulator;
 use anyhow::Result;
 use ark_pallas::PallasConfig;
 use ark_serialize::CanonicalSerialize;
-use pcdl::Instance;
-
-pub mod acc;
-pub mod pcdl;
-mod pedersen;
+use halo_accumulation::{
+    acc::{self, Accumulator},
+    pcdl::Instance,
+};
 
 // -------------------- Benchmarking Functions --------------------
 
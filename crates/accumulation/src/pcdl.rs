@@ -1,7 +1,7 @@
 #![allow(non_snake_case, clippy::let_and_return)]
 
 //! Bulletproofs-style polynomial commitments based on the Discrete Log assumption
-use anyhow::{ensure, Result};
+use anyhow::{ensure, Context, Result};
 use ark_ec::{short_weierstrass::Affine, CurveGroup};
 use ark_ff::{AdditiveGroup, Field};
 use ark_pallas::PallasConfig;
@@ -13,7 +13,8 @@ use rand::{thread_rng, Rng};
 use rayon::prelude::*;
 
 use halo_group::{
-    construct_powers, point_dot_affine, scalar_dot, PastaConfig, Point, Poly, PublicParams, Scalar,
+    construct_powers, point_dot, point_dot_affine, scalar_dot, PastaConfig, Point, Poly,
+    PublicParams, Scalar,
 };
 use halo_poseidon::{Protocols, Sponge};
 
@@ -45,9 +46,20 @@ impl<P: PastaConfig> Instance<P> {
         z: &Scalar<P>,
         w: Option<&Scalar<P>>,
     ) -> Self {
-        let C = commit(&p, d, w);
+        Self::open_with_pp(rng, PublicParams::get_pp(), p, d, z, w)
+    }
+
+    pub fn open_with_pp<R: Rng>(
+        rng: &mut R,
+        pp: &PublicParams<P>,
+        p: Poly<P>,
+        d: usize,
+        z: &Scalar<P>,
+        w: Option<&Scalar<P>>,
+    ) -> Self {
+        let C = commit_with_pp(pp, &p, d, w);
         let v = p.evaluate(z);
-        let pi = open(rng, p, C, d, z, w);
+        let pi = open_with_pp(rng, pp, p, C, d, z, w);
         Self { C, d, z: *z, v, pi }
     }
 
@@ -108,11 +120,19 @@ impl<P: PastaConfig> Instance<P> {
     }
 
     pub fn check(&self) -> Result<()> {
-        check(&self.C, self.d, &self.z, &self.v, self.pi.clone())
+        self.check_with_pp(PublicParams::get_pp())
+    }
+
+    pub fn check_with_pp(&self, pp: &PublicParams<P>) -> Result<()> {
+        check_with_pp(pp, &self.C, self.d, &self.z, &self.v, self.pi.clone())
     }
 
     pub fn succinct_check(&self) -> Result<(HPoly<P>, Point<P>)> {
-        succinct_check(self.C, self.d, &self.z, &self.v, self.pi.clone())
+        self.succinct_check_with_pp(PublicParams::get_pp())
+    }
+
+    pub fn succinct_check_with_pp(&self, pp: &PublicParams<P>) -> Result<(HPoly<P>, Point<P>)> {
+        succinct_check_with_pp(pp, self.C, self.d, &self.z, &self.v, self.pi.clone())
     }
 
     pub fn tuple(&self) -> (&Point<P>, &usize, &Scalar<P>, &Scalar<P>, &EvalProof<P>) {
@@ -144,6 +164,61 @@ impl<P: PastaConfig> Instance<P> {
     }
 }
 
+/// Openings of many polynomials, each at every point of `zs`, with `vs[j][k]` = p_j(z_k)
+#[derive(Educe, CanonicalSerialize, CanonicalDeserialize)]
+#[educe(Debug, Clone, PartialEq, Eq)]
+pub struct BatchInstance<P: PastaConfig> {
+    pub Cs: Vec<Point<P>>,
+    pub d: usize,
+    pub zs: Vec<Scalar<P>>,
+    pub vs: Vec<Vec<Scalar<P>>>,
+    pub pi: BatchEvalProof<P>,
+}
+
+impl<P: PastaConfig> BatchInstance<P> {
+    pub fn open<R: Rng>(rng: &mut R, ps: Vec<Poly<P>>, d: usize, zs: &[Scalar<P>]) -> Result<Self> {
+        Self::open_with_pp(rng, PublicParams::get_pp(), ps, d, zs)
+    }
+
+    pub fn open_with_pp<R: Rng>(
+        rng: &mut R,
+        pp: &PublicParams<P>,
+        ps: Vec<Poly<P>>,
+        d: usize,
+        zs: &[Scalar<P>],
+    ) -> Result<Self> {
+        let Cs: Vec<Point<P>> = ps.iter().map(|p| commit_with_pp(pp, p, d, None)).collect();
+        let vs = ps
+            .iter()
+            .map(|p| zs.iter().map(|z| p.evaluate(z)).collect())
+            .collect();
+        let pi = batch_open_with_pp(rng, pp, ps, &Cs, d, zs)?;
+        Ok(Self {
+            Cs,
+            d,
+            zs: zs.to_vec(),
+            vs,
+            pi,
+        })
+    }
+
+    pub fn succinct_check(&self) -> Result<Instance<P>> {
+        self.succinct_check_with_pp(PublicParams::get_pp())
+    }
+
+    pub fn succinct_check_with_pp(&self, pp: &PublicParams<P>) -> Result<Instance<P>> {
+        batch_succinct_check_with_pp(pp, &self.Cs, self.d, &self.zs, &self.vs, self.pi.clone())
+    }
+
+    pub fn check(&self) -> Result<()> {
+        self.check_with_pp(PublicParams::get_pp())
+    }
+
+    pub fn check_with_pp(&self, pp: &PublicParams<P>) -> Result<()> {
+        batch_check_with_pp(pp, &self.Cs, self.d, &self.zs, &self.vs, self.pi.clone())
+    }
+}
+
 // impl PartialOrd for Instance {
 //     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
 //         Some(self.z.cmp(&other.z))
@@ -179,6 +254,35 @@ impl<P: PastaConfig> EvalProof<P> {
     ) {
         (self.Ls, self.Rs, self.U, self.c, self.C_bar, self.w_prime)
     }
+
+    /// Checks that the proof has the shape of an opening of a polynomial of degree bound `d`: one
+    /// L and R per halving of the d + 1 coefficients, and either both or neither of the hiding
+    /// commitment C_bar and its randomness ω'.
+    pub fn check_shape(&self, d: usize) -> Result<()> {
+        let n = d.checked_add(1).context("d is too large")?;
+        ensure!(n.is_power_of_two(), "n ({n}) is not a power of two");
+        let lg_n = n.ilog2() as usize;
+        ensure!(
+            self.Ls.len() == lg_n && self.Rs.len() == lg_n,
+            "Expected {lg_n} L and R, but the proof has {} L and {} R",
+            self.Ls.len(),
+            self.Rs.len()
+        );
+        ensure!(
+            self.C_bar.is_some() == self.w_prime.is_some(),
+            "C_bar and ω' must both be present or both be absent"
+        );
+        Ok(())
+    }
+}
+
+/// The proof of a batched opening: a commitment to the quotient q(X) = (p(X) - I(X)) / Z(X) of the
+/// combined polynomial, and the opening of p(X) - Z(x) · q(X) at the challenge x
+#[derive(Educe, CanonicalSerialize, CanonicalDeserialize)]
+#[educe(Debug, Clone, PartialEq, Eq)]
+pub struct BatchEvalProof<P: PastaConfig> {
+    pub C_q: Point<P>,
+    pub pi: EvalProof<P>,
 }
 
 /// Special struct to denote the polynomial h(X). The struct is needed in order to evaluate h(X) in sub-linear time
@@ -187,7 +291,6 @@ pub struct HPoly<P: PastaConfig> {
     pub(crate) xis: Vec<Scalar<P>>,
 }
 
-// TODO: Privacy
 impl<P: PastaConfig> HPoly<P> {
     pub(crate) fn new(xis: Vec<Scalar<P>>) -> Self {
         Self { xis }
@@ -273,7 +376,16 @@ pub fn setup(n: usize) -> Result<()> {
 /// d: A degree bound for p, we require that p.degree() <= d,
 /// w: Optional hiding to pass to the underlying Pederson Commitment
 pub fn commit<P: PastaConfig>(p: &Poly<P>, d: usize, w: Option<&Scalar<P>>) -> Point<P> {
-    let pp = PublicParams::get_pp();
+    commit_with_pp(PublicParams::get_pp(), p, d, w)
+}
+
+/// Same as `commit`, using the public parameters `pp`
+pub fn commit_with_pp<P: PastaConfig>(
+    pp: &PublicParams<P>,
+    p: &Poly<P>,
+    d: usize,
+    w: Option<&Scalar<P>>,
+) -> Point<P> {
     let n = d + 1;
     let p_deg = p.degree();
     let pp_len = pp.len();
@@ -283,7 +395,7 @@ pub fn commit<P: PastaConfig>(p: &Poly<P>, d: usize, w: Option<&Scalar<P>>) -> P
     assert!(p_deg <= d, "p_deg ({p_deg}) <= d ({d})");
     assert!(d <= D, "d ({d}) <= D ({D}) (pp_len = {pp_len})",);
 
-    pedersen::commit(w, &pp.Gs[0..n], &p.coeffs)
+    pedersen::commit_with_pp(pp, w, &pp.Gs[0..n], &p.coeffs)
 }
 
 /// Creates a commitment to the coefficients of the polynomial $p$ of degree $d' < d$, with optional hiding $\o$, using pedersen commitments.
@@ -297,7 +409,17 @@ pub fn chunked_commit<P: PastaConfig>(
     w: Option<&Scalar<P>>,
     chunk_size: usize,
 ) -> Vec<Point<P>> {
-    let pp = PublicParams::get_pp();
+    chunked_commit_with_pp(PublicParams::get_pp(), p, d, w, chunk_size)
+}
+
+/// Same as `chunked_commit`, using the public parameters `pp`
+pub fn chunked_commit_with_pp<P: PastaConfig>(
+    pp: &PublicParams<P>,
+    p: &Poly<P>,
+    d: usize,
+    w: Option<&Scalar<P>>,
+    chunk_size: usize,
+) -> Vec<Point<P>> {
     let n = d + 1;
     // let p_deg = p.degree();
     let pp_len = pp.len();
@@ -309,7 +431,7 @@ pub fn chunked_commit<P: PastaConfig>(
 
     p.coeffs()
         .chunks(chunk_size)
-        .map(|x| pedersen::commit(w, &pp.Gs[0..n], x))
+        .map(|x| pedersen::commit_with_pp(pp, w, &pp.Gs[0..n], x))
         .collect()
 }
 
@@ -332,7 +454,21 @@ pub fn open_without_eval<R: Rng, P: PastaConfig>(
     v: &Scalar<P>,
     w: Option<&Scalar<P>>,
 ) -> EvalProof<P> {
-    let pp = PublicParams::get_pp();
+    open_without_eval_with_pp(rng, PublicParams::get_pp(), p, C, d, z, v, w)
+}
+
+/// Same as `open_without_eval`, using the public parameters `pp`
+#[allow(clippy::too_many_arguments)]
+pub fn open_without_eval_with_pp<R: Rng, P: PastaConfig>(
+    rng: &mut R,
+    pp: &PublicParams<P>,
+    p: Poly<P>,
+    C: Point<P>,
+    d: usize,
+    z: &Scalar<P>,
+    v: &Scalar<P>,
+    w: Option<&Scalar<P>>,
+) -> EvalProof<P> {
     let mut transcript = Sponge::<P>::new(Protocols::PCDL);
     let n = d + 1;
     let lg_n = n.ilog2() as usize;
@@ -352,7 +488,7 @@ pub fn open_without_eval<R: Rng, P: PastaConfig>(
         let w_bar = Scalar::<P>::rand(rng);
 
         // (4). Compute a hiding commitment to p_bar: C_bar ← CM.Commit^(ρ0)(ck, p_bar; ω_bar) ∈ G.
-        let C_bar = commit(&p_bar, d, Some(&w_bar));
+        let C_bar = commit_with_pp(pp, &p_bar, d, Some(&w_bar));
 
         // (5). Compute the challenge α := ρ(C, z, v, C_bar) ∈ F^∗_q.
         transcript.absorb_g(&[C, C_bar]);
@@ -467,9 +603,22 @@ pub fn open<R: Rng, P: PastaConfig>(
     d: usize,
     z: &Scalar<P>,
     w: Option<&Scalar<P>>,
+) -> EvalProof<P> {
+    open_with_pp(rng, PublicParams::get_pp(), p, C, d, z, w)
+}
+
+/// Same as `open`, using the public parameters `pp`
+pub fn open_with_pp<R: Rng, P: PastaConfig>(
+    rng: &mut R,
+    pp: &PublicParams<P>,
+    p: Poly<P>,
+    C: Point<P>,
+    d: usize,
+    z: &Scalar<P>,
+    w: Option<&Scalar<P>>,
 ) -> EvalProof<P> {
     let v = p.evaluate(z);
-    open_without_eval(rng, p, C, d, z, &v, w)
+    open_without_eval_with_pp(rng, pp, p, C, d, z, &v, w)
 }
 
 /// Cheaply checks that a proof, pi, is correct. It is not a full check
@@ -487,11 +636,21 @@ pub fn succinct_check<P: PastaConfig>(
     v: &Scalar<P>,
     pi: EvalProof<P>,
 ) -> Result<(HPoly<P>, Point<P>)> {
-    let pp = PublicParams::get_pp();
-    let n = d + 1;
-    let lg_n = n.ilog2() as usize;
-    assert!(n.is_power_of_two(), "n ({n}) is not a power of two");
+    succinct_check_with_pp(PublicParams::get_pp(), C, d, z, v, pi)
+}
+
+/// Same as `succinct_check`, using the public parameters `pp`
+pub fn succinct_check_with_pp<P: PastaConfig>(
+    pp: &PublicParams<P>,
+    C: Point<P>,
+    d: usize,
+    z: &Scalar<P>,
+    v: &Scalar<P>,
+    pi: EvalProof<P>,
+) -> Result<(HPoly<P>, Point<P>)> {
     ensure!(d <= pp.D, "d was larger than D!");
+    pi.check_shape(d)?;
+    let lg_n = (d + 1).ilog2() as usize;
 
     let mut transcript = Sponge::new(Protocols::PCDL);
 
@@ -567,21 +726,261 @@ pub fn check<P: PastaConfig>(
     v: &Scalar<P>,
     pi: EvalProof<P>,
 ) -> Result<()> {
-    let pp = PublicParams::get_pp();
+    check_with_pp(PublicParams::get_pp(), C, d, z, v, pi)
+}
+
+/// Same as `check`, using the public parameters `pp`
+pub fn check_with_pp<P: PastaConfig>(
+    pp: &PublicParams<P>,
+    C: &Point<P>,
+    d: usize,
+    z: &Scalar<P>,
+    v: &Scalar<P>,
+    pi: EvalProof<P>,
+) -> Result<()> {
     // 1. Parse ck as (⟨group⟩, hk, S).
     // 2. Set d' := |hk| - 1.
     // 3. Set rk := (⟨group⟩, S, H, d').
 
     // 4. Check that PC_DL.SuccinctCheck_ρ0(rk, C, d, z, v, π) accepts and outputs (h, U).
-    let (h, U) = succinct_check(*C, d, z, v, pi)?;
+    let (h, U) = succinct_check_with_pp(pp, *C, d, z, v, pi)?;
 
     // 5. Check that U = CM.Commit(ck, h_vec), where h_vec is the coefficient vector of the polynomial h.
-    let comm = pedersen::commit(None, &pp.Gs[0..(d + 1)], &h.get_poly().coeffs);
+    let comm = pedersen::commit_with_pp(pp, None, &pp.Gs[0..(d + 1)], &h.get_poly().coeffs);
     ensure!(U == comm, "U ≠ CM.Commit(ck, h_vec)");
 
     Ok(())
 }
 
+// -------------------- Batched Openings -------------------- //
+//
+// Many polynomials p_j are opened at many points z_k at once, by the usual random linear
+// combination and quotient argument:
+// 1. The polynomials are combined as p := Σ_j α^j · p_j, with C := Σ_j α^j · C_j and
+//    u_k := Σ_j α^j · v_(j,k), for the challenge α := ρ_0(C_j, z_k, v_(j,k)).
+// 2. With Z(X) := π_k (X - z_k) and I(X) the interpolation of the u_k at the z_k, the prover
+//    commits to the quotient q(X) := (p(X) - I(X)) / Z(X) as C_q.
+// 3. For the challenge x := ρ_0(α, C_q), p(X) - Z(x) · q(X) evaluates to I(x) at x, which is a
+//    single opening with commitment C - Z(x) · C_q. It can only be made if q(X) is a polynomial,
+//    i.e. if p(z_k) = u_k for every k, except with negligible probability.
+
+/// The transcript of a batched opening after absorbing its statement, and the challenge α
+fn batch_alpha<P: PastaConfig>(
+    Cs: &[Point<P>],
+    zs: &[Scalar<P>],
+    vs: &[Vec<Scalar<P>>],
+) -> (Sponge<P>, Scalar<P>) {
+    let mut transcript = Sponge::new(Protocols::PCDL);
+    transcript.absorb_g(Cs);
+    transcript.absorb_fr(zs);
+    for v in vs {
+        transcript.absorb_fr(v);
+    }
+    let a = transcript.challenge();
+    (transcript, a)
+}
+
+/// The challenge x := ρ_0(α, C_q)
+fn batch_x<P: PastaConfig>(mut transcript: Sponge<P>, a: Scalar<P>, C_q: Point<P>) -> Scalar<P> {
+    transcript.absorb_fr(&[a]);
+    transcript.absorb_g(&[C_q]);
+    transcript.challenge()
+}
+
+/// Z(x) and I(x) = Σ_k u_k · π_(l≠k) (x - z_l) / (z_k - z_l), where u_k := Σ_j α^j · v_(j,k)
+fn batch_eval_terms<P: PastaConfig>(
+    zs: &[Scalar<P>],
+    vs: &[Vec<Scalar<P>>],
+    a: Scalar<P>,
+    x: Scalar<P>,
+) -> (Scalar<P>, Scalar<P>) {
+    let alphas = construct_powers::<P>(&a, vs.len());
+    let us = (0..zs.len()).map(|k| vs.iter().zip(&alphas).map(|(v, a_j)| v[k] * a_j).sum());
+
+    let Z_x = zs.iter().map(|z| x - z).product();
+    let I_x = zs
+        .iter()
+        .zip(us)
+        .enumerate()
+        .map(|(k, (z_k, u_k)): (usize, (&Scalar<P>, Scalar<P>))| {
+            let (num, den) = zs
+                .iter()
+                .enumerate()
+                .filter(|(l, _)| *l != k)
+                .fold((u_k, Scalar::<P>::one()), |(num, den), (_, z_l)| {
+                    (num * (x - z_l), den * (*z_k - z_l))
+                });
+            num * den.inverse().unwrap()
+        })
+        .sum();
+    (Z_x, I_x)
+}
+
+/// The quotient of p(X) by X - z with Ruffini's rule, shifting the quotient down by one
+/// coefficient and the remainder p(z) out
+pub(crate) fn divide_by_linear<P: PastaConfig>(p: &Poly<P>, z: &Scalar<P>) -> Poly<P> {
+    let mut qs = p.coeffs.clone();
+    let mut carry = Scalar::<P>::zero();
+    for c in qs.iter_mut().rev() {
+        (*c, carry) = (carry, *c + carry * z);
+    }
+    qs.pop();
+    Poly::<P>::from_coefficients_vec(qs)
+}
+
+fn ensure_distinct_points<P: PastaConfig>(zs: &[Scalar<P>]) -> Result<()> {
+    ensure!(!zs.is_empty(), "There are no points to open at");
+    for (k, z) in zs.iter().enumerate() {
+        ensure!(!zs[..k].contains(z), "The points are not distinct");
+    }
+    Ok(())
+}
+
+/// Creates a proof that states: "I know polynomials p_j of degree d' less than d, with commitments
+/// C_j s.t. p_j(z_k) = v_(j,k)" for every j and k, where the p_j are private and d, z_k, v_(j,k)
+/// are public.
+///
+/// rng: Required since the function uses randomness
+/// ps: Univariate polynomials p_j(X)
+/// Cs: Non-hiding commitments to the p_j
+/// d: A degree bound for the p_j, we require that p_j.degree() <= d,
+/// zs: Distinct evaluation points z_k
+pub fn batch_open<R: Rng, P: PastaConfig>(
+    rng: &mut R,
+    ps: Vec<Poly<P>>,
+    Cs: &[Point<P>],
+    d: usize,
+    zs: &[Scalar<P>],
+) -> Result<BatchEvalProof<P>> {
+    batch_open_with_pp(rng, PublicParams::get_pp(), ps, Cs, d, zs)
+}
+
+/// Same as `batch_open`, using the public parameters `pp`
+pub fn batch_open_with_pp<R: Rng, P: PastaConfig>(
+    rng: &mut R,
+    pp: &PublicParams<P>,
+    ps: Vec<Poly<P>>,
+    Cs: &[Point<P>],
+    d: usize,
+    zs: &[Scalar<P>],
+) -> Result<BatchEvalProof<P>> {
+    ensure!(ps.len() == Cs.len(), "Every polynomial needs a commitment");
+    ensure_distinct_points::<P>(zs)?;
+
+    let vs: Vec<Vec<Scalar<P>>> = ps
+        .iter()
+        .map(|p| zs.iter().map(|z| p.evaluate(z)).collect())
+        .collect();
+    let (transcript, a) = batch_alpha(Cs, zs, &vs);
+
+    // 1. Compute p(X) := Σ_j α^j · p_j(X) and C := Σ_j α^j · C_j.
+    let alphas = construct_powers::<P>(&a, ps.len());
+    let mut p = Poly::<P>::zero();
+    for (p_j, a_j) in ps.iter().zip(&alphas) {
+        p += (*a_j, p_j);
+    }
+    let C = point_dot(&alphas, Cs);
+
+    // 2. Compute q(X) := (p(X) - I(X)) / Z(X) by dividing by each (X - z_k) in turn, the
+    //    remainders of which make up I(X), and commit to it.
+    let q = zs
+        .iter()
+        .fold(p.clone(), |q, z| divide_by_linear::<P>(&q, z));
+    let C_q = commit_with_pp(pp, &q, d, None);
+
+    // 3. Open p'(X) := p(X) - Z(x) · q(X) at x, where p'(x) = I(x).
+    let x = batch_x(transcript, a, C_q);
+    let (Z_x, I_x) = batch_eval_terms::<P>(zs, &vs, a, x);
+    let p_prime = &p - &(&q * Z_x);
+    let C_prime = C - C_q * Z_x;
+    let pi = open_without_eval_with_pp(rng, pp, p_prime, C_prime, d, &x, &I_x, None);
+
+    Ok(BatchEvalProof { C_q, pi })
+}
+
+/// The single opening that a batched opening reduces to
+fn batch_instance<P: PastaConfig>(
+    Cs: &[Point<P>],
+    d: usize,
+    zs: &[Scalar<P>],
+    vs: &[Vec<Scalar<P>>],
+    pi: BatchEvalProof<P>,
+) -> Result<Instance<P>> {
+    ensure!(
+        Cs.len() == vs.len(),
+        "{} commitments, but evaluations for {} polynomials",
+        Cs.len(),
+        vs.len()
+    );
+    ensure!(
+        vs.iter().all(|v| v.len() == zs.len()),
+        "Every polynomial needs an evaluation at each point"
+    );
+    ensure_distinct_points::<P>(zs)?;
+
+    let (transcript, a) = batch_alpha(Cs, zs, vs);
+    let x = batch_x(transcript, a, pi.C_q);
+    let (Z_x, I_x) = batch_eval_terms::<P>(zs, vs, a, x);
+    let C = point_dot(&construct_powers::<P>(&a, Cs.len()), Cs);
+
+    Ok(Instance::new(C - pi.C_q * Z_x, d, x, I_x, pi.pi))
+}
+
+/// Cheaply checks a batched opening, and outputs the single opening it reduces to, which can be
+/// accumulated like any other instance to defer the expensive part of the check.
+///
+/// Cs: Commitments to the p_j,
+/// d: A degree bound for the p_j,
+/// zs: The evaluation points z_k
+/// vs: v_(j,k) = p_j(z_k)
+/// pi: The batched evaluation proof
+pub fn batch_succinct_check<P: PastaConfig>(
+    Cs: &[Point<P>],
+    d: usize,
+    zs: &[Scalar<P>],
+    vs: &[Vec<Scalar<P>>],
+    pi: BatchEvalProof<P>,
+) -> Result<Instance<P>> {
+    batch_succinct_check_with_pp(PublicParams::get_pp(), Cs, d, zs, vs, pi)
+}
+
+/// Same as `batch_succinct_check`, using the public parameters `pp`
+pub fn batch_succinct_check_with_pp<P: PastaConfig>(
+    pp: &PublicParams<P>,
+    Cs: &[Point<P>],
+    d: usize,
+    zs: &[Scalar<P>],
+    vs: &[Vec<Scalar<P>>],
+    pi: BatchEvalProof<P>,
+) -> Result<Instance<P>> {
+    let q = batch_instance(Cs, d, zs, vs, pi)?;
+    let _ = q.succinct_check_with_pp(pp)?;
+    Ok(q)
+}
+
+/// The full check on a batched opening
+pub fn batch_check<P: PastaConfig>(
+    Cs: &[Point<P>],
+    d: usize,
+    zs: &[Scalar<P>],
+    vs: &[Vec<Scalar<P>>],
+    pi: BatchEvalProof<P>,
+) -> Result<()> {
+    batch_check_with_pp(PublicParams::get_pp(), Cs, d, zs, vs, pi)
+}
+
+/// Same as `batch_check`, using the public parameters `pp`
+pub fn batch_check_with_pp<P: PastaConfig>(
+    pp: &PublicParams<P>,
+    Cs: &[Point<P>],
+    d: usize,
+    zs: &[Scalar<P>],
+    vs: &[Vec<Scalar<P>>],
+    pi: BatchEvalProof<P>,
+) -> Result<()> {
+    batch_instance(Cs, d, zs, vs, pi)?.check_with_pp(pp)
+}
+
 // -------------------- Tests -------------------- //
 
 #[cfg(test)]
@@ -592,6 +991,7 @@ mod tests {
     use halo_group::{PallasPoint, PallasPoly, PallasScalar};
 
     use super::*;
+    use crate::acc;
 
     #[test]
     fn test_z() {
@@ -719,6 +1119,69 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_check_with_pp() -> Result<()> {
+        let mut rng = rand::thread_rng();
+        let n = 2_usize.pow(rng.sample(Uniform::new(2, 10)));
+        let d = n - 1;
+        let pp_small = PublicParams::<PallasConfig>::new(n);
+        let pp_large = PublicParams::<PallasConfig>::new(n * 2);
+
+        // An instance opened under one handle checks under any handle that is large enough
+        let p = PallasPoly::rand(d, &mut rng);
+        let z = PallasScalar::rand(&mut rng);
+        let w = PallasScalar::rand(&mut rng);
+        let q = Instance::open_with_pp(&mut rng, &pp_small, p, d, &z, Some(&w));
+        q.check_with_pp(&pp_small)?;
+        q.check_with_pp(&pp_large)?;
+
+        let pp_tiny = PublicParams::<PallasConfig>::new(n / 2);
+        assert!(q.check_with_pp(&pp_tiny).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_batch_check() -> Result<()> {
+        let mut rng = rand::thread_rng();
+        let n = 2_usize.pow(rng.sample(Uniform::new(2, 8)));
+        let d = n - 1;
+
+        for (k, m) in [(1, 1), (3, 1), (1, 3), (4, 2), (2, n)] {
+            let ps: Vec<PallasPoly> = (0..k).map(|_| PallasPoly::rand(d, &mut rng)).collect();
+            let zs: Vec<PallasScalar> = (0..m).map(|_| PallasScalar::rand(&mut rng)).collect();
+            let batch = BatchInstance::<PallasConfig>::open(&mut rng, ps.clone(), d, &zs)?;
+            batch.check()?;
+
+            // The reduced instance accumulates like a single opening
+            let q = batch.succinct_check()?;
+            let acc = acc::prover(&mut rng, std::slice::from_ref(&q))?;
+            acc::verifier(&[q], acc.clone())?;
+            acc::decider(acc)?;
+
+            // A wrong evaluation of any polynomial at any point is rejected
+            let mut wrong = batch.clone();
+            wrong.vs[k - 1][m - 1] += PallasScalar::one();
+            assert!(wrong.check().is_err());
+            assert!(wrong.succinct_check().is_err());
+        }
+
+        // So are repeated points and missing evaluations
+        let p = PallasPoly::rand(d, &mut rng);
+        let z = PallasScalar::rand(&mut rng);
+        let batch = BatchInstance::<PallasConfig>::open(&mut rng, vec![p.clone()], d, &[z])?;
+        let (Cs, pi) = (batch.Cs.clone(), batch.pi.clone());
+        let v = p.evaluate(&z);
+        assert!(batch_check(&Cs, d, &[z, z], &[vec![v, v]], pi.clone()).is_err());
+        assert!(batch_check(&Cs, d, &[z], &[vec![]], pi).is_err());
+        assert!(
+            BatchInstance::<PallasConfig>::open(&mut rng, vec![p.clone()], d, &[z, z]).is_err()
+        );
+        assert!(BatchInstance::<PallasConfig>::open(&mut rng, vec![p], d, &[]).is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_zero() -> Result<()> {
         let mut rng = rand::thread_rng();
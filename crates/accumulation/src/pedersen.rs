@@ -9,8 +9,16 @@ pub fn commit<P: PastaConfig>(
     Gs: &[Affine<P>],
     ms: &[Scalar<P>],
 ) -> Point<P> {
-    let pp = PublicParams::get_pp();
+    commit_with_pp(PublicParams::get_pp(), w, Gs, ms)
+}
 
+/// Same as `commit`, with the hiding generator S taken from `pp`
+pub fn commit_with_pp<P: PastaConfig>(
+    pp: &PublicParams<P>,
+    w: Option<&Scalar<P>>,
+    Gs: &[Affine<P>],
+    ms: &[Scalar<P>],
+) -> Point<P> {
     assert!(
         Gs.len() >= ms.len(),
         "ms must be larger than Gs: (Gs: {}), (ms: {})",
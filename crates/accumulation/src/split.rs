@@ -0,0 +1,303 @@
+#![allow(non_snake_case)]
+
+//! Split accumulation for Pedersen polynomial commitments, following Bünz, Chiesa, Lin, Mishra
+//! and Spooner. Claims p(z) = v about committed polynomials are accumulated directly, with the
+//! polynomial as a linear-size witness, so unlike `acc` there are no evaluation proofs and no
+//! succinct checks. The verifier only does one MSM over the commitments of the claims.
+
+use anyhow::{ensure, Context, Result};
+use ark_ff::UniformRand;
+use ark_poly::{DenseUVPolynomial, Polynomial};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::Zero;
+use educe::Educe;
+use rand::Rng;
+
+use halo_group::{construct_powers, point_dot, PastaConfig, Point, Poly, PublicParams, Scalar};
+use halo_poseidon::{Protocols, Sponge};
+
+use crate::pcdl;
+
+// -------------------- Accumulation Data Structures --------------------
+
+/// The claim that the polynomial of degree at most d committed to in C evaluates to v at z
+#[derive(Educe, CanonicalSerialize, CanonicalDeserialize)]
+#[educe(Debug, Clone, PartialEq, Eq)]
+pub struct Claim<P: PastaConfig> {
+    pub C: Point<P>,
+    pub d: usize,
+    pub z: Scalar<P>,
+    pub v: Scalar<P>,
+}
+
+/// acc in the paper, a claim and its witness p. Claims to accumulate come with their witness too,
+/// so both fresh claims and earlier accumulators are accumulated alike.
+#[derive(Educe, CanonicalSerialize, CanonicalDeserialize)]
+#[educe(Debug, Clone, PartialEq, Eq)]
+pub struct Accumulator<P: PastaConfig> {
+    pub q: Claim<P>,
+    pub p: Poly<P>,
+}
+
+impl<P: PastaConfig> Accumulator<P> {
+    /// The claim p(z) = v, committing to p
+    pub fn open(p: Poly<P>, d: usize, z: &Scalar<P>) -> Self {
+        Self::open_with_pp(PublicParams::get_pp(), p, d, z)
+    }
+
+    pub fn open_with_pp(pp: &PublicParams<P>, p: Poly<P>, d: usize, z: &Scalar<P>) -> Self {
+        let C = pcdl::commit_with_pp(pp, &p, d, None);
+        let v = p.evaluate(z);
+        let q = Claim { C, d, z: *z, v };
+        Self { q, p }
+    }
+
+    pub fn rand<R: Rng>(rng: &mut R, n: usize) -> Self {
+        assert!(n.is_power_of_two(), "n ({n}) is not a power of two");
+        let d = n - 1;
+        let p: Poly<P> = DenseUVPolynomial::rand(d, rng);
+        let z = Scalar::<P>::rand(rng);
+        Self::open(p, d, &z)
+    }
+
+    pub fn new(qs: &[Accumulator<P>]) -> Result<(Self, AccumulationProof<P>)> {
+        prover(qs)
+    }
+
+    pub fn verifier(&self, qs: &[Claim<P>], pi: &AccumulationProof<P>) -> Result<()> {
+        verifier(qs, &self.q, pi)
+    }
+
+    pub fn decider(&self) -> Result<()> {
+        decider(self)
+    }
+
+    pub fn decider_with_pp(&self, pp: &PublicParams<P>) -> Result<()> {
+        decider_with_pp(pp, self)
+    }
+}
+
+/// pi_V in the paper. For each claim p_i(z_i) = v_i, the commitment W_i to the quotient
+/// w_i(X) = (p_i(X) - v_i) / (X - z_i), and the evaluations of p_i and w_i at the new point.
+#[derive(Educe, CanonicalSerialize, CanonicalDeserialize)]
+#[educe(Debug, Clone, PartialEq, Eq)]
+pub struct AccumulationProof<P: PastaConfig> {
+    pub Ws: Vec<Point<P>>,
+    pub ys: Vec<Scalar<P>>,
+    pub ys_w: Vec<Scalar<P>>,
+}
+
+// -------------------- Accumulation Functions --------------------
+
+/// The evaluations (y_i, y'_i) of the p_i and w_i at the challenge z
+type Evaluations<P> = (Vec<Scalar<P>>, Vec<Scalar<P>>);
+
+/// The challenges z := ρ(q_i, W_i) and α := ρ(z, y_i, y'_i)
+fn challenges<P: PastaConfig>(
+    qs: &[Claim<P>],
+    Ws: &[Point<P>],
+    ys: impl FnOnce(&Scalar<P>) -> Evaluations<P>,
+) -> (Scalar<P>, Scalar<P>, Evaluations<P>) {
+    let mut transcript = Sponge::new(Protocols::SPLIT);
+    for q in qs {
+        transcript.absorb_g(&[q.C]);
+        transcript.absorb_fr(&[q.z, q.v]);
+    }
+    transcript.absorb_g(Ws);
+    let z = transcript.challenge();
+
+    let (ys, ys_w) = ys(&z);
+    transcript.absorb_fr(&ys);
+    transcript.absorb_fr(&ys_w);
+    let alpha = transcript.challenge();
+
+    (z, alpha, (ys, ys_w))
+}
+
+/// The accumulated claim: with the weights α^(2i) for p_i and α^(2i+1) for w_i,
+/// C := Σ α^(2i) · C_i + α^(2i+1) · W_i, and v := Σ α^(2i) · y_i + α^(2i+1) · y'_i
+fn accumulated_claim<P: PastaConfig>(
+    qs: &[Claim<P>],
+    d: usize,
+    z: Scalar<P>,
+    alphas: &[Scalar<P>],
+    pi: &AccumulationProof<P>,
+) -> Claim<P> {
+    let Cs: Vec<Point<P>> = qs.iter().zip(&pi.Ws).flat_map(|(q, W)| [q.C, *W]).collect();
+    let vs = pi.ys.iter().zip(&pi.ys_w).flat_map(|(y, y_w)| [*y, *y_w]);
+
+    let C = point_dot(alphas, &Cs);
+    let v = vs.zip(alphas).map(|(v, a)| v * a).sum();
+    Claim { C, d, z, v }
+}
+
+pub fn prover<P: PastaConfig>(
+    qs: &[Accumulator<P>],
+) -> Result<(Accumulator<P>, AccumulationProof<P>)> {
+    prover_with_pp(PublicParams::get_pp(), qs)
+}
+
+/// Same as `prover`, using the public parameters `pp`
+pub fn prover_with_pp<P: PastaConfig>(
+    pp: &PublicParams<P>,
+    qs: &[Accumulator<P>],
+) -> Result<(Accumulator<P>, AccumulationProof<P>)> {
+    let d = qs.first().context("No claims given")?.q.d;
+    for acc in qs {
+        ensure!(acc.q.d == d, "d_i ≠ d");
+    }
+
+    // 1. For each i, compute the quotient w_i(X) := (p_i(X) - v_i) / (X - z_i) and commit to it.
+    //    The division leaves no remainder exactly when p_i(z_i) = v_i.
+    let ws: Vec<Poly<P>> = qs
+        .iter()
+        .map(|Accumulator { q, p }| pcdl::divide_by_linear::<P>(p, &q.z))
+        .collect();
+    let Ws: Vec<Point<P>> = ws
+        .iter()
+        .map(|w| pcdl::commit_with_pp(pp, w, d, None))
+        .collect();
+
+    // 2. Evaluate every p_i and w_i at the challenge z.
+    let claims: Vec<Claim<P>> = qs.iter().map(|acc| acc.q.clone()).collect();
+    let (z, alpha, (ys, ys_w)) = challenges(&claims, &Ws, |z| {
+        let ys = qs.iter().map(|acc| acc.p.evaluate(z)).collect();
+        let ys_w = ws.iter().map(|w| w.evaluate(z)).collect();
+        (ys, ys_w)
+    });
+    let pi = AccumulationProof { Ws, ys, ys_w };
+
+    // 3. Accumulate p(X) := Σ α^(2i) · p_i(X) + α^(2i+1) · w_i(X) at z.
+    let alphas = construct_powers::<P>(&alpha, 2 * qs.len());
+    let mut p = Poly::<P>::zero();
+    for (i, (acc, w)) in qs.iter().zip(&ws).enumerate() {
+        p += (alphas[2 * i], &acc.p);
+        p += (alphas[2 * i + 1], w);
+    }
+    let q = accumulated_claim(&claims, d, z, &alphas, &pi);
+
+    Ok((Accumulator { q, p }, pi))
+}
+
+pub fn verifier<P: PastaConfig>(
+    qs: &[Claim<P>],
+    acc: &Claim<P>,
+    pi: &AccumulationProof<P>,
+) -> Result<()> {
+    let m = qs.len();
+    let d = qs.first().context("No claims given")?.d;
+    ensure!(
+        pi.Ws.len() == m && pi.ys.len() == m && pi.ys_w.len() == m,
+        "The accumulation proof does not match the {m} claims"
+    );
+    for q in qs {
+        ensure!(q.d == d, "d_i ≠ d");
+    }
+
+    let (z, alpha, _) = challenges(qs, &pi.Ws, |_| (pi.ys.clone(), pi.ys_w.clone()));
+
+    // 1. Check that p_i(z) - v_i = (z - z_i) · w_i(z) at the challenge z, so that w_i(X) is the
+    //    quotient of p_i(X) - v_i by X - z_i, except with negligible probability.
+    for (i, q) in qs.iter().enumerate() {
+        ensure!(
+            pi.ys[i] - q.v == (z - q.z) * pi.ys_w[i],
+            "p_{i}(z) - v_{i} ≠ (z - z_{i}) · w_{i}(z)"
+        );
+    }
+
+    // 2. Check that the accumulator holds the combination of the claims.
+    let alphas = construct_powers::<P>(&alpha, 2 * m);
+    let expected = accumulated_claim(qs, d, z, &alphas, pi);
+    ensure!(acc.C == expected.C, "C ≠ Σ α^(2i) · C_i + α^(2i+1) · W_i");
+    ensure!(acc.d == d, "d' ≠ d");
+    ensure!(acc.z == z, "z' ≠ z");
+    ensure!(acc.v == expected.v, "v ≠ Σ α^(2i) · y_i + α^(2i+1) · y'_i");
+
+    Ok(())
+}
+
+pub fn decider<P: PastaConfig>(acc: &Accumulator<P>) -> Result<()> {
+    decider_with_pp(PublicParams::get_pp(), acc)
+}
+
+/// Same as `decider`, using the public parameters `pp`
+pub fn decider_with_pp<P: PastaConfig>(pp: &PublicParams<P>, acc: &Accumulator<P>) -> Result<()> {
+    let Accumulator { q, p } = acc;
+    let n = q.d + 1;
+    ensure!(n.is_power_of_two(), "n ({n}) is not a power of two");
+    ensure!(q.d <= pp.D, "d was larger than D!");
+    ensure!(p.degree() <= q.d, "deg(p) > d");
+    ensure!(
+        pcdl::commit_with_pp(pp, p, q.d, None) == q.C,
+        "C ≠ CM.Commit(p)"
+    );
+    ensure!(p.evaluate(&q.z) == q.v, "p(z) ≠ v");
+    Ok(())
+}
+
+// -------------------- Tests --------------------
+
+#[cfg(test)]
+mod tests {
+    use ark_pallas::PallasConfig;
+    use ark_std::One;
+    use rand::distributions::Uniform;
+
+    use super::*;
+
+    #[test]
+    fn test_split_acc_scheme() -> Result<()> {
+        let mut rng = rand::thread_rng();
+        let n = 2_usize.pow(rng.sample(Uniform::new(2, 8)));
+
+        let mut acc = Accumulator::<PallasConfig>::rand(&mut rng, n);
+        acc.decider()?;
+        for k in 1..4 {
+            let mut qs = vec![acc];
+            qs.extend((0..k).map(|_| Accumulator::rand(&mut rng, n)));
+
+            let (next, pi) = prover(&qs)?;
+            let claims: Vec<Claim<PallasConfig>> = qs.iter().map(|acc| acc.q.clone()).collect();
+            next.verifier(&claims, &pi)?;
+            acc = next;
+        }
+        acc.decider()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_acc_false_claims() -> Result<()> {
+        let mut rng = rand::thread_rng();
+        let n = 16;
+
+        // The quotient of a false claim has a remainder, which the verifier catches at z
+        let mut false_claim = Accumulator::<PallasConfig>::rand(&mut rng, n);
+        false_claim.q.v += Scalar::<PallasConfig>::one();
+        assert!(false_claim.decider().is_err());
+        let qs = vec![Accumulator::rand(&mut rng, n), false_claim];
+        let claims: Vec<_> = qs.iter().map(|acc| acc.q.clone()).collect();
+        let (acc, pi) = prover(&qs)?;
+        assert!(acc.verifier(&claims, &pi).is_err());
+
+        // Tampering with the proof or the accumulator is caught by the verifier
+        let qs: Vec<Accumulator<PallasConfig>> =
+            vec![Accumulator::rand(&mut rng, n), Accumulator::rand(&mut rng, n)];
+        let claims: Vec<_> = qs.iter().map(|acc| acc.q.clone()).collect();
+        let (acc, pi) = prover(&qs)?;
+        acc.verifier(&claims, &pi)?;
+
+        let mut wrong = pi.clone();
+        wrong.ys[1] += Scalar::<PallasConfig>::one();
+        assert!(acc.verifier(&claims, &wrong).is_err());
+        let mut wrong = pi.clone();
+        wrong.Ws.swap(0, 1);
+        assert!(acc.verifier(&claims, &wrong).is_err());
+        let mut wrong = acc.clone();
+        wrong.q.v += Scalar::<PallasConfig>::one();
+        assert!(wrong.verifier(&claims, &pi).is_err());
+        assert!(acc.verifier(&claims[..1], &pi).is_err());
+
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
+/// The number of generators embedded in the binary, `PublicParams` derives any beyond them
 pub const N: usize = 2usize.pow(20);
 pub(crate) const G_BLOCKS_NO: usize = 64;
 pub(crate) const G_BLOCKS_SIZE: usize = N / G_BLOCKS_NO;
@@ -39,34 +39,12 @@ pub use wrappers::WrappedPoint;
 mod gen_pp {
     use super::*;
 
-    use ark_ec::PrimeGroup;
-    use ark_ff::PrimeField;
     use bincode::config::standard;
     use consts::{G_BLOCKS_NO, G_BLOCKS_SIZE, N};
-    use group::Scalar;
-    use sha3::{Digest, Sha3_256};
+    use pp::{generator_index, get_generator_hash};
     use std::path::Path;
     use wrappers::{PastaConfig, WrappedPoint};
 
-    use crate::group::Point;
-
-    // Function to generate a random generator for the Curve.
-    // Since the order of the curve is prime, any point that is not the identity point is a generator.
-    fn get_generator_hash<P: PastaConfig>(i: usize) -> WrappedPoint {
-        let genesis_string =
-            "To understand recursion, one must first understand recursion".as_bytes();
-
-        // Hash `genesis_string` concatinated with `i`
-        let mut hasher = Sha3_256::new();
-        hasher.update(i.to_le_bytes());
-        hasher.update(genesis_string);
-        let hash_result = hasher.finalize();
-
-        // Generate a uniformly sampled point from the uniformly sampled field element
-        let point = Point::<P>::generator() * Scalar::<P>::from_le_bytes_mod_order(&hash_result);
-        P::wrap_projective(point)
-    }
-
     pub fn write_pp<P: PastaConfig>(out_dir: PathBuf) -> Result<()> {
         const CHUNKSIZE: usize = 4;
 
@@ -86,8 +64,8 @@ mod gen_pp {
             println!("sh file already exists at {:?}", sh_path);
         } else {
             println!("Creating {:?}", sh_path);
-            let s = get_generator_hash::<P>(0);
-            let h = get_generator_hash::<P>(1);
+            let s = P::wrap_projective(get_generator_hash::<P>(0));
+            let h = P::wrap_projective(get_generator_hash::<P>(1));
             let bytes = bincode::encode_to_vec((s, h), standard())?;
 
             // Write serialized data to file
@@ -112,7 +90,7 @@ mod gen_pp {
                 println!("Creating {} at {:?}", g_file, g_path);
                 let gs: Vec<WrappedPoint> = (0..G_BLOCKS_SIZE)
                     .into_par_iter()
-                    .map(|k| get_generator_hash::<P>(i + k + 2))
+                    .map(|k| P::wrap_projective(get_generator_hash::<P>(generator_index(i, k))))
                     .collect();
                 let bytes = bincode::encode_to_vec(gs, standard())?;
 
@@ -1,10 +1,118 @@
 #![allow(non_snake_case)]
 
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
 use crate::consts::*;
-use crate::group::{Affine, Point};
+use crate::group::{point_dot_affine, Affine, Point, Scalar};
 use crate::wrappers::{PastaConfig, WrappedPoint};
-use anyhow::{bail, Result};
+use anyhow::{bail, ensure, Context, Result};
+use ark_ec::{CurveGroup, PrimeGroup};
+use ark_ff::{PrimeField, UniformRand};
 use bincode::config::standard;
+use rand::{distributions::Uniform, thread_rng, Rng};
+use rayon::prelude::*;
+use sha3::{Digest, Sha3_256};
+
+/// Derives the i'th point of the public parameters by hashing `i` to a scalar and multiplying
+/// the curve generator by it. S and H are points 0 and 1.
+/// Since the order of the curve is prime, any point that is not the identity point is a generator.
+pub fn get_generator_hash<P: PastaConfig>(i: usize) -> Point<P> {
+    Point::<P>::generator() * generator_scalar::<P>(i)
+}
+
+/// The scalar that the curve generator is multiplied by for the i'th point
+fn generator_scalar<P: PastaConfig>(i: usize) -> Scalar<P> {
+    let genesis_string = "To understand recursion, one must first understand recursion".as_bytes();
+
+    // Hash `genesis_string` concatinated with `i`
+    let mut hasher = Sha3_256::new();
+    hasher.update(i.to_le_bytes());
+    hasher.update(genesis_string);
+    let hash_result = hasher.finalize();
+
+    Scalar::<P>::from_le_bytes_mod_order(&hash_result)
+}
+
+/// The hash index of the k'th generator of block b. The blocks take consecutive ranges of
+/// indices after S and H, so no two generators are the same, which the binding of commitments
+/// to vectors longer than a block relies on.
+pub fn generator_index(b: usize, k: usize) -> usize {
+    b * G_BLOCKS_SIZE + k + 2
+}
+
+/// Derives block `b` of the generators
+pub fn derive_block<P: PastaConfig>(b: usize) -> Vec<Affine<P>> {
+    let gs: Vec<Point<P>> = (0..G_BLOCKS_SIZE)
+        .into_par_iter()
+        .map(|k| get_generator_hash::<P>(generator_index(b, k)))
+        .collect();
+    Point::normalize_batch(&gs)
+}
+
+/// Where block `b` of the generators is cached in `dir`, laid out like `.precompute`
+fn block_path<P: PastaConfig>(dir: &Path, b: usize) -> PathBuf {
+    dir.join(P::CURVE_NAME.to_lowercase())
+        .join(format!("gs-{:02}.bin", b))
+}
+
+/// Checks every generator G_k of block `b` against its derivation h_k · G at the cost of a
+/// single MSM. With random weights r_k, Σ r_k · G_k = (Σ r_k · h_k) · G only holds for a block
+/// with a wrong generator with negligible probability.
+fn check_block<P: PastaConfig>(b: usize, gs: &[Affine<P>]) -> bool {
+    let rng = &mut thread_rng();
+    let rs: Vec<Scalar<P>> = (0..gs.len()).map(|_| Scalar::<P>::rand(rng)).collect();
+    let h: Scalar<P> = (0..gs.len())
+        .into_par_iter()
+        .zip(&rs)
+        .map(|(k, r)| generator_scalar::<P>(generator_index(b, k)) * r)
+        .sum();
+    point_dot_affine(&rs, gs) == Point::<P>::generator() * h
+}
+
+/// Loads block `b` of the generators from `dir`, or derives it and stores it there if it is not
+/// cached. Every generator of a cached block is checked against the derivation, see
+/// `check_block`.
+pub fn load_block<P: PastaConfig>(b: usize, dir: &Path) -> Result<Vec<Affine<P>>> {
+    let path = block_path::<P>(dir, b);
+    if !path.exists() {
+        let gs = derive_block::<P>(b);
+        let wrapped: Vec<WrappedPoint> = gs.iter().map(|g| P::wrap_affine(*g)).collect();
+        let bytes = bincode::encode_to_vec(wrapped, standard())?;
+        let parent = path.parent().unwrap();
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+        // Write to a temporary file first, so a crash never leaves a truncated block behind
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, bytes)
+            .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &path)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        return Ok(gs);
+    }
+
+    let bytes = fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let (wrapped, _): (Vec<WrappedPoint>, usize) =
+        bincode::decode_from_slice(&bytes, standard())
+            .with_context(|| format!("Failed to decode {}", path.display()))?;
+    let gs: Vec<Affine<P>> = wrapped.into_iter().map(P::unwrap_affine).collect();
+    ensure!(
+        gs.len() == G_BLOCKS_SIZE,
+        "{} holds {} generators, expected {}",
+        path.display(),
+        gs.len(),
+        G_BLOCKS_SIZE
+    );
+
+    ensure!(
+        check_block::<P>(b, &gs),
+        "The generators of {} do not match their derivation",
+        path.display()
+    );
+    Ok(gs)
+}
 
 #[derive(Debug)]
 pub struct PublicParams<P: PastaConfig> {
@@ -23,7 +131,67 @@ impl<P: PastaConfig> PublicParams<P> {
         self.Gs.len() == 0
     }
 
+    /// Public parameters with `n` generators. The first N are read from the embedded blocks,
+    /// any further ones are derived, see `new_cached` to keep them across runs.
     pub fn new(n: usize) -> Self {
+        let mut pp = Self::embedded(n.min(N));
+        pp.extend(n, None).unwrap();
+        pp
+    }
+
+    /// Same as `new`, but the generators past the embedded blocks are loaded from `dir` and
+    /// only derived (and then stored in `dir`) if they are not cached yet.
+    pub fn new_cached(n: usize, dir: &Path) -> Result<Self> {
+        let mut pp = Self::embedded(n.min(N));
+        pp.extend(n, Some(dir))?;
+        Ok(pp)
+    }
+
+    /// Grows the public parameters to `n` generators, taking the missing blocks from `dir` if
+    /// given and deriving them otherwise. Does nothing if there already are `n` generators.
+    pub fn extend(&mut self, n: usize, dir: Option<&Path>) -> Result<()> {
+        ensure!(n.is_power_of_two(), "n ({n}) is not a power of two");
+        if n <= self.len() {
+            return Ok(());
+        }
+        if self.len() < N {
+            *self = Self::embedded(n.min(N));
+        }
+        while self.len() < n {
+            let b = self.len() / G_BLOCKS_SIZE;
+            match dir {
+                Some(dir) => self.Gs.extend(load_block::<P>(b, dir)?),
+                None => self.Gs.extend(derive_block::<P>(b)),
+            }
+        }
+        self.D = n - 1;
+        Ok(())
+    }
+
+    /// Checks S, H and `samples` random generators against the derivation
+    pub fn verify(&self, samples: usize) -> Result<()> {
+        ensure!(
+            self.S == get_generator_hash::<P>(0),
+            "S does not match its derivation"
+        );
+        ensure!(
+            self.H == get_generator_hash::<P>(1),
+            "H does not match its derivation"
+        );
+        let rng = &mut thread_rng();
+        for _ in 0..samples {
+            let j = rng.sample(Uniform::new(0, self.len()));
+            let (b, k) = (j / G_BLOCKS_SIZE, j % G_BLOCKS_SIZE);
+            ensure!(
+                self.Gs[j] == get_generator_hash::<P>(generator_index(b, k)).into_affine(),
+                "Generator {j} does not match its derivation"
+            );
+        }
+        Ok(())
+    }
+
+    /// The first `n` generators from the blocks embedded in the binary
+    fn embedded(n: usize) -> Self {
         assert!(
             !cfg!(feature = "bootstrap"),
             "Can't initialize public parameters in bootstrap mode!"
@@ -92,6 +260,26 @@ impl<P: PastaConfig> PublicParams<P> {
             }
         }
     }
+
+    /// Same as `set_pp`, but the generators past the embedded blocks are cached in `dir`
+    pub fn set_pp_cached(n: usize, dir: &Path) -> Result<()> {
+        match P::get_loaded_public_params().get() {
+            Some(pp) if n > pp.Gs.len() => bail!(
+                "Previous public parameters defined to be {}, which is smaller than new public parameters {}",
+                pp.Gs.len(),
+                n
+            ),
+            Some(&_) => Ok(()),
+            None => match P::get_loaded_public_params().set(PublicParams::new_cached(n, dir)?) {
+                Ok(_) => Ok(()),
+                Err(pp) => if pp.len() == n {
+                    Ok(())
+                } else {
+                    bail!("pp was already set with length: {}", pp.len())
+                }
+            },
+        }
+    }
 }
 
 #[cfg(test)]
@@ -101,7 +289,7 @@ mod tests {
     use ark_pallas::PallasConfig;
     use rand::{distributions::Uniform, Rng};
 
-    use super::PublicParams;
+    use super::*;
 
     const LAMBDA: usize = 1;
 
@@ -118,6 +306,7 @@ mod tests {
             let j = rng.sample(Uniform::new(n / 2, n));
             assert!(pp.Gs[j].is_on_curve());
         }
+        pp.verify(sec)?;
 
         Ok(())
     }
@@ -139,4 +328,73 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_extend() -> Result<()> {
+        let mut pp = PublicParams::<PallasConfig>::new(2usize.pow(10));
+        pp.extend(2usize.pow(12), None)?;
+        assert_eq!(pp.D, 2usize.pow(12) - 1);
+        assert_eq!(pp.Gs, PublicParams::<PallasConfig>::new(2usize.pow(12)).Gs);
+        assert!(pp.extend(3 * 2usize.pow(12), None).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_distinct_generators() {
+        // The blocks, embedded or not, never share a generator index with each other or S and H
+        let mut indices: Vec<usize> = (0..G_BLOCKS_NO + 2)
+            .flat_map(|b| (0..G_BLOCKS_SIZE).map(move |k| generator_index(b, k)))
+            .collect();
+        indices.sort_unstable();
+        indices.dedup();
+        assert_eq!(indices.len(), (G_BLOCKS_NO + 2) * G_BLOCKS_SIZE);
+        assert_eq!(indices[0], 2);
+
+        // And the embedded blocks are the ones derived from those indices
+        let pp = PublicParams::<PallasConfig>::new(2 * G_BLOCKS_SIZE);
+        assert_ne!(pp.Gs[G_BLOCKS_SIZE], pp.Gs[1]);
+        for j in [0, 1, G_BLOCKS_SIZE, 2 * G_BLOCKS_SIZE - 1] {
+            let (b, k) = (j / G_BLOCKS_SIZE, j % G_BLOCKS_SIZE);
+            assert_eq!(
+                pp.Gs[j],
+                get_generator_hash::<PallasConfig>(generator_index(b, k)).into_affine()
+            );
+        }
+    }
+
+    #[test]
+    fn test_cached_block() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("halo-pp-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        // The first block past the embedded ones is derived, stored and then read back
+        let gs = load_block::<PallasConfig>(G_BLOCKS_NO, &dir)?;
+        assert_eq!(gs.len(), G_BLOCKS_SIZE);
+        assert_eq!(load_block::<PallasConfig>(G_BLOCKS_NO, &dir)?, gs);
+        let embedded = PublicParams::<PallasConfig>::new(G_BLOCKS_SIZE);
+        assert!(!embedded.Gs.contains(&gs[0]));
+
+        // A cache entry that does not match the derivation is rejected
+        let mut tampered: Vec<WrappedPoint> =
+            gs.iter().map(|g| PallasConfig::wrap_affine(*g)).collect();
+        tampered.swap(0, 1);
+        fs::write(
+            block_path::<PallasConfig>(&dir, G_BLOCKS_NO),
+            bincode::encode_to_vec(&tampered, standard())?,
+        )?;
+        assert!(load_block::<PallasConfig>(G_BLOCKS_NO, &dir).is_err());
+
+        // Wherever the wrong generator is
+        tampered.swap(0, 1);
+        let k = G_BLOCKS_SIZE / 2 + 1;
+        tampered[k] = PallasConfig::wrap_affine((gs[k] + gs[k]).into_affine());
+        fs::write(
+            block_path::<PallasConfig>(&dir, G_BLOCKS_NO),
+            bincode::encode_to_vec(&tampered, standard())?,
+        )?;
+        assert!(load_block::<PallasConfig>(G_BLOCKS_NO, &dir).is_err());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
 }
@@ -0,0 +1,161 @@
+use std::time::Duration;
+
+use arith::{
+    circuit::{PlonkCircuit, PlonkPublicInputs, PlonkWitness},
+    frontend::{
+        Call, Frontend,
+        ivc::{IVC_FP_PUBLIC_INPUT_COUNT, IVC_FQ_PUBLIC_INPUT_COUNT, IVC_ROW_COUNT, WireIVCState},
+        plonk::WirePlonkCircuit,
+        primitives::{WireAffine, WireScalar},
+        signature::WireSchnorrSignature,
+    },
+    plonk::{PlonkProof, PlonkProofEvals},
+};
+use criterion::{BenchmarkId, Criterion, criterion_group};
+use halo_group::{
+    Fp, PallasConfig, PastaConfig, Point, Scalar,
+    ark_ff::UniformRand,
+    ark_serialize::CanonicalSerialize,
+    ark_std::{rand::Rng, test_rng},
+};
+
+const SAMPLE_SIZE: usize = 10;
+const SECONDS: u64 = 2;
+const MIN: usize = 8;
+const MAX: usize = 14;
+
+/// A circuit of 2^size rows, along with the matching public inputs and witness
+fn circuit<R: Rng>(
+    rng: &mut R,
+    size: usize,
+) -> (
+    PlonkCircuit<PallasConfig>,
+    PlonkPublicInputs<PallasConfig>,
+    PlonkWitness<PallasConfig>,
+) {
+    let x = WireScalar::<PallasConfig>::witness();
+    let y = WireScalar::<PallasConfig>::public_input();
+    let mut acc = x;
+    for _ in 0..(1 << (size - 1)) - 8 {
+        acc = acc * acc + y;
+    }
+    acc.output();
+
+    let mut call = Call::new();
+    call.witness(x, Fp::rand(rng)).unwrap();
+    call.public_input(y, Fp::rand(rng)).unwrap();
+    let (fp_trace, _) = call.trace().unwrap();
+    Frontend::reset();
+
+    assert_eq!(fp_trace.rows, 1 << size);
+    fp_trace.consume()
+}
+
+fn evals<P: PastaConfig>(vs: &PlonkProofEvals<P>) -> Vec<Scalar<P>> {
+    let mut vec = [&vs.ws[..], &vs.rs, &vs.sigmas, &vs.w_omegas].concat();
+    vec.push(vs.z_omega);
+    if let Some(vs) = &vs.lookup {
+        vec.extend([&vs.qs[..], &vs.tables, &vs.tables_omega, &vs.hs].concat());
+        vec.extend([vs.h1_omega, vs.z_omega]);
+    }
+    vec
+}
+
+fn commitments<P: PastaConfig>(pi: &PlonkProof<P>) -> Vec<Point<P>> {
+    let mut vec = [&pi.Cs.ws[..], &pi.Cs.ts, &[pi.Cs.z]].concat();
+    if let Some(hiding) = &pi.Cs.hiding {
+        vec.extend([&hiding.ws_hi[..], &[hiding.z_hi]].concat());
+    }
+    if let Some(lookup) = &pi.Cs.lookup {
+        vec.extend([&lookup.hs[..], &[lookup.z]].concat());
+        if let Some(hiding) = &lookup.hiding {
+            vec.extend([&hiding.hs_hi[..], &[hiding.z_hi]].concat());
+        }
+    }
+    vec
+}
+
+/// Prints the size of a proof and the rows of the IVC circuit, which verifies two proofs. The
+/// linearization polynomial replaces the evaluations of the selectors, the chunks of T and z.
+fn sizes() {
+    let rng = &mut test_rng();
+
+    println!("Proof size:");
+    for size in MIN..MAX + 1 {
+        let (circuit, x, w) = circuit(rng, size);
+        let pi = PlonkProof::naive_prover(rng, circuit, &x, w);
+
+        let evals = evals(&pi.vs);
+        let commitments = commitments(&pi);
+        let linearized = circuit.Cs.qs.len() + pi.Cs.ts.len() + 1;
+        let bytes = evals.iter().map(|v| v.compressed_size()).sum::<usize>()
+            + commitments
+                .iter()
+                .map(|c| c.compressed_size())
+                .sum::<usize>()
+            + pi.pis.r.compressed_size()
+            + pi.pis.r_omega.compressed_size()
+            + pi.acc_next.compressed_size();
+        println!(
+            "    2^{size} rows: {} evaluations ({} without linearization), {} commitments, {bytes} bytes",
+            evals.len(),
+            evals.len() + linearized,
+            commitments.len(),
+        );
+    }
+
+    let rows = IVC_ROW_COUNT;
+    let wire_fp_circuit = WirePlonkCircuit::public_input(rows, IVC_FP_PUBLIC_INPUT_COUNT);
+    let wire_fq_circuit = WirePlonkCircuit::public_input(rows, IVC_FQ_PUBLIC_INPUT_COUNT);
+    WireIVCState::witness().ivc_circuit(
+        wire_fp_circuit,
+        wire_fq_circuit,
+        WireAffine::public_input(),
+        WireSchnorrSignature::public_input(),
+        WireScalar::public_input(),
+    );
+    let [fp_rows, fq_rows] = Frontend::row_count();
+    Frontend::reset();
+    println!("IVC circuit rows: {fp_rows} (Fp), {fq_rows} (Fq), padded to {rows}");
+}
+
+pub fn plonk_prover(c: &mut Criterion) {
+    let rng = &mut test_rng();
+
+    let mut group = c.benchmark_group("plonk_prover");
+    for size in MIN..MAX + 1 {
+        let (circuit, x, w) = circuit(rng, size);
+        group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, _| {
+            b.iter(|| PlonkProof::naive_prover(rng, circuit, &x, w.clone()))
+        });
+    }
+    group.finish();
+}
+
+pub fn plonk_verify_succinct(c: &mut Criterion) {
+    let rng = &mut test_rng();
+
+    let mut group = c.benchmark_group("plonk_verify_succinct");
+    for size in MIN..MAX + 1 {
+        let (circuit, x, w) = circuit(rng, size);
+        let pi = PlonkProof::naive_prover(rng, circuit, &x, w);
+        group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, _| {
+            b.iter(|| pi.verify_succinct(circuit, &x).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = plonk;
+    config = Criterion::default().sample_size(SAMPLE_SIZE).measurement_time(Duration::from_secs(SECONDS));
+    targets =
+        plonk_prover,
+        plonk_verify_succinct,
+}
+
+fn main() {
+    sizes();
+    plonk();
+    Criterion::default().configure_from_args().final_summary();
+}
@@ -2,8 +2,12 @@ use std::{
     array,
     fmt::{self, Debug},
     marker::PhantomData,
+    mem,
+    panic::Location,
+    sync::atomic::{AtomicU64, Ordering},
 };
 
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use halo_group::{
     Fp, Fq, PastaConfig, PastaFE, PastaFieldId, PastaScalar, Scalar,
     ark_ec::CurveConfig,
@@ -14,10 +18,18 @@ use petgraph::{
     dot::{Config, Dot},
     graph::{DiGraph, NodeIndex},
 };
+use sha3::{Digest, Sha3_256};
 
-use crate::utils::{R_POLYS, W_POLYS};
+use crate::{
+    circuit::{CircuitDigest, LookupTable},
+    utils::{GENERIC_GATES, HIDING_MIN_ROWS, LOOKUP_WIRES, MIN_ROWS, R_POLYS, S_POLYS, W_POLYS},
+};
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+/// Part of `CircuitSpec::digest`, bumped whenever the same gates are laid out in another trace,
+/// such as when generic gates are packed differently or circuits are padded to other row counts
+pub const TRACE_LAYOUT_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, CanonicalSerialize, CanonicalDeserialize)]
 pub struct SlotId {
     row: usize,
     column: usize,
@@ -56,8 +68,15 @@ impl SlotId {
         }
     }
 
-    pub fn to_scalar<P: PastaConfig>(&self, total_rows: usize) -> Scalar<P> {
-        P::scalar_from_u64((self.row + (self.column - 1) * total_rows) as u64)
+    /// kⱼ ωⁱ⁺¹ for the slot in row i and column j, counting from 0, where `omegas` are the
+    /// elements of the domain and `shifts` the kⱼ of `permutation_shifts`. Row i is evaluated at
+    /// ωⁱ⁺¹, see `Evals::from_vec_and_domain`.
+    pub fn to_scalar<P: PastaConfig>(
+        &self,
+        shifts: &[Scalar<P>; S_POLYS],
+        omegas: &[Scalar<P>],
+    ) -> Scalar<P> {
+        shifts[self.column_0_indexed()] * omegas[(self.row_0_indexed() + 1) % omegas.len()]
     }
 }
 
@@ -76,6 +95,9 @@ pub struct Wire {
     /// An identifier for which output of the incoming gate this wire carries
     pub(crate) output_id: u32,
     pub(crate) fid: PastaFieldId,
+    /// The `CircuitSpec::id` of the circuit the wire belongs to, which is not part of its `Debug`
+    /// and thus not of the digest
+    pub(crate) circuit_id: u64,
 }
 impl std::fmt::Debug for Wire {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -116,6 +138,159 @@ pub(crate) enum GateType {
     // Booleans
     WitnessBool((), [Wire; 1]),
     Eq([Wire; 2], [Wire; 1]),
+    Bit([Wire; 1], [Wire; 1], usize),
+    PackBits([Wire; S_POLYS], [Wire; 1], usize),
+    // Lookups
+    Lookup([Wire; LOOKUP_WIRES], (), LookupTable),
+}
+impl GateType {
+    /// The wires into the gate
+    pub(crate) fn inputs(&self) -> &[Wire] {
+        match self {
+            GateType::Witness(..)
+            | GateType::PublicInput(..)
+            | GateType::Constant(..)
+            | GateType::WitnessBool(..) => &[],
+            GateType::Output(inputs, ..)
+            | GateType::Print(inputs, ..)
+            | GateType::FpMessagePass(inputs, _)
+            | GateType::FqMessagePass(inputs, _)
+            | GateType::FpBoolMessagePass(inputs, _)
+            | GateType::FqBoolMessagePass(inputs, _)
+            | GateType::Bit(inputs, ..) => inputs,
+            GateType::Invert(inputs, _)
+            | GateType::Negate(inputs, _)
+            | GateType::AssertEq(inputs, _)
+            | GateType::Add(inputs, _)
+            | GateType::Multiply(inputs, _)
+            | GateType::Eq(inputs, _) => inputs,
+            GateType::ScalarMulVesta(inputs, _)
+            | GateType::PoseidonEnd(inputs, _)
+            | GateType::Poseidon(inputs, ..)
+            | GateType::Lookup(inputs, ..) => inputs,
+            GateType::ScalarMulPallas(inputs, _) | GateType::AffineAdd(inputs, _) => inputs,
+            GateType::PackBits(inputs, ..) => inputs,
+        }
+    }
+
+    /// The wires out of the gate
+    pub(crate) fn outputs(&self) -> &[Wire] {
+        match self {
+            GateType::Output(..)
+            | GateType::Print(..)
+            | GateType::AssertEq(..)
+            | GateType::Lookup(..) => &[],
+            GateType::Witness(_, outputs)
+            | GateType::PublicInput(_, outputs)
+            | GateType::Constant(_, outputs, _)
+            | GateType::FqMessagePass(_, outputs)
+            | GateType::FpBoolMessagePass(_, outputs)
+            | GateType::FqBoolMessagePass(_, outputs)
+            | GateType::Invert(_, outputs)
+            | GateType::Negate(_, outputs)
+            | GateType::Add(_, outputs)
+            | GateType::Multiply(_, outputs)
+            | GateType::WitnessBool(_, outputs)
+            | GateType::Eq(_, outputs)
+            | GateType::Bit(_, outputs, _)
+            | GateType::PackBits(_, outputs, _) => outputs,
+            GateType::ScalarMulPallas(_, outputs)
+            | GateType::ScalarMulVesta(_, outputs)
+            | GateType::FpMessagePass(_, outputs)
+            | GateType::AffineAdd(_, outputs) => outputs,
+            GateType::PoseidonEnd(_, outputs) | GateType::Poseidon(_, outputs, _) => outputs,
+        }
+    }
+
+    /// The names of the variants, in the order of their declaration
+    pub(crate) const NAMES: [&'static str; 24] = [
+        "Witness",
+        "PublicInput",
+        "Constant",
+        "Output",
+        "Print",
+        "ScalarMulPallas",
+        "ScalarMulVesta",
+        "FpMessagePass",
+        "FqMessagePass",
+        "FpBoolMessagePass",
+        "FqBoolMessagePass",
+        "Invert",
+        "Negate",
+        "AssertEq",
+        "Add",
+        "Multiply",
+        "PoseidonEnd",
+        "Poseidon",
+        "AffineAdd",
+        "WitnessBool",
+        "Eq",
+        "Bit",
+        "PackBits",
+        "Lookup",
+    ];
+
+    /// The name of the variant, see `GateType::NAMES`
+    pub(crate) fn name(&self) -> &'static str {
+        let index = match self {
+            GateType::Witness(..) => 0,
+            GateType::PublicInput(..) => 1,
+            GateType::Constant(..) => 2,
+            GateType::Output(..) => 3,
+            GateType::Print(..) => 4,
+            GateType::ScalarMulPallas(..) => 5,
+            GateType::ScalarMulVesta(..) => 6,
+            GateType::FpMessagePass(..) => 7,
+            GateType::FqMessagePass(..) => 8,
+            GateType::FpBoolMessagePass(..) => 9,
+            GateType::FqBoolMessagePass(..) => 10,
+            GateType::Invert(..) => 11,
+            GateType::Negate(..) => 12,
+            GateType::AssertEq(..) => 13,
+            GateType::Add(..) => 14,
+            GateType::Multiply(..) => 15,
+            GateType::PoseidonEnd(..) => 16,
+            GateType::Poseidon(..) => 17,
+            GateType::AffineAdd(..) => 18,
+            GateType::WitnessBool(..) => 19,
+            GateType::Eq(..) => 20,
+            GateType::Bit(..) => 21,
+            GateType::PackBits(..) => 22,
+            GateType::Lookup(..) => 23,
+        };
+        Self::NAMES[index]
+    }
+
+    /// The field of the trace that the gate is laid out in, which is that of its outputs, as
+    /// message passes take their input from the other field
+    pub(crate) fn fid(&self) -> PastaFieldId {
+        let wire = self.outputs().first().or(self.inputs().first());
+        wire.expect("Every gate has a wire").fid
+    }
+
+    /// Whether the gate creates a wire that is assigned by the caller
+    pub(crate) fn is_input(&self) -> bool {
+        matches!(
+            self,
+            GateType::Witness(..) | GateType::PublicInput(..) | GateType::WitnessBool(..)
+        )
+    }
+
+    /// Whether the gate only computes its outputs, so that it constrains nothing when they are
+    /// not used. Gates like `Invert` or `AffineAdd` also constrain their inputs.
+    pub(crate) fn is_pure(&self) -> bool {
+        matches!(
+            self,
+            GateType::Constant(..)
+                | GateType::Add(..)
+                | GateType::Multiply(..)
+                | GateType::Negate(..)
+                | GateType::Eq(..)
+                | GateType::Poseidon(..)
+                | GateType::PoseidonEnd(..)
+                | GateType::Bit(..)
+        )
+    }
 }
 impl fmt::Display for GateType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -123,8 +298,30 @@ impl fmt::Display for GateType {
     }
 }
 
+/// The generic gates that share rows with gates of the same kind. Every position of a row uses
+/// the selectors [q_l, q_r, q_o, q_m] of the row, so only gates of the same kind are packed
+/// together. Selectors per position would pack any mix of gates, but cost four more selector
+/// polynomials for each of the `GENERIC_GATES` positions, to be committed and opened in every
+/// proof. Negations are additions with zero. Constants are never packed, as the q_c of a row
+/// only holds one constant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum GenericGate {
+    Add,
+    Multiply,
+    AssertEq,
+}
+impl GenericGate {
+    pub(crate) const COUNT: usize = 3;
+}
+
+/// The next `CircuitSpec::id`
+static NEXT_CIRCUIT_ID: AtomicU64 = AtomicU64::new(0);
+
 #[derive(Clone)]
 pub struct CircuitSpec {
+    /// An identifier unique in the process, which rejects the wires of other circuits and is not
+    /// part of the digest. A clone keeps the identifier, as it has the same wires.
+    pub(crate) id: u64,
     pub(crate) graph: DiGraph<GateType, Wire>,
     pub(crate) zero: [Wire; 2],
     pub(crate) one: [Wire; 2],
@@ -132,19 +329,39 @@ pub struct CircuitSpec {
     pub(crate) public_input_wire_count: [usize; 2],
     pub(crate) message_pass_wire_count: [usize; 2],
     pub(crate) output_wire_count: [usize; 2],
+    /// The rows of all gates but the `GenericGate`s, see `CircuitSpec::used_rows`
     pub(crate) row_count: [usize; 2],
+    pub(crate) generic_gate_count: [[usize; GenericGate::COUNT]; 2],
     pub(crate) wire_count: [usize; 2],
+    /// The tables used by lookup gates, in order of first use
+    pub(crate) lookup_tables: [Vec<LookupTable>; 2],
+    /// The call site that added each node, which is not part of the digest
+    pub(crate) locations: Vec<&'static Location<'static>>,
+    /// The call site that gates are attributed to instead of their own, see `CircuitSpec::at`
+    pub(crate) caller: Option<&'static Location<'static>>,
+    /// The paths of nested scope names that gates are attributed to, the first one being empty,
+    /// see `Frontend::scope`
+    pub(crate) scope_paths: Vec<Vec<&'static str>>,
+    /// The scope path of each node, and that of the gates being added
+    pub(crate) scopes: Vec<usize>,
+    pub(crate) scope: usize,
+    /// Whether the circuit is padded to `HIDING_MIN_ROWS` rows for hiding proofs, see
+    /// `Frontend::hiding`
+    pub(crate) hiding: bool,
 }
 
 impl CircuitSpec {
     fn new_without_zero_one() -> Self {
+        let id = NEXT_CIRCUIT_ID.fetch_add(1, Ordering::Relaxed);
         let dummy_wire = Wire {
             id: 0,
             fid: PastaFieldId::Fp,
             node_idx: NodeIndex::new(0),
             output_id: 0,
+            circuit_id: id,
         };
         Self {
+            id,
             graph: DiGraph::new(),
             zero: [dummy_wire, dummy_wire],
             one: [dummy_wire, dummy_wire],
@@ -154,9 +371,18 @@ impl CircuitSpec {
             output_wire_count: [0, 0],
             wire_count: [0, 0],
             row_count: [0, 0],
+            generic_gate_count: [[0; GenericGate::COUNT]; 2],
+            lookup_tables: [Vec::new(), Vec::new()],
+            locations: Vec::new(),
+            caller: None,
+            scope_paths: vec![Vec::new()],
+            scopes: Vec::new(),
+            scope: 0,
+            hiding: false,
         }
     }
 
+    #[track_caller]
     pub fn new() -> Self {
         let mut spec = Self::new_without_zero_one();
         let fp_zero = spec.constant(PastaFE::zero(Some(PastaFieldId::Fp)));
@@ -172,6 +398,57 @@ impl CircuitSpec {
         self.wire_count[0] == 0 && self.wire_count[1] == 0
     }
 
+    /// Runs `f` with the gates it adds attributed to `location`, such that the gadgets of the
+    /// frontend are located by their callers rather than by their own code
+    pub(crate) fn at<T>(
+        &mut self,
+        location: &'static Location<'static>,
+        f: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        let caller = self.caller.replace(location);
+        let result = f(self);
+        self.caller = caller;
+        result
+    }
+
+    /// The call site that added the gate at `node_idx`
+    pub(crate) fn location(&self, node_idx: NodeIndex) -> &'static Location<'static> {
+        self.locations[node_idx.index()]
+    }
+
+    /// Attributes the gates added from now on to the scope `name` nested in the current one, and
+    /// returns the current one to restore afterwards
+    pub(crate) fn enter_scope(&mut self, name: &'static str) -> usize {
+        let mut path = self.scope_paths[self.scope].clone();
+        path.push(name);
+        let scope = match self.scope_paths.iter().position(|p| *p == path) {
+            Some(scope) => scope,
+            None => {
+                self.scope_paths.push(path);
+                self.scope_paths.len() - 1
+            }
+        };
+        mem::replace(&mut self.scope, scope)
+    }
+
+    #[track_caller]
+    fn add_node(&mut self, gate: GateType) -> NodeIndex {
+        self.locations
+            .push(self.caller.unwrap_or(Location::caller()));
+        self.scopes.push(self.scope);
+        self.graph.add_node(gate)
+    }
+
+    /// Adds the edge of `wire` into `node`, which panics if the wire belongs to another circuit
+    #[track_caller]
+    fn add_edge(&mut self, wire: Wire, node: NodeIndex) {
+        assert_eq!(
+            wire.circuit_id, self.id,
+            "The wire {wire:?} belongs to another circuit"
+        );
+        self.graph.add_edge(wire.node_idx, node, wire);
+    }
+
     // WARNING: This might be dangerous if the petgraph crate changes its internals
     fn next_node_index(&self) -> NodeIndex {
         let node_index: NodeIndex = NodeIndex::new(self.graph.node_count());
@@ -188,76 +465,85 @@ impl CircuitSpec {
                 fid,
                 node_idx,
                 output_id: i as u32,
+                circuit_id: self.id,
             }
         };
         array::from_fn(|i| new_wire(i))
     }
 
+    #[track_caller]
     pub fn witness(&mut self, fid: PastaFieldId) -> Wire {
         self.witness_wire_count[fid as usize] += 1;
 
         let out_wires = self.new_wires(fid);
 
-        let node = self.graph.add_node(GateType::Witness((), out_wires));
+        let node = self.add_node(GateType::Witness((), out_wires));
 
         out_wires.iter().for_each(|x| assert_eq!(x.node_idx, node));
 
         out_wires[0]
     }
 
+    #[track_caller]
     pub fn public_input(&mut self, fid: PastaFieldId) -> Wire {
         self.public_input_wire_count[fid as usize] += 1;
         self.row_count[fid as usize] += 1;
 
         let out_wires = self.new_wires(fid);
 
-        let node = self.graph.add_node(GateType::PublicInput((), out_wires));
+        let node = self.add_node(GateType::PublicInput((), out_wires));
 
         out_wires.iter().for_each(|x| assert_eq!(x.node_idx, node));
 
         out_wires[0]
     }
 
+    #[track_caller]
     pub fn fp_witness(&mut self) -> Wire {
         self.witness(PastaFieldId::Fp)
     }
 
+    #[track_caller]
     pub fn fq_witness(&mut self) -> Wire {
         self.witness(PastaFieldId::Fq)
     }
 
+    #[track_caller]
     pub fn fp_public_input(&mut self) -> Wire {
         self.public_input(PastaFieldId::Fp)
     }
 
+    #[track_caller]
     pub fn fq_public_input(&mut self) -> Wire {
         self.public_input(PastaFieldId::Fq)
     }
 
+    #[track_caller]
     pub fn constant(&mut self, c: PastaFE) -> Wire {
         let fid = c.fid.unwrap();
         self.row_count[fid as usize] += 1;
 
         let out_wires = self.new_wires(fid);
 
-        let node = self.graph.add_node(GateType::Constant((), out_wires, c));
+        let node = self.add_node(GateType::Constant((), out_wires, c));
 
         out_wires.iter().for_each(|x| assert_eq!(x.node_idx, node));
 
         out_wires[0]
     }
 
+    #[track_caller]
     pub fn add_gate(&mut self, left: Wire, right: Wire) -> Wire {
         let fid = left.fid;
-        self.row_count[fid as usize] += 1;
+        self.generic_gate_count[fid as usize][GenericGate::Add as usize] += 1;
 
         let in_wires = [left, right];
         let out_wires = self.new_wires(fid);
 
-        let node = self.graph.add_node(GateType::Add(in_wires, out_wires));
+        let node = self.add_node(GateType::Add(in_wires, out_wires));
 
-        self.graph.add_edge(left.node_idx, node, left);
-        self.graph.add_edge(right.node_idx, node, right);
+        self.add_edge(left, node);
+        self.add_edge(right, node);
 
         in_wires.iter().for_each(|x| assert_eq!(fid, x.fid));
         out_wires.iter().for_each(|x| assert_eq!(x.node_idx, node));
@@ -265,16 +551,17 @@ impl CircuitSpec {
         out_wires[0]
     }
 
+    #[track_caller]
     pub fn mul_gate(&mut self, left: Wire, right: Wire) -> Wire {
         let fid = left.fid;
-        self.row_count[fid as usize] += 1;
+        self.generic_gate_count[fid as usize][GenericGate::Multiply as usize] += 1;
 
         let in_wires = [left, right];
         let out_wires = self.new_wires(fid);
 
-        let node = self.graph.add_node(GateType::Multiply(in_wires, out_wires));
-        self.graph.add_edge(left.node_idx, node, left);
-        self.graph.add_edge(right.node_idx, node, right);
+        let node = self.add_node(GateType::Multiply(in_wires, out_wires));
+        self.add_edge(left, node);
+        self.add_edge(right, node);
 
         in_wires.iter().for_each(|x| assert_eq!(fid, x.fid));
         out_wires.iter().for_each(|x| assert_eq!(x.node_idx, node));
@@ -282,19 +569,31 @@ impl CircuitSpec {
         out_wires[0]
     }
 
+    #[track_caller]
     pub fn poseidon(&mut self, round: usize, in_wires: [Wire; 3]) -> [Wire; 3] {
+        let fid = in_wires[0].fid;
+        let round_constants: [PastaFE; R_POLYS] =
+            array::from_fn(|i| fid.poseidon_round_constants()[5 * round + i / 3][i % 3]);
+        self.poseidon_gate(in_wires, round_constants)
+    }
+
+    /// The Poseidon rounds with `round_constants`, see `poseidon`
+    #[track_caller]
+    pub(crate) fn poseidon_gate(
+        &mut self,
+        in_wires: [Wire; 3],
+        round_constants: [PastaFE; R_POLYS],
+    ) -> [Wire; 3] {
         let fid = in_wires[0].fid;
         self.row_count[fid as usize] += 1;
 
         let out_wires = self.new_wires(in_wires[0].fid);
 
-        let round_constants: [PastaFE; R_POLYS] =
-            array::from_fn(|i| fid.poseidon_round_constants()[5 * round + i / 3][i % 3]);
         let gate_type = GateType::Poseidon(in_wires, out_wires, round_constants);
-        let node = self.graph.add_node(gate_type);
+        let node = self.add_node(gate_type);
 
         for wire in in_wires {
-            self.graph.add_edge(wire.node_idx, node, wire);
+            self.add_edge(wire, node);
         }
 
         in_wires.iter().for_each(|x| assert_eq!(fid, x.fid));
@@ -303,6 +602,7 @@ impl CircuitSpec {
         out_wires
     }
 
+    #[track_caller]
     pub fn poseidon_finish(&mut self, in_wires: [Wire; 3]) -> [Wire; 3] {
         let fid = in_wires[0].fid;
         self.row_count[fid as usize] += 1;
@@ -310,9 +610,9 @@ impl CircuitSpec {
         let out_wires = self.new_wires(fid);
 
         let gate_type = GateType::PoseidonEnd(in_wires, out_wires);
-        let node = self.graph.add_node(gate_type);
+        let node = self.add_node(gate_type);
         for wire in in_wires {
-            self.graph.add_edge(wire.node_idx, node, wire);
+            self.add_edge(wire, node);
         }
 
         in_wires.iter().for_each(|x| assert_eq!(fid, x.fid));
@@ -321,6 +621,7 @@ impl CircuitSpec {
         out_wires
     }
 
+    #[track_caller]
     pub fn add_points(&mut self, p: (Wire, Wire), q: (Wire, Wire)) -> (Wire, Wire) {
         let fid = p.0.fid;
         self.row_count[fid as usize] += 1;
@@ -328,11 +629,9 @@ impl CircuitSpec {
         let in_wires = [p.0, p.1, q.0, q.1];
         let out_wires = self.new_wires(p.0.fid);
 
-        let node = self
-            .graph
-            .add_node(GateType::AffineAdd(in_wires, out_wires));
+        let node = self.add_node(GateType::AffineAdd(in_wires, out_wires));
         for wire in in_wires {
-            self.graph.add_edge(wire.node_idx, node, wire);
+            self.add_edge(wire, node);
         }
 
         in_wires.iter().for_each(|x| assert_eq!(fid, x.fid));
@@ -341,18 +640,19 @@ impl CircuitSpec {
         out_wires.into()
     }
 
+    #[track_caller]
     pub fn neg_gate(&mut self, x: Wire) -> Wire {
         let fid = x.fid;
         let zero = self.zero[fid as usize];
-        self.row_count[fid as usize] += 1;
+        self.generic_gate_count[fid as usize][GenericGate::Add as usize] += 1;
 
         let in_wires = [x, zero];
         let out_wires = self.new_wires(fid);
 
-        let node = self.graph.add_node(GateType::Negate(in_wires, out_wires));
+        let node = self.add_node(GateType::Negate(in_wires, out_wires));
 
-        self.graph.add_edge(x.node_idx, node, x);
-        self.graph.add_edge(zero.node_idx, node, zero);
+        self.add_edge(x, node);
+        self.add_edge(zero, node);
 
         in_wires.iter().for_each(|x| assert_eq!(fid, x.fid));
         out_wires.iter().for_each(|x| assert_eq!(x.node_idx, node));
@@ -360,6 +660,7 @@ impl CircuitSpec {
         out_wires[0]
     }
 
+    #[track_caller]
     pub fn inv(&mut self, x: Wire) -> Wire {
         let fid = x.fid;
         let one = self.one[fid as usize];
@@ -368,9 +669,9 @@ impl CircuitSpec {
         let in_wires = [x, one];
         let out_wires = self.new_wires(fid);
 
-        let node = self.graph.add_node(GateType::Invert(in_wires, out_wires));
-        self.graph.add_edge(x.node_idx, node, x);
-        self.graph.add_edge(one.node_idx, node, one);
+        let node = self.add_node(GateType::Invert(in_wires, out_wires));
+        self.add_edge(x, node);
+        self.add_edge(one, node);
 
         in_wires.iter().for_each(|x| assert_eq!(fid, x.fid));
         out_wires.iter().for_each(|x| assert_eq!(x.node_idx, node));
@@ -378,14 +679,18 @@ impl CircuitSpec {
         out_wires[0]
     }
 
+    #[track_caller]
     pub fn assert_eq_gate(&mut self, left: Wire, right: Wire) {
-        let node = self.graph.add_node(GateType::AssertEq([left, right], ()));
-        self.graph.add_edge(left.node_idx, node, left);
-        self.graph.add_edge(right.node_idx, node, right);
+        self.generic_gate_count[left.fid as usize][GenericGate::AssertEq as usize] += 1;
+
+        let node = self.add_node(GateType::AssertEq([left, right], ()));
+        self.add_edge(left, node);
+        self.add_edge(right, node);
 
         assert_eq!(left.fid, right.fid);
     }
 
+    #[track_caller]
     pub fn scalar_mul_pallas(&mut self, scalar: (Wire, Wire), point: (Wire, Wire)) -> (Wire, Wire) {
         let fid = point.0.fid;
         // 255 rows needed for scalar 1 zero row at the end
@@ -395,11 +700,11 @@ impl CircuitSpec {
 
         let gate_type =
             GateType::ScalarMulPallas([scalar.0, scalar.1, point.0, point.1], out_wires);
-        let node = self.graph.add_node(gate_type);
-        self.graph.add_edge(scalar.0.node_idx, node, scalar.0);
-        self.graph.add_edge(scalar.1.node_idx, node, scalar.1);
-        self.graph.add_edge(point.0.node_idx, node, point.0);
-        self.graph.add_edge(point.1.node_idx, node, point.1);
+        let node = self.add_node(gate_type);
+        self.add_edge(scalar.0, node);
+        self.add_edge(scalar.1, node);
+        self.add_edge(point.0, node);
+        self.add_edge(point.1, node);
 
         assert_eq!(fid, scalar.0.fid);
         assert_eq!(fid, scalar.1.fid);
@@ -411,6 +716,7 @@ impl CircuitSpec {
         (out_wires[0], out_wires[1])
     }
 
+    #[track_caller]
     pub fn scalar_mul_vesta(&mut self, scalar: Wire, point: (Wire, Wire)) -> (Wire, Wire) {
         let fid = point.0.fid;
         // 255 rows needed for scalar 1 zero row at the end
@@ -419,10 +725,10 @@ impl CircuitSpec {
         let out_wires = self.new_wires(fid);
 
         let gate_type = GateType::ScalarMulVesta([scalar, point.0, point.1], out_wires);
-        let node = self.graph.add_node(gate_type);
-        self.graph.add_edge(scalar.node_idx, node, scalar);
-        self.graph.add_edge(point.0.node_idx, node, point.0);
-        self.graph.add_edge(point.1.node_idx, node, point.1);
+        let node = self.add_node(gate_type);
+        self.add_edge(scalar, node);
+        self.add_edge(point.0, node);
+        self.add_edge(point.1, node);
 
         assert_eq!(fid, scalar.fid);
         assert_eq!(fid, point.0.fid);
@@ -433,18 +739,20 @@ impl CircuitSpec {
         (out_wires[0], out_wires[1])
     }
 
+    #[track_caller]
     pub fn fp_message_pass(&mut self, input: Wire) -> (Wire, Wire) {
         assert_eq!(input.fid, PastaFieldId::Fp);
         let fid = input.fid.inv();
         self.message_pass_wire_count[fid as usize] += 2;
         self.row_count[fid as usize] += 2;
-        self.row_count[fid as usize] += 17;
+        // 17 rows of 15 of the high bits each, and a row for their sum
+        self.row_count[fid as usize] += 17 + 1;
 
         let out_wires = self.new_wires(fid);
 
         let gate_type = GateType::FpMessagePass([input], out_wires);
-        let node = self.graph.add_node(gate_type);
-        self.graph.add_edge(input.node_idx, node, input);
+        let node = self.add_node(gate_type);
+        self.add_edge(input, node);
 
         assert_eq!(out_wires[0].node_idx, node);
         assert_eq!(out_wires[1].node_idx, node);
@@ -454,6 +762,7 @@ impl CircuitSpec {
         (out_wires[0], out_wires[1])
     }
 
+    #[track_caller]
     pub fn fq_message_pass(&mut self, input: Wire) -> Wire {
         assert_eq!(input.fid, PastaFieldId::Fq);
         let fid = input.fid.inv();
@@ -463,8 +772,8 @@ impl CircuitSpec {
         let out_wires = self.new_wires(fid);
 
         let gate_type = GateType::FqMessagePass([input], out_wires);
-        let node = self.graph.add_node(gate_type);
-        self.graph.add_edge(input.node_idx, node, input);
+        let node = self.add_node(gate_type);
+        self.add_edge(input, node);
 
         assert_eq!(out_wires[0].node_idx, node);
         assert_eq!(out_wires[0].fid, PastaFieldId::Fp);
@@ -472,6 +781,7 @@ impl CircuitSpec {
         out_wires[0]
     }
 
+    #[track_caller]
     pub fn fp_bool_message_pass(&mut self, input: Wire) -> Wire {
         assert_eq!(input.fid, PastaFieldId::Fp);
         let fid = input.fid.inv();
@@ -481,8 +791,8 @@ impl CircuitSpec {
         let out_wires = self.new_wires(fid);
 
         let gate_type = GateType::FpBoolMessagePass([input], out_wires);
-        let node = self.graph.add_node(gate_type);
-        self.graph.add_edge(input.node_idx, node, input);
+        let node = self.add_node(gate_type);
+        self.add_edge(input, node);
 
         assert_eq!(out_wires[0].node_idx, node);
         assert_eq!(out_wires[0].fid, PastaFieldId::Fq);
@@ -490,6 +800,7 @@ impl CircuitSpec {
         out_wires[0]
     }
 
+    #[track_caller]
     pub fn fq_bool_message_pass(&mut self, input: Wire) -> Wire {
         assert_eq!(input.fid, PastaFieldId::Fq);
         let fid = input.fid.inv();
@@ -499,8 +810,8 @@ impl CircuitSpec {
         let out_wires = self.new_wires(fid);
 
         let gate_type = GateType::FqBoolMessagePass([input], out_wires);
-        let node = self.graph.add_node(gate_type);
-        self.graph.add_edge(input.node_idx, node, input);
+        let node = self.add_node(gate_type);
+        self.add_edge(input, node);
 
         assert_eq!(out_wires[0].node_idx, node);
         assert_eq!(out_wires[0].fid, PastaFieldId::Fp);
@@ -508,6 +819,7 @@ impl CircuitSpec {
         out_wires[0]
     }
 
+    #[track_caller]
     pub fn eq_gate(&mut self, a: Wire, b: Wire) -> Wire {
         let fid = a.fid;
         self.row_count[fid as usize] += 1;
@@ -515,9 +827,9 @@ impl CircuitSpec {
         let out_wires = self.new_wires(fid);
 
         let gate_type = GateType::Eq([a, b], out_wires);
-        let node = self.graph.add_node(gate_type);
-        self.graph.add_edge(a.node_idx, node, a);
-        self.graph.add_edge(b.node_idx, node, b);
+        let node = self.add_node(gate_type);
+        self.add_edge(a, node);
+        self.add_edge(b, node);
 
         assert_eq!(out_wires[0].node_idx, node);
         assert_eq!(b.fid, fid);
@@ -525,33 +837,174 @@ impl CircuitSpec {
         out_wires[0]
     }
 
+    #[track_caller]
     pub fn witness_bool(&mut self, fid: PastaFieldId) -> Wire {
         self.witness_wire_count[fid as usize] += 1;
         self.row_count[fid as usize] += 1;
 
         let out_wires = self.new_wires(fid);
 
-        let node = self.graph.add_node(GateType::WitnessBool((), out_wires));
+        let node = self.add_node(GateType::WitnessBool((), out_wires));
+
+        assert_eq!(out_wires[0].node_idx, node);
+
+        out_wires[0]
+    }
+
+    /// Bit `i` of `input`, which is constrained to be boolean but not to be that bit. See
+    /// `pack_bits` for binding the bits to the input.
+    #[track_caller]
+    pub fn bit_gate(&mut self, input: Wire, i: usize) -> Wire {
+        let fid = input.fid;
+        self.row_count[fid as usize] += 1;
+
+        let out_wires = self.new_wires(fid);
+
+        let node = self.add_node(GateType::Bit([input], out_wires, i));
+        self.add_edge(input, node);
+
+        assert_eq!(out_wires[0].node_idx, node);
+
+        out_wires[0]
+    }
+
+    /// acc + Σⱼ bitsⱼ 2ᵏ⁺ʲ for fewer than `S_POLYS` bits, using the range check gate. Only the
+    /// columns with copy constraints are used, and the bits are not constrained to be boolean.
+    #[track_caller]
+    pub fn pack_bits(&mut self, acc: Wire, bits: &[Wire], k: usize) -> Wire {
+        assert!(bits.len() < S_POLYS);
+        let fid = acc.fid;
+        let zero = self.zero[fid as usize];
+        self.row_count[fid as usize] += 2;
+
+        let in_wires: [Wire; S_POLYS] = array::from_fn(|i| match i {
+            0 => acc,
+            i => *bits.get(i - 1).unwrap_or(&zero),
+        });
+        let out_wires = self.new_wires(fid);
+
+        let node = self.add_node(GateType::PackBits(in_wires, out_wires, k));
+        for wire in in_wires {
+            self.add_edge(wire, node);
+        }
 
+        in_wires.iter().for_each(|x| assert_eq!(fid, x.fid));
         assert_eq!(out_wires[0].node_idx, node);
 
         out_wires[0]
     }
 
+    /// Constrains `wires` to be an entry of `table_id`. The trace gets at least as many rows as
+    /// the used tables have entries in total.
+    #[track_caller]
+    pub fn lookup(&mut self, table_id: LookupTable, wires: &[Wire]) {
+        assert_eq!(wires.len(), table_id.arity());
+        let fid = wires[0].fid;
+        let zero = self.zero[fid as usize];
+        self.row_count[fid as usize] += 1;
+
+        if !self.lookup_tables[fid as usize].contains(&table_id) {
+            self.lookup_tables[fid as usize].push(table_id);
+        }
+
+        let in_wires: [Wire; LOOKUP_WIRES] = array::from_fn(|i| *wires.get(i).unwrap_or(&zero));
+        let node = self.add_node(GateType::Lookup(in_wires, (), table_id));
+        for wire in in_wires {
+            self.add_edge(wire, node);
+        }
+
+        in_wires.iter().for_each(|x| assert_eq!(fid, x.fid));
+    }
+
+    /// The total number of entries in the lookup tables used for `fid`
+    pub(crate) fn lookup_table_size(&self, fid: PastaFieldId) -> usize {
+        self.lookup_tables[fid as usize]
+            .iter()
+            .map(LookupTable::size)
+            .sum()
+    }
+
+    /// The rows used by the gates for `fid`, where up to `GENERIC_GATES` generic gates of the same
+    /// kind share a row
+    pub(crate) fn used_rows(&self, fid: PastaFieldId) -> usize {
+        let generic_rows: usize = self.generic_gate_count[fid as usize]
+            .iter()
+            .map(|count| count.div_ceil(GENERIC_GATES))
+            .sum();
+        self.row_count[fid as usize] + generic_rows
+    }
+
+    /// The rows of the trace for `fid`, padded to a power of two and to at least `MIN_ROWS`, or
+    /// `HIDING_MIN_ROWS` for a circuit with hiding proofs
+    pub(crate) fn padded_row_count(&self, fid: PastaFieldId) -> usize {
+        let rows = self.used_rows(fid).max(self.lookup_table_size(fid));
+        let min_rows = if self.hiding {
+            HIDING_MIN_ROWS
+        } else {
+            MIN_ROWS
+        };
+        rows.next_power_of_two().max(min_rows)
+    }
+
+    /// The number of public inputs of the trace for `fid`, including the message passed values
+    pub(crate) fn public_input_count(&self, fid: PastaFieldId) -> usize {
+        self.public_input_wire_count[fid as usize] + self.message_pass_wire_count[fid as usize]
+    }
+
+    /// The position of the public input `wire` in the public inputs of the trace. Public inputs
+    /// are laid out in the order they were created, followed by the message passed values.
+    pub(crate) fn public_input_index(&self, wire: Wire) -> Option<usize> {
+        match self.graph.node_weight(wire.node_idx) {
+            Some(GateType::PublicInput(..)) => (),
+            _ => return None,
+        }
+        let index = self
+            .graph
+            .node_indices()
+            .take_while(|node_idx| *node_idx != wire.node_idx)
+            .filter(|node_idx| {
+                matches!(self.graph[*node_idx], GateType::PublicInput(_, [x]) if x.fid == wire.fid)
+            })
+            .count();
+        Some(index)
+    }
+
+    /// The wires of the witnesses and public inputs, which are assigned by the caller
+    pub(crate) fn input_wires(&self) -> impl Iterator<Item = Wire> + '_ {
+        self.graph
+            .node_weights()
+            .filter(|gate| gate.is_input())
+            .flat_map(|gate| gate.outputs().iter().copied())
+    }
+
+    /// A hash of the gates and their wires in the order they were added, which determines the
+    /// trace up to the assigned values together with `TRACE_LAYOUT_VERSION`. Traces and keys are
+    /// identified by the digest of the optimized circuit, see `TraceBuilder::new`.
+    pub fn digest(&self) -> CircuitDigest {
+        let mut hasher = Sha3_256::new();
+        hasher.update(b"halo-plonk-circuit-v2");
+        hasher.update(TRACE_LAYOUT_VERSION.to_le_bytes());
+        for gate in self.graph.node_weights() {
+            hasher.update(format!("{gate:?};").as_bytes());
+        }
+        hasher.update([self.hiding as u8]);
+        hasher.finalize().into()
+    }
+
+    #[track_caller]
     pub fn output_gate(&mut self, input: Wire) {
         let fid = input.fid;
         let out_id = self.output_wire_count[fid as usize];
         self.output_wire_count[fid as usize] += 1;
 
-        let node = self.graph.add_node(GateType::Output([input], (), out_id));
-        self.graph.add_edge(input.node_idx, node, input);
+        let node = self.add_node(GateType::Output([input], (), out_id));
+        self.add_edge(input, node);
     }
 
+    #[track_caller]
     pub fn print(&mut self, input: Wire, label_1: &'static str, label_2: &'static str) {
-        let node = self
-            .graph
-            .add_node(GateType::Print([input], (), (label_1, label_2)));
-        self.graph.add_edge(input.node_idx, node, input);
+        let node = self.add_node(GateType::Print([input], (), (label_1, label_2)));
+        self.add_edge(input, node);
     }
 }
 
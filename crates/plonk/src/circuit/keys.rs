@@ -0,0 +1,503 @@
+//! Preprocessing of a circuit into the keys of the prover and the verifier. The circuit is traced
+//! once, and everything that does not depend on the assigned values is kept: the commitments, the
+//! selector, permutation and table polynomials, the evaluations of the selectors over the large
+//! domain and the permutation itself. Keys can be cached on disk under the digest of the circuit,
+//! so a changed circuit never picks up stale keys. The cache directory is only accessible by its
+//! owner, and the commitments of a loaded key are recomputed from its polynomials, so a verifier
+//! never uses commitments that were not derived from them.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, ensure};
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, Read, SerializationError, Valid, Validate,
+    Write,
+};
+use halo_accumulation::pcdl::commit_with_pp;
+use halo_group::{
+    Domain, Evals, PastaConfig, Poly, PublicParams,
+    ark_poly::{EvaluationDomain, Polynomial},
+};
+
+use crate::{
+    circuit::{
+        PlonkCircuit, PlonkCircuitCommitments, PlonkCircuitLookupCommitments,
+        PlonkWitnessLookupPolys, SlotId, Trace,
+    },
+    plonk::{envelope_from_bytes, envelope_to_bytes},
+    utils::{CONSTRAINT_DEGREE_MULTIPLIER, Q_POLYS, R_POLYS, S_POLYS, permutation_shifts},
+};
+
+/// Identifies a circuit by the gates and wires of its optimized version, see
+/// `CircuitSpec::digest`
+pub type CircuitDigest = [u8; 32];
+
+/// The polynomials fixed by the circuit
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct PlonkCircuitPolys<P: PastaConfig> {
+    pub qs: [Poly<P>; Q_POLYS],
+    pub rs: [Poly<P>; R_POLYS],
+    pub ids: [Poly<P>; S_POLYS],
+    pub sigmas: [Poly<P>; S_POLYS],
+    pub lookup: Option<PlonkWitnessLookupPolys<P>>,
+}
+
+impl<P: PastaConfig> PlonkCircuitPolys<P> {
+    fn iter(&self) -> impl Iterator<Item = &Poly<P>> {
+        let lookup = self
+            .lookup
+            .iter()
+            .flat_map(|lookup| lookup.qs.iter().chain(&lookup.tables));
+        (self.qs.iter())
+            .chain(&self.rs)
+            .chain(&self.ids)
+            .chain(&self.sigmas)
+            .chain(lookup)
+    }
+
+    /// The commitments to the polynomials of a circuit of `rows` rows, made with `pp`
+    pub(crate) fn commit(
+        &self,
+        pp: &PublicParams<P>,
+        rows: usize,
+    ) -> (
+        PlonkCircuitCommitments<P>,
+        Option<PlonkCircuitLookupCommitments<P>>,
+    ) {
+        let d = rows - 1;
+        let commitments = PlonkCircuitCommitments {
+            qs: self.qs.each_ref().map(|q| commit_with_pp(pp, q, d, None)),
+            rs: self.rs.each_ref().map(|r| commit_with_pp(pp, r, d, None)),
+            ids: self
+                .ids
+                .each_ref()
+                .map(|id| commit_with_pp(pp, id, d, None)),
+            sigmas: self
+                .sigmas
+                .each_ref()
+                .map(|sigma| commit_with_pp(pp, sigma, d, None)),
+        };
+        let lookup = self
+            .lookup
+            .as_ref()
+            .map(|lookup| PlonkCircuitLookupCommitments {
+                qs: lookup.qs.each_ref().map(|q| commit_with_pp(pp, q, d, None)),
+                tables: lookup
+                    .tables
+                    .each_ref()
+                    .map(|table| commit_with_pp(pp, table, d, None)),
+            });
+        (commitments, lookup)
+    }
+}
+
+/// The selector and round coefficient polynomials evaluated over the large domain of a proof
+/// without hiding, `CONSTRAINT_DEGREE_MULTIPLIER` times the rows
+#[derive(Clone)]
+pub struct PlonkCircuitEvals<P: PastaConfig> {
+    pub qs: [Evals<P>; Q_POLYS],
+    pub rs: [Evals<P>; R_POLYS],
+}
+
+impl<P: PastaConfig> PlonkCircuitEvals<P> {
+    pub fn new(polys: &PlonkCircuitPolys<P>, rows: usize) -> Self {
+        let large_domain = Domain::<P>::new(rows * CONSTRAINT_DEGREE_MULTIPLIER).unwrap();
+        Self {
+            qs: polys
+                .qs
+                .each_ref()
+                .map(|q| Evals::from_poly_ref(q, large_domain)),
+            rs: polys
+                .rs
+                .each_ref()
+                .map(|r| Evals::from_poly_ref(r, large_domain)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct VerifyingKey<P: PastaConfig> {
+    pub digest: CircuitDigest,
+    pub circuit: PlonkCircuit<P>,
+}
+
+/// Everything the prover needs that is fixed by the circuit. Only `vk`, `polys` and `sigma` are
+/// serialized, the evaluations are recomputed when a key is deserialized since they are several
+/// times larger than the polynomials.
+#[derive(Clone)]
+pub struct ProvingKey<P: PastaConfig> {
+    pub vk: VerifyingKey<P>,
+    pub polys: PlonkCircuitPolys<P>,
+    pub evals: PlonkCircuitEvals<P>,
+    /// The permutation of the copy constraints, the slot each slot is mapped to
+    pub(crate) sigma: Vec<SlotId>,
+}
+
+impl<P: PastaConfig> ProvingKey<P> {
+    fn new(vk: VerifyingKey<P>, polys: PlonkCircuitPolys<P>, sigma: Vec<SlotId>) -> Self {
+        let evals = PlonkCircuitEvals::new(&polys, vk.circuit.rows);
+        Self {
+            vk,
+            polys,
+            evals,
+            sigma,
+        }
+    }
+
+    /// The parts of `trace` that do not depend on the assigned values
+    pub(crate) fn from_trace(digest: CircuitDigest, trace: &Trace<P>) -> Self {
+        let polys = PlonkCircuitPolys {
+            qs: trace.q_polys.clone(),
+            rs: trace.r_polys.clone(),
+            ids: trace.id_polys.clone(),
+            sigmas: trace.sigma_polys.clone(),
+            lookup: trace.lookup_polys.clone(),
+        };
+        let (circuit, _, _) = trace.clone().consume();
+        Self::new(VerifyingKey { digest, circuit }, polys, trace.sigma.clone())
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        envelope_to_bytes::<P, _>(self, self.vk.circuit.rows, Compress::Yes)
+    }
+
+    /// Deserializes a key written by `to_bytes`, which must be the key of the circuit `digest`.
+    /// The commitments of the circuit are recomputed and must match the serialized ones.
+    pub fn from_bytes(bytes: &[u8], digest: CircuitDigest) -> Result<Self> {
+        Self::from_bytes_with_pp(PublicParams::get_pp(), bytes, digest)
+    }
+
+    /// Same as `from_bytes`, recomputing the commitments with the public parameters `pp`
+    pub fn from_bytes_with_pp(
+        pp: &PublicParams<P>,
+        bytes: &[u8],
+        digest: CircuitDigest,
+    ) -> Result<Self> {
+        let (header, pk) = envelope_from_bytes::<P, Self>(bytes, None, Compress::Yes)?;
+        ensure!(
+            pk.vk.digest == digest,
+            "The proving key is for another circuit"
+        );
+        ensure!(
+            header.rows == pk.vk.circuit.rows,
+            "The proving key is for a circuit of {} rows, but its envelope says {}",
+            pk.vk.circuit.rows,
+            header.rows
+        );
+        let rows = pk.vk.circuit.rows;
+        ensure!(
+            rows <= pp.D + 1,
+            "The proving key is for a circuit of {rows} rows, more than the public parameters allow"
+        );
+        ensure!(
+            pk.polys.iter().all(|poly| poly.degree() < rows),
+            "The proving key has polynomials of a degree too large for {rows} rows"
+        );
+        ensure!(
+            pk.vk.circuit.omega == Domain::<P>::new(rows).unwrap().element(1),
+            "The proving key has the wrong root of unity"
+        );
+        let (commitments, lookup) = pk.polys.commit(pp, rows);
+        ensure!(
+            commitments == pk.vk.circuit.Cs && lookup == pk.vk.circuit.lookup,
+            "The commitments of the proving key do not match its polynomials"
+        );
+        ensure!(
+            pk.sigma_matches_polys(),
+            "The permutation of the proving key does not match its sigma polynomials"
+        );
+        Ok(pk)
+    }
+
+    /// Whether `sigma` is the permutation the `sigmas` polynomials encode, the slot of row i and
+    /// column j being kⱼ ωⁱ⁺¹ as in `build_sigma`
+    fn sigma_matches_polys(&self) -> bool {
+        let rows = self.vk.circuit.rows;
+        let in_range = |slot: &SlotId| {
+            (1..=rows).contains(&slot.row()) && (1..=S_POLYS).contains(&slot.column())
+        };
+        if self.sigma.len() != rows * S_POLYS || !self.sigma.iter().all(in_range) {
+            return false;
+        }
+
+        let domain = Domain::<P>::new(rows).unwrap();
+        let shifts = permutation_shifts::<P>();
+        let omegas: Vec<_> = domain.elements().collect();
+        self.sigma
+            .chunks(rows)
+            .zip(&self.polys.sigmas)
+            .all(|(slots, poly)| {
+                let scalars = slots
+                    .iter()
+                    .map(|slot| slot.to_scalar::<P>(&shifts, &omegas));
+                let expected = Evals::<P>::from_vec_and_domain(scalars.collect(), domain);
+                expected.evals.evals == Evals::<P>::from_poly_ref(poly, domain).evals.evals
+            })
+    }
+
+    /// Writes the key to the cache directory `dir`, creating it if needed, see `create_cache_dir`
+    pub fn save(&self, dir: impl AsRef<Path>) -> Result<()> {
+        let dir = dir.as_ref();
+        create_cache_dir(dir)?;
+        let path = key_path::<P>(dir, self.vk.digest);
+        let mut tmp_path = path.clone();
+        tmp_path.set_extension("tmp");
+        fs::write(&tmp_path, self.to_bytes()?)
+            .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &path)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Reads the key of the circuit `digest` from the cache directory `dir`
+    pub fn load(dir: impl AsRef<Path>, digest: CircuitDigest) -> Result<Self> {
+        check_cache_dir(dir.as_ref())?;
+        let path = key_path::<P>(dir.as_ref(), digest);
+        let bytes =
+            fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::from_bytes(&bytes, digest).with_context(|| format!("Invalid {}", path.display()))
+    }
+}
+
+impl<P: PastaConfig> Valid for ProvingKey<P> {
+    fn check(&self) -> Result<(), SerializationError> {
+        self.vk.check()?;
+        self.polys.check()
+    }
+}
+
+impl<P: PastaConfig> CanonicalSerialize for ProvingKey<P> {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        compress: Compress,
+    ) -> Result<(), SerializationError> {
+        self.vk.serialize_with_mode(&mut writer, compress)?;
+        self.polys.serialize_with_mode(&mut writer, compress)?;
+        self.sigma.serialize_with_mode(&mut writer, compress)
+    }
+
+    fn serialized_size(&self, compress: Compress) -> usize {
+        self.vk.serialized_size(compress)
+            + self.polys.serialized_size(compress)
+            + self.sigma.serialized_size(compress)
+    }
+}
+
+impl<P: PastaConfig> CanonicalDeserialize for ProvingKey<P> {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        compress: Compress,
+        validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let vk = VerifyingKey::deserialize_with_mode(&mut reader, compress, validate)?;
+        let polys = PlonkCircuitPolys::deserialize_with_mode(&mut reader, compress, validate)?;
+        let sigma = Vec::deserialize_with_mode(&mut reader, compress, validate)?;
+        let rows = vk.circuit.rows;
+        if !rows.is_power_of_two() || sigma.len() != rows * S_POLYS {
+            return Err(SerializationError::InvalidData);
+        }
+        Ok(Self::new(vk, polys, sigma))
+    }
+}
+
+/// Creates the cache directory `dir` and its parents, only accessible by the owner on unix
+pub fn create_cache_dir(dir: &Path) -> Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder
+        .create(dir)
+        .with_context(|| format!("Failed to create {}", dir.display()))?;
+    check_cache_dir(dir)
+}
+
+/// Keys are only read from a directory that no other user can write to
+fn check_cache_dir(dir: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let metadata =
+            fs::metadata(dir).with_context(|| format!("Failed to read {}", dir.display()))?;
+        let mode = metadata.permissions().mode();
+        ensure!(
+            metadata.is_dir() && mode & 0o022 == 0,
+            "{} is not a directory only writable by its owner (mode {:o})",
+            dir.display(),
+            mode & 0o777
+        );
+    }
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+/// Where the proving key of the circuit `digest` over `P` is cached in `dir`
+pub fn key_path<P: PastaConfig>(dir: &Path, digest: CircuitDigest) -> PathBuf {
+    let hex: String = digest.iter().map(|b| format!("{b:02x}")).collect();
+    dir.join(format!("{hex}.{}.pk", P::CURVE_NAME.to_lowercase()))
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use halo_group::{
+        Fp, PallasConfig,
+        ark_ff::{Field, UniformRand},
+        ark_std::test_rng,
+    };
+
+    use crate::{
+        frontend::{Call, Frontend, primitives::WireScalar},
+        plonk::PlonkProof,
+    };
+
+    use super::*;
+
+    fn mul_circuit() -> (
+        WireScalar<'static, PallasConfig>,
+        WireScalar<'static, PallasConfig>,
+    ) {
+        let x = WireScalar::<PallasConfig>::witness();
+        let y = WireScalar::<PallasConfig>::witness();
+        (x * y).output();
+        (x, y)
+    }
+
+    #[test]
+    fn preprocess_save_load() -> Result<()> {
+        let rng = &mut test_rng();
+        let dir = std::env::temp_dir().join(format!("halo-keys-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        // The keys derived from dummy values match the circuit traced with real ones
+        let (x, y) = mul_circuit();
+        let digest = Frontend::digest();
+        let mut call = Call::new();
+        call.witness(x, Fp::from(0))?;
+        call.witness(y, Fp::from(0))?;
+        let (fp_pk, fq_pk) = call.preprocess_cached(&dir)?;
+        let mut call = Call::new();
+        call.witness(x, Fp::rand(rng))?;
+        call.witness(y, Fp::rand(rng))?;
+        let (fp_trace, fq_trace) = call.trace()?;
+        Frontend::reset();
+        assert_eq!(fp_pk.vk.digest, digest);
+        assert_eq!(fp_pk.vk.circuit, fp_trace.consume().0);
+        assert_eq!(fq_pk.vk.circuit, fq_trace.consume().0);
+
+        let loaded = ProvingKey::<PallasConfig>::load(&dir, digest)?;
+        assert_eq!(loaded.to_bytes()?, fp_pk.to_bytes()?);
+        assert_eq!(loaded.sigma, fp_pk.sigma);
+
+        // Another circuit neither shares the digest nor accepts the cached keys
+        let (x, _) = mul_circuit();
+        x.output();
+        let other_digest = Frontend::digest();
+        Frontend::reset();
+        assert_ne!(other_digest, digest);
+        assert!(ProvingKey::<PallasConfig>::load(&dir, other_digest).is_err());
+        assert!(ProvingKey::<PallasConfig>::from_bytes(&fp_pk.to_bytes()?, other_digest).is_err());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn load_rejects_untrusted() -> Result<()> {
+        let (x, y) = mul_circuit();
+        let mut call = Call::new();
+        call.witness(x, Fp::from(0))?;
+        call.witness(y, Fp::from(0))?;
+        let (fp_pk, _) = call.preprocess()?;
+        Frontend::reset();
+        let digest = fp_pk.vk.digest;
+
+        // Commitments that do not match the polynomials of the key are rejected
+        let mut tampered = fp_pk.clone();
+        tampered.vk.circuit.Cs.ids.swap(0, 1);
+        assert!(ProvingKey::<PallasConfig>::from_bytes(&tampered.to_bytes()?, digest).is_err());
+        let mut tampered = fp_pk.clone();
+        tampered.vk.circuit.omega = tampered.vk.circuit.omega.square();
+        assert!(ProvingKey::<PallasConfig>::from_bytes(&tampered.to_bytes()?, digest).is_err());
+
+        // So is a permutation that is not the one of the sigma polynomials, or not a slot at all
+        let mut tampered = fp_pk.clone();
+        tampered.sigma.swap(0, 1);
+        assert!(ProvingKey::<PallasConfig>::from_bytes(&tampered.to_bytes()?, digest).is_err());
+        let mut tampered = fp_pk.clone();
+        tampered.sigma[0] = SlotId::new(1, S_POLYS + 1);
+        assert!(ProvingKey::<PallasConfig>::from_bytes(&tampered.to_bytes()?, digest).is_err());
+        let mut tampered = fp_pk.clone();
+        tampered.sigma.pop();
+        assert!(ProvingKey::<PallasConfig>::from_bytes(&tampered.to_bytes()?, digest).is_err());
+
+        // The cache directory is created for its owner only, and keys are not read from a
+        // directory that others can write to
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let dir = std::env::temp_dir().join(format!("halo-keys-mode-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fp_pk.save(&dir)?;
+            assert_eq!(fs::metadata(&dir)?.permissions().mode() & 0o777, 0o700);
+            ProvingKey::<PallasConfig>::load(&dir, digest)?;
+            fs::set_permissions(&dir, fs::Permissions::from_mode(0o777))?;
+            assert!(ProvingKey::<PallasConfig>::load(&dir, digest).is_err());
+            assert!(fp_pk.save(&dir).is_err());
+            fs::remove_dir_all(&dir)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn prove_with_key() -> Result<()> {
+        let rng = &mut test_rng();
+        let (x, y) = mul_circuit();
+        Frontend::hiding();
+        let mut call = Call::new();
+        call.witness(x, Fp::from(0))?;
+        call.witness(y, Fp::from(0))?;
+        let (fp_pk, fq_pk) = call.preprocess()?;
+
+        let loaded = ProvingKey::<PallasConfig>::from_bytes(&fp_pk.to_bytes()?, fp_pk.vk.digest)?;
+        assert_eq!(loaded.evals.qs, fp_pk.evals.qs);
+        assert_eq!(loaded.evals.rs, fp_pk.evals.rs);
+
+        let mut call = Call::new();
+        call.witness(x, Fp::rand(rng))?;
+        call.witness(y, Fp::rand(rng))?;
+        let (fp_trace, _) = call.trace_with_params(None, Some((&fp_pk, &fq_pk)))?;
+        fp_trace.test_copy_constraints();
+        let (circuit, x_fp, w) = fp_trace.consume();
+        assert_eq!(circuit, fp_pk.vk.circuit);
+        PlonkProof::naive_prover_with_key(rng, PublicParams::get_pp(), &fp_pk, &x_fp, w.clone())
+            .verify(circuit, &x_fp)?;
+        PlonkProof::naive_prover_hiding_with_key(rng, PublicParams::get_pp(), &fp_pk, &x_fp, w)?
+            .verify(circuit, &x_fp)?;
+
+        // The keys of one circuit can not be used to trace another
+        let z = WireScalar::<PallasConfig>::witness();
+        z.output();
+        let mut call = Call::new();
+        call.witness(x, Fp::rand(rng))?;
+        call.witness(y, Fp::rand(rng))?;
+        call.witness(z, Fp::rand(rng))?;
+        assert!(
+            call.trace_with_params(None, Some((&fp_pk, &fq_pk)))
+                .is_err()
+        );
+        Frontend::reset();
+
+        Ok(())
+    }
+}
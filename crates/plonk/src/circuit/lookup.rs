@@ -0,0 +1,87 @@
+use crate::utils::LOOKUP_WIRES;
+
+/// The built-in lookup tables. Entries have `LOOKUP_WIRES` columns, narrower tables are padded
+/// with zeros. Every table starts with the all-zero entry, which doubles as the query of rows
+/// without a lookup gate and as padding for the table polynomials.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LookupTable {
+    /// (a, b, a ⊕ b) for a, b < 2⁸
+    Xor8,
+    /// (x) for x < 2¹⁶
+    Range16,
+    /// (x, i, bit i of x) for x < 2⁸ and i < 8
+    ByteToBits,
+}
+
+impl LookupTable {
+    /// The value of the table-id column, never zero
+    pub fn id(&self) -> u64 {
+        match self {
+            LookupTable::Xor8 => 1,
+            LookupTable::Range16 => 2,
+            LookupTable::ByteToBits => 3,
+        }
+    }
+
+    /// How many wires a lookup into this table reads
+    pub fn arity(&self) -> usize {
+        match self {
+            LookupTable::Xor8 => 3,
+            LookupTable::Range16 => 1,
+            LookupTable::ByteToBits => 3,
+        }
+    }
+
+    /// The number of entries in the table
+    pub fn size(&self) -> usize {
+        match self {
+            LookupTable::Xor8 => 1 << 16,
+            LookupTable::Range16 => 1 << 16,
+            LookupTable::ByteToBits => 1 << 11,
+        }
+    }
+
+    pub fn entries(&self) -> Vec<[u64; LOOKUP_WIRES]> {
+        match self {
+            LookupTable::Xor8 => (0..1 << 8)
+                .flat_map(|a| (0..1 << 8).map(move |b| [a, b, a ^ b]))
+                .collect(),
+            LookupTable::Range16 => (0..1 << 16).map(|x| [x, 0, 0]).collect(),
+            LookupTable::ByteToBits => (0..1 << 8)
+                .flat_map(|x| (0..8).map(move |i| [x, i, (x >> i) & 1]))
+                .collect(),
+        }
+    }
+
+    pub fn contains(&self, entry: [u64; LOOKUP_WIRES]) -> bool {
+        let [a, b, c] = entry;
+        match self {
+            LookupTable::Xor8 => a < 1 << 8 && b < 1 << 8 && c == a ^ b,
+            LookupTable::Range16 => a < 1 << 16 && b == 0 && c == 0,
+            LookupTable::ByteToBits => a < 1 << 8 && b < 8 && c == (a >> b) & 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tables() {
+        for table in [LookupTable::Xor8, LookupTable::Range16, LookupTable::ByteToBits] {
+            let entries = table.entries();
+            assert_eq!(entries.len(), table.size());
+            assert_eq!(entries[0], [0; LOOKUP_WIRES]);
+            assert!(entries.iter().all(|e| table.contains(*e)));
+            assert!(
+                entries
+                    .iter()
+                    .all(|e| e[table.arity()..].iter().all(|x| *x == 0))
+            );
+        }
+        assert!(!LookupTable::Xor8.contains([3, 5, 7]));
+        assert!(!LookupTable::Range16.contains([1 << 16, 0, 0]));
+        assert!(!LookupTable::ByteToBits.contains([0b100, 2, 0]));
+    }
+}
@@ -1,7 +1,14 @@
 mod circuit_spec;
+mod keys;
+mod lookup;
+mod optimizer;
+mod stats;
 mod trace;
 mod trace_builder;
 
 pub use circuit_spec::*;
+pub use keys::*;
+pub use lookup::*;
+pub use stats::*;
 pub use trace::*;
 pub use trace_builder::*;
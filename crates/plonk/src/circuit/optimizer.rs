@@ -0,0 +1,491 @@
+//! Rewrites of a `CircuitSpec` that keep its semantics: the optimized circuit has the same
+//! inputs, public inputs and outputs, and it is satisfied by exactly the assignments that satisfy
+//! the original one. `CircuitSpec::optimize` runs the passes until none of them applies:
+//!
+//! - Constant folding: gates of constants, up to whole Poseidon permutations, are replaced by the
+//!   constants they compute unless that adds rows, as are additions of zero and products with
+//!   zero or one, and assertions that always hold are removed.
+//! - Constant deduplication: every value gets a single `Constant` gate.
+//! - Common subexpression elimination: gates of the same kind and parameters over the same wires
+//!   are merged.
+//! - Dead gate elimination: gates that do not constrain their inputs are removed when their
+//!   outputs reach no output, constraint or message pass.
+
+use std::{
+    array,
+    collections::{HashMap, HashSet},
+    mem,
+};
+
+use halo_group::{PastaFE, PastaFieldId, ark_ff::BigInt};
+use log::debug;
+use petgraph::Direction;
+
+use super::trace_builder::poseidon_round;
+use crate::circuit::{CircuitSpec, GateType, Wire};
+
+impl CircuitSpec {
+    /// The optimized circuit, and the wires of the witnesses and public inputs of this circuit in
+    /// it
+    pub(crate) fn optimize(&self) -> (CircuitSpec, HashMap<Wire, Wire>) {
+        let mut inputs: HashMap<Wire, Wire> = self.input_wires().map(|wire| (wire, wire)).collect();
+
+        let mut spec = self.clone();
+        loop {
+            let rewriter = Rewriter::rewrite(&spec);
+            for wire in inputs.values_mut() {
+                *wire = rewriter.wires[wire];
+            }
+            spec = rewriter.spec;
+            if !rewriter.changed {
+                break;
+            }
+        }
+
+        debug!(
+            "Optimized the circuit from {:?} to {:?} rows",
+            [PastaFieldId::Fp, PastaFieldId::Fq].map(|fid| self.used_rows(fid)),
+            [PastaFieldId::Fp, PastaFieldId::Fq].map(|fid| spec.used_rows(fid)),
+        );
+        (spec, inputs)
+    }
+
+    /// The constants with a single use, but for the zero and one that every circuit has
+    fn single_use_constants(&self) -> HashSet<Wire> {
+        self.graph
+            .node_indices()
+            .filter(|node_idx| {
+                self.graph
+                    .neighbors_directed(*node_idx, Direction::Outgoing)
+                    .count()
+                    == 1
+            })
+            .filter_map(|node_idx| match self.graph[node_idx] {
+                GateType::Constant((), [wire], _) => Some(wire),
+                _ => None,
+            })
+            .filter(|wire| !self.zero.contains(wire) && !self.one.contains(wire))
+            .collect()
+    }
+
+    /// Whether each gate, by node index, constrains something or has an output that reaches such
+    /// a gate
+    fn live_gates(&self) -> Vec<bool> {
+        let mut live = vec![false; self.graph.node_count()];
+        // Node indices are in the order the gates were added, so gates come after their inputs
+        for node_idx in self.graph.node_indices().rev() {
+            live[node_idx.index()] = !self.graph[node_idx].is_pure()
+                || self
+                    .graph
+                    .neighbors_directed(node_idx, Direction::Outgoing)
+                    .any(|next| live[next.index()]);
+        }
+        live
+    }
+}
+
+/// What makes two gates compute the same outputs under the same constraints
+#[derive(PartialEq, Eq, Hash)]
+struct GateKey {
+    kind: mem::Discriminant<GateType>,
+    inputs: Vec<Wire>,
+    params: Vec<u64>,
+}
+impl GateKey {
+    /// The key of `gate` over `inputs`, or `None` for gates that must not be merged
+    fn new(gate: &GateType, inputs: &[Wire]) -> Option<Self> {
+        let mut inputs = inputs.to_vec();
+        let params = match gate {
+            GateType::Add(..)
+            | GateType::Multiply(..)
+            | GateType::Eq(..)
+            | GateType::AssertEq(..) => {
+                inputs.sort_by_key(|wire| wire.id);
+                Vec::new()
+            }
+            GateType::Negate(..)
+            | GateType::Invert(..)
+            | GateType::PoseidonEnd(..)
+            | GateType::AffineAdd(..)
+            | GateType::ScalarMulPallas(..)
+            | GateType::ScalarMulVesta(..) => Vec::new(),
+            GateType::Poseidon(_, _, round_constants) => round_constants
+                .iter()
+                .flat_map(|c| c.into_bigint().0)
+                .collect(),
+            GateType::Bit(_, _, i) => vec![*i as u64],
+            GateType::PackBits(_, _, k) => vec![*k as u64],
+            GateType::Lookup(_, (), table) => vec![table.id()],
+            // Inputs, outputs and message passes are part of the interface of the circuit
+            _ => return None,
+        };
+        Some(Self {
+            kind: mem::discriminant(gate),
+            inputs,
+            params,
+        })
+    }
+}
+
+/// One pass over the gates of a circuit, which are added to a new circuit unless they are dead,
+/// fold to a constant or duplicate a gate that was added before
+struct Rewriter {
+    spec: CircuitSpec,
+    /// The wires of the old circuit in the new one
+    wires: HashMap<Wire, Wire>,
+    /// The `Constant` gate of each value
+    constants: HashMap<(PastaFieldId, BigInt<4>), Wire>,
+    /// The values of the `Constant` gates
+    values: HashMap<Wire, PastaFE>,
+    /// The outputs of the gates that can be merged
+    gates: HashMap<GateKey, Vec<Wire>>,
+    /// The wires of the old circuit that are constants with a single use
+    single_use: HashSet<Wire>,
+    changed: bool,
+}
+impl Rewriter {
+    fn rewrite(old: &CircuitSpec) -> Self {
+        let mut rewriter = Self {
+            spec: CircuitSpec::new(),
+            wires: HashMap::with_capacity(old.wire_count[0] + old.wire_count[1]),
+            constants: HashMap::new(),
+            values: HashMap::new(),
+            gates: HashMap::new(),
+            single_use: old.single_use_constants(),
+            changed: false,
+        };
+        // Every circuit starts with the zero and one constants of both fields
+        for (old_wires, new_wires) in [(old.zero, rewriter.spec.zero), (old.one, rewriter.spec.one)]
+        {
+            for (old_wire, new_wire) in old_wires.into_iter().zip(new_wires) {
+                let GateType::Constant((), _, c) = rewriter.spec.graph[new_wire.node_idx] else {
+                    unreachable!("The zero and one wires are constants")
+                };
+                rewriter.wires.insert(old_wire, new_wire);
+                rewriter.constants.insert(constant_key(c), new_wire);
+                rewriter.values.insert(new_wire, c);
+            }
+        }
+
+        rewriter.spec.scope_paths = old.scope_paths.clone();
+        rewriter.spec.hiding = old.hiding;
+
+        let live = old.live_gates();
+        for node_idx in old.graph.node_indices() {
+            let gate = &old.graph[node_idx];
+            let old_outputs = gate.outputs();
+            if old_outputs
+                .first()
+                .is_some_and(|wire| rewriter.wires.contains_key(wire))
+            {
+                continue;
+            }
+            if !live[node_idx.index()] {
+                rewriter.changed = true;
+                continue;
+            }
+
+            let inputs: Vec<Wire> = gate
+                .inputs()
+                .iter()
+                .map(|wire| rewriter.wires[wire])
+                .collect();
+            // The gates that replace this one keep its call site and scope
+            rewriter.spec.caller = Some(old.location(node_idx));
+            rewriter.spec.scope = old.scopes[node_idx.index()];
+            let outputs = match rewriter.fold(gate, &inputs) {
+                Some(outputs) => {
+                    rewriter.changed = true;
+                    outputs
+                }
+                None => rewriter.merge_or_add(gate, &inputs),
+            };
+            for (old_wire, new_wire) in old_outputs.iter().zip(outputs) {
+                rewriter.wires.insert(*old_wire, new_wire);
+            }
+        }
+        rewriter.spec.caller = None;
+        rewriter.spec.scope = 0;
+        rewriter
+    }
+
+    /// The constant `c`, added unless the circuit has it already
+    fn constant(&mut self, c: PastaFE) -> Wire {
+        match self.constants.get(&constant_key(c)) {
+            Some(wire) => *wire,
+            None => {
+                let wire = self.spec.constant(c);
+                self.constants.insert(constant_key(c), wire);
+                self.values.insert(wire, c);
+                wire
+            }
+        }
+    }
+
+    /// The outputs of `gate` when they do not depend on the values of its non-constant inputs,
+    /// which are none for assertions that always hold
+    fn fold(&mut self, gate: &GateType, inputs: &[Wire]) -> Option<Vec<Wire>> {
+        if let Some(outputs) = self.simplify(gate, inputs) {
+            return Some(outputs);
+        }
+        let values: Vec<PastaFE> = inputs
+            .iter()
+            .map(|wire| self.values.get(wire).copied())
+            .collect::<Option<_>>()?;
+        let outputs = evaluate(gate, &values)?;
+
+        // A new `Constant` gate takes a row of its own while generic gates share theirs, so these
+        // only fold to a new constant when one of their inputs has no other use and is removed
+        // with them. The other gates take a row each, and the rounds of a permutation of constants
+        // all fold to the three constants of its output.
+        let is_generic = matches!(
+            gate,
+            GateType::Add(..) | GateType::Multiply(..) | GateType::Negate(..)
+        );
+        let adds_row = outputs
+            .iter()
+            .any(|c| !self.constants.contains_key(&constant_key(*c)));
+        let frees_row = gate
+            .inputs()
+            .iter()
+            .any(|wire| self.single_use.contains(wire));
+        if is_generic && adds_row && !frees_row {
+            return None;
+        }
+        Some(outputs.into_iter().map(|c| self.constant(c)).collect())
+    }
+
+    /// The outputs of `gate` by x + 0 = x, x * 0 = 0, x * 1 = x and x = x, and the constants that
+    /// are in the circuit already
+    fn simplify(&self, gate: &GateType, inputs: &[Wire]) -> Option<Vec<Wire>> {
+        let [a, b] = [0, 1].map(|i| {
+            inputs
+                .get(i)
+                .and_then(|wire| self.values.get(wire))
+                .copied()
+        });
+        let is_zero = |x: Option<PastaFE>| x.is_some_and(|x| x.is_zero());
+        let is_one = |x: Option<PastaFE>| x.is_some_and(|x| x == PastaFE::one(x.fid));
+
+        let output = match gate {
+            GateType::Constant((), _, c) => *self.constants.get(&constant_key(*c))?,
+            GateType::Add(..) if is_zero(a) => inputs[1],
+            GateType::Add(..) if is_zero(b) => inputs[0],
+            GateType::Multiply(..) if is_zero(a) || is_one(b) => inputs[0],
+            GateType::Multiply(..) if is_zero(b) || is_one(a) => inputs[1],
+            GateType::Eq([wire, _], _) if inputs[0] == inputs[1] => {
+                self.spec.one[wire.fid as usize]
+            }
+            GateType::AssertEq(..) if inputs[0] == inputs[1] || (a.is_some() && a == b) => {
+                return Some(Vec::new());
+            }
+            _ => return None,
+        };
+        Some(vec![output])
+    }
+
+    /// The outputs of a gate like `gate` over `inputs` that was added before, or else of `gate`
+    /// added to the new circuit
+    fn merge_or_add(&mut self, gate: &GateType, inputs: &[Wire]) -> Vec<Wire> {
+        let key = GateKey::new(gate, inputs);
+        if let Some(outputs) = key.as_ref().and_then(|key| self.gates.get(key)) {
+            self.changed = true;
+            return outputs.clone();
+        }
+        let outputs = self.add(gate, inputs);
+        if let Some(key) = key {
+            self.gates.insert(key, outputs.clone());
+        }
+        outputs
+    }
+
+    /// The outputs of `gate` added to the new circuit with `inputs`
+    fn add(&mut self, gate: &GateType, inputs: &[Wire]) -> Vec<Wire> {
+        if let GateType::Constant((), _, c) = *gate {
+            return vec![self.constant(c)];
+        }
+        let spec = &mut self.spec;
+        let w = |i: usize| inputs[i];
+        match *gate {
+            GateType::Witness((), [wire]) => vec![spec.witness(wire.fid)],
+            GateType::PublicInput((), [wire]) => vec![spec.public_input(wire.fid)],
+            GateType::WitnessBool((), [wire]) => vec![spec.witness_bool(wire.fid)],
+            GateType::Constant(..) => unreachable!("Constants are added above"),
+            GateType::Output(..) => {
+                spec.output_gate(w(0));
+                Vec::new()
+            }
+            GateType::Print(_, (), (label_1, label_2)) => {
+                spec.print(w(0), label_1, label_2);
+                Vec::new()
+            }
+            GateType::ScalarMulPallas(..) => {
+                let (x, y) = spec.scalar_mul_pallas((w(0), w(1)), (w(2), w(3)));
+                vec![x, y]
+            }
+            GateType::ScalarMulVesta(..) => {
+                let (x, y) = spec.scalar_mul_vesta(w(0), (w(1), w(2)));
+                vec![x, y]
+            }
+            GateType::FpMessagePass(..) => {
+                let (low, high) = spec.fp_message_pass(w(0));
+                vec![low, high]
+            }
+            GateType::FqMessagePass(..) => vec![spec.fq_message_pass(w(0))],
+            GateType::FpBoolMessagePass(..) => vec![spec.fp_bool_message_pass(w(0))],
+            GateType::FqBoolMessagePass(..) => vec![spec.fq_bool_message_pass(w(0))],
+            GateType::Invert(..) => vec![spec.inv(w(0))],
+            GateType::Negate(..) => vec![spec.neg_gate(w(0))],
+            GateType::AssertEq(..) => {
+                spec.assert_eq_gate(w(0), w(1));
+                Vec::new()
+            }
+            GateType::Add(..) => vec![spec.add_gate(w(0), w(1))],
+            GateType::Multiply(..) => vec![spec.mul_gate(w(0), w(1))],
+            GateType::PoseidonEnd(..) => spec.poseidon_finish(array::from_fn(w)).to_vec(),
+            GateType::Poseidon(_, _, round_constants) => spec
+                .poseidon_gate(array::from_fn(w), round_constants)
+                .to_vec(),
+            GateType::AffineAdd(..) => {
+                let (x, y) = spec.add_points((w(0), w(1)), (w(2), w(3)));
+                vec![x, y]
+            }
+            GateType::Eq(..) => vec![spec.eq_gate(w(0), w(1))],
+            GateType::Bit(_, _, i) => vec![spec.bit_gate(w(0), i)],
+            GateType::PackBits(_, _, k) => vec![spec.pack_bits(w(0), &inputs[1..], k)],
+            GateType::Lookup(_, (), table) => {
+                spec.lookup(table, &inputs[..table.arity()]);
+                Vec::new()
+            }
+        }
+    }
+}
+
+/// The outputs of `gate` for the values of its inputs, for the gates that only compute them.
+/// The inverse of zero is left to fail in the trace.
+fn evaluate(gate: &GateType, values: &[PastaFE]) -> Option<Vec<PastaFE>> {
+    let outputs = match gate {
+        GateType::Add(..) => vec![values[0] + values[1]],
+        GateType::Negate(..) => vec![-values[0]],
+        GateType::Multiply(..) => vec![values[0] * values[1]],
+        GateType::Invert(..) => vec![values[0].inverse()?],
+        GateType::Eq([wire, _], _) => {
+            vec![PastaFE::from_bool(values[0] == values[1], Some(wire.fid))]
+        }
+        GateType::Poseidon([wire, ..], _, r) => {
+            let mut w = (values[0], values[1], values[2]);
+            for r in r.chunks(3) {
+                w = poseidon_round(wire.fid, r[0], r[1], r[2], w.0, w.1, w.2);
+            }
+            vec![w.0, w.1, w.2]
+        }
+        GateType::PoseidonEnd(..) => values.to_vec(),
+        _ => return None,
+    };
+    Some(outputs)
+}
+
+fn constant_key(c: PastaFE) -> (PastaFieldId, BigInt<4>) {
+    (c.fid.expect("Constants have a field"), c.into_bigint())
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use halo_group::{Fp, PastaFE, PastaFieldId, ark_ff::UniformRand, ark_std::test_rng};
+
+    use crate::{
+        circuit::{CircuitSpec, GateType, TraceBuilder},
+        plonk::PlonkProof,
+    };
+
+    #[test]
+    fn folds_merges_and_removes() -> Result<()> {
+        let rng = &mut test_rng();
+        let fid = PastaFieldId::Fp;
+        let mut spec = CircuitSpec::new();
+        let (zero, one) = (spec.zero[fid as usize], spec.one[fid as usize]);
+        let x = spec.witness(fid);
+        let y = spec.public_input(fid);
+
+        // 2 * 3 + 1 folds to 7, as 2 and 3 have no other use
+        let [two, three] = [2, 3].map(|c| spec.constant(PastaFE::from(Fp::from(c))));
+        let six = spec.mul_gate(two, three);
+        let seven = spec.add_gate(six, one);
+        let out = spec.mul_gate(seven, x);
+        spec.output_gate(out);
+
+        // x * y and y * x are merged, which makes the assertion hold
+        let xy = spec.mul_gate(x, y);
+        let yx = spec.mul_gate(y, x);
+        let out = spec.add_gate(xy, yx);
+        spec.output_gate(out);
+        spec.assert_eq_gate(xy, yx);
+
+        // x * 1 + 0 is x
+        let x1 = spec.mul_gate(x, one);
+        let out = spec.add_gate(x1, zero);
+        spec.output_gate(out);
+
+        // Gates that reach no output and constants that are asserted equal
+        let sum = spec.add_gate(x, y);
+        spec.mul_gate(sum, sum);
+        let [five, five_again] = [5, 5].map(|c| spec.constant(PastaFE::from(Fp::from(c))));
+        spec.assert_eq_gate(five, five_again);
+
+        let (optimized, inputs) = spec.optimize();
+        assert_eq!(optimized.generic_gate_count[fid as usize], [1, 2, 0]);
+        let constants = optimized
+            .graph
+            .node_weights()
+            .filter(|gate| matches!(gate, GateType::Constant((), [wire], _) if wire.fid == fid))
+            .count();
+        assert_eq!(constants, 3);
+        assert!(optimized.used_rows(fid) < spec.used_rows(fid));
+        assert_eq!(inputs.len(), 2);
+
+        // The optimized circuit computes the same outputs from the same inputs
+        let (x_v, y_v) = (Fp::rand(rng), Fp::rand(rng));
+        let expected = vec![Fp::from(7) * x_v, Fp::from(2) * x_v * y_v, x_v];
+        for mut trace_builder in [TraceBuilder::new(spec.clone()), TraceBuilder::unoptimized(spec)]
+        {
+            trace_builder.witness(x, PastaFE::from(x_v))?;
+            trace_builder.public_input(y, PastaFE::from(y_v))?;
+            assert!(trace_builder.witness(out, PastaFE::from(x_v)).is_err());
+
+            let (fp_trace, _) = trace_builder.trace(None, None)?;
+            assert_eq!(fp_trace.outputs, expected);
+            assert_eq!(fp_trace.public_inputs, vec![y_v]);
+            let (circuit, x, w) = fp_trace.consume();
+            PlonkProof::naive_prover(rng, circuit, &x, w).verify(circuit, &x)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn optimize_is_idempotent() {
+        let fid = PastaFieldId::Fp;
+        let mut spec = CircuitSpec::new();
+        let x = spec.witness(fid);
+        let y = spec.witness(fid);
+        let one = spec.one[fid as usize];
+        let sum = spec.add_gate(x, y);
+        let sum_again = spec.add_gate(y, x);
+        let product = spec.mul_gate(sum, sum_again);
+        let out = spec.mul_gate(product, one);
+        spec.output_gate(out);
+
+        let (optimized, _) = spec.optimize();
+        let (again, inputs) = optimized.optimize();
+        assert_eq!(again.digest(), optimized.digest());
+        // The inputs keep their positions, in the circuit of the new spec
+        assert!(inputs.iter().all(|(old, new)| {
+            (old.id, old.node_idx, old.output_id) == (new.id, new.node_idx, new.output_id)
+                && new.circuit_id == again.id
+        }));
+        assert!(optimized.digest() != spec.digest());
+        // The trace and its keys are identified by the circuit that is laid out
+        assert_eq!(TraceBuilder::new(spec).digest(), optimized.digest());
+    }
+}
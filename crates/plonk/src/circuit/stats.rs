@@ -0,0 +1,189 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use halo_group::PastaFieldId;
+use serde::Serialize;
+
+use crate::circuit::{CircuitSpec, GateType};
+
+/// The gates, rows and inputs of a circuit in each field, see `CircuitSpec::stats`. It is
+/// printed as a table by `Display`, and as JSON by `CircuitStats::to_json`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct CircuitStats {
+    pub fp: FieldStats,
+    pub fq: FieldStats,
+}
+
+/// The part of `CircuitStats` that is laid out in the trace of one field
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct FieldStats {
+    /// The number of gates of every `GateType` variant, by name
+    pub gates: BTreeMap<&'static str, usize>,
+    /// The number of gates of the variants used in each scope, by the path of nested scope
+    /// names such as "ivc/plonk verifier", see `Frontend::scope`. Gates outside of every scope
+    /// are only counted in `gates`.
+    pub scopes: BTreeMap<String, BTreeMap<&'static str, usize>>,
+    /// The rows used by the gates, see `CircuitSpec::used_rows`
+    pub rows: usize,
+    /// The rows of the trace, see `CircuitSpec::padded_row_count`
+    pub padded_rows: usize,
+    pub witness_wires: usize,
+    pub public_input_wires: usize,
+    pub message_pass_wires: usize,
+}
+
+impl CircuitStats {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("The stats are always serializable")
+    }
+}
+
+impl CircuitSpec {
+    /// The stats of the circuit as it is, before the optimizations of `TraceBuilder::new`. Those
+    /// of the trace are given by `Frontend::stats`.
+    pub fn stats(&self) -> CircuitStats {
+        let [fp, fq] = [PastaFieldId::Fp, PastaFieldId::Fq].map(|fid| {
+            let mut gates: BTreeMap<_, _> = GateType::NAMES.iter().map(|name| (*name, 0)).collect();
+            let mut scopes = BTreeMap::<_, BTreeMap<_, _>>::new();
+            for node_idx in self.graph.node_indices() {
+                let gate = &self.graph[node_idx];
+                if gate.fid() != fid {
+                    continue;
+                }
+                *gates.get_mut(gate.name()).unwrap() += 1;
+                let path = &self.scope_paths[self.scopes[node_idx.index()]];
+                if !path.is_empty() {
+                    let scope = scopes.entry(path.join("/")).or_default();
+                    *scope.entry(gate.name()).or_insert(0) += 1;
+                }
+            }
+
+            let i = fid as usize;
+            FieldStats {
+                gates,
+                scopes,
+                rows: self.used_rows(fid),
+                padded_rows: self.padded_row_count(fid),
+                witness_wires: self.witness_wire_count[i],
+                public_input_wires: self.public_input_wire_count[i],
+                message_pass_wires: self.message_pass_wire_count[i],
+            }
+        });
+        CircuitStats { fp, fq }
+    }
+}
+
+fn write_row(f: &mut fmt::Formatter<'_>, label: &str, [fp, fq]: [usize; 2]) -> fmt::Result {
+    writeln!(f, "{label:<24}{fp:>12}{fq:>12}")
+}
+
+/// The counts of the gate variants that are used in either field
+fn write_gates(
+    f: &mut fmt::Formatter<'_>,
+    gates: [Option<&BTreeMap<&'static str, usize>>; 2],
+) -> fmt::Result {
+    for name in GateType::NAMES {
+        let counts = gates.map(|gates| gates.and_then(|gates| gates.get(name)).map_or(0, |c| *c));
+        if counts != [0, 0] {
+            write_row(f, &format!("  {name}"), counts)?;
+        }
+    }
+    Ok(())
+}
+
+impl fmt::Display for CircuitStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fields = [&self.fp, &self.fq];
+        writeln!(f, "{:<24}{:>12}{:>12}", "", "Fp", "Fq")?;
+        write_row(f, "rows", fields.map(|stats| stats.rows))?;
+        write_row(f, "padded rows", fields.map(|stats| stats.padded_rows))?;
+        write_row(f, "witness wires", fields.map(|stats| stats.witness_wires))?;
+        write_row(
+            f,
+            "public input wires",
+            fields.map(|stats| stats.public_input_wires),
+        )?;
+        write_row(
+            f,
+            "message pass wires",
+            fields.map(|stats| stats.message_pass_wires),
+        )?;
+
+        writeln!(f, "gates")?;
+        write_gates(f, fields.map(|stats| Some(&stats.gates)))?;
+        let scopes: BTreeSet<_> = fields
+            .iter()
+            .flat_map(|stats| stats.scopes.keys())
+            .collect();
+        for scope in scopes {
+            writeln!(f, "gates in {scope}")?;
+            write_gates(f, fields.map(|stats| stats.scopes.get(scope)))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use halo_group::PastaFieldId;
+
+    use crate::circuit::{CircuitSpec, GateType};
+
+    #[test]
+    fn stats() {
+        let mut circuit = CircuitSpec::new();
+        let x = circuit.fp_witness();
+        let y = circuit.fq_public_input();
+        let root = circuit.enter_scope("outer");
+        let xx = circuit.mul_gate(x, x);
+        let outer = circuit.enter_scope("inner");
+        let yy = circuit.add_gate(y, y);
+        let z = circuit.fq_message_pass(yy);
+        circuit.scope = outer;
+        circuit.output_gate(xx);
+        circuit.scope = root;
+        circuit.output_gate(z);
+
+        let stats = circuit.stats();
+        assert_eq!(stats.fp.gates.len(), GateType::NAMES.len());
+        assert_eq!(stats.fp.gates["Constant"], 2);
+        assert_eq!(stats.fp.gates["Output"], 2);
+        assert_eq!(stats.fq.gates["Add"], 1);
+        // Message passes are laid out in the field of their output
+        assert_eq!(stats.fp.gates["FqMessagePass"], 1);
+        assert_eq!(stats.fq.gates["FqMessagePass"], 0);
+        assert_eq!(
+            stats.fp.scopes,
+            BTreeMap::from([
+                (
+                    "outer".to_string(),
+                    BTreeMap::from([("Multiply", 1), ("Output", 1)])
+                ),
+                (
+                    "outer/inner".to_string(),
+                    BTreeMap::from([("FqMessagePass", 1)])
+                ),
+            ])
+        );
+        assert_eq!(stats.fq.scopes["outer/inner"], BTreeMap::from([("Add", 1)]));
+        assert_eq!(stats.fp.witness_wires, 1);
+        assert_eq!(stats.fq.public_input_wires, 1);
+        assert_eq!(stats.fp.message_pass_wires, 1);
+        assert_eq!(stats.fp.rows, circuit.used_rows(PastaFieldId::Fp));
+        assert_eq!(stats.fp.padded_rows, 4);
+
+        let table = stats.to_string();
+        assert!(table.contains("gates in outer/inner"), "{table}");
+        let json: serde_json::Value = serde_json::from_str(&stats.to_json()).unwrap();
+        assert_eq!(json["fq"]["scopes"]["outer/inner"]["Add"], 1);
+        assert_eq!(json["fp"]["padded_rows"], 4);
+
+        // Only circuits with hiding proofs are padded to more rows
+        circuit.hiding = true;
+        assert_eq!(circuit.stats().fp.padded_rows, 64);
+    }
+}
@@ -1,14 +1,11 @@
 #![allow(non_snake_case)]
 
-use std::{array, time::Instant};
+use std::{array, collections::HashSet, sync::Arc, time::Instant};
 
-use anyhow::Result;
+use anyhow::{Result, bail, ensure};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use derivative::Derivative;
-use halo_accumulation::{
-    acc::Accumulator,
-    pcdl::{self, commit},
-    pedersen,
-};
+use halo_accumulation::{acc::Accumulator, pedersen};
 use halo_group::{
     Domain, Evals, PastaConfig, Point, Poly, PublicParams, Scalar,
     ark_ff::Field,
@@ -16,14 +13,23 @@ use halo_group::{
     ark_std::Zero,
 };
 use log::debug;
+use petgraph::graph::NodeIndex;
 
 use crate::{
-    circuit::SlotId,
-    utils::{Q_POLYS, R_POLYS, S_POLYS, W_POLYS},
+    circuit::{CircuitSpec, PlonkCircuitPolys, ProvingKey, SlotId},
+    plonk::gate_terms_generic,
+    utils::{
+        GENERIC_GATES, L_POLYS, LOOKUP_WIRES, Q_POLYS, R_POLYS, S_POLYS, W_POLYS,
+        permutation_shifts,
+    },
 };
 
-#[derive(Derivative, Copy, Clone, PartialEq, Eq)]
-#[derivative(Debug(bound = "Scalar<P>: std::fmt::Debug"))]
+#[derive(Derivative, Copy, Clone, CanonicalSerialize, CanonicalDeserialize)]
+#[derivative(
+    Debug(bound = "Scalar<P>: std::fmt::Debug"),
+    PartialEq(bound = ""),
+    Eq(bound = "")
+)]
 pub struct PlonkCircuitCommitments<P: PastaConfig> {
     pub qs: [Point<P>; Q_POLYS],
     pub rs: [Point<P>; R_POLYS],
@@ -31,19 +37,33 @@ pub struct PlonkCircuitCommitments<P: PastaConfig> {
     pub sigmas: [Point<P>; S_POLYS],
 }
 
-#[derive(Clone)]
+/// Commitments to the lookup selector q_K, the table-id selector q_T and the table polynomials
+#[derive(Derivative, Copy, Clone, CanonicalSerialize, CanonicalDeserialize)]
+#[derivative(
+    Debug(bound = "Scalar<P>: std::fmt::Debug"),
+    PartialEq(bound = ""),
+    Eq(bound = "")
+)]
+pub struct PlonkCircuitLookupCommitments<P: PastaConfig> {
+    pub qs: [Point<P>; 2],
+    pub tables: [Point<P>; L_POLYS],
+}
+
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
 pub struct PlonkPublicInputs<P: PastaConfig> {
     pub public_inputs: Vec<Scalar<P>>,
     pub acc_prev: Accumulator<P>,
 }
 
-#[derive(Derivative, Copy, Clone, PartialEq, Eq)]
+#[derive(Derivative, Copy, Clone, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
 #[derivative(Debug(bound = "Scalar<P>: std::fmt::Debug"))]
 pub struct PlonkCircuit<P: PastaConfig> {
     pub rows: usize,
     pub public_input_count: usize,
     pub omega: Scalar<P>,
     pub Cs: PlonkCircuitCommitments<P>,
+    /// `None` if the circuit has no lookup gates
+    pub lookup: Option<PlonkCircuitLookupCommitments<P>>,
 }
 
 #[derive(Clone)]
@@ -53,6 +73,28 @@ pub struct PlonkWitnessPolys<P: PastaConfig> {
     pub rs: [Poly<P>; R_POLYS],
     pub ids: [Poly<P>; S_POLYS],
     pub sigmas: [Poly<P>; S_POLYS],
+    pub lookup: Option<PlonkWitnessLookupPolys<P>>,
+}
+
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct PlonkWitnessLookupPolys<P: PastaConfig> {
+    pub qs: [Poly<P>; 2],
+    pub tables: [Poly<P>; L_POLYS],
+}
+
+/// The lookup selectors `[q_K, q_T]` and table columns, as evaluations over the domain
+pub type LookupColumns<P> = ([Vec<Scalar<P>>; 2], [Vec<Scalar<P>>; L_POLYS]);
+
+/// The columns of a trace laid out by `TraceBuilder`, as evaluations over the domain, together
+/// with the copy constraints between their slots and the gates of each row
+pub struct TraceColumns<P: PastaConfig> {
+    pub ws: [Vec<Scalar<P>>; W_POLYS],
+    pub rs: [Vec<Scalar<P>>; R_POLYS],
+    pub qs: [Vec<Scalar<P>>; Q_POLYS],
+    /// The slots of each wire, which the permutation cycles through
+    pub copy_constraints: Vec<Vec<SlotId>>,
+    pub lookup: Option<LookupColumns<P>>,
+    pub gates: TraceGates,
 }
 
 #[derive(Clone)]
@@ -88,12 +130,15 @@ pub(crate) fn build_sigma<P: PastaConfig>(
         }
     }
 
+    // 4. Encode slot (i, j) as kⱼ ωⁱ⁺¹, the point row i is evaluated at, so that idⱼ(X) = kⱼ X
+    let shifts = permutation_shifts::<P>();
+    let omegas: Vec<_> = domain.elements().collect();
     let mut id_vecs: [Vec<_>; S_POLYS] = array::from_fn(|_| Vec::with_capacity(rows));
     let mut sigma_vecs: [Vec<_>; S_POLYS] = array::from_fn(|_| Vec::with_capacity(rows));
     for (i, (id_chunk, sigma_chunk)) in id.chunks(rows).zip(sigma.chunks(rows)).enumerate() {
         for (id, sigma) in id_chunk.iter().zip(sigma_chunk) {
-            id_vecs[i].push(id.to_scalar::<P>(rows));
-            sigma_vecs[i].push(sigma.to_scalar::<P>(rows));
+            id_vecs[i].push(id.to_scalar::<P>(&shifts, &omegas));
+            sigma_vecs[i].push(sigma.to_scalar::<P>(&shifts, &omegas));
         }
     }
 
@@ -104,6 +149,29 @@ pub(crate) fn build_sigma<P: PastaConfig>(
     )
 }
 
+/// The gates that each row of a trace was laid out for, see `Trace::check_constraints`
+#[derive(Clone)]
+pub struct TraceGates {
+    pub(crate) spec: Arc<CircuitSpec>,
+    /// The nodes of `spec` in each row, in the order of their positions in the row
+    pub(crate) rows: Vec<Vec<NodeIndex>>,
+}
+impl TraceGates {
+    /// The gate of the slot in `column` of `row`, and the call site that added it
+    fn describe(&self, row: usize, column: usize) -> String {
+        let nodes = &self.rows[row];
+        // Only generic gates share a row, and they take three columns each
+        match nodes.get((column / 3).min(nodes.len().saturating_sub(1))) {
+            Some(node_idx) => format!(
+                "{} added at {}",
+                self.spec.graph[*node_idx],
+                self.spec.location(*node_idx)
+            ),
+            None => "no gate".to_string(),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Trace<P: PastaConfig> {
     pub rows: usize,
@@ -129,71 +197,103 @@ pub struct Trace<P: PastaConfig> {
     pub(crate) w_polys: [Poly<P>; W_POLYS],
     // pub(crate) r_evals: [Evals<P>; R_POLYS],
     pub(crate) r_polys: [Poly<P>; R_POLYS],
+    pub(crate) lookup_polys: Option<PlonkWitnessLookupPolys<P>>,
+    pub(crate) C_lookup: Option<PlonkCircuitLookupCommitments<P>>,
     pub(crate) acc_prev: Accumulator<P>,
     pub(crate) message_pass_inputs: Vec<Scalar<P>>,
+    pub(crate) gates: TraceGates,
 }
 
 impl<P: PastaConfig> Trace<P> {
+    /// The trace of `columns`, of as many rows as the columns have evaluations
     pub fn new(
-        copy_constraints: Vec<Vec<SlotId>>,
+        pp: &PublicParams<P>,
+        columns: TraceColumns<P>,
         public_inputs: Vec<Scalar<P>>,
-        ws: [Vec<Scalar<P>>; W_POLYS],
-        rs: [Vec<Scalar<P>>; R_POLYS],
-        qs: [Vec<Scalar<P>>; Q_POLYS],
         outputs: Vec<P::ScalarField>,
-        n: usize,
         acc_prev: Accumulator<P>,
-        circuit: Option<PlonkCircuit<P>>,
+        key: Option<&ProvingKey<P>>,
         message_pass_inputs: Vec<Scalar<P>>,
     ) -> Self {
         let now = Instant::now();
 
-        let d = n - 1;
+        let TraceColumns {
+            ws,
+            rs,
+            qs,
+            copy_constraints,
+            lookup,
+            gates,
+        } = columns;
+        let n = ws[0].len();
         let domain = Domain::<P>::new(n).unwrap();
         let omega = domain.element(1);
 
-        let (sigma, id_evals, sigma_evals) = build_sigma::<P>(copy_constraints, domain);
-        debug!(
-            "build_pi time({}): {:?}",
-            P::SFID,
-            now.elapsed().as_secs_f32()
-        );
-
         let mut public_inputs_clone = public_inputs.clone();
         public_inputs_clone.resize(n, Scalar::<P>::zero());
         public_inputs_clone = public_inputs_clone.into_iter().map(|x| -x).collect();
         let public_inputs_evals = Evals::<P>::from_vec_and_domain(public_inputs_clone, domain);
 
         let w_evals = ws.map(|vec| Evals::<P>::from_vec_and_domain(vec, domain));
-        let r_evals = rs.map(|vec| Evals::<P>::from_vec_and_domain(vec, domain));
-        let q_evals = qs.map(|vec| Evals::<P>::from_vec_and_domain(vec, domain));
-
-        let id_polys: [Poly<P>; S_POLYS] = array::from_fn(|i| id_evals[i].interpolate_by_ref());
-        let sigma_polys: [Poly<P>; S_POLYS] =
-            array::from_fn(|i| sigma_evals[i].interpolate_by_ref());
         let w_polys: [Poly<P>; W_POLYS] = array::from_fn(|i| w_evals[i].interpolate_by_ref());
-        let r_polys: [Poly<P>; R_POLYS] = array::from_fn(|i| r_evals[i].interpolate_by_ref());
-        let q_polys: [Poly<P>; Q_POLYS] = array::from_fn(|i| q_evals[i].interpolate_by_ref());
         let public_inputs_poly = public_inputs_evals.interpolate_by_ref();
 
-        debug!(
-            "interpolate time({}): {:?}",
-            P::SFID,
-            now.elapsed().as_secs_f32()
-        );
-
-        let (C_qs, C_rs, C_ids, C_sigmas) = if let Some(circ) = circuit {
-            (circ.Cs.qs, circ.Cs.rs, circ.Cs.ids, circ.Cs.sigmas)
+        // Everything fixed by the circuit is taken from the proving key if there is one, so only
+        // the witness polynomials are interpolated
+        let (sigma, polys, Cs, C_lookup) = if let Some(key) = key {
+            assert_eq!(key.vk.circuit.rows, n);
+            (
+                key.sigma.clone(),
+                key.polys.clone(),
+                key.vk.circuit.Cs,
+                key.vk.circuit.lookup,
+            )
         } else {
-            let C_qs: [Point<P>; Q_POLYS] = array::from_fn(|i| pcdl::commit(&q_polys[i], d, None));
-            let C_rs: [Point<P>; R_POLYS] = array::from_fn(|i| pcdl::commit(&r_polys[i], d, None));
-            let C_ids: [Point<P>; S_POLYS] = array::from_fn(|i| commit(&id_polys[i], d, None));
-            let C_sigmas: [Point<P>; S_POLYS] =
-                array::from_fn(|i| commit(&sigma_polys[i], d, None));
-            (C_qs, C_rs, C_ids, C_sigmas)
+            let (sigma, id_evals, sigma_evals) = build_sigma::<P>(copy_constraints, domain);
+            debug!(
+                "build_pi time({}): {:?}",
+                P::SFID,
+                now.elapsed().as_secs_f32()
+            );
+
+            let r_evals = rs.map(|vec| Evals::<P>::from_vec_and_domain(vec, domain));
+            let q_evals = qs.map(|vec| Evals::<P>::from_vec_and_domain(vec, domain));
+            let polys = PlonkCircuitPolys {
+                qs: array::from_fn(|i| q_evals[i].interpolate_by_ref()),
+                rs: array::from_fn(|i| r_evals[i].interpolate_by_ref()),
+                ids: array::from_fn(|i| id_evals[i].interpolate_by_ref()),
+                sigmas: array::from_fn(|i| sigma_evals[i].interpolate_by_ref()),
+                lookup: lookup.map(|(qs, tables)| PlonkWitnessLookupPolys {
+                    qs: qs.map(|vec| Evals::<P>::from_vec_and_domain(vec, domain).interpolate()),
+                    tables: tables
+                        .map(|vec| Evals::<P>::from_vec_and_domain(vec, domain).interpolate()),
+                }),
+            };
+
+            debug!(
+                "interpolate time({}): {:?}",
+                P::SFID,
+                now.elapsed().as_secs_f32()
+            );
+
+            let (Cs, C_lookup) = polys.commit(pp, n);
+            (sigma, polys, Cs, C_lookup)
         };
-        let pp = PublicParams::get_pp();
-        let C_public_inputs: Point<P> = pedersen::commit(None, &pp.Gs, &public_inputs);
+        let PlonkCircuitPolys {
+            qs: q_polys,
+            rs: r_polys,
+            ids: id_polys,
+            sigmas: sigma_polys,
+            lookup: lookup_polys,
+        } = polys;
+        let PlonkCircuitCommitments {
+            qs: C_qs,
+            rs: C_rs,
+            ids: C_ids,
+            sigmas: C_sigmas,
+        } = Cs;
+        assert_eq!(C_lookup.is_some(), lookup_polys.is_some());
+        let C_public_inputs: Point<P> = pedersen::commit_with_pp(pp, None, &pp.Gs, &public_inputs);
 
         debug!(
             "commit time({}): {:?}",
@@ -226,8 +326,11 @@ impl<P: PastaConfig> Trace<P> {
             C_ids,
             C_sigmas,
             C_public_inputs,
+            lookup_polys,
+            C_lookup,
             acc_prev,
             message_pass_inputs,
+            gates,
         }
     }
 
@@ -251,8 +354,11 @@ impl<P: PastaConfig> Trace<P> {
             C_ids,
             C_sigmas,
             C_public_inputs,
+            lookup_polys,
+            C_lookup,
             acc_prev,
             message_pass_inputs,
+            gates: _,
         } = self;
 
         let Cs = PlonkCircuitCommitments {
@@ -267,6 +373,7 @@ impl<P: PastaConfig> Trace<P> {
             rows,
             omega,
             Cs,
+            lookup: C_lookup,
         };
 
         let plonk_public_inputs = PlonkPublicInputs {
@@ -280,6 +387,7 @@ impl<P: PastaConfig> Trace<P> {
             rs: r_polys,
             ids: id_polys,
             sigmas: sigma_polys,
+            lookup: lookup_polys,
         };
 
         let plonk_witness = PlonkWitness {
@@ -291,6 +399,106 @@ impl<P: PastaConfig> Trace<P> {
         (plonk_circuit, plonk_public_inputs, plonk_witness)
     }
 
+    /// Checks the gate constraints of every row and the copy constraints of every wire on the
+    /// assigned values. Unlike a failing `PlonkProof::verify`, the error names the first gate
+    /// that is not satisfied, with its row, wire values and the call site that added it, or the
+    /// first pair of slots that should be equal but are not.
+    pub fn check_constraints(&self) -> Result<()> {
+        let n = self.rows;
+        let fid = P::SFID;
+        let zero = Scalar::<P>::zero();
+        // Row i of the trace is evaluated at ω^(i+1), see `Evals::from_vec_and_domain`
+        let at = |row: usize| (row + 1) % n;
+        let evals = |p: &Poly<P>| p.evaluate_over_domain_by_ref(self.domain).evals;
+        let qs: [Vec<Scalar<P>>; Q_POLYS] = array::from_fn(|j| evals(&self.q_polys[j]));
+        let rs: [Vec<Scalar<P>>; R_POLYS] = array::from_fn(|j| evals(&self.r_polys[j]));
+        let ws =
+            |row: usize| -> [Scalar<P>; W_POLYS] { array::from_fn(|j| self.w_evals[j][at(row)]) };
+        let fmt = |xs: &[Scalar<P>]| xs.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+
+        // The specialized gates, by selector, use the next row as well
+        let names = ["Poseidon", "affine add", "affine mul", "equality", "range check"];
+        for row in 0..n {
+            let w = ws(row);
+            let w_omega: [_; 3] = array::from_fn(|j| ws((row + 1) % n)[j]);
+            let q: [Scalar<P>; Q_POLYS] = array::from_fn(|j| qs[j][at(row)]);
+            let r = array::from_fn(|j| rs[j][at(row)]);
+
+            // q_l·a + q_r·b + q_o·c + q_m·a·b in every position, and q_c + PI in the first
+            for k in 0..GENERIC_GATES {
+                let [a, b, c] = array::from_fn(|i| w[3 * k + i]);
+                let mut value = q[0] * a + q[1] * b + q[2] * c + q[3] * a * b;
+                if k == 0 {
+                    value += q[4] - self.public_inputs.get(row).copied().unwrap_or(zero);
+                }
+                ensure!(
+                    value.is_zero(),
+                    "{fid}: The generic gate {} is not satisfied in row {row}, with [a, b, c] = \
+                     {:?} and [q_l, q_r, q_o, q_m, q_c] = {:?}",
+                    self.gates.describe(row, 3 * k),
+                    fmt(&[a, b, c]),
+                    fmt(&q[..5]),
+                );
+            }
+
+            let terms = gate_terms_generic(P::SCALAR_POSEIDON_MDS, r, w, w_omega, zero);
+            for (j, name) in (5..Q_POLYS).zip(names) {
+                ensure!(
+                    (q[j] * terms[j]).is_zero(),
+                    "{fid}: The {name} constraint of {} is not satisfied in row {row}, with wires \
+                     {:?} and {:?} in the next row",
+                    self.gates.describe(row, 0),
+                    fmt(&w),
+                    fmt(&w_omega),
+                );
+            }
+        }
+
+        // Every queried row must be an entry of the table with its id, see `lookup_columns`
+        if let Some(lookup) = &self.lookup_polys {
+            let [q_K, q_T] = lookup.qs.each_ref().map(evals);
+            let tables = lookup.tables.each_ref().map(evals);
+            let entries: HashSet<[Scalar<P>; L_POLYS]> = (0..n)
+                .map(|row| array::from_fn(|j| tables[j][row]))
+                .collect();
+            for row in (0..n).filter(|row| !q_K[at(*row)].is_zero()) {
+                let w = ws(row);
+                let query: [_; L_POLYS] = array::from_fn(|j| match j < LOOKUP_WIRES {
+                    true => w[j],
+                    false => q_T[at(row)],
+                });
+                ensure!(
+                    entries.contains(&query),
+                    "{fid}: The lookup {} is not in its table in row {row}, with wires {:?}",
+                    self.gates.describe(row, 0),
+                    fmt(&query[..LOOKUP_WIRES]),
+                );
+            }
+        }
+
+        for (i, sigma) in self.sigma.iter().enumerate() {
+            let id = SlotId::from_usize(i, n);
+            let value =
+                |slot: &SlotId| self.w_evals[slot.column_0_indexed()][at(slot.row_0_indexed())];
+            if value(&id) != value(sigma) {
+                let describe = |slot: &SlotId| {
+                    self.gates
+                        .describe(slot.row_0_indexed(), slot.column_0_indexed())
+                };
+                bail!(
+                    "{fid}: The copy constraint between slots {id:?} and {sigma:?} is not \
+                     satisfied, with {} in {id:?} of {} and {} in {sigma:?} of {}",
+                    value(&id),
+                    describe(&id),
+                    value(sigma),
+                    describe(sigma),
+                );
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn test_copy_constraints(&self) {
         for i in 0..self.sigma.len() {
             let id = SlotId::from_usize(i, self.rows);
@@ -307,12 +515,14 @@ impl<P: PastaConfig> Trace<P> {
 #[cfg(test)]
 mod tests {
     use crate::{
-        circuit::{CircuitSpec, GateType, TraceBuilder},
+        circuit::{CircuitSpec, GateType, InputKind, LookupTable, TraceBuilder, TraceError, Wire},
         plonk::PlonkProof,
+        utils::{HIDING_MIN_ROWS, R_POLYS, S_POLYS, permutation_shifts},
     };
     use anyhow::Result;
+    use halo_accumulation::acc::Accumulator;
     use halo_group::{
-        PallasConfig, PallasScalar, PastaConfig,
+        Fq, PallasConfig, PallasScalar, PastaConfig, PastaFieldId, PublicParams,
         ark_std::{
             rand::{Rng, thread_rng},
             test_rng,
@@ -364,6 +574,246 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_tampered_evals() -> Result<()> {
+        let mut circuit = CircuitSpec::new();
+        let x = circuit.fp_public_input();
+        let y = circuit.fp_witness();
+        let xy = circuit.mul_gate(x, y);
+        circuit.output_gate(xy);
+
+        let mut trace_builder = TraceBuilder::new(circuit);
+        trace_builder.public_input(x, scalar(7).into())?;
+        trace_builder.witness(y, scalar(6).into())?;
+        let (fp_trace, _) = trace_builder.trace(None, None)?;
+
+        let rng = &mut thread_rng();
+        let (circuit, public_inputs, witness) = fp_trace.consume();
+        // The verifier computes idⱼ(𝔷) = kⱼ 𝔷 itself
+        for (id, k) in witness
+            .polys
+            .ids
+            .iter()
+            .zip(permutation_shifts::<PallasConfig>())
+        {
+            assert_eq!(id.coeffs, [scalar(0), k]);
+        }
+        let pi = PlonkProof::naive_prover(rng, circuit, &public_inputs, witness);
+        pi.verify(circuit, &public_inputs)?;
+
+        // The selectors, T and z are only checked through the linearization polynomial, so
+        // changing any of the evaluations it is built from must be caught
+        let mut tampered = pi.clone();
+        tampered.vs.ws[1] += scalar(1);
+        assert!(tampered.verify(circuit, &public_inputs).is_err());
+        let mut tampered = pi.clone();
+        tampered.vs.sigmas[0] += scalar(1);
+        assert!(tampered.verify(circuit, &public_inputs).is_err());
+        // The round constants and permutation are opened along with the witness
+        for i in [0, R_POLYS - 1] {
+            let mut tampered = pi.clone();
+            tampered.vs.rs[i] += scalar(1);
+            assert!(tampered.verify(circuit, &public_inputs).is_err());
+        }
+        let mut tampered = pi.clone();
+        tampered.vs.sigmas[S_POLYS - 1] += scalar(1);
+        assert!(tampered.verify(circuit, &public_inputs).is_err());
+        let mut tampered = pi.clone();
+        tampered.vs.z_omega += scalar(1);
+        assert!(tampered.verify(circuit, &public_inputs).is_err());
+        let mut public_inputs = public_inputs;
+        public_inputs.public_inputs[0] = scalar(8);
+        assert!(pi.verify(circuit, &public_inputs).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_trace_errors() -> Result<()> {
+        let mut circuit = CircuitSpec::new();
+        let x = circuit.fp_public_input();
+        let y = circuit.fp_witness();
+        let z = circuit.fq_witness();
+        let xy = circuit.mul_gate(x, y);
+        circuit.output_gate(xy);
+        let err = |result: Result<_>| result.unwrap_err().downcast::<TraceError>().unwrap();
+
+        let mut trace_builder = TraceBuilder::new(circuit.clone());
+        assert_eq!(
+            err(trace_builder.witness(z, scalar(3).into())),
+            TraceError::WrongField {
+                wire: z,
+                value: PastaFieldId::Fp
+            }
+        );
+        assert_eq!(
+            err(trace_builder.witness(x, scalar(3).into())),
+            TraceError::NotAnInput {
+                wire: x,
+                expected: InputKind::Witness
+            }
+        );
+        assert_eq!(
+            err(trace_builder.public_input(xy, scalar(3).into())),
+            TraceError::NotAnInput {
+                wire: xy,
+                expected: InputKind::PublicInput
+            }
+        );
+        trace_builder.witness(y, scalar(3).into())?;
+        assert_eq!(
+            err(trace_builder.witness(y, scalar(4).into())),
+            TraceError::AlreadyAssigned { wire: y }
+        );
+
+        // The inputs of both fields are checked
+        assert_eq!(
+            err(trace_builder.trace(None, None).map(|_| ())),
+            TraceError::Unassigned { wires: vec![x, z] }
+        );
+        let mut trace_builder = TraceBuilder::new(circuit);
+        trace_builder.public_input(x, scalar(2).into())?;
+        trace_builder.witness(y, scalar(3).into())?;
+        assert_eq!(
+            err(trace_builder.trace(None, None).map(|_| ())),
+            TraceError::Unassigned { wires: vec![z] }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_constraints() -> Result<()> {
+        let mut circuit = CircuitSpec::new();
+        let x = circuit.fp_witness();
+        let y = circuit.fp_witness();
+        let z = circuit.fp_public_input();
+        let xy = circuit.mul_gate(x, y);
+        let x_plus_y = circuit.add_gate(x, y);
+        let line = line!() + 1;
+        circuit.assert_eq_gate(xy, z);
+        circuit.output_gate(x_plus_y);
+
+        let trace = |xy: u64| -> Result<_> {
+            let mut trace_builder = TraceBuilder::new(circuit.clone());
+            trace_builder.witness(x, scalar(6).into())?;
+            trace_builder.witness(y, scalar(7).into())?;
+            trace_builder.public_input(z, scalar(xy).into())?;
+            let (fp_trace, _) = trace_builder.trace(None, None)?;
+            Ok(fp_trace)
+        };
+        let fp_trace = trace(42)?;
+        fp_trace.check_constraints()?;
+
+        // The unsatisfied gate is named with the line that added it
+        let err = trace(43)?.check_constraints().unwrap_err().to_string();
+        assert!(err.contains("AssertEq"), "{err}");
+        assert!(err.contains(&format!("{}:{line}", file!())), "{err}");
+
+        // Shifting both a and c of the addition keeps its gate satisfied but not its copy of x
+        let mut tampered = fp_trace.clone();
+        let row = (0..tampered.rows)
+            .find(|row| {
+                let gates = &tampered.gates;
+                gates.rows[*row]
+                    .iter()
+                    .any(|node| matches!(gates.spec.graph[*node], GateType::Add(..)))
+            })
+            .unwrap();
+        let i = (row + 1) % tampered.rows;
+        tampered.w_evals[0].evals.evals[i] += scalar(1);
+        tampered.w_evals[2].evals.evals[i] += scalar(1);
+        let err = tampered.check_constraints().unwrap_err().to_string();
+        assert!(err.contains("copy constraint"), "{err}");
+        assert!(err.contains("Add"), "{err}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_batch_verify() -> Result<()> {
+        let rng = &mut thread_rng();
+        let mut prove = |x: u64, y: u64, accs_prev| -> Result<_> {
+            let mut circuit = CircuitSpec::new();
+            let xw = circuit.fp_public_input();
+            let yw = circuit.fp_witness();
+            let xy = circuit.mul_gate(xw, yw);
+            circuit.output_gate(xy);
+
+            let mut trace_builder = TraceBuilder::new(circuit);
+            trace_builder.public_input(xw, scalar(x).into())?;
+            trace_builder.witness(yw, scalar(y).into())?;
+            let (fp_trace, _) = trace_builder.trace(accs_prev, None)?;
+            let (circuit, public_inputs, witness) = fp_trace.consume();
+            let pi = PlonkProof::naive_prover(rng, circuit, &public_inputs, witness);
+            Ok((circuit, public_inputs, pi))
+        };
+        let mut proofs = vec![prove(7, 6, None)?, prove(2, 3, None)?, prove(5, 11, None)?];
+        let rows = proofs[0].0.rows;
+        let invalid = prove(
+            5,
+            11,
+            Some((
+                Accumulator::zero_invalid(rows),
+                Accumulator::zero_invalid(rows),
+            )),
+        )?;
+
+        let rng = &mut thread_rng();
+        PlonkProof::batch_verify(rng, &proofs)?;
+
+        // A proof failing its succinct check is reported by index
+        let mut tampered = proofs.clone();
+        tampered[1].2.vs.ws[0] += scalar(1);
+        let err = PlonkProof::batch_verify(rng, &tampered).unwrap_err();
+        assert!(err.to_string().contains("Proof 1"), "{err}");
+
+        // So is a proof that only the decider rejects, since it accumulates an invalid acc_prev
+        invalid.2.verify_succinct(invalid.0, &invalid.1)?;
+        proofs[2] = invalid;
+        let err = PlonkProof::batch_verify(rng, &proofs).unwrap_err();
+        assert!(err.to_string().contains("Proof 2"), "{err}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_explicit_pp() -> Result<()> {
+        let mut circuit = CircuitSpec::new();
+        let x = circuit.fp_public_input();
+        let y = circuit.fp_witness();
+        let xy = circuit.mul_gate(x, y);
+        circuit.output_gate(xy);
+
+        let mut trace_builder = TraceBuilder::new(circuit);
+        trace_builder.public_input(x, scalar(7).into())?;
+        trace_builder.witness(y, scalar(6).into())?;
+        let (fp_trace, _) = trace_builder.trace(None, None)?;
+
+        // Two independent parameter sets of different sizes can be used side by side
+        let rng = &mut thread_rng();
+        let (circuit, public_inputs, witness) = fp_trace.consume();
+        let pp_small = PublicParams::new(circuit.rows);
+        let pp_large = PublicParams::new(circuit.rows * 4);
+        let pi = PlonkProof::naive_prover_with_pp(rng, &pp_small, circuit, &public_inputs, witness);
+        pi.verify_with_pp(&pp_small, circuit, &public_inputs)?;
+        pi.verify_with_pp(&pp_large, circuit, &public_inputs)?;
+        PlonkProof::batch_verify_with_pp(
+            rng,
+            &pp_large,
+            &[(circuit, public_inputs.clone(), pi.clone())],
+        )?;
+
+        // But parameters too small for the circuit are rejected
+        let pp_tiny = PublicParams::new(circuit.rows / 2);
+        assert!(
+            pi.verify_with_pp(&pp_tiny, circuit, &public_inputs)
+                .is_err()
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_circuit_eval2() -> Result<()> {
         // Create circuit: (x1 + x2) * x3
@@ -432,6 +882,109 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_circuit_eval_hiding() -> Result<()> {
+        let mut circuit = CircuitSpec::new();
+        let x7 = circuit.fp_public_input();
+        let x2 = circuit.fp_witness();
+        let x3 = circuit.fp_witness();
+        let a5 = circuit.add_gate(x2, x3);
+        let mul35 = circuit.mul_gate(x7, a5);
+        circuit.output_gate(mul35);
+
+        circuit.hiding = true;
+
+        let mut trace_builder = TraceBuilder::new(circuit);
+        trace_builder.witness(x2, scalar(2).into())?;
+        trace_builder.witness(x3, scalar(3).into())?;
+        trace_builder.public_input(x7, scalar(7).into())?;
+        let (fp_trace, fq_trace) = trace_builder.trace(None, None)?;
+
+        assert_eq!(vec![scalar(35)], fp_trace.outputs);
+
+        let rng = &mut thread_rng();
+        let (circuit, x, w) = fp_trace.consume();
+        let pi = PlonkProof::naive_prover_hiding(rng, circuit, &x, w)?;
+        assert!(pi.Cs.hiding.is_some());
+        assert!(pi.pis.r.C_bar.is_some() && pi.pis.r_omega.C_bar.is_some());
+        pi.verify(circuit, &x)?;
+        let (circuit, x, w) = fq_trace.consume();
+        PlonkProof::naive_prover_hiding(rng, circuit, &x, w)?.verify(circuit, &x)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_circuit_eval_assert_neq_hiding() -> Result<()> {
+        let mut circuit = CircuitSpec::new();
+        let x = circuit.fp_witness();
+        let xx = circuit.mul_gate(x, x);
+        let y = circuit.fp_witness();
+        circuit.assert_eq_gate(xx, y);
+
+        circuit.hiding = true;
+
+        let mut trace_builder = TraceBuilder::new(circuit);
+        trace_builder.witness(x, scalar(3).into())?;
+        trace_builder.witness(y, scalar(5).into())?;
+        let (fp_trace, _) = trace_builder.trace(None, None)?;
+
+        let rng = &mut thread_rng();
+        let (circuit, x, w) = fp_trace.consume();
+        assert!(
+            PlonkProof::naive_prover_hiding(rng, circuit, &x, w)?
+                .verify(circuit, &x)
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hiding_needs_min_rows() -> Result<()> {
+        let mut circuit = CircuitSpec::new();
+        let x = circuit.fp_witness();
+        circuit.output_gate(x);
+
+        let mut trace_builder = TraceBuilder::new(circuit);
+        trace_builder.witness(x, scalar(3).into())?;
+        let (fp_trace, _) = trace_builder.trace(None, None)?;
+
+        let (circuit, x, w) = fp_trace.consume();
+        assert!(circuit.rows < HIDING_MIN_ROWS);
+        let err = PlonkProof::naive_prover_hiding(&mut thread_rng(), circuit, &x, w).err();
+        assert!(err.is_some_and(|err| err.to_string().contains("rows")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_poseidon_hiding() -> Result<()> {
+        let rng = &mut test_rng();
+        let mut circuit = CircuitSpec::new();
+        let x1 = circuit.fp_witness();
+        let x2 = circuit.fp_witness();
+        let x3 = circuit.fp_witness();
+        let [p0, p1, p2] = circuit.poseidon(0, [x1, x2, x3]);
+        let [p3, _, _] = circuit.poseidon_finish([p0, p1, p2]);
+        circuit.output_gate(p3);
+
+        circuit.hiding = true;
+
+        let mut trace_builder = TraceBuilder::new(circuit);
+        trace_builder.witness(x1, scalar(rng.gen_range(1..10)).into())?;
+        trace_builder.witness(x2, scalar(rng.gen_range(1..10)).into())?;
+        trace_builder.witness(x3, scalar(rng.gen_range(1..10)).into())?;
+
+        let (fp_trace, fq_trace) = trace_builder.trace(None, None)?;
+        let (circuit, x, w) = fp_trace.consume();
+        PlonkProof::naive_prover_hiding(rng, circuit, &x, w)?.verify(circuit, &x)?;
+        let (circuit, x, w) = fq_trace.consume();
+        PlonkProof::naive_prover_hiding(rng, circuit, &x, w)?.verify(circuit, &x)?;
+
+        Ok(())
+    }
+
     #[test]
     fn test_circuit_eval_assert_neq() -> Result<()> {
         // Create circuit: (x1 + x2) * x3
@@ -529,4 +1082,118 @@ mod tests {
 
         Ok(())
     }
+
+    fn byte_to_bits_circuit() -> (CircuitSpec, Wire, Vec<Wire>) {
+        let mut circuit = CircuitSpec::new();
+        let x = circuit.fp_witness();
+        let bits: Vec<_> = (0..8).map(|_| circuit.fp_witness()).collect();
+        for (i, bit) in bits.iter().enumerate() {
+            let i = circuit.constant(scalar(i as u64).into());
+            circuit.lookup(LookupTable::ByteToBits, &[x, i, *bit]);
+        }
+        (circuit, x, bits)
+    }
+
+    #[test]
+    fn test_lookup_byte_to_bits() -> Result<()> {
+        let rng = &mut thread_rng();
+        let byte = 0b0110_1001;
+
+        let (circuit, x, bits) = byte_to_bits_circuit();
+        let mut trace_builder = TraceBuilder::new(circuit);
+        trace_builder.witness(x, scalar(byte).into())?;
+        for (i, bit) in bits.iter().enumerate() {
+            trace_builder.witness(*bit, scalar((byte >> i) & 1).into())?;
+        }
+        let (fp_trace, fq_trace) = trace_builder.trace(None, None)?;
+        assert_eq!(fp_trace.rows, LookupTable::ByteToBits.size());
+
+        let (circuit, x, w) = fp_trace.consume();
+        assert!(circuit.lookup.is_some());
+        PlonkProof::naive_prover(rng, circuit, &x, w).verify(circuit, &x)?;
+        let (circuit, x, w) = fq_trace.consume();
+        assert!(circuit.lookup.is_none());
+        PlonkProof::naive_prover(rng, circuit, &x, w).verify(circuit, &x)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lookup_byte_to_bits_hiding() -> Result<()> {
+        let rng = &mut thread_rng();
+        let byte = 0b1100_0101;
+
+        let (mut circuit, x, bits) = byte_to_bits_circuit();
+        circuit.hiding = true;
+        let mut trace_builder = TraceBuilder::new(circuit);
+        trace_builder.witness(x, scalar(byte).into())?;
+        for (i, bit) in bits.iter().enumerate() {
+            trace_builder.witness(*bit, scalar((byte >> i) & 1).into())?;
+        }
+        let (fp_trace, _) = trace_builder.trace(None, None)?;
+
+        let (circuit, x, w) = fp_trace.consume();
+        let pi = PlonkProof::naive_prover_hiding(rng, circuit, &x, w)?;
+        assert!(pi.Cs.lookup.as_ref().unwrap().hiding.is_some());
+        pi.verify(circuit, &x)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lookup_not_in_table() -> Result<()> {
+        let rng = &mut thread_rng();
+        let byte = 0b0110_1001;
+
+        let (circuit, x, bits) = byte_to_bits_circuit();
+        let mut trace_builder = TraceBuilder::new(circuit);
+        trace_builder.witness(x, scalar(byte).into())?;
+        for (i, bit) in bits.iter().enumerate() {
+            // Bit 3 is flipped
+            let b = ((byte >> i) & 1) ^ (i == 3) as u64;
+            trace_builder.witness(*bit, scalar(b).into())?;
+        }
+        let (fp_trace, _) = trace_builder.trace(None, None)?;
+        let err = fp_trace.check_constraints().unwrap_err().to_string();
+        assert!(err.contains("lookup"), "{err}");
+
+        let (circuit, x, w) = fp_trace.consume();
+        assert!(
+            PlonkProof::naive_prover(rng, circuit, &x, w)
+                .verify(circuit, &x)
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lookup_xor8_range16() -> Result<()> {
+        let rng = &mut thread_rng();
+        let (a, b, r) = (0xa7, 0x3c, 0xbeef);
+
+        let mut circuit = CircuitSpec::new();
+        let a_wire = circuit.fp_witness();
+        let b_wire = circuit.fp_witness();
+        let c_wire = circuit.fp_witness();
+        let r_wire = circuit.fq_witness();
+        circuit.lookup(LookupTable::Xor8, &[a_wire, b_wire, c_wire]);
+        circuit.lookup(LookupTable::Range16, &[r_wire]);
+
+        let mut trace_builder = TraceBuilder::new(circuit);
+        trace_builder.witness(a_wire, scalar(a).into())?;
+        trace_builder.witness(b_wire, scalar(b).into())?;
+        trace_builder.witness(c_wire, scalar(a ^ b).into())?;
+        trace_builder.witness(r_wire, Fq::from(r).into())?;
+        let (fp_trace, fq_trace) = trace_builder.trace(None, None)?;
+        assert_eq!(fp_trace.rows, 1 << 16);
+        assert_eq!(fq_trace.rows, 1 << 16);
+
+        let (circuit, x, w) = fp_trace.consume();
+        PlonkProof::naive_prover(rng, circuit, &x, w).verify(circuit, &x)?;
+        let (circuit, x, w) = fq_trace.consume();
+        PlonkProof::naive_prover(rng, circuit, &x, w).verify(circuit, &x)?;
+
+        Ok(())
+    }
 }
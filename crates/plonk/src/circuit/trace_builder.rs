@@ -1,45 +1,147 @@
-use std::{array, collections::HashMap, time::Instant};
+use std::{array, collections::HashMap, fmt, iter, sync::Arc, time::Instant};
 
-use anyhow::{Context, Result, anyhow, bail, ensure};
+use anyhow::{Context, Result, ensure};
 use halo_accumulation::acc::Accumulator;
 use halo_group::{
-    Fp, Fq, PallasConfig, PastaAffine, PastaFE, PastaFieldId, VestaConfig,
+    Fp, Fq, PallasConfig, PastaAffine, PastaFE, PastaFieldId, PublicParams, VestaConfig,
     ark_ff::{BigInt, BigInteger, Field, PrimeField},
     ark_std::{One, Zero},
 };
 use log::debug;
-use petgraph::algo::toposort;
+use petgraph::graph::NodeIndex;
 
 use crate::{
-    circuit::{CircuitSpec, GateType, PlonkCircuit, Trace, Wire},
-    utils::{MultiAssign, Q_POLYS, R_POLYS, W_POLYS},
+    circuit::{
+        CircuitDigest, CircuitSpec, GateType, GenericGate, LookupTable, ProvingKey, Trace,
+        TraceColumns, TraceGates, Wire,
+    },
+    utils::{
+        GENERIC_GATES, L_POLYS, LOOKUP_WIRES, MultiAssign, Q_POLYS, R_POLYS, S_POLYS, W_POLYS,
+    },
 };
 
 use super::SlotId;
 
+/// The kinds of wires that are assigned by the caller of `TraceBuilder`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputKind {
+    Witness,
+    PublicInput,
+}
+
+/// An input of the circuit that is assigned wrongly or not at all, returned by
+/// `TraceBuilder::witness`, `TraceBuilder::public_input` and `TraceBuilder::trace`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TraceError {
+    /// The wire is not an input of the circuit of the expected kind
+    NotAnInput { wire: Wire, expected: InputKind },
+    /// The value is an element of the other field than the wire
+    WrongField { wire: Wire, value: PastaFieldId },
+    /// The wire is assigned twice
+    AlreadyAssigned { wire: Wire },
+    /// The input wire does not map to an input of the optimized circuit, which is a bug of the
+    /// optimizer rather than of the caller
+    LostInput { wire: Wire },
+    /// The inputs of both fields that are not assigned, ordered by field and creation
+    Unassigned { wires: Vec<Wire> },
+    /// The number of assigned inputs of a kind differs from the number in the circuit
+    CountMismatch {
+        fid: PastaFieldId,
+        kind: InputKind,
+        expected: usize,
+        got: usize,
+    },
+}
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceError::NotAnInput { wire, expected } => {
+                write!(f, "The wire {wire:?} is not a {expected:?} wire")
+            }
+            TraceError::WrongField { wire, value } => {
+                write!(f, "The wire {wire:?} is assigned a value in {value}")
+            }
+            TraceError::AlreadyAssigned { wire } => {
+                write!(f, "The wire {wire:?} is already assigned")
+            }
+            TraceError::LostInput { wire } => write!(
+                f,
+                "The input wire {wire:?} is not an input of the optimized circuit, this should be impossible!"
+            ),
+            TraceError::Unassigned { wires } => {
+                write!(f, "{} input wires are unassigned: {wires:?}", wires.len())
+            }
+            TraceError::CountMismatch {
+                fid,
+                kind,
+                expected,
+                got,
+            } => write!(f, "{fid}: Expected {expected} {kind:?} wires, got {got}"),
+        }
+    }
+}
+impl std::error::Error for TraceError {}
+
+/// The number of gates in the last row of a kind of generic gate, and the slots of that row
+type GenericRow = Option<(usize, [SlotId; W_POLYS])>;
+
 pub struct TraceBuilder {
+    /// The optimized circuit, see `CircuitSpec::optimize`
     spec: CircuitSpec,
+    /// The digest of the circuit before optimization
+    digest: CircuitDigest,
+    /// The witness and public input wires of the circuit in the optimized one
+    inputs: HashMap<Wire, Wire>,
     witnesses: [HashMap<Wire, PastaFE>; 2],
     public_inputs: [HashMap<Wire, PastaFE>; 2],
     public_row_count: [usize; 2],
     message_pass_row_count: [usize; 2],
     row_count: [usize; 2],
+    /// For each kind of generic gate, the gates in its last row and the slots of that row
+    generic_rows: [[GenericRow; GenericGate::COUNT]; 2],
+    /// The gate being laid out, and the gates laid out in each row so far
+    node: NodeIndex,
+    row_gates: [Vec<Vec<NodeIndex>>; 2],
 }
 
 impl TraceBuilder {
+    pub fn digest(&self) -> CircuitDigest {
+        self.digest
+    }
+
     fn get_slot_ids(&mut self, fid: PastaFieldId) -> [SlotId; W_POLYS] {
         let row = self.row_count[fid as usize]
             + self.spec.public_input_wire_count[fid as usize]
             + self.spec.message_pass_wire_count[fid as usize];
         self.row_count[fid as usize] += 1;
+        self.row_gates[fid as usize][row].push(self.node);
         let f = |column| SlotId::new(row + 1, column + 1);
         let slot_ids = array::from_fn(f);
         slot_ids
     }
 
+    /// The slots [a, b, c] of a generic gate and its position k in the row. Generic gates fill
+    /// the last row of their kind before a new one is started, see `GenericGate`.
+    fn get_generic_slot_ids(
+        &mut self,
+        fid: PastaFieldId,
+        gate: GenericGate,
+    ) -> (usize, [SlotId; 3]) {
+        let (k, slots) = match self.generic_rows[fid as usize][gate as usize] {
+            Some((k, slots)) if k < GENERIC_GATES => {
+                self.row_gates[fid as usize][slots[0].row_0_indexed()].push(self.node);
+                (k, slots)
+            }
+            _ => (0, self.get_slot_ids(fid)),
+        };
+        self.generic_rows[fid as usize][gate as usize] = Some((k + 1, slots));
+        (k, array::from_fn(|i| slots[3 * k + i]))
+    }
+
     fn get_public_inputs_slot_ids(&mut self, fid: PastaFieldId) -> [SlotId; W_POLYS] {
         let row = self.public_row_count[fid as usize];
         self.public_row_count[fid as usize] += 1;
+        self.row_gates[fid as usize][row].push(self.node);
         let f = |column| SlotId::new(row + 1, column + 1);
         let slot_ids = array::from_fn(f);
         slot_ids
@@ -49,87 +151,165 @@ impl TraceBuilder {
         let row = self.message_pass_row_count[fid as usize]
             + self.spec.public_input_wire_count[fid as usize];
         self.message_pass_row_count[fid as usize] += 1;
+        self.row_gates[fid as usize][row].push(self.node);
         let f = |column| SlotId::new(row + 1, column + 1);
         let slot_ids = array::from_fn(f);
         slot_ids
     }
 
+    /// The trace builder of `spec` after `CircuitSpec::optimize`. Values are still assigned to
+    /// the wires of `spec`, but the digest is that of the optimized circuit that is traced.
     pub fn new(spec: CircuitSpec) -> Self {
+        let (optimized, inputs) = spec.optimize();
+        let digest = optimized.digest();
+        Self::with_inputs(optimized, digest, inputs)
+    }
+
+    /// The trace builder of `spec` as it is, for comparing against the optimized one
+    #[cfg(test)]
+    pub(crate) fn unoptimized(spec: CircuitSpec) -> Self {
+        let digest = spec.digest();
+        let inputs = spec.input_wires().map(|wire| (wire, wire)).collect();
+        Self::with_inputs(spec, digest, inputs)
+    }
+
+    fn with_inputs(spec: CircuitSpec, digest: CircuitDigest, inputs: HashMap<Wire, Wire>) -> Self {
         let witnesses = array::from_fn(|i| HashMap::with_capacity(spec.witness_wire_count[i]));
         let public_inputs =
             array::from_fn(|i| HashMap::with_capacity(spec.public_input_wire_count[i]));
+        let row_gates = [PastaFieldId::Fp, PastaFieldId::Fq]
+            .map(|fid| vec![Vec::new(); spec.padded_row_count(fid)]);
         Self {
             row_count: [0, 0],
             public_row_count: [0, 0],
             message_pass_row_count: [0, 0],
+            generic_rows: [[None; GenericGate::COUNT]; 2],
+            node: NodeIndex::end(),
+            row_gates,
             spec,
+            digest,
+            inputs,
             witnesses,
             public_inputs,
         }
     }
 
     pub fn witness(&mut self, wire: Wire, w: PastaFE) -> Result<()> {
-        let fid = wire.fid;
-        assert_eq!(fid, w.fid.unwrap());
-        match self.spec.graph.node_weight(wire.node_idx) {
-            Some(GateType::Witness(..)) => (),
-            Some(GateType::WitnessBool(..)) => (),
-            Some(_) => bail!("The provided wire was not a witness wire!"),
-            None => bail!("Wire does not exist, this should be impossible!"),
-        }
-        let already_assigned = self.witnesses[fid as usize].insert(wire, w).is_some();
-        if already_assigned {
-            bail!("Wire already assigned! ({wire:?}, {w:?})")
+        let fid = self.check_input(wire, w, InputKind::Witness)?;
+        let optimized = self.inputs[&wire];
+        if self.witnesses[fid as usize].insert(optimized, w).is_some() {
+            return Err(TraceError::AlreadyAssigned { wire }.into());
         };
         Ok(())
     }
 
     pub fn public_input(&mut self, wire: Wire, x: PastaFE) -> Result<()> {
-        let fid = wire.fid;
-        assert_eq!(fid, x.fid.unwrap());
-        match self.spec.graph.node_weight(wire.node_idx) {
-            Some(GateType::PublicInput(..)) => (),
-            Some(_) => bail!("The provided wire was not a public input wire!"),
-            None => bail!("Wire does not exist, this should be impossible!"),
-        }
-        let already_assigned = self.public_inputs[fid as usize].insert(wire, x).is_some();
-        if already_assigned {
-            bail!("Wire already assigned! ({wire:?}, {x:?})")
+        let fid = self.check_input(wire, x, InputKind::PublicInput)?;
+        let optimized = self.inputs[&wire];
+        if self.public_inputs[fid as usize]
+            .insert(optimized, x)
+            .is_some()
+        {
+            return Err(TraceError::AlreadyAssigned { wire }.into());
+        };
+        Ok(())
+    }
+
+    /// The field of `wire` if it is an input of kind `expected` that `value` can be assigned to.
+    /// Values without a field, such as small constants, fit either.
+    fn check_input(
+        &self,
+        wire: Wire,
+        value: PastaFE,
+        expected: InputKind,
+    ) -> Result<PastaFieldId, TraceError> {
+        let optimized = self
+            .inputs
+            .get(&wire)
+            .ok_or(TraceError::NotAnInput { wire, expected })?;
+        let kind = match self.spec.graph.node_weight(optimized.node_idx) {
+            Some(GateType::Witness(..) | GateType::WitnessBool(..)) => InputKind::Witness,
+            Some(GateType::PublicInput(..)) => InputKind::PublicInput,
+            _ => return Err(TraceError::LostInput { wire }),
         };
+        if kind != expected {
+            return Err(TraceError::NotAnInput { wire, expected });
+        }
+        match value.fid {
+            Some(fid) if fid != wire.fid => Err(TraceError::WrongField { wire, value: fid }),
+            _ => Ok(wire.fid),
+        }
+    }
+
+    /// Checks that every input of both fields is assigned, so that a missing one is reported
+    /// with the others rather than where the walk over the gates first needs it
+    fn check_assigned(&self) -> Result<(), TraceError> {
+        let mut unassigned: Vec<_> = self
+            .inputs
+            .iter()
+            .filter(|(_, optimized)| {
+                let fid = optimized.fid as usize;
+                !self.witnesses[fid].contains_key(optimized)
+                    && !self.public_inputs[fid].contains_key(optimized)
+            })
+            .map(|(wire, _)| *wire)
+            .collect();
+        if !unassigned.is_empty() {
+            unassigned.sort_by_key(|wire| (wire.fid as usize, wire.id));
+            return Err(TraceError::Unassigned { wires: unassigned });
+        }
+
+        for fid in [PastaFieldId::Fp, PastaFieldId::Fq] {
+            let i = fid as usize;
+            let counts = [
+                (
+                    InputKind::Witness,
+                    self.spec.witness_wire_count[i],
+                    self.witnesses[i].len(),
+                ),
+                (
+                    InputKind::PublicInput,
+                    self.spec.public_input_wire_count[i],
+                    self.public_inputs[i].len(),
+                ),
+            ];
+            for (kind, expected, got) in counts {
+                if expected != got {
+                    return Err(TraceError::CountMismatch {
+                        fid,
+                        kind,
+                        expected,
+                        got,
+                    });
+                }
+            }
+        }
 
         Ok(())
     }
 
     pub fn trace(
+        self,
+        accs_prev: Option<(Accumulator<PallasConfig>, Accumulator<VestaConfig>)>,
+        keys: Option<(&ProvingKey<PallasConfig>, &ProvingKey<VestaConfig>)>,
+    ) -> Result<(Trace<PallasConfig>, Trace<VestaConfig>)> {
+        let pps = (PublicParams::get_pp(), PublicParams::get_pp());
+        self.trace_with_pp(pps, accs_prev, keys)
+    }
+
+    /// Same as `trace`, committing to the circuit polynomials and public inputs with the public
+    /// parameters `pps` of each field instead of the global ones
+    pub fn trace_with_pp(
         mut self,
+        pps: (&PublicParams<PallasConfig>, &PublicParams<VestaConfig>),
         accs_prev: Option<(Accumulator<PallasConfig>, Accumulator<VestaConfig>)>,
-        static_circuits: Option<(PlonkCircuit<PallasConfig>, PlonkCircuit<VestaConfig>)>,
+        keys: Option<(&ProvingKey<PallasConfig>, &ProvingKey<VestaConfig>)>,
     ) -> Result<(Trace<PallasConfig>, Trace<VestaConfig>)> {
         let now = Instant::now();
-        let spec = self.spec.clone();
-
-        let row_counts: [usize; 2] =
-            array::from_fn(|i| spec.row_count[i].next_power_of_two().max(4));
-
-        for i in 0..1 {
-            let fid = if i == 0 {
-                PastaFieldId::Fp
-            } else {
-                PastaFieldId::Fq
-            };
-            ensure!(
-                self.witnesses[i].len() == spec.witness_wire_count[i],
-                "{fid} Expected {} witnesses, got {}",
-                spec.witness_wire_count[i],
-                self.witnesses[i].len()
-            );
-            ensure!(
-                self.public_inputs[i].len() == spec.public_input_wire_count[i],
-                "{fid} Expected {} public inputs, got {}",
-                spec.public_input_wire_count[i],
-                self.public_inputs[i].len()
-            );
-        }
+        self.check_assigned()?;
+        let spec = Arc::new(self.spec.clone());
+
+        let row_counts = [PastaFieldId::Fp, PastaFieldId::Fq].map(|fid| spec.padded_row_count(fid));
 
         let O = PastaFE::zero(None);
         let I = PastaFE::one(None);
@@ -148,9 +328,14 @@ impl TraceBuilder {
             array::from_fn(|_| vec![O; row_counts[0]]),
             array::from_fn(|_| vec![O; row_counts[1]]),
         ];
+        // [K, T], rows without a lookup gate query the all-zero entry of the first table
+        let mut lookup_qs: [[Vec<PastaFE>; 2]; 2] = array::from_fn(|i| {
+            let T = spec.lookup_tables[i]
+                .first()
+                .map_or(O, |table| PastaFE::from(table.id()));
+            [vec![O; row_counts[i]], vec![T; row_counts[i]]]
+        });
 
-        let topo_order =
-            toposort(&spec.graph, None).map_err(|_| anyhow!("Cycle detected {spec:?}"))?;
         let mut wire_vals = [
             vec![PastaFE::zero(None); spec.wire_count[0]],
             vec![PastaFE::zero(None); spec.wire_count[1]],
@@ -170,8 +355,42 @@ impl TraceBuilder {
             Vec::with_capacity(spec.message_pass_wire_count[1]),
         ];
 
-        // Evaluate and collect inputs/outputs for gates
-        for node_idx in topo_order {
+        // Public inputs have no incoming wires, so they are laid out first and in the order they
+        // were created, see `CircuitSpec::public_input_index`
+        for node_idx in spec.graph.node_indices() {
+            self.node = node_idx;
+            if let GateType::PublicInput(_, [out_wire]) = spec.graph[node_idx] {
+                let fid = out_wire.fid;
+                let slots = self.get_public_inputs_slot_ids(fid);
+                node_map.insert(node_idx, slots);
+
+                // ----- Values ----- //
+                let v = *self.public_inputs[fid as usize]
+                    .get(&out_wire)
+                    .context(format!("{fid}: Wire unassigned ({out_wire:?})!"))?;
+                public_inputs[fid as usize].push(v);
+                wire_vals[fid as usize][out_wire.id] = v;
+
+                // ----- Gate Constraints ----- //
+                let row = slots[0].row_0_indexed();
+                let (ws, qs) = (&mut ws[fid as usize], &mut qs[fid as usize]);
+                //                    [l, r, o, m, c, p, +, *, +, R]
+                let q: [_; Q_POLYS] = [I, O, O, O, O, O, O, O, O, O];
+                let w: [_; W_POLYS] = [v, O, O, O, O, O, O, O, O, O, O, O, O, O, O, O];
+                qs.multi_assign(row, q);
+                ws.multi_assign(row, w);
+
+                // ----- Copy Constraints ----- //
+                wire_output_slots[fid as usize][out_wire.id] = Some(slots[0]);
+                copy_constraints[fid as usize][out_wire.id].push(slots[0]);
+            }
+        }
+
+        // Evaluate and collect inputs/outputs for gates. Gates are added after their inputs, so
+        // the order they were added in is topological, and the message passed values are laid
+        // out in the order they were created.
+        for node_idx in spec.graph.node_indices() {
+            self.node = node_idx;
             match spec.graph[node_idx] {
                 GateType::Witness(_, [out_wire]) => {
                     let fid = out_wire.fid;
@@ -180,31 +399,7 @@ impl TraceBuilder {
                         .context("Wire unassigned!")?;
                     wire_vals[fid as usize][out_wire.id] = *v;
                 }
-                GateType::PublicInput(_, [out_wire]) => {
-                    let fid = out_wire.fid;
-                    let slots = self.get_public_inputs_slot_ids(fid);
-                    node_map.insert(node_idx, slots);
-
-                    // ----- Values ----- //
-                    let v = *self.public_inputs[fid as usize]
-                        .get(&out_wire)
-                        .context(format!("{fid}: Wire unassigned ({out_wire:?})!"))?;
-                    public_inputs[fid as usize].push(v);
-                    wire_vals[fid as usize][out_wire.id] = v;
-
-                    // ----- Gate Constraints ----- //
-                    let row = slots[0].row_0_indexed();
-                    let (ws, qs) = (&mut ws[fid as usize], &mut qs[fid as usize]);
-                    //                    [l, r, o, m, c, p, +, *, +, R]
-                    let q: [_; Q_POLYS] = [I, O, O, O, O, O, O, O, O, O];
-                    let w: [_; W_POLYS] = [v, O, O, O, O, O, O, O, O, O, O, O, O, O, O, O];
-                    qs.multi_assign(row, q);
-                    ws.multi_assign(row, w);
-
-                    // ----- Copy Constraints ----- //
-                    wire_output_slots[fid as usize][out_wire.id] = Some(slots[0]);
-                    copy_constraints[fid as usize][out_wire.id].push(slots[0]);
-                }
+                GateType::PublicInput(..) => (),
                 GateType::Constant((), [out_wire], c) => {
                     let fid = out_wire.fid;
 
@@ -238,13 +433,13 @@ impl TraceBuilder {
                     let fid_idx = left_wire.fid as usize;
 
                     // ----- Copy Constraints ----- //
-                    let slots = self.get_slot_ids(fid);
+                    let (k, slots) = self.get_generic_slot_ids(fid, GenericGate::AssertEq);
                     let l = wire_vals[fid as usize][left_wire.id];
                     let r = wire_vals[fid as usize][right_wire.id];
-                    let q: [_; Q_POLYS] = [I, NI, O, O, O, O, O, O, O, O];
-                    let w: [_; W_POLYS] = [l, r, O, O, O, O, O, O, O, O, O, O, O, O, O, O];
-                    qs[fid_idx].multi_assign(slots[0].row_0_indexed(), q);
-                    ws[fid_idx].multi_assign(slots[0].row_0_indexed(), w);
+                    let (ws, qs) = (&mut ws[fid_idx], &mut qs[fid_idx]);
+                    let row = slots[0].row_0_indexed();
+                    //                                             [l,  r, o, m]
+                    assign_generic_gate(ws, qs, row, k, [l, r, O], [I, NI, O, O]);
 
                     copy_constraints[fid_idx][left_wire.id].push(slots[0]);
                     copy_constraints[fid_idx][right_wire.id].push(slots[1]);
@@ -252,8 +447,7 @@ impl TraceBuilder {
                 GateType::Add([left_wire, right_wire], [out_wire]) => {
                     let fid = left_wire.fid;
 
-                    let slots = self.get_slot_ids(fid);
-                    node_map.insert(node_idx, slots);
+                    let (k, slots) = self.get_generic_slot_ids(fid, GenericGate::Add);
 
                     // ----- Values ----- //
                     let a = wire_vals[fid as usize][left_wire.id];
@@ -264,11 +458,8 @@ impl TraceBuilder {
                     // ----- Gate Constraints ----- //
                     let row = slots[0].row_0_indexed();
                     let (ws, qs) = (&mut ws[fid as usize], &mut qs[fid as usize]);
-                    //                    [l, r,  o, m, c, p, +, *, =, R]
-                    let q: [_; Q_POLYS] = [I, I, NI, O, O, O, O, O, O, O];
-                    let w: [_; W_POLYS] = [a, b, c, O, O, O, O, O, O, O, O, O, O, O, O, O];
-                    ws.multi_assign(row, w);
-                    qs.multi_assign(row, q);
+                    //                                             [l, r,  o, m]
+                    assign_generic_gate(ws, qs, row, k, [a, b, c], [I, I, NI, O]);
 
                     // ----- Copy Constraints ----- //
                     wire_output_slots[fid as usize][out_wire.id] = Some(slots[2]);
@@ -279,8 +470,7 @@ impl TraceBuilder {
                 GateType::Multiply([left_wire, right_wire], [out_wire]) => {
                     let fid = left_wire.fid;
 
-                    let slots = self.get_slot_ids(fid);
-                    node_map.insert(node_idx, slots);
+                    let (k, slots) = self.get_generic_slot_ids(fid, GenericGate::Multiply);
 
                     // ----- Values ----- //
                     let a = wire_vals[fid as usize][left_wire.id];
@@ -291,11 +481,8 @@ impl TraceBuilder {
                     // ----- Gate Constraints ----- //
                     let row = slots[0].row_0_indexed();
                     let (ws, qs) = (&mut ws[fid as usize], &mut qs[fid as usize]);
-                    //                    [l, r,  o, m, c, p, +, *, =, R]
-                    let q: [_; Q_POLYS] = [O, O, NI, I, O, O, O, O, O, O];
-                    let w: [_; W_POLYS] = [a, b, c, O, O, O, O, O, O, O, O, O, O, O, O, O];
-                    ws.multi_assign(row, w);
-                    qs.multi_assign(row, q);
+                    //                                             [l, r,  o, m]
+                    assign_generic_gate(ws, qs, row, k, [a, b, c], [O, O, NI, I]);
 
                     // ----- Copy Constraints ----- //
                     wire_output_slots[fid as usize][out_wire.id] = Some(slots[2]);
@@ -457,8 +644,7 @@ impl TraceBuilder {
                 GateType::Negate([in_wire, zero_wire], [out_wire]) => {
                     let fid = in_wire.fid;
 
-                    let slots = self.get_slot_ids(fid);
-                    node_map.insert(node_idx, slots);
+                    let (k, slots) = self.get_generic_slot_ids(fid, GenericGate::Add);
 
                     // ----- Values ----- //
                     let x = wire_vals[fid as usize][in_wire.id];
@@ -470,11 +656,8 @@ impl TraceBuilder {
                     // ----- Gate Constraints ----- //
                     let row = slots[0].row_0_indexed();
                     let (ws, qs) = (&mut ws[fid as usize], &mut qs[fid as usize]);
-                    //                    [l, r,  o, m, c, p, +, *, =, R]
-                    let q: [_; Q_POLYS] = [I, I, NI, O, O, O, O, O, O, O];
-                    let w: [_; W_POLYS] = [x, x_neg, O, O, O, O, O, O, O, O, O, O, O, O, O, O];
-                    ws.multi_assign(row, w);
-                    qs.multi_assign(row, q);
+                    //                                                 [l, r,  o, m]
+                    assign_generic_gate(ws, qs, row, k, [x, x_neg, O], [I, I, NI, O]);
 
                     // ----- Copy Constraints ----- //
                     wire_output_slots[fid as usize][out_wire.id] = Some(slots[1]);
@@ -855,6 +1038,101 @@ impl TraceBuilder {
                     copy_constraints[fid as usize][spec.one[fid as usize].id].push(slots[2]);
                     copy_constraints[fid as usize][out_wire.id].push(slots[3]);
                 }
+                GateType::Bit([in_wire], [out_wire], i) => {
+                    let fid = out_wire.fid;
+
+                    // ----- Values ----- //
+                    let x = wire_vals[fid as usize][in_wire.id];
+                    let b = PastaFE::from_bool(x.into_bigint().get_bit(i), Some(fid));
+                    wire_vals[fid as usize][out_wire.id] = b;
+
+                    // ----- Gate Constraints ----- //
+                    let slots = self.get_slot_ids(fid);
+                    let row = slots[0].row_0_indexed();
+                    let (ws, qs) = (&mut ws[fid as usize], &mut qs[fid as usize]);
+                    //                    [l,  r, o, m, c, p, +, *, =, R]
+                    let q: [_; Q_POLYS] = [NI, O, O, I, O, O, O, O, O, O];
+                    let w: [_; W_POLYS] = [b, b, O, O, O, O, O, O, O, O, O, O, O, O, O, O];
+                    qs.multi_assign(row, q);
+                    ws.multi_assign(row, w);
+
+                    // ----- Copy Constraints ----- //
+                    wire_output_slots[fid as usize][out_wire.id] = Some(slots[0]);
+                    copy_constraints[fid as usize][out_wire.id].push(slots[0]);
+                    copy_constraints[fid as usize][out_wire.id].push(slots[1]);
+                }
+                GateType::PackBits(in_wires, [out_wire], k) => {
+                    let fid = out_wire.fid;
+                    let fid_idx = fid as usize;
+
+                    // ----- Values ----- //
+                    let w: [_; W_POLYS] = array::from_fn(|i| match in_wires.get(i) {
+                        Some(wire) => wire_vals[fid_idx][wire.id],
+                        None => O,
+                    });
+                    let two = PastaFE::from_u64(2, Some(fid));
+                    let r: [_; R_POLYS] = array::from_fn(|j| match j < S_POLYS - 1 {
+                        true => two.pow(k + j),
+                        false => O,
+                    });
+                    let mut acc = w[0];
+                    for j in 0..S_POLYS - 1 {
+                        acc += w[j + 1] * r[j];
+                    }
+                    wire_vals[fid_idx][out_wire.id] = acc;
+
+                    // ----- Gate Constraints ----- //
+                    let slots = self.get_slot_ids(fid);
+                    let row = slots[0].row_0_indexed();
+                    //                    [l, r, o, m, c, p, +, *, =, R]
+                    let q: [_; Q_POLYS] = [O, O, O, O, O, O, O, O, O, I];
+                    qs[fid_idx].multi_assign(row, q);
+                    ws[fid_idx].multi_assign(row, w);
+                    rs[fid_idx].multi_assign(row, r);
+                    for (i, wire) in in_wires.iter().enumerate() {
+                        copy_constraints[fid_idx][wire.id].push(slots[i]);
+                    }
+
+                    // The range check gate reads the sum from the first wire of the next row
+                    let slots = self.get_slot_ids(fid);
+                    let row = slots[0].row_0_indexed();
+                    //                    [l, r, o, m, c, p, +, *, =, R]
+                    let q: [_; Q_POLYS] = [O, O, O, O, O, O, O, O, O, O];
+                    let w: [_; W_POLYS] = [acc, O, O, O, O, O, O, O, O, O, O, O, O, O, O, O];
+                    qs[fid_idx].multi_assign(row, q);
+                    ws[fid_idx].multi_assign(row, w);
+
+                    // ----- Copy Constraints ----- //
+                    wire_output_slots[fid_idx][out_wire.id] = Some(slots[0]);
+                    copy_constraints[fid_idx][out_wire.id].push(slots[0]);
+                }
+                GateType::Lookup(in_wires, (), table) => {
+                    let fid = in_wires[0].fid;
+                    let fid_idx = fid as usize;
+
+                    let slots = self.get_slot_ids(fid);
+                    node_map.insert(node_idx, slots);
+
+                    // ----- Values ----- //
+                    let [a, b, c] = in_wires.map(|wire| wire_vals[fid_idx][wire.id]);
+
+                    // ----- Gate Constraints ----- //
+                    let row = slots[0].row_0_indexed();
+                    let T = PastaFE::from(table.id());
+                    //                    [l, r, o, m, c, p, +, *, =, R]
+                    let q: [_; Q_POLYS] = [O, O, O, O, O, O, O, O, O, O];
+                    let w: [_; W_POLYS] = [a, b, c, O, O, O, O, O, O, O, O, O, O, O, O, O];
+                    //                  [K, T]
+                    let l: [_; 2] = [I, T];
+                    qs[fid_idx].multi_assign(row, q);
+                    ws[fid_idx].multi_assign(row, w);
+                    lookup_qs[fid_idx].multi_assign(row, l);
+
+                    // ----- Copy Constraints ----- //
+                    for (i, wire) in in_wires.iter().enumerate() {
+                        copy_constraints[fid_idx][wire.id].push(slots[i]);
+                    }
+                }
             }
         }
 
@@ -869,14 +1147,26 @@ impl TraceBuilder {
         let [fp_public_inputs, fq_public_inputs] = public_inputs;
         let [fp_copy_constraints, fq_copy_constraints] = copy_constraints;
         let [fp_out_wires, fq_out_wires] = out_wires;
+        let [fp_lookup_qs, fq_lookup_qs] = lookup_qs;
+        let [fp_row_gates, fq_row_gates] = self.row_gates.map(|rows| TraceGates {
+            spec: spec.clone(),
+            rows,
+        });
         let [fp_rows, fq_rows] = row_counts;
         let (fp_acc_prev, fq_acc_prev) = match accs_prev {
             None => (Accumulator::zero(fp_rows, 1), Accumulator::zero(fq_rows, 1)),
             Some((fp, fq)) => (fp, fq),
         };
-        let (fp_circuit, fq_circuit) = match static_circuits {
+        let (fp_key, fq_key) = match keys {
             None => (None, None),
-            Some((fp, fq)) => (Some(fp), Some(fq)),
+            Some((fp, fq)) => {
+                let digest = self.digest;
+                ensure!(
+                    fp.vk.digest == digest && fq.vk.digest == digest,
+                    "The proving keys are for another circuit"
+                );
+                (Some(fp), Some(fq))
+            }
         };
 
         let fp_public_inputs: Vec<Fp> = fp_public_inputs.into_iter().map(Fp::from).collect();
@@ -885,16 +1175,28 @@ impl TraceBuilder {
         let fp_qs = fp_qs.map(|x| x.into_iter().map(Fp::from).collect());
         let fp_out_wires = fp_out_wires.into_iter().map(Fp::from).collect();
         let fp_message_pass_inputs = message_pass_inputs[0].iter().map(|x| (*x).into()).collect();
+        let fp_lookup = lookup_columns(&spec.lookup_tables[0], fp_rows).map(|ts| {
+            let fp_lookup_qs = fp_lookup_qs.map(|x| x.into_iter().map(Fp::from).collect());
+            (
+                fp_lookup_qs,
+                ts.map(|x| x.into_iter().map(Fp::from).collect()),
+            )
+        });
+        let fp_columns = TraceColumns {
+            ws: fp_ws,
+            rs: fp_rs,
+            qs: fp_qs,
+            copy_constraints: fp_copy_constraints,
+            lookup: fp_lookup,
+            gates: fp_row_gates,
+        };
         let fp_trace = Trace::<PallasConfig>::new(
-            fp_copy_constraints,
+            pps.0,
+            fp_columns,
             fp_public_inputs,
-            fp_ws,
-            fp_rs,
-            fp_qs,
             fp_out_wires,
-            fp_rows,
             fp_acc_prev,
-            fp_circuit,
+            fp_key,
             fp_message_pass_inputs,
         );
 
@@ -904,16 +1206,28 @@ impl TraceBuilder {
         let fq_qs = fq_qs.map(|x| x.into_iter().map(Fq::from).collect());
         let fq_out_wires = fq_out_wires.into_iter().map(Fq::from).collect();
         let fq_message_pass_inputs = message_pass_inputs[1].iter().map(|x| (*x).into()).collect();
+        let fq_lookup = lookup_columns(&spec.lookup_tables[1], fq_rows).map(|ts| {
+            let fq_lookup_qs = fq_lookup_qs.map(|x| x.into_iter().map(Fq::from).collect());
+            (
+                fq_lookup_qs,
+                ts.map(|x| x.into_iter().map(Fq::from).collect()),
+            )
+        });
+        let fq_columns = TraceColumns {
+            ws: fq_ws,
+            rs: fq_rs,
+            qs: fq_qs,
+            copy_constraints: fq_copy_constraints,
+            lookup: fq_lookup,
+            gates: fq_row_gates,
+        };
         let fq_trace = Trace::<VestaConfig>::new(
-            fq_copy_constraints,
+            pps.1,
+            fq_columns,
             fq_public_inputs,
-            fq_ws,
-            fq_rs,
-            fq_qs,
             fq_out_wires,
-            fq_rows,
             fq_acc_prev,
-            fq_circuit,
+            fq_key,
             fq_message_pass_inputs,
         );
 
@@ -921,7 +1235,44 @@ impl TraceBuilder {
     }
 }
 
-fn poseidon_round(
+/// Assigns the wires [a, b, c] of the generic gate in position `k` of `row` and the selectors
+/// [q_l, q_r, q_o, q_m] of the row, which constrain q_l·a + q_r·b + q_o·c + q_m·a·b = 0 in every
+/// position. The selectors are the same for all gates of a `GenericGate` kind, and the positions
+/// that are left empty satisfy them with zeros.
+fn assign_generic_gate(
+    ws: &mut [Vec<PastaFE>; W_POLYS],
+    qs: &mut [Vec<PastaFE>; Q_POLYS],
+    row: usize,
+    k: usize,
+    w: [PastaFE; 3],
+    q: [PastaFE; 4],
+) {
+    ws.multi_assign_at(row, 3 * k, w);
+    qs.multi_assign_at(row, 0, q);
+}
+
+/// The table polynomial evaluations of the given tables, or `None` if there are no lookups. The
+/// tables are padded in front with the all-zero entry of the first table, such that equal
+/// entries stay adjacent.
+fn lookup_columns(tables: &[LookupTable], rows: usize) -> Option<[Vec<PastaFE>; L_POLYS]> {
+    let first = tables.first()?;
+    let size: usize = tables.iter().map(LookupTable::size).sum();
+    let padding = iter::repeat_n(([0; LOOKUP_WIRES], first.id()), rows - size);
+    let entries = tables
+        .iter()
+        .flat_map(|table| table.entries().into_iter().map(|e| (e, table.id())));
+
+    let mut ts: [Vec<PastaFE>; L_POLYS] = array::from_fn(|_| Vec::with_capacity(rows));
+    for (entry, id) in padding.chain(entries) {
+        for (t, x) in ts.iter_mut().zip(entry) {
+            t.push(PastaFE::from(x));
+        }
+        ts[LOOKUP_WIRES].push(PastaFE::from(id));
+    }
+    Some(ts)
+}
+
+pub(crate) fn poseidon_round(
     fid: PastaFieldId,
     r0: PastaFE,
     r1: PastaFE,
@@ -4,13 +4,16 @@ use halo_group::PastaConfig;
 use halo_poseidon::Protocols;
 
 use crate::frontend::{
-    Call,
+    Call, CircuitBuilder,
     pcdl::{CallInstance, WireHPoly, WireInstance, WirePublicParams},
     poseidon::outer_sponge::OuterSponge,
     primitives::{WireAffine, WireBool, WireScalar},
 };
 
-fn point_dot<P: PastaConfig>(a: &[WireScalar<P>], p: &[WireAffine<P>]) -> WireAffine<P> {
+fn point_dot<'a, P: PastaConfig>(
+    a: &[WireScalar<'a, P>],
+    p: &[WireAffine<'a, P>],
+) -> WireAffine<'a, P> {
     assert_eq!(a.len(), p.len());
     assert!(a.len() >= 1);
 
@@ -24,19 +27,19 @@ fn point_dot<P: PastaConfig>(a: &[WireScalar<P>], p: &[WireAffine<P>]) -> WireAf
 pub trait CallAccumulator {
     fn public_input_accumulator<P: PastaConfig>(
         &mut self,
-        wire_accumulator: &WireAccumulator<P>,
+        wire_accumulator: &WireAccumulator<'_, P>,
         accumulator: &Accumulator<P>,
     ) -> Result<()>;
     fn witness_accumulator<P: PastaConfig>(
         &mut self,
-        wire_accumulator: &WireAccumulator<P>,
+        wire_accumulator: &WireAccumulator<'_, P>,
         accumulator: &Accumulator<P>,
     ) -> Result<()>;
 }
 impl CallAccumulator for Call {
     fn witness_accumulator<P: PastaConfig>(
         &mut self,
-        wire_accumulator: &WireAccumulator<P>,
+        wire_accumulator: &WireAccumulator<'_, P>,
         accumulator: &Accumulator<P>,
     ) -> Result<()> {
         let instance = &accumulator.q;
@@ -46,7 +49,7 @@ impl CallAccumulator for Call {
     }
     fn public_input_accumulator<P: PastaConfig>(
         &mut self,
-        wire_accumulator: &WireAccumulator<P>,
+        wire_accumulator: &WireAccumulator<'_, P>,
         accumulator: &Accumulator<P>,
     ) -> Result<()> {
         let instance = &accumulator.q;
@@ -57,13 +60,13 @@ impl CallAccumulator for Call {
 }
 
 #[derive(Clone)]
-pub struct AccumulatedHPolys<P: PastaConfig> {
-    pub(crate) hs: Vec<WireHPoly<P>>,
-    alpha: Option<WireScalar<P>>,
-    alphas: Vec<WireScalar<P>>,
+pub struct AccumulatedHPolys<'a, P: PastaConfig> {
+    pub(crate) hs: Vec<WireHPoly<'a, P>>,
+    alpha: Option<WireScalar<'a, P>>,
+    alphas: Vec<WireScalar<'a, P>>,
 }
 
-impl<P: PastaConfig> AccumulatedHPolys<P> {
+impl<'a, P: PastaConfig> AccumulatedHPolys<'a, P> {
     pub(crate) fn with_capacity(capacity: usize) -> Self {
         Self {
             hs: Vec::with_capacity(capacity),
@@ -72,21 +75,21 @@ impl<P: PastaConfig> AccumulatedHPolys<P> {
         }
     }
 
-    pub(crate) fn set_alpha(&mut self, alpha: WireScalar<P>) {
+    pub(crate) fn set_alpha(&mut self, alpha: WireScalar<'a, P>) {
         self.alphas = alpha.geometric_series(self.alphas.capacity());
         self.alpha = Some(alpha)
     }
 
     // WARNING: This will panic if alphas has not been initialized, but should be fine since this is private
-    pub(crate) fn eval(&self, z: WireScalar<P>) -> WireScalar<P> {
-        let mut v = WireScalar::<P>::zero();
+    pub(crate) fn eval(&self, z: WireScalar<'a, P>) -> WireScalar<'a, P> {
+        let mut v = WireScalar::<P>::zero_in(z.builder);
         for i in 0..self.hs.len() {
             v += self.hs[i].eval(z) * self.alphas[i];
         }
         v
     }
 
-    pub(crate) fn get_scalars(&self) -> Vec<WireScalar<P>> {
+    pub(crate) fn get_scalars(&self) -> Vec<WireScalar<'a, P>> {
         let mut vec: Vec<_> = self.hs.iter().flat_map(|x| x.xis.clone()).collect();
         if let Some(alpha) = self.alpha {
             vec.push(alpha)
@@ -96,32 +99,41 @@ impl<P: PastaConfig> AccumulatedHPolys<P> {
 }
 
 #[derive(Clone)]
-pub struct WireAccumulator<P: PastaConfig> {
-    pub instance: WireInstance<P>,
+pub struct WireAccumulator<'a, P: PastaConfig> {
+    pub instance: WireInstance<'a, P>,
 }
-impl<P: PastaConfig> WireAccumulator<P> {
+impl<'a, P: PastaConfig> WireAccumulator<'a, P> {
     pub fn witness(n: usize) -> Self {
-        let instance = WireInstance::witness(n);
+        Self::witness_in(CircuitBuilder::current(), n)
+    }
+
+    pub fn witness_in(builder: &'a CircuitBuilder, n: usize) -> Self {
+        let instance = WireInstance::witness_in(builder, n);
         Self { instance }
     }
 
     pub fn public_input(n: usize) -> Self {
-        let instance = WireInstance::public_input(n);
+        Self::public_input_in(CircuitBuilder::current(), n)
+    }
+
+    pub fn public_input_in(builder: &'a CircuitBuilder, n: usize) -> Self {
+        let instance = WireInstance::public_input_in(builder, n);
         Self { instance }
     }
 
     pub fn common_subroutine(
-        pp: WirePublicParams<P>,
-        qs: Vec<WireInstance<P>>,
+        builder: &'a CircuitBuilder,
+        pp: WirePublicParams<'a, P>,
+        qs: Vec<WireInstance<'a, P>>,
     ) -> (
-        WireBool<P::OtherCurve>,
-        WireAffine<P>,
-        WireScalar<P>,
-        AccumulatedHPolys<P>,
+        WireBool<'a, P::OtherCurve>,
+        WireAffine<'a, P>,
+        WireScalar<'a, P>,
+        AccumulatedHPolys<'a, P>,
     ) {
         let m = qs.len();
 
-        let mut transcript = OuterSponge::new(Protocols::ASDL);
+        let mut transcript = OuterSponge::new_in(builder, Protocols::ASDL);
 
         // 1. Parse avk as (rk, ck^(1)_(PC)), and rk as (⟨group⟩ = (G, q, G), S, H, D).
         let mut hs = AccumulatedHPolys::with_capacity(m);
@@ -132,7 +144,7 @@ impl<P: PastaConfig> WireAccumulator<P> {
         // (3). Check that U_0 is a deterministic commitment to h_0: U_0 = PCDL.Commit_ρ0(ck^(1)_PC, h; ω = ⊥).
 
         // 4. For each i ∈ [m]:
-        let mut res = WireBool::t();
+        let mut res = WireBool::constant_in(builder, true);
         for q in qs {
             // 4.a Parse q_i as a tuple ((C_i, d_i, z_i, v_i), π_i).
             // 4.b Compute (h_i(X), U_i) := PCDL.SuccinctCheckρ0(rk, C_i, z_i, v_i, π_i) (see Figure 2).
@@ -165,12 +177,18 @@ impl<P: PastaConfig> WireAccumulator<P> {
         (res, C_bar, z, hs)
     }
 
-    pub fn verify(self, pp: WirePublicParams<P>, qs: Vec<WireInstance<P>>) -> WireBool<P> {
+    pub fn verify(
+        self,
+        pp: WirePublicParams<'a, P>,
+        qs: Vec<WireInstance<'a, P>>,
+    ) -> WireBool<'a, P> {
+        let builder = self.instance.C.builder();
+        let _scope = builder.scope("asdl");
         let acc = self;
         let WireInstance { C, z, v, pi: _ } = acc.instance;
 
         // 1. The accumulation verifier V computes (C_bar', d', z', h(X)) := T^ρ(avk, [qi]^n_(i=1), π_V)
-        let (is_subroutine_ok, C_bar_prime, z_prime, h) = Self::common_subroutine(pp, qs);
+        let (is_subroutine_ok, C_bar_prime, z_prime, h) = Self::common_subroutine(builder, pp, qs);
 
         // 2. Then checks that C_bar' = C_bar, d' = d, z' = z, and h(z) = v.
         let is_C_bar_eq = C_bar_prime.equals(C);
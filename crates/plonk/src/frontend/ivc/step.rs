@@ -0,0 +1,703 @@
+//! Incrementally verifiable computation over a user-defined step function F. A proof for step i
+//! shows that z_i = Fⁱ(z_0). The framework builds the recursive circuit around the step: it checks
+//! that z_{i+1} = F(z_i), and that the proofs of the previous step verify against the same two
+//! circuits with i, z_0 and z_i as public inputs, unless i = 0 in which case z_i = z_0.
+
+use std::array;
+
+use anyhow::{Result, ensure};
+use halo_accumulation::{acc::Accumulator, pcdl::Instance};
+use halo_group::{
+    Affine, BaseField, Fp, PallasConfig, PastaConfig, PublicParams, Scalar, VestaConfig,
+    ark_ec::CurveGroup,
+    ark_std::{Zero, rand::Rng},
+};
+use itertools::Itertools;
+
+use crate::{
+    circuit::{PlonkCircuit, PlonkPublicInputs, ProvingKey},
+    frontend::{
+        Call, CircuitBuilder,
+        ivc::WireIVCState,
+        plonk::{CallPlonk, WirePlonkCircuit, WirePlonkProof, WirePlonkPublicInputs},
+        primitives::{WireBool, WireScalar},
+    },
+    plonk::{PlonkProof, PlonkProofCommitments, PlonkProofEvalProofs, PlonkProofEvals},
+};
+
+/// A step function F of an incrementally verifiable computation z_{i+1} = F(z_i). The state is
+/// made of `STATE_LEN` elements of Fp, and F is given both as a circuit and natively.
+pub trait IvcStep {
+    /// The native state z_i
+    type State: Clone;
+    /// The state as wires of the Fp circuit, borrowing the builder of that circuit
+    type WireState<'a>;
+
+    /// The number of Fp elements the state is made of
+    const STATE_LEN: usize;
+
+    fn to_scalars(&self, state: &Self::State) -> Vec<Fp>;
+    fn wire_state<'a>(&self, wires: &[WireScalar<'a, PallasConfig>]) -> Self::WireState<'a>;
+    fn to_wires<'a>(&self, state: &Self::WireState<'a>) -> Vec<WireScalar<'a, PallasConfig>>;
+
+    /// F(z_i) in the circuit, which is built in the builder of the wires of `state`
+    fn synthesize<'a>(&self, state: &Self::WireState<'a>) -> Self::WireState<'a>;
+    /// F(z_i) natively, must agree with `synthesize`
+    fn step(&self, state: &Self::State) -> Self::State;
+}
+
+/// A proof that `z_i` is the result of applying the step function `i` times to `z_0`
+pub struct IvcProof<S: IvcStep> {
+    pub i: u64,
+    pub z_0: S::State,
+    pub z_i: S::State,
+    pub fp_proof: PlonkProof<PallasConfig>,
+    pub fp_public_input: PlonkPublicInputs<PallasConfig>,
+    pub fq_proof: PlonkProof<VestaConfig>,
+    pub fq_public_input: PlonkPublicInputs<VestaConfig>,
+}
+
+impl<S: IvcStep> Clone for IvcProof<S> {
+    fn clone(&self) -> Self {
+        Self {
+            i: self.i,
+            z_0: self.z_0.clone(),
+            z_i: self.z_i.clone(),
+            fp_proof: self.fp_proof.clone(),
+            fp_public_input: self.fp_public_input.clone(),
+            fq_proof: self.fq_proof.clone(),
+            fq_public_input: self.fq_public_input.clone(),
+        }
+    }
+}
+
+/// The rows and public input counts of the two circuits, as [Fp, Fq]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct IvcShape {
+    rows: [usize; 2],
+    public_input_counts: [usize; 2],
+}
+
+/// Where the values of a step end up in the public inputs of the two circuits
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct IvcLayout {
+    /// The Fp circuit commitment coordinates in the Fq public inputs
+    fp_circuit: Vec<usize>,
+    /// The Fq circuit commitment coordinates in the Fp public inputs
+    fq_circuit: Vec<usize>,
+    i: usize,
+    z_0: Vec<usize>,
+    z: Vec<usize>,
+}
+
+/// The IVC circuits of a step function, preprocessed once by `Ivc::setup`
+pub struct Ivc<S: IvcStep> {
+    step: S,
+    shape: IvcShape,
+    layout: IvcLayout,
+    fp_key: ProvingKey<PallasConfig>,
+    fq_key: ProvingKey<VestaConfig>,
+}
+
+impl<S: IvcStep> Ivc<S> {
+    /// Builds the recursive circuit around `step` and preprocesses the two fixed circuits. The
+    /// circuits verify proofs of themselves, so their shape is found by building them until the
+    /// rows and public input counts no longer change.
+    pub fn setup(step: S) -> Result<Self> {
+        let mut shape = initial_shape::<S>();
+        let layout = loop {
+            let builder = CircuitBuilder::new();
+            let (_, layout) = synthesize(&builder, &step, shape);
+            let (rows, public_input_counts) = builder.trace_shape();
+            let next = IvcShape {
+                rows,
+                public_input_counts,
+            };
+            if next == shape {
+                break layout;
+            }
+            shape = next;
+        };
+
+        // The commitments do not depend on the assigned values, so dummies will do
+        let [fp_rows, fq_rows] = shape.rows;
+        let [fp_public_input_count, fq_public_input_count] = shape.public_input_counts;
+        let fp_circuit = WireIVCState::circuit_dummy(fp_rows, fp_public_input_count);
+        let fq_circuit = WireIVCState::circuit_dummy(fq_rows, fq_public_input_count);
+        let (fp_proof, fp_public_input) = dummy_proof(fp_rows, fp_public_input_count);
+        let (fq_proof, fq_public_input) = dummy_proof(fq_rows, fq_public_input_count);
+        let zeros = vec![Fp::zero(); S::STATE_LEN];
+        let assignment = IvcAssignment {
+            i: 0,
+            z_0: &zeros,
+            z: &zeros,
+            z_next: &zeros,
+            fp_proof: &fp_proof,
+            fp_public_input: &fp_public_input,
+            fq_proof: &fq_proof,
+            fq_public_input: &fq_public_input,
+        };
+
+        let builder = CircuitBuilder::new();
+        let (wires, _) = synthesize(&builder, &step, shape);
+        let mut call = builder.call();
+        assignment.assign(&mut call, &wires, &fp_circuit, &fq_circuit)?;
+        let (fp_key, fq_key) = call.preprocess()?;
+
+        ensure!(
+            fp_key.vk.circuit.lookup.is_none() && fq_key.vk.circuit.lookup.is_none(),
+            "IVC step functions can not use lookup gates"
+        );
+
+        Ok(Self {
+            step,
+            shape,
+            layout,
+            fp_key,
+            fq_key,
+        })
+    }
+
+    /// The fixed Fp and Fq circuits
+    pub fn circuits(&self) -> (PlonkCircuit<PallasConfig>, PlonkCircuit<VestaConfig>) {
+        (self.fp_key.vk.circuit, self.fq_key.vk.circuit)
+    }
+
+    /// The proof of step 0, which carries dummy proofs as there is nothing to verify yet
+    pub fn init(&self, z_0: S::State) -> IvcProof<S> {
+        let [fp_rows, fq_rows] = self.shape.rows;
+        let [fp_public_input_count, fq_public_input_count] = self.shape.public_input_counts;
+        let (fp_proof, fp_public_input) = dummy_proof(fp_rows, fp_public_input_count);
+        let (fq_proof, fq_public_input) = dummy_proof(fq_rows, fq_public_input_count);
+        IvcProof {
+            i: 0,
+            z_0: z_0.clone(),
+            z_i: z_0,
+            fp_proof,
+            fp_public_input,
+            fq_proof,
+            fq_public_input,
+        }
+    }
+
+    /// Proves step i + 1 from the proof of step i
+    pub fn prove<R: Rng>(&self, rng: &mut R, proof: &IvcProof<S>) -> Result<IvcProof<S>> {
+        let z_next = self.step.step(&proof.z_i);
+        let z_0_scalars = self.step.to_scalars(&proof.z_0);
+        let z_scalars = self.step.to_scalars(&proof.z_i);
+        let z_next_scalars = self.step.to_scalars(&z_next);
+        let assignment = IvcAssignment {
+            i: proof.i,
+            z_0: &z_0_scalars,
+            z: &z_scalars,
+            z_next: &z_next_scalars,
+            fp_proof: &proof.fp_proof,
+            fp_public_input: &proof.fp_public_input,
+            fq_proof: &proof.fq_proof,
+            fq_public_input: &proof.fq_public_input,
+        };
+
+        let builder = CircuitBuilder::new();
+        let (wires, layout) = synthesize(&builder, &self.step, self.shape);
+        let mut call = builder.call();
+        assignment.assign(
+            &mut call,
+            &wires,
+            &self.fp_key.vk.circuit,
+            &self.fq_key.vk.circuit,
+        )?;
+        let accs = Some((
+            proof.fp_proof.acc_next.clone(),
+            proof.fq_proof.acc_next.clone(),
+        ));
+        let (fp_trace, fq_trace) =
+            call.trace_with_params(accs, Some((&self.fp_key, &self.fq_key)))?;
+        ensure!(
+            layout == self.layout,
+            "The step function changed since setup"
+        );
+
+        let (_, fp_x, fp_w) = fp_trace.consume();
+        let (_, fq_x, fq_w) = fq_trace.consume();
+        let fp_pi = PlonkProof::naive_prover_with_key(
+            rng,
+            PublicParams::get_pp(),
+            &self.fp_key,
+            &fp_x,
+            fp_w,
+        );
+        let fq_pi = PlonkProof::naive_prover_with_key(
+            rng,
+            PublicParams::get_pp(),
+            &self.fq_key,
+            &fq_x,
+            fq_w,
+        );
+
+        let proof_next = IvcProof {
+            i: proof.i + 1,
+            z_0: proof.z_0.clone(),
+            z_i: z_next,
+            fp_proof: fp_pi,
+            fp_public_input: fp_x,
+            fq_proof: fq_pi,
+            fq_public_input: fq_x,
+        };
+        self.verify(&proof_next)?;
+
+        Ok(proof_next)
+    }
+
+    /// Checks that `proof.z_i` is the result of `proof.i` steps from `proof.z_0`
+    pub fn verify(&self, proof: &IvcProof<S>) -> Result<()> {
+        let z_0 = self.step.to_scalars(&proof.z_0);
+        let z_i = self.step.to_scalars(&proof.z_i);
+        ensure!(z_0.len() == S::STATE_LEN && z_i.len() == S::STATE_LEN);
+        if proof.i == 0 {
+            ensure!(z_0 == z_i, "The state of step 0 is not z_0");
+            return Ok(());
+        }
+
+        let fp_x = &proof.fp_public_input.public_inputs;
+        let fq_x = &proof.fq_public_input.public_inputs;
+        let layout = &self.layout;
+        ensure!(
+            fp_x.len() == self.fp_key.vk.circuit.public_input_count
+                && fq_x.len() == self.fq_key.vk.circuit.public_input_count,
+            "Wrong number of public inputs"
+        );
+        ensure!(
+            fp_x[layout.i] == Fp::from(proof.i),
+            "The proofs are for another step"
+        );
+        ensure!(
+            layout.z_0.iter().map(|&k| fp_x[k]).eq(z_0),
+            "The proofs are for another z_0"
+        );
+        ensure!(
+            layout.z.iter().map(|&k| fp_x[k]).eq(z_i),
+            "The proofs are for another z_i"
+        );
+        ensure!(
+            layout
+                .fq_circuit
+                .iter()
+                .map(|&k| fp_x[k])
+                .eq(circuit_coordinates(&self.fq_key.vk.circuit)),
+            "The proofs are for another Fq circuit"
+        );
+        ensure!(
+            layout
+                .fp_circuit
+                .iter()
+                .map(|&k| fq_x[k])
+                .eq(circuit_coordinates(&self.fp_key.vk.circuit)),
+            "The proofs are for another Fp circuit"
+        );
+
+        proof
+            .fp_proof
+            .verify(self.fp_key.vk.circuit, &proof.fp_public_input)?;
+        proof
+            .fq_proof
+            .verify(self.fq_key.vk.circuit, &proof.fq_public_input)
+    }
+}
+
+struct IvcWires<'a> {
+    circuit_fp: WirePlonkCircuit<'a, PallasConfig>,
+    circuit_fq: WirePlonkCircuit<'a, VestaConfig>,
+    i_next: WireScalar<'a, PallasConfig>,
+    z_0: Vec<WireScalar<'a, PallasConfig>>,
+    z_next: Vec<WireScalar<'a, PallasConfig>>,
+    i: WireScalar<'a, PallasConfig>,
+    z: Vec<WireScalar<'a, PallasConfig>>,
+    fp_proof: WirePlonkProof<'a, PallasConfig>,
+    fp_public_input: WirePlonkPublicInputs<'a, PallasConfig>,
+    fq_proof: WirePlonkProof<'a, VestaConfig>,
+    fq_public_input: WirePlonkPublicInputs<'a, VestaConfig>,
+}
+
+/// The values of a step, along with the proofs of the previous step
+struct IvcAssignment<'a> {
+    i: u64,
+    z_0: &'a [Fp],
+    z: &'a [Fp],
+    z_next: &'a [Fp],
+    fp_proof: &'a PlonkProof<PallasConfig>,
+    fp_public_input: &'a PlonkPublicInputs<PallasConfig>,
+    fq_proof: &'a PlonkProof<VestaConfig>,
+    fq_public_input: &'a PlonkPublicInputs<VestaConfig>,
+}
+
+impl IvcAssignment<'_> {
+    fn assign(
+        &self,
+        call: &mut Call,
+        wires: &IvcWires<'_>,
+        fp_circuit: &PlonkCircuit<PallasConfig>,
+        fq_circuit: &PlonkCircuit<VestaConfig>,
+    ) -> Result<()> {
+        ensure!(
+            self.z_0.len() == wires.z_0.len()
+                && self.z.len() == wires.z.len()
+                && self.z_next.len() == wires.z_next.len(),
+            "The state does not have STATE_LEN elements"
+        );
+
+        call.public_input_plonk_circuit(&wires.circuit_fp, fp_circuit)?;
+        call.public_input_plonk_circuit(&wires.circuit_fq, fq_circuit)?;
+        call.public_input(wires.i_next, Fp::from(self.i + 1))?;
+        for (wire, x) in wires.z_0.iter().zip(self.z_0) {
+            call.public_input(*wire, *x)?;
+        }
+        for (wire, x) in wires.z_next.iter().zip(self.z_next) {
+            call.public_input(*wire, *x)?;
+        }
+
+        call.witness(wires.i, Fp::from(self.i))?;
+        for (wire, x) in wires.z.iter().zip(self.z) {
+            call.witness(*wire, *x)?;
+        }
+        call.witness_plonk_proof(&wires.fp_proof, self.fp_proof)?;
+        call.witness_plonk_public_input(&wires.fp_public_input, self.fp_public_input)?;
+        call.witness_plonk_proof(&wires.fq_proof, self.fq_proof)?;
+        call.witness_plonk_public_input(&wires.fq_public_input, self.fq_public_input)?;
+
+        Ok(())
+    }
+}
+
+/// Builds the IVC circuit of `step` in `builder`, which should be empty. The public inputs are
+/// created first, so their positions are known when binding them to the public inputs of the
+/// previous step.
+fn synthesize<'a, S: IvcStep>(
+    builder: &'a CircuitBuilder,
+    step: &S,
+    shape: IvcShape,
+) -> (IvcWires<'a>, IvcLayout) {
+    let [fp_rows, fq_rows] = shape.rows;
+    let [fp_public_input_count, fq_public_input_count] = shape.public_input_counts;
+
+    let circuit_fp = WirePlonkCircuit::public_input_in(builder, fp_rows, fp_public_input_count);
+    let circuit_fq = WirePlonkCircuit::public_input_in(builder, fq_rows, fq_public_input_count);
+    let i_next = WireScalar::public_input_in(builder);
+    let z_0 = (0..S::STATE_LEN)
+        .map(|_| WireScalar::public_input_in(builder))
+        .collect_vec();
+    let z_next = (0..S::STATE_LEN)
+        .map(|_| WireScalar::public_input_in(builder))
+        .collect_vec();
+    let layout = IvcLayout {
+        fp_circuit: public_input_indices(&wire_circuit_coordinates(&circuit_fp)),
+        fq_circuit: public_input_indices(&wire_circuit_coordinates(&circuit_fq)),
+        i: builder.public_input_index(i_next.wire),
+        z_0: public_input_indices(&z_0),
+        z: public_input_indices(&z_next),
+    };
+
+    let i = WireScalar::witness_in(builder);
+    let z = (0..S::STATE_LEN)
+        .map(|_| WireScalar::witness_in(builder))
+        .collect_vec();
+    let fp_proof = WirePlonkProof::witness_in(builder, fp_rows);
+    let fp_public_input =
+        WirePlonkPublicInputs::witness_in(builder, fp_rows, fp_public_input_count);
+    let fq_proof = WirePlonkProof::witness_in(builder, fq_rows);
+    let fq_public_input =
+        WirePlonkPublicInputs::witness_in(builder, fq_rows, fq_public_input_count);
+
+    // z_{i+1} = F(z_i)
+    let z_step = step.to_wires(&step.synthesize(&step.wire_state(&z)));
+    assert_eq!(z_step.len(), S::STATE_LEN);
+    for (x, y) in z_step.into_iter().zip(&z_next) {
+        x.assert_eq(*y);
+    }
+    (i + WireScalar::one_in(builder)).assert_eq(i_next);
+
+    // The previous proofs verify...
+    let c1_fp = fp_proof.verify_succinct(circuit_fp, fp_public_input.clone());
+    let c1_fq = fq_proof
+        .verify_succinct(circuit_fq, fq_public_input.clone())
+        .message_pass();
+    let c1 = c1_fp & c1_fq;
+
+    // ...and are for these circuits, i, z_0 and z_i
+    let prev_fp_x = &fp_public_input.public_inputs;
+    let prev_fq_x = &fq_public_input.public_inputs;
+    let mut c2_fp = prev_fp_x[layout.i].equals(i);
+    for (ks, xs) in [
+        (&layout.z_0, z_0.clone()),
+        (&layout.z, z.clone()),
+        (&layout.fq_circuit, wire_circuit_coordinates(&circuit_fq)),
+    ] {
+        for (&k, x) in ks.iter().zip(xs) {
+            c2_fp &= prev_fp_x[k].equals(x);
+        }
+    }
+    let mut c2_fq = WireBool::<VestaConfig>::constant_in(builder, true);
+    for (&k, x) in layout
+        .fp_circuit
+        .iter()
+        .zip(wire_circuit_coordinates(&circuit_fp))
+    {
+        c2_fq &= prev_fq_x[k].equals(x);
+    }
+    let c2 = c2_fp & c2_fq.message_pass();
+
+    // Or this is the first step and z_i = z_0
+    let mut c3 = i.equals(WireScalar::zero_in(builder));
+    for (x, y) in z.iter().zip(&z_0) {
+        c3 &= x.equals(*y);
+    }
+
+    ((c1 & c2) | c3).assert_eq(WireBool::constant_in(builder, true));
+
+    let wires = IvcWires {
+        circuit_fp,
+        circuit_fq,
+        i_next,
+        z_0,
+        z_next,
+        i,
+        z,
+        fp_proof,
+        fp_public_input,
+        fq_proof,
+        fq_public_input,
+    };
+    (wires, layout)
+}
+
+/// The shape of a circuit with only the public inputs created by `synthesize`, which is a lower
+/// bound that every position of the layout fits in
+fn initial_shape<S: IvcStep>() -> IvcShape {
+    let builder = CircuitBuilder::new();
+    WirePlonkCircuit::<PallasConfig>::public_input_in(&builder, 4, 0);
+    WirePlonkCircuit::<VestaConfig>::public_input_in(&builder, 4, 0);
+    for _ in 0..1 + 2 * S::STATE_LEN {
+        WireScalar::<PallasConfig>::public_input_in(&builder);
+    }
+    let (rows, public_input_counts) = builder.trace_shape();
+    IvcShape {
+        rows,
+        public_input_counts,
+    }
+}
+
+fn public_input_indices<P: PastaConfig>(xs: &[WireScalar<P>]) -> Vec<usize> {
+    xs.iter()
+        .map(|x| x.builder.public_input_index(x.wire))
+        .collect()
+}
+
+/// The coordinates of the circuit commitments, in the order `public_input_plonk_circuit` assigns
+/// them
+fn wire_circuit_coordinates<'a, P: PastaConfig>(
+    circuit: &WirePlonkCircuit<'a, P>,
+) -> Vec<WireScalar<'a, P::OtherCurve>> {
+    let Cs = &circuit.Cs;
+    Cs.qs
+        .iter()
+        .chain(&Cs.rs)
+        .chain(&Cs.ids)
+        .chain(&Cs.sigmas)
+        .flat_map(|p| [p.x, p.y])
+        .collect()
+}
+
+/// The native counterpart of `wire_circuit_coordinates`
+fn circuit_coordinates<P: PastaConfig>(circuit: &PlonkCircuit<P>) -> Vec<BaseField<P>> {
+    let Cs = &circuit.Cs;
+    Cs.qs
+        .iter()
+        .chain(&Cs.rs)
+        .chain(&Cs.ids)
+        .chain(&Cs.sigmas)
+        .flat_map(|p| {
+            let p = p.into_affine();
+            [p.x, p.y]
+        })
+        .collect()
+}
+
+/// Stands in for the proofs of the step before step 0, which are never checked
+fn dummy_proof<P: PastaConfig>(
+    rows: usize,
+    public_input_count: usize,
+) -> (PlonkProof<P>, PlonkPublicInputs<P>) {
+    let acc = Accumulator::zero(rows, 1);
+    let proof = PlonkProof {
+        vs: PlonkProofEvals {
+            ws: array::from_fn(|_| Scalar::<P>::zero()),
+            rs: array::from_fn(|_| Scalar::<P>::zero()),
+            sigmas: array::from_fn(|_| Scalar::<P>::zero()),
+            z_omega: Scalar::<P>::zero(),
+            w_omegas: array::from_fn(|_| Scalar::<P>::zero()),
+            lookup: None,
+        },
+        Cs: PlonkProofCommitments {
+            ws: array::from_fn(|_| Affine::<P>::identity().into()),
+            ts: array::from_fn(|_| Affine::<P>::identity().into()),
+            z: Affine::<P>::identity().into(),
+            hiding: None,
+            lookup: None,
+        },
+        pis: PlonkProofEvalProofs {
+            r: Instance::<P>::zero_invalid(rows).pi,
+            r_omega: Instance::<P>::zero_invalid(rows).pi,
+        },
+        acc_next: acc.clone(),
+    };
+    let public_input = PlonkPublicInputs {
+        public_inputs: vec![Scalar::<P>::zero(); public_input_count],
+        acc_prev: acc,
+    };
+    (proof, public_input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::poseidon::inner_sponge::InnerSponge;
+    use halo_group::{
+        ark_ff::{Field, UniformRand},
+        ark_std::{One, test_rng},
+    };
+    use halo_poseidon::inner_sponge::PoseidonSponge;
+
+    /// z_{i+1} = z_i + 1
+    struct Counter;
+    impl IvcStep for Counter {
+        type State = Fp;
+        type WireState<'a> = WireScalar<'a, PallasConfig>;
+        const STATE_LEN: usize = 1;
+
+        fn to_scalars(&self, state: &Fp) -> Vec<Fp> {
+            vec![*state]
+        }
+        fn wire_state<'a>(&self, wires: &[WireScalar<'a, PallasConfig>]) -> Self::WireState<'a> {
+            wires[0]
+        }
+        fn to_wires<'a>(&self, state: &Self::WireState<'a>) -> Vec<WireScalar<'a, PallasConfig>> {
+            vec![*state]
+        }
+        fn synthesize<'a>(&self, state: &Self::WireState<'a>) -> Self::WireState<'a> {
+            *state + WireScalar::one_in(state.builder())
+        }
+        fn step(&self, state: &Fp) -> Fp {
+            *state + Fp::one()
+        }
+    }
+
+    /// z_{i+1} = (H(h_i, x_i), x_i²)
+    struct HashChain;
+    impl IvcStep for HashChain {
+        type State = (Fp, Fp);
+        type WireState<'a> = (WireScalar<'a, PallasConfig>, WireScalar<'a, PallasConfig>);
+        const STATE_LEN: usize = 2;
+
+        fn to_scalars(&self, (h, x): &(Fp, Fp)) -> Vec<Fp> {
+            vec![*h, *x]
+        }
+        fn wire_state<'a>(&self, wires: &[WireScalar<'a, PallasConfig>]) -> Self::WireState<'a> {
+            (wires[0], wires[1])
+        }
+        fn to_wires<'a>(&self, (h, x): &Self::WireState<'a>) -> Vec<WireScalar<'a, PallasConfig>> {
+            vec![*h, *x]
+        }
+        fn synthesize<'a>(&self, (h, x): &Self::WireState<'a>) -> Self::WireState<'a> {
+            let mut sponge = InnerSponge::new(h.builder());
+            sponge.absorb(&[*h, *x]);
+            (sponge.squeeze(), x.square())
+        }
+        fn step(&self, (h, x): &(Fp, Fp)) -> (Fp, Fp) {
+            let mut sponge = PoseidonSponge::<VestaConfig>::new();
+            sponge.absorb(&[*h, *x]);
+            (sponge.squeeze(), x.square())
+        }
+    }
+
+    fn assert_step_parity<S: IvcStep>(step: S, state: S::State) -> Result<()> {
+        let rng = &mut test_rng();
+        let builder = CircuitBuilder::new();
+        let wires = (0..S::STATE_LEN)
+            .map(|_| WireScalar::<PallasConfig>::witness_in(&builder))
+            .collect_vec();
+        for x in step.to_wires(&step.synthesize(&step.wire_state(&wires))) {
+            x.output();
+        }
+
+        let mut call = builder.call();
+        for (wire, x) in wires.iter().zip(step.to_scalars(&state)) {
+            call.witness(*wire, x)?;
+        }
+        let (fp_trace, _) = call.trace()?;
+
+        assert_eq!(fp_trace.outputs, step.to_scalars(&step.step(&state)));
+        let (circuit, x, w) = fp_trace.consume();
+        PlonkProof::naive_prover(rng, circuit, &x, w).verify(circuit, &x)
+    }
+
+    #[test]
+    fn step_parity() -> Result<()> {
+        let rng = &mut test_rng();
+        assert_step_parity(Counter, Fp::rand(rng))?;
+        assert_step_parity(HashChain, (Fp::rand(rng), Fp::rand(rng)))
+    }
+
+    #[test]
+    fn hash_chain() -> Result<()> {
+        let rng = &mut test_rng();
+        let ivc = Ivc::setup(HashChain)?;
+        let z_0 = (Fp::rand(rng), Fp::rand(rng));
+
+        let proof_0 = ivc.init(z_0);
+        ivc.verify(&proof_0)?;
+        let proof_1 = ivc.prove(rng, &proof_0)?;
+        assert_eq!(proof_1.i, 1);
+        assert_eq!(proof_1.z_i, HashChain.step(&z_0));
+        ivc.verify(&proof_1)?;
+
+        let mut tampered = proof_1.clone();
+        tampered.z_i.1 += Fp::one();
+        assert!(ivc.verify(&tampered).is_err());
+        let mut tampered = proof_1;
+        tampered.z_0.0 += Fp::one();
+        assert!(ivc.verify(&tampered).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    #[ignore = "proves two IVC steps, takes minutes even with --release"]
+    fn counter() -> Result<()> {
+        let rng = &mut test_rng();
+        let ivc = Ivc::setup(Counter)?;
+        let z_0 = Fp::from(42);
+
+        let proof_0 = ivc.init(z_0);
+        ivc.verify(&proof_0)?;
+        let proof_1 = ivc.prove(rng, &proof_0)?;
+        let proof_2 = ivc.prove(rng, &proof_1)?;
+        assert_eq!(proof_2.i, 2);
+        assert_eq!(proof_2.z_i, Fp::from(44));
+        ivc.verify(&proof_2)?;
+
+        // The proofs are bound to i, z_0 and z_i
+        let mut tampered = proof_2.clone();
+        tampered.z_i = Fp::from(45);
+        assert!(ivc.verify(&tampered).is_err());
+        let mut tampered = proof_2.clone();
+        tampered.z_0 = Fp::from(43);
+        assert!(ivc.verify(&tampered).is_err());
+        let mut tampered = proof_2;
+        tampered.i = 1;
+        assert!(ivc.verify(&tampered).is_err());
+        let mut tampered = proof_1;
+        tampered.i = 0;
+        assert!(ivc.verify(&tampered).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,284 @@
+//! On-disk format of an `IVCState`, so that a chain can be resumed after the process restarts.
+//! The public proof material and the secret key are written to separate files, each behind the
+//! envelope header of the Fp circuit, so that the secret key can be kept somewhere else.
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, ensure};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress};
+use halo_group::{PallasConfig, Scalar, VestaConfig};
+use halo_schnorr::{PublicKey, SchnorrSignature, SecretKey};
+
+use crate::{
+    circuit::PlonkPublicInputs,
+    frontend::ivc::{IVC_ROW_COUNT, IVCState},
+    plonk::{PlonkProof, envelope_from_bytes, envelope_to_bytes},
+};
+
+/// The file holding the public proof material in a directory written by `IVCState::save`
+pub const IVC_STATE_FILE: &str = "state.bin";
+/// The file holding the secret key in a directory written by `IVCState::save`
+pub const IVC_SECRET_KEY_FILE: &str = "secret_key.bin";
+
+/// Everything in an `IVCState` except the secret key
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+struct IVCPublicState {
+    pk: PublicKey<PallasConfig>,
+    signature: SchnorrSignature<PallasConfig>,
+    i: Scalar<PallasConfig>,
+    fp_proof: PlonkProof<PallasConfig>,
+    fp_public_input: PlonkPublicInputs<PallasConfig>,
+    fq_proof: PlonkProof<VestaConfig>,
+    fq_public_input: PlonkPublicInputs<VestaConfig>,
+}
+
+impl IVCState {
+    /// Serializes everything but the secret key
+    pub fn public_to_bytes(&self) -> Result<Vec<u8>> {
+        let public = IVCPublicState {
+            pk: self.pk.clone(),
+            signature: self.signature,
+            i: self.i,
+            fp_proof: self.fp_proof.clone(),
+            fp_public_input: self.fp_public_input.clone(),
+            fq_proof: self.fq_proof.clone(),
+            fq_public_input: self.fq_public_input.clone(),
+        };
+        envelope_to_bytes::<PallasConfig, _>(&public, IVC_ROW_COUNT, Compress::Yes)
+    }
+
+    pub fn secret_key_to_bytes(&self) -> Result<Vec<u8>> {
+        envelope_to_bytes::<PallasConfig, _>(&self.sk, IVC_ROW_COUNT, Compress::Yes)
+    }
+
+    /// Deserializes a state written by `public_to_bytes` and `secret_key_to_bytes`. The secret
+    /// key must belong to the public key of the state, and the state must pass `IVCState::verify`.
+    pub fn from_bytes(public_bytes: &[u8], secret_key_bytes: &[u8]) -> Result<Self> {
+        let rows = Some(IVC_ROW_COUNT);
+        let (_, public) =
+            envelope_from_bytes::<PallasConfig, IVCPublicState>(public_bytes, rows, Compress::Yes)
+                .context("Invalid IVC state")?;
+        let (_, sk) = envelope_from_bytes::<PallasConfig, SecretKey<PallasConfig>>(
+            secret_key_bytes,
+            rows,
+            Compress::Yes,
+        )
+        .context("Invalid IVC secret key")?;
+        ensure!(
+            sk.public_key() == public.pk,
+            "The secret key does not belong to the public key of the IVC state"
+        );
+
+        let state = Self {
+            pk: public.pk,
+            sk,
+            signature: public.signature,
+            i: public.i,
+            fp_proof: public.fp_proof,
+            fp_public_input: public.fp_public_input,
+            fq_proof: public.fq_proof,
+            fq_public_input: public.fq_public_input,
+        };
+        state
+            .verify()
+            .context("The loaded IVC state does not verify")?;
+        Ok(state)
+    }
+
+    /// Writes the state to the directory `path`, creating it if needed. The public proof material
+    /// goes to `IVC_STATE_FILE` and the secret key to `IVC_SECRET_KEY_FILE`.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        fs::create_dir_all(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        self.save_split(path.join(IVC_STATE_FILE), path.join(IVC_SECRET_KEY_FILE))
+    }
+
+    /// Like `save`, but the public proof material and the secret key are written to two
+    /// arbitrary paths
+    pub fn save_split(
+        &self,
+        public_path: impl AsRef<Path>,
+        secret_key_path: impl AsRef<Path>,
+    ) -> Result<()> {
+        write_atomic(public_path.as_ref(), &self.public_to_bytes()?, false)?;
+        write_atomic(secret_key_path.as_ref(), &self.secret_key_to_bytes()?, true)
+    }
+
+    /// Reads a state written by `save`. Fails if the files are malformed or tampered with, see
+    /// `IVCState::from_bytes`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        Self::load_split(path.join(IVC_STATE_FILE), path.join(IVC_SECRET_KEY_FILE))
+    }
+
+    /// Reads a state written by `save_split`
+    pub fn load_split(
+        public_path: impl AsRef<Path>,
+        secret_key_path: impl AsRef<Path>,
+    ) -> Result<Self> {
+        let public_path = public_path.as_ref();
+        let secret_key_path = secret_key_path.as_ref();
+        let public_bytes = fs::read(public_path)
+            .with_context(|| format!("Failed to read {}", public_path.display()))?;
+        let secret_key_bytes = fs::read(secret_key_path)
+            .with_context(|| format!("Failed to read {}", secret_key_path.display()))?;
+        Self::from_bytes(&public_bytes, &secret_key_bytes)
+    }
+}
+
+/// Writes to a temporary file next to `path` and renames it, so a crash never leaves a partially
+/// written state behind. Secret files are only readable by the owner on unix.
+fn write_atomic(path: &Path, bytes: &[u8], secret: bool) -> Result<()> {
+    let mut tmp_path = PathBuf::from(path);
+    tmp_path.as_mut_os_string().push(".tmp");
+
+    // A temporary file left by a crash keeps its permissions when it is opened again, so it is
+    // removed and the file is always created anew
+    match fs::remove_file(&tmp_path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => {
+            return Err(err).with_context(|| format!("Failed to remove {}", tmp_path.display()));
+        }
+        _ => (),
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    if secret {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    #[cfg(not(unix))]
+    let _ = secret;
+
+    let mut file = options
+        .open(&tmp_path)
+        .with_context(|| format!("Failed to create {}", tmp_path.display()))?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, path).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo_group::ark_std::One;
+    use halo_schnorr::generate_keypair;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("halo-ivc-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        path
+    }
+
+    #[test]
+    fn test_save_load() -> Result<()> {
+        let path = temp_dir("save-load");
+        let state = IVCState::init();
+        state.save(&path)?;
+
+        let loaded = IVCState::load(&path)?;
+        assert_eq!(loaded.public_to_bytes()?, state.public_to_bytes()?);
+        assert_eq!(loaded.secret_key_to_bytes()?, state.secret_key_to_bytes()?);
+
+        // The secret key can live somewhere else than the proofs
+        let secret_key_path = path.join("elsewhere").join("sk.bin");
+        fs::create_dir_all(secret_key_path.parent().unwrap())?;
+        state.save_split(path.join("public.bin"), &secret_key_path)?;
+        let loaded = IVCState::load_split(path.join("public.bin"), &secret_key_path)?;
+        assert_eq!(loaded.public_to_bytes()?, state.public_to_bytes()?);
+
+        fs::remove_dir_all(&path)?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomic_replaces_stale_tmp() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = temp_dir("stale-tmp");
+        fs::create_dir_all(&path)?;
+        let secret_path = path.join(IVC_SECRET_KEY_FILE);
+        let tmp_path = path.join(format!("{IVC_SECRET_KEY_FILE}.tmp"));
+        fs::write(&tmp_path, b"stale")?;
+        fs::set_permissions(&tmp_path, fs::Permissions::from_mode(0o644))?;
+
+        write_atomic(&secret_path, b"secret", true)?;
+        assert_eq!(fs::read(&secret_path)?, b"secret");
+        let mode = fs::metadata(&secret_path)?.permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        fs::remove_dir_all(&path)?;
+        Ok(())
+    }
+
+    /// A copy of `state`, which is not `Clone` so that the secret key is not copied by accident
+    fn copy(state: &IVCState) -> IVCState {
+        IVCState {
+            pk: state.pk.clone(),
+            sk: state.sk.clone(),
+            signature: state.signature,
+            i: state.i,
+            fp_proof: state.fp_proof.clone(),
+            fp_public_input: state.fp_public_input.clone(),
+            fq_proof: state.fq_proof.clone(),
+            fq_public_input: state.fq_public_input.clone(),
+        }
+    }
+
+    #[test]
+    fn test_load_rejects_tampered() -> Result<()> {
+        let state = IVCState::init().prove()?;
+        let sk_bytes = state.secret_key_to_bytes()?;
+        let public_bytes = state.public_to_bytes()?;
+        IVCState::from_bytes(&public_bytes, &sk_bytes)?;
+
+        // Each field changed on its own, with a matching key pair, is caught
+        let (sk, pk) = generate_keypair();
+        let signature = state.sk.sign(&[pk.0.x, pk.0.y]);
+        let one = Scalar::<PallasConfig>::one();
+        type Tampering<'a> = (&'a dyn Fn(&mut IVCState), &'a str);
+        let tamperings: [Tampering; 4] = [
+            (&|s| s.i += one, "another step"),
+            (
+                &|s| {
+                    s.sk = sk.clone();
+                    s.pk = pk.clone();
+                },
+                "another public key",
+            ),
+            (&|s| s.signature = signature, "another signature"),
+            (&|s| s.fp_public_input.public_inputs[0] += one, ""),
+        ];
+        for (tamper, reason) in tamperings {
+            let mut tampered = copy(&state);
+            tamper(&mut tampered);
+            let Err(err) = IVCState::from_bytes(
+                &tampered.public_to_bytes()?,
+                &tampered.secret_key_to_bytes()?,
+            ) else {
+                panic!("A tampered IVC state was loaded, expected: {reason}");
+            };
+            assert!(format!("{err:#}").contains(reason), "{err:#}");
+        }
+
+        // A public key that does not belong to the secret key
+        let mut tampered = copy(&state);
+        tampered.pk = pk;
+        assert!(IVCState::from_bytes(&tampered.public_to_bytes()?, &sk_bytes).is_err());
+
+        // Corrupted and truncated bytes
+        let mut corrupted = public_bytes.clone();
+        corrupted[0] ^= 1;
+        assert!(IVCState::from_bytes(&corrupted, &sk_bytes).is_err());
+        assert!(IVCState::from_bytes(&public_bytes[..public_bytes.len() - 1], &sk_bytes).is_err());
+        assert!(IVCState::from_bytes(&public_bytes, &public_bytes).is_err());
+
+        Ok(())
+    }
+}
@@ -1,11 +1,19 @@
 use anyhow::Result;
 use halo_accumulation::acc::Accumulator;
-use std::{cell::RefCell, marker::PhantomData};
+use log::debug;
+use std::{
+    fmt,
+    panic::Location,
+    path::Path,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
 
-use halo_group::{Affine, PallasConfig, PastaConfig, PastaFE, Scalar, VestaConfig};
+use halo_group::{
+    Affine, PallasConfig, PastaConfig, PastaFE, PastaFieldId, PublicParams, Scalar, VestaConfig,
+};
 
 use crate::{
-    circuit::{CircuitSpec, PlonkCircuit, Trace, TraceBuilder},
+    circuit::{CircuitDigest, CircuitSpec, CircuitStats, ProvingKey, Trace, TraceBuilder, Wire},
     frontend::primitives::{WireAffine, WireBool, WireScalar},
 };
 
@@ -18,45 +26,214 @@ pub mod primitives;
 pub mod signature;
 
 thread_local! {
-    static FRONTEND: RefCell<Frontend> = RefCell::new(Frontend::new());
+    static FRONTEND: ThreadBuilder = ThreadBuilder(Box::leak(Box::default()));
 }
 
-#[derive(Clone)]
-pub struct Frontend {
-    circuit: CircuitSpec,
+/// The builder of the thread-local frontend. It is leaked so that its wires can borrow it for
+/// `'static`, and its circuit is emptied when the thread exits, which leaves an empty builder
+/// behind for each thread that used it.
+struct ThreadBuilder(&'static CircuitBuilder);
+impl Drop for ThreadBuilder {
+    fn drop(&mut self) {
+        *self.0.lock() = CircuitSpec::new()
+    }
 }
+
+/// The thread-local frontend, a compatibility layer over `CircuitBuilder` for the wires and
+/// gadgets created without a builder. Each thread has a builder of its own, which is replaced
+/// by an empty circuit with `Frontend::reset`.
+pub enum Frontend {}
 impl Frontend {
+    /// Replaces the thread-local circuit with an empty one. The wires created before are rejected
+    /// by the new circuit.
+    pub fn reset() {
+        *CircuitBuilder::current().lock() = CircuitSpec::new()
+    }
+
+    /// See `CircuitBuilder::row_count`
+    pub fn row_count() -> [usize; 2] {
+        CircuitBuilder::current().row_count()
+    }
+
+    /// See `CircuitBuilder::trace_shape`
+    pub fn trace_shape() -> ([usize; 2], [usize; 2]) {
+        CircuitBuilder::current().trace_shape()
+    }
+
+    /// See `CircuitBuilder::digest`
+    pub fn digest() -> CircuitDigest {
+        CircuitBuilder::current().digest()
+    }
+
+    /// See `CircuitBuilder::stats`
+    pub fn stats() -> CircuitStats {
+        CircuitBuilder::current().stats()
+    }
+
+    /// See `CircuitBuilder::hiding`
+    pub fn hiding() {
+        CircuitBuilder::current().hiding()
+    }
+
+    /// See `CircuitBuilder::scope`
+    pub fn scope(name: &'static str) -> Scope<'static> {
+        CircuitBuilder::current().scope(name)
+    }
+
+    /// Runs `f` on the thread-local circuit, see `CircuitBuilder::with_circuit`
+    #[cfg(test)]
+    #[track_caller]
+    pub(crate) fn with_circuit<T>(f: impl FnOnce(&mut CircuitSpec) -> T) -> T {
+        CircuitBuilder::current().with_circuit(f)
+    }
+}
+
+/// The guard of `CircuitBuilder::scope`, which restores the outer scope when it is dropped
+#[must_use]
+pub struct Scope<'a> {
+    builder: &'a CircuitBuilder,
+    outer: usize,
+}
+impl Drop for Scope<'_> {
+    fn drop(&mut self) {
+        self.builder.lock().scope = self.outer;
+    }
+}
+
+/// A circuit being built. The wires and gadgets borrow the builder they are created in, with the
+/// `_in` constructors, and the operations on wires add their gates to it, on any thread. The
+/// wires of other circuits are rejected. Every builder starts from an empty circuit, so none of
+/// them needs a `Frontend::reset`.
+pub struct CircuitBuilder {
+    spec: Mutex<CircuitSpec>,
+}
+impl CircuitBuilder {
     pub fn new() -> Self {
         Self {
-            circuit: CircuitSpec::new(),
+            spec: Mutex::new(CircuitSpec::new()),
         }
     }
 
-    pub fn reset() {
-        FRONTEND.with(|frontend| {
-            let mut frontend = frontend.borrow_mut();
-            *frontend = Self::new()
-        })
+    /// The builder of the thread-local frontend, which the constructors of wires and gadgets
+    /// without a builder are a wrapper of
+    pub(crate) fn current() -> &'static Self {
+        FRONTEND.with(|builder| builder.0)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CircuitSpec> {
+        self.spec.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Runs `f` on this circuit, with the gates it adds attributed to the caller, see
+    /// `Trace::check_constraints`
+    #[track_caller]
+    pub(crate) fn with_circuit<T>(&self, f: impl FnOnce(&mut CircuitSpec) -> T) -> T {
+        self.lock().at(Location::caller(), f)
+    }
+
+    /// The rows used by the circuit built so far, before padding and before the optimizations
+    /// of `Call::trace`, as [Fp, Fq]
+    pub fn row_count(&self) -> [usize; 2] {
+        let circuit = self.lock();
+        [PastaFieldId::Fp, PastaFieldId::Fq].map(|fid| circuit.used_rows(fid))
+    }
+
+    /// The rows, padded to a power of two, and the public input counts of the trace of the
+    /// circuit built so far, as [Fp, Fq]. It is the shape of the optimized circuit.
+    pub fn trace_shape(&self) -> ([usize; 2], [usize; 2]) {
+        let (circuit, _) = self.lock().optimize();
+        let fids = [PastaFieldId::Fp, PastaFieldId::Fq];
+        (
+            fids.map(|fid| circuit.padded_row_count(fid)),
+            fids.map(|fid| circuit.public_input_count(fid)),
+        )
+    }
+
+    /// The digest of the trace of the circuit built so far, which is that of the optimized
+    /// circuit, see `CircuitSpec::digest`
+    pub fn digest(&self) -> CircuitDigest {
+        self.lock().optimize().0.digest()
+    }
+
+    /// The stats of the circuit built so far after the optimizations of `Call::trace`, which
+    /// describe the trace, see `CircuitSpec::stats`
+    pub fn stats(&self) -> CircuitStats {
+        self.lock().optimize().0.stats()
+    }
+
+    /// Pads the circuit to at least `HIDING_MIN_ROWS` rows, which its hiding proofs need. Other
+    /// circuits keep the minimum of `MIN_ROWS`.
+    pub fn hiding(&self) {
+        self.lock().hiding = true
+    }
+
+    /// Attributes the gates added until the returned guard is dropped to the scope `name`, nested
+    /// in the current scope, see `CircuitStats`
+    pub fn scope(&self, name: &'static str) -> Scope<'_> {
+        let outer = self.lock().enter_scope(name);
+        Scope {
+            builder: self,
+            outer,
+        }
+    }
+
+    /// The position of the public input `wire` in the public inputs of the trace of its field
+    pub(crate) fn public_input_index(&self, wire: Wire) -> usize {
+        self.lock()
+            .public_input_index(wire)
+            .expect("Not a public input wire")
+    }
+
+    /// A call of the circuit built so far, like `Call::new` for the thread-local frontend
+    pub fn call(&self) -> Call {
+        Call {
+            trace_builder: TraceBuilder::new(self.lock().clone()),
+            check: false,
+        }
+    }
+
+    /// A call of the circuit built so far without the optimizations of `Call::trace`
+    #[cfg(test)]
+    pub(crate) fn call_unoptimized(&self) -> Call {
+        Call {
+            trace_builder: TraceBuilder::unoptimized(self.lock().clone()),
+            check: false,
+        }
+    }
+}
+
+impl Default for CircuitBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for CircuitBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CircuitBuilder").finish_non_exhaustive()
     }
 }
 
 pub struct Call {
     trace_builder: TraceBuilder,
+    /// Whether tracing checks the constraints, see `Call::check`
+    check: bool,
 }
 impl Call {
     pub fn new() -> Self {
-        FRONTEND.with(|frontend| {
-            let frontend = frontend.borrow();
-            let trace_builder = TraceBuilder::new(frontend.circuit.clone());
-            Call { trace_builder }
-        })
+        CircuitBuilder::current().call()
+    }
+    /// Makes `trace` check every constraint of the traces, so an unsatisfied one is reported with
+    /// the gate and call site it comes from instead of as a proof that does not verify, see
+    /// `Trace::check_constraints`
+    pub fn check(&mut self) {
+        self.check = true;
     }
     pub fn witness<P: PastaConfig>(&mut self, fp: WireScalar<P>, scalar: Scalar<P>) -> Result<()> {
         self.trace_builder
             .witness(fp.wire, PastaFE::from_scalar::<P>(scalar))
     }
     pub fn witness_bool<P: PastaConfig>(&mut self, fp: WireBool<P>, b: bool) -> Result<()> {
-        assert_eq!(fp.wire.fid, P::SFID);
         let fe = if b {
             PastaFE::one(Some(P::SFID))
         } else {
@@ -116,14 +293,290 @@ impl Call {
         self.trace_builder
             .public_input(fp.wire, PastaFE::from_scalar::<P>(scalar))
     }
+    /// The traces of the call. With `check`, an unsatisfied constraint is reported here.
     pub fn trace(self) -> Result<(Trace<PallasConfig>, Trace<VestaConfig>)> {
-        Ok(self.trace_builder.trace(None, None)?)
+        self.trace_with_params(None, None)
     }
+    /// With `keys`, which must come from `preprocess` of the same circuit, only the witness
+    /// polynomials are computed and everything else is taken from the keys
     pub fn trace_with_params(
         self,
         accs_prev: Option<(Accumulator<PallasConfig>, Accumulator<VestaConfig>)>,
-        static_circuit: Option<(PlonkCircuit<PallasConfig>, PlonkCircuit<VestaConfig>)>,
+        keys: Option<(&ProvingKey<PallasConfig>, &ProvingKey<VestaConfig>)>,
+    ) -> Result<(Trace<PallasConfig>, Trace<VestaConfig>)> {
+        let pps = (PublicParams::get_pp(), PublicParams::get_pp());
+        self.trace_with_pp(pps, accs_prev, keys)
+    }
+    /// Same as `trace_with_params`, committing with the public parameters `pps` of each field,
+    /// which must be the ones `keys` were committed with
+    pub fn trace_with_pp(
+        self,
+        pps: (&PublicParams<PallasConfig>, &PublicParams<VestaConfig>),
+        accs_prev: Option<(Accumulator<PallasConfig>, Accumulator<VestaConfig>)>,
+        keys: Option<(&ProvingKey<PallasConfig>, &ProvingKey<VestaConfig>)>,
     ) -> Result<(Trace<PallasConfig>, Trace<VestaConfig>)> {
-        Ok(self.trace_builder.trace(accs_prev, static_circuit)?)
+        let (fp_trace, fq_trace) = self.trace_builder.trace_with_pp(pps, accs_prev, keys)?;
+        if self.check {
+            fp_trace.check_constraints()?;
+            fq_trace.check_constraints()?;
+        }
+        Ok((fp_trace, fq_trace))
+    }
+
+    /// Traces the circuit and keeps the parts that do not depend on the assigned values, which
+    /// may be dummies
+    pub fn preprocess(self) -> Result<(ProvingKey<PallasConfig>, ProvingKey<VestaConfig>)> {
+        let digest = self.trace_builder.digest();
+        let (fp_trace, fq_trace) = self.trace_builder.trace(None, None)?;
+        Ok((
+            ProvingKey::from_trace(digest, &fp_trace),
+            ProvingKey::from_trace(digest, &fq_trace),
+        ))
+    }
+
+    /// Same as `preprocess`, but the keys are read from `cache_dir` if this circuit was
+    /// preprocessed before, and written there otherwise
+    pub fn preprocess_cached(
+        self,
+        cache_dir: impl AsRef<Path>,
+    ) -> Result<(ProvingKey<PallasConfig>, ProvingKey<VestaConfig>)> {
+        let cache_dir = cache_dir.as_ref();
+        let digest = self.trace_builder.digest();
+        let cached = ProvingKey::load(cache_dir, digest)
+            .and_then(|fp_pk| Ok((fp_pk, ProvingKey::load(cache_dir, digest)?)));
+        match cached {
+            Ok(keys) => Ok(keys),
+            Err(e) => {
+                debug!("Preprocessing the circuit, no cached keys: {e:#}");
+                let (fp_pk, fq_pk) = self.preprocess()?;
+                fp_pk.save(cache_dir)?;
+                fq_pk.save(cache_dir)?;
+                Ok((fp_pk, fq_pk))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use halo_group::{PallasConfig, PastaConfig, ark_std::test_rng};
+    use rayon::prelude::*;
+
+    use crate::{
+        frontend::{
+            Call, CircuitBuilder, Frontend, poseidon::hash::PoseidonHash, primitives::WireScalar,
+        },
+        plonk::PlonkProof,
+        utils::GENERIC_GATES,
+    };
+
+    fn mul_add(
+        builder: &CircuitBuilder,
+    ) -> (WireScalar<'_, PallasConfig>, WireScalar<'_, PallasConfig>) {
+        let x = WireScalar::<PallasConfig>::witness_in(builder);
+        let y = WireScalar::<PallasConfig>::witness_in(builder);
+        (x * y + WireScalar::constant_in(builder, PallasConfig::scalar_from_u64(5))).output();
+        (x, y)
+    }
+
+    #[test]
+    fn builders_are_independent() -> Result<()> {
+        let rng = &mut test_rng();
+        let expected = CircuitBuilder::new();
+        mul_add(&expected);
+        mul_add(&expected);
+
+        // Circuits built in turns do not see each other's gates, nor those of the thread-local
+        // circuit
+        Frontend::reset();
+        WireScalar::<PallasConfig>::witness();
+        let row_count = Frontend::row_count();
+        let a = CircuitBuilder::new();
+        let b = CircuitBuilder::new();
+        let c = CircuitBuilder::new();
+        let (x1, y1) = mul_add(&a);
+        mul_add(&b);
+        mul_add(&c);
+        mul_add(&b);
+        let (x2, y2) = mul_add(&a);
+        assert_eq!(a.digest(), expected.digest());
+        assert_eq!(b.digest(), expected.digest());
+        assert_ne!(c.digest(), expected.digest());
+        assert_eq!(Frontend::row_count(), row_count);
+        Frontend::reset();
+
+        let mut call = a.call();
+        for (wire, x) in [(x1, 6), (y1, 7), (x2, 2), (y2, 3)] {
+            call.witness(wire, PallasConfig::scalar_from_u64(x))?;
+        }
+        let (fp_trace, _) = call.trace()?;
+        assert_eq!(
+            fp_trace.outputs,
+            [47, 11].map(PallasConfig::scalar_from_u64)
+        );
+        let (circuit, x, w) = fp_trace.consume();
+        PlonkProof::naive_prover(rng, circuit, &x, w).verify(circuit, &x)
+    }
+
+    #[test]
+    fn builders_on_rayon_workers() {
+        let expected = CircuitBuilder::new();
+        mul_add(&expected);
+
+        let digests: Vec<_> = (0..8)
+            .into_par_iter()
+            .map(|_| {
+                let builder = CircuitBuilder::new();
+                mul_add(&builder);
+                builder
+            })
+            .map(|builder| builder.digest())
+            .collect();
+        assert!(digests.iter().all(|digest| *digest == expected.digest()));
+    }
+
+    #[test]
+    fn wires_add_gates_to_their_circuit() -> Result<()> {
+        let a = CircuitBuilder::new();
+        let b = CircuitBuilder::new();
+        let (x, y) = mul_add(&a);
+        let z = WireScalar::<PallasConfig>::witness_in(&b);
+        let rows = (a.row_count(), b.row_count());
+
+        // Operations on the wires of `a` add their gates to `a`, also on the rayon workers
+        (0..4).into_par_iter().for_each(|_| (x * y).output());
+        // Builders can be shared by the workers of a parallel iterator
+        (0..4).into_par_iter().for_each(|_| {
+            mul_add(&a);
+        });
+        assert_eq!(b.row_count(), rows.1);
+        let expected = CircuitBuilder::new();
+        let (x_e, y_e) = mul_add(&expected);
+        (0..4).for_each(|_| (x_e * y_e).output());
+        (0..4).for_each(|_| {
+            mul_add(&expected);
+        });
+        assert_eq!(a.row_count(), expected.row_count());
+        assert_ne!(a.row_count(), rows.0);
+
+        // The wires of other circuits are not inputs of a call
+        let mut call = a.call();
+        assert!(call.witness(z, PallasConfig::scalar_from_u64(1)).is_err());
+        let mut call = b.call();
+        assert!(call.witness(x, PallasConfig::scalar_from_u64(1)).is_err());
+        call.witness(z, PallasConfig::scalar_from_u64(1))
+    }
+
+    #[test]
+    #[should_panic(expected = "belongs to another circuit")]
+    fn foreign_wires_are_rejected() {
+        let a = CircuitBuilder::new();
+        let b = CircuitBuilder::new();
+        let (x, _) = mul_add(&a);
+        let _ = x * WireScalar::witness_in(&b);
+    }
+
+    #[test]
+    fn generic_gates_share_rows() -> Result<()> {
+        let rng = &mut test_rng();
+        let n = 2 * GENERIC_GATES + 1;
+
+        let builder = CircuitBuilder::new();
+        let rows = builder.row_count();
+        let xs: Vec<WireScalar<PallasConfig>> =
+            (0..n).map(|_| WireScalar::witness_in(&builder)).collect();
+        let ys: Vec<WireScalar<PallasConfig>> =
+            (0..n).map(|_| WireScalar::witness_in(&builder)).collect();
+        // n products and sums, interleaved, and a negation with the sums
+        let mut product = WireScalar::one_in(&builder);
+        let mut sum = -WireScalar::one_in(&builder);
+        for (x, y) in xs.iter().zip(&ys) {
+            product *= *x;
+            sum += *x;
+            x.assert_eq(*y);
+        }
+        product.output();
+        sum.output();
+        // The n + 1 additions, n products and n assertions take 3, 3 and 3 rows
+        assert_eq!(builder.row_count()[0] - rows[0], 9);
+
+        let values: Vec<_> = (0..n as u64)
+            .map(|i| PallasConfig::scalar_from_u64(i + 2))
+            .collect();
+        let mut satisfied = |wrong: Option<usize>| -> Result<bool> {
+            let mut call = builder.call();
+            for (i, ((x, y), v)) in xs.iter().zip(&ys).zip(&values).enumerate() {
+                call.witness(*x, *v)?;
+                let v = if wrong == Some(i) { *v + v } else { *v };
+                call.witness(*y, v)?;
+            }
+            let (fp_trace, _) = call.trace()?;
+            let (circuit, x, w) = fp_trace.consume();
+            Ok(PlonkProof::naive_prover(rng, circuit, &x, w)
+                .verify(circuit, &x)
+                .is_ok())
+        };
+
+        assert!(satisfied(None)?);
+        // Every position of a row is constrained, not only the first
+        for i in [0, 3, GENERIC_GATES + 4, 2 * GENERIC_GATES] {
+            assert!(!satisfied(Some(i))?);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn unsatisfied_gates_are_located() -> Result<()> {
+        let builder = CircuitBuilder::new();
+        let x = WireScalar::<PallasConfig>::witness_in(&builder);
+        let y = WireScalar::<PallasConfig>::witness_in(&builder);
+        let line = line!() + 2;
+        (x * x).output();
+        (x * y).assert_eq(y + y);
+
+        let assign = |x_v: u64| -> Result<Call> {
+            let mut call = builder.call();
+            call.witness(x, PallasConfig::scalar_from_u64(x_v))?;
+            call.witness(y, PallasConfig::scalar_from_u64(3))?;
+            Ok(call)
+        };
+        let check = |x_v: u64| -> Result<()> {
+            let mut call = assign(x_v)?;
+            call.check();
+            call.trace().map(|_| ())
+        };
+
+        check(2)?;
+        // Only checked calls look at the constraints
+        assign(3)?.trace()?;
+        // The gates of composite primitives are attributed to their caller
+        let err = check(3).unwrap_err().to_string();
+        assert!(err.contains(&format!("{}:{line}", file!())), "{err}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn scopes_nest() {
+        let builder = CircuitBuilder::new();
+        {
+            let _scope = builder.scope("merkle");
+            let x = WireScalar::<PallasConfig>::witness_in(&builder);
+            PoseidonHash::hash(&[x, x]).output();
+        }
+        WireScalar::<PallasConfig>::witness_in(&builder).output();
+
+        let stats = builder.stats();
+        let sponge = &stats.fp.scopes["merkle/sponge"];
+        assert_eq!((sponge["Poseidon"], sponge["PoseidonEnd"]), (11, 1));
+        let merkle = &stats.fp.scopes["merkle"];
+        assert_eq!((merkle["Witness"], merkle["Output"]), (1, 1));
+        // The scope ends with its guard
+        assert_eq!(
+            (stats.fp.gates["Witness"], stats.fp.gates["Output"]),
+            (2, 2)
+        );
     }
 }
@@ -4,7 +4,7 @@ use halo_group::{PastaConfig, PublicParams, ark_ec::CurveGroup};
 use halo_poseidon::Protocols;
 
 use crate::frontend::{
-    Call,
+    Call, CircuitBuilder,
     poseidon::outer_sponge::OuterSponge,
     primitives::{WireAffine, WireBool, WireScalar},
 };
@@ -12,29 +12,29 @@ use crate::frontend::{
 pub trait CallInstance {
     fn public_input_eval_proof<P: PastaConfig>(
         &mut self,
-        wire_eval_proof: &WireEvalProof<P>,
+        wire_eval_proof: &WireEvalProof<'_, P>,
         eval_proof: &EvalProof<P>,
     ) -> Result<()>;
     fn public_input_instance<P: PastaConfig>(
         &mut self,
-        wire_instance: &WireInstance<P>,
+        wire_instance: &WireInstance<'_, P>,
         instance: &Instance<P>,
     ) -> Result<()>;
     fn witness_instance<P: PastaConfig>(
         &mut self,
-        wire_instance: &WireInstance<P>,
+        wire_instance: &WireInstance<'_, P>,
         instance: &Instance<P>,
     ) -> Result<()>;
     fn witness_eval_proof<P: PastaConfig>(
         &mut self,
-        wire_instance: &WireEvalProof<P>,
+        wire_instance: &WireEvalProof<'_, P>,
         instance: &EvalProof<P>,
     ) -> Result<()>;
 }
 impl CallInstance for Call {
     fn witness_instance<P: PastaConfig>(
         &mut self,
-        wire_instance: &WireInstance<P>,
+        wire_instance: &WireInstance<'_, P>,
         instance: &Instance<P>,
     ) -> Result<()> {
         self.witness_eval_proof(&wire_instance.pi, &instance.pi)?;
@@ -46,7 +46,7 @@ impl CallInstance for Call {
     }
     fn public_input_instance<P: PastaConfig>(
         &mut self,
-        wire_instance: &WireInstance<P>,
+        wire_instance: &WireInstance<'_, P>,
         instance: &Instance<P>,
     ) -> Result<()> {
         self.public_input_eval_proof(&wire_instance.pi, &instance.pi)?;
@@ -58,7 +58,7 @@ impl CallInstance for Call {
     }
     fn witness_eval_proof<P: PastaConfig>(
         &mut self,
-        wire_eval_proof: &WireEvalProof<P>,
+        wire_eval_proof: &WireEvalProof<'_, P>,
         eval_proof: &EvalProof<P>,
     ) -> Result<()> {
         assert_eq!(eval_proof.Ls.len(), eval_proof.Rs.len());
@@ -70,12 +70,23 @@ impl CallInstance for Call {
         }
         self.witness_affine(wire_eval_proof.U, eval_proof.U.into_affine())?;
         self.witness(wire_eval_proof.c, eval_proof.c)?;
+        assert_eq!(eval_proof.C_bar.is_some(), wire_eval_proof.C_bar.is_some());
+        if let (Some(wire_C_bar), Some(C_bar)) = (wire_eval_proof.C_bar, eval_proof.C_bar) {
+            self.witness_affine(wire_C_bar, C_bar.into_affine())?;
+        }
+        assert_eq!(
+            eval_proof.w_prime.is_some(),
+            wire_eval_proof.w_prime.is_some()
+        );
+        if let (Some(wire_w_prime), Some(w_prime)) = (wire_eval_proof.w_prime, eval_proof.w_prime) {
+            self.witness(wire_w_prime, w_prime)?;
+        }
 
         Ok(())
     }
     fn public_input_eval_proof<P: PastaConfig>(
         &mut self,
-        wire_eval_proof: &WireEvalProof<P>,
+        wire_eval_proof: &WireEvalProof<'_, P>,
         eval_proof: &EvalProof<P>,
     ) -> Result<()> {
         assert_eq!(eval_proof.Ls.len(), eval_proof.Rs.len());
@@ -87,23 +98,34 @@ impl CallInstance for Call {
         }
         self.public_input_affine(wire_eval_proof.U, eval_proof.U.into_affine())?;
         self.public_input(wire_eval_proof.c, eval_proof.c)?;
+        assert_eq!(eval_proof.C_bar.is_some(), wire_eval_proof.C_bar.is_some());
+        if let (Some(wire_C_bar), Some(C_bar)) = (wire_eval_proof.C_bar, eval_proof.C_bar) {
+            self.public_input_affine(wire_C_bar, C_bar.into_affine())?;
+        }
+        assert_eq!(
+            eval_proof.w_prime.is_some(),
+            wire_eval_proof.w_prime.is_some()
+        );
+        if let (Some(wire_w_prime), Some(w_prime)) = (wire_eval_proof.w_prime, eval_proof.w_prime) {
+            self.public_input(wire_w_prime, w_prime)?;
+        }
 
         Ok(())
     }
 }
 
 #[derive(Clone)]
-pub struct WireHPoly<P: PastaConfig> {
-    pub xis: Vec<WireScalar<P>>,
+pub struct WireHPoly<'a, P: PastaConfig> {
+    pub xis: Vec<WireScalar<'a, P>>,
 }
-impl<P: PastaConfig> WireHPoly<P> {
-    pub(crate) fn new(xis: Vec<WireScalar<P>>) -> Self {
+impl<'a, P: PastaConfig> WireHPoly<'a, P> {
+    pub(crate) fn new(xis: Vec<WireScalar<'a, P>>) -> Self {
         Self { xis }
     }
 
-    pub(crate) fn eval(&self, z: WireScalar<P>) -> WireScalar<P> {
+    pub(crate) fn eval(&self, z: WireScalar<'a, P>) -> WireScalar<'a, P> {
         let lg_n = self.xis.len() - 1;
-        let one = WireScalar::<P>::one();
+        let one = WireScalar::<P>::one_in(z.builder);
 
         let mut v = one + self.xis[lg_n] * z;
         let mut z_i = z;
@@ -117,101 +139,166 @@ impl<P: PastaConfig> WireHPoly<P> {
 }
 
 #[derive(Clone)]
-pub struct WireEvalProof<P: PastaConfig> {
-    pub Ls: Vec<WireAffine<P>>,
-    pub Rs: Vec<WireAffine<P>>,
-    pub U: WireAffine<P>,
-    pub c: WireScalar<P>,
+pub struct WireEvalProof<'a, P: PastaConfig> {
+    pub Ls: Vec<WireAffine<'a, P>>,
+    pub Rs: Vec<WireAffine<'a, P>>,
+    pub U: WireAffine<'a, P>,
+    pub c: WireScalar<'a, P>,
+    pub C_bar: Option<WireAffine<'a, P>>,
+    pub w_prime: Option<WireScalar<'a, P>>,
 }
-impl<P: PastaConfig> WireEvalProof<P> {
+impl<'a, P: PastaConfig> WireEvalProof<'a, P> {
     pub fn witness(n: usize) -> Self {
+        Self::witness_in(CircuitBuilder::current(), n)
+    }
+    pub fn witness_in(builder: &'a CircuitBuilder, n: usize) -> Self {
         let lg_n = n.ilog2() as usize;
         let mut Ls = Vec::with_capacity(lg_n);
         let mut Rs = Vec::with_capacity(lg_n);
         for _ in 0..lg_n {
-            Ls.push(WireAffine::witness());
-            Rs.push(WireAffine::witness());
+            Ls.push(WireAffine::witness_in(builder));
+            Rs.push(WireAffine::witness_in(builder));
+        }
+        let U = WireAffine::<P>::witness_in(builder);
+        let c = WireScalar::<P>::witness_in(builder);
+        WireEvalProof {
+            Ls,
+            Rs,
+            U,
+            c,
+            C_bar: None,
+            w_prime: None,
         }
-        let U = WireAffine::<P>::witness();
-        let c = WireScalar::<P>::witness();
-        WireEvalProof { Ls, Rs, U, c }
+    }
+    /// An evaluation proof made with hiding randomness, i.e. with `C_bar` and `w_prime` present
+    pub fn witness_hiding(n: usize) -> Self {
+        Self::witness_hiding_in(CircuitBuilder::current(), n)
+    }
+    pub fn witness_hiding_in(builder: &'a CircuitBuilder, n: usize) -> Self {
+        let mut pi = Self::witness_in(builder, n);
+        pi.C_bar = Some(WireAffine::<P>::witness_in(builder));
+        pi.w_prime = Some(WireScalar::<P>::witness_in(builder));
+        pi
     }
     pub fn public_input(n: usize) -> Self {
+        Self::public_input_in(CircuitBuilder::current(), n)
+    }
+    pub fn public_input_in(builder: &'a CircuitBuilder, n: usize) -> Self {
         let lg_n = n.ilog2() as usize;
         let mut Ls = Vec::with_capacity(lg_n);
         let mut Rs = Vec::with_capacity(lg_n);
         for _ in 0..lg_n {
-            Ls.push(WireAffine::public_input());
-            Rs.push(WireAffine::public_input());
+            Ls.push(WireAffine::public_input_in(builder));
+            Rs.push(WireAffine::public_input_in(builder));
+        }
+        let U = WireAffine::<P>::public_input_in(builder);
+        let c = WireScalar::<P>::public_input_in(builder);
+        WireEvalProof {
+            Ls,
+            Rs,
+            U,
+            c,
+            C_bar: None,
+            w_prime: None,
         }
-        let U = WireAffine::<P>::public_input();
-        let c = WireScalar::<P>::public_input();
-        WireEvalProof { Ls, Rs, U, c }
     }
 }
 
 #[derive(Clone, Copy)]
-pub struct WirePublicParams<P: PastaConfig> {
-    pub H: WireAffine<P>,
+pub struct WirePublicParams<'a, P: PastaConfig> {
+    pub H: WireAffine<'a, P>,
     pub d: usize,
     pub lg_n: usize,
 }
-impl<P: PastaConfig> WirePublicParams<P> {
+impl<'a, P: PastaConfig> WirePublicParams<'a, P> {
     pub fn new(n: usize) -> Self {
+        Self::new_in(CircuitBuilder::current(), n)
+    }
+    pub fn new_in(builder: &'a CircuitBuilder, n: usize) -> Self {
         assert!(n.is_power_of_two());
         let H = PublicParams::get_pp().H.into_affine();
         let d = n - 1;
         let lg_n = n.ilog2() as usize;
         WirePublicParams {
-            H: WireAffine::constant(H),
+            H: WireAffine::constant_in(builder, H),
             d,
             lg_n,
         }
     }
 }
 #[derive(Clone)]
-pub struct WireInstance<P: PastaConfig> {
-    pub C: WireAffine<P>,
-    pub z: WireScalar<P>,
-    pub v: WireScalar<P>,
-    pub pi: WireEvalProof<P>,
+pub struct WireInstance<'a, P: PastaConfig> {
+    pub C: WireAffine<'a, P>,
+    pub z: WireScalar<'a, P>,
+    pub v: WireScalar<'a, P>,
+    pub pi: WireEvalProof<'a, P>,
 }
-impl<P: PastaConfig> WireInstance<P> {
-    pub fn new(C: WireAffine<P>, z: WireScalar<P>, v: WireScalar<P>, pi: WireEvalProof<P>) -> Self {
+impl<'a, P: PastaConfig> WireInstance<'a, P> {
+    pub fn new(
+        C: WireAffine<'a, P>,
+        z: WireScalar<'a, P>,
+        v: WireScalar<'a, P>,
+        pi: WireEvalProof<'a, P>,
+    ) -> Self {
         WireInstance { C, z, v, pi }
     }
 
     pub fn witness(n: usize) -> Self {
-        let C = WireAffine::<P>::witness();
-        let z = WireScalar::<P>::witness();
-        let v = WireScalar::<P>::witness();
-        let pi = WireEvalProof::witness(n);
+        Self::witness_in(CircuitBuilder::current(), n)
+    }
+
+    pub fn witness_in(builder: &'a CircuitBuilder, n: usize) -> Self {
+        let C = WireAffine::<P>::witness_in(builder);
+        let z = WireScalar::<P>::witness_in(builder);
+        let v = WireScalar::<P>::witness_in(builder);
+        let pi = WireEvalProof::witness_in(builder, n);
         WireInstance { C, z, v, pi }
     }
 
     pub fn public_input(n: usize) -> Self {
-        let C = WireAffine::<P>::public_input();
-        let z = WireScalar::<P>::public_input();
-        let v = WireScalar::<P>::public_input();
-        let pi = WireEvalProof::public_input(n);
+        Self::public_input_in(CircuitBuilder::current(), n)
+    }
+
+    pub fn public_input_in(builder: &'a CircuitBuilder, n: usize) -> Self {
+        let C = WireAffine::<P>::public_input_in(builder);
+        let z = WireScalar::<P>::public_input_in(builder);
+        let v = WireScalar::<P>::public_input_in(builder);
+        let pi = WireEvalProof::public_input_in(builder, n);
         WireInstance { C, z, v, pi }
     }
 
     pub fn succinct_check(
         self,
-        pp: WirePublicParams<P>,
-    ) -> (WireBool<P::OtherCurve>, WireHPoly<P>, WireAffine<P>) {
-        let mut transcript = OuterSponge::new(Protocols::PCDL);
+        pp: WirePublicParams<'a, P>,
+    ) -> (
+        WireBool<'a, P::OtherCurve>,
+        WireHPoly<'a, P>,
+        WireAffine<'a, P>,
+    ) {
+        let builder = self.C.builder();
+        let _scope = builder.scope("pcdl");
+        let mut transcript = OuterSponge::new_in(builder, Protocols::PCDL);
         let WireInstance { C, z, v, pi } = self;
 
         // 1. Parse rk as (⟨group⟩, S, H, d'), and π as (L, R, U, c, C_bar, ω').
-        let WireEvalProof { Ls, Rs, U, c } = pi;
+        #[rustfmt::skip]
+        let WireEvalProof { Ls, Rs, U, c, C_bar, w_prime } = pi;
 
         // 2. Check that d = d'. Irrelevant, we just removed d'
         //ensure!(d == d_prime, "d ≠ d'");
 
         // 4. Compute the non-hiding commitment C' := C + α · C_bar − ω'· S ∈ G.
-        let C_prime = C;
+        let C_prime = if let (Some(C_bar), Some(w_prime)) = (C_bar, w_prime) {
+            // (3). Compute the challenge α := ρ_0(C, z, v, C_bar) ∈ F^∗_q.
+            transcript.absorb_g(&[C, C_bar]);
+            transcript.absorb_fp(&[z, v]);
+            let a = transcript.challenge();
+
+            let S = WireAffine::constant_in(builder, PublicParams::get_pp().S.into_affine());
+            C + C_bar * a + -(S * w_prime)
+        } else {
+            C
+        };
 
         // 5. Compute the 0-th challenge ξ_0 := ρ_0(C', z, v), and set H' := ξ_0 · H ∈ G.
         transcript.absorb_g(&[C_prime]);
@@ -1,59 +1,60 @@
 use std::array;
 
-use anyhow::Result;
+use anyhow::{Result, ensure};
 use halo_group::{
     Domain, PastaConfig, Scalar, ark_ec::CurveGroup, ark_poly::EvaluationDomain, ark_std::Zero,
 };
 use halo_poseidon::Protocols;
 
 use crate::{
-    circuit::{PlonkCircuit, PlonkCircuitCommitments, PlonkPublicInputs},
+    circuit::{
+        PlonkCircuit, PlonkCircuitCommitments, PlonkCircuitLookupCommitments, PlonkPublicInputs,
+    },
     frontend::{
-        Call,
+        Call, CircuitBuilder,
         asdl::{CallAccumulator, WireAccumulator},
         pcdl::{CallInstance, WireEvalProof, WireInstance, WirePublicParams},
         poseidon::outer_sponge::OuterSponge,
         primitives::{WireAffine, WireBool, WireScalar},
     },
     plonk::{
-        PlonkProof, affine_add_constraints_generic, affine_mul_constraints_generic, eq_generic,
-        geometric_generic, poseidon_constraints_generic, pow_n, public_input_eval_generic,
-        range_check_generic, t_reconstruct_generic,
+        PlonkProof, gate_terms_generic, geometric_generic, lookup_query_generic,
+        plookup_terms_generic, pow_n, public_input_eval_generic,
     },
-    utils::{Q_POLYS, R_POLYS, S_POLYS, T_POLYS, W_POLYS},
+    utils::{L_POLYS, Q_POLYS, R_POLYS, S_POLYS, T_POLYS, W_POLYS, permutation_shifts},
 };
 
 pub trait CallPlonk {
     fn witness_plonk_proof<P: PastaConfig>(
         &mut self,
-        wire_proof: &WirePlonkProof<P>,
+        wire_proof: &WirePlonkProof<'_, P>,
         proof: &PlonkProof<P>,
     ) -> Result<()>;
     fn public_input_plonk_proof<P: PastaConfig>(
         &mut self,
-        wire_proof: WirePlonkProof<P>,
+        wire_proof: WirePlonkProof<'_, P>,
         proof: PlonkProof<P>,
     ) -> Result<()>;
     fn witness_plonk_public_input<P: PastaConfig>(
         &mut self,
-        wire_public_input: &WirePlonkPublicInputs<P>,
+        wire_public_input: &WirePlonkPublicInputs<'_, P>,
         plonk_public_input: &PlonkPublicInputs<P>,
     ) -> Result<()>;
     fn public_input_plonk_public_input<P: PastaConfig>(
         &mut self,
-        wire_public_input: &WirePlonkPublicInputs<P>,
+        wire_public_input: &WirePlonkPublicInputs<'_, P>,
         plonk_public_input: &PlonkPublicInputs<P>,
     ) -> Result<()>;
     fn public_input_plonk_circuit<P: PastaConfig>(
         &mut self,
-        wire_plonk_circuit: &WirePlonkCircuit<P>,
+        wire_plonk_circuit: &WirePlonkCircuit<'_, P>,
         plonk_circuit: &PlonkCircuit<P>,
     ) -> Result<()>;
 }
 impl CallPlonk for Call {
     fn witness_plonk_proof<P: PastaConfig>(
         &mut self,
-        wire_proof: &WirePlonkProof<P>,
+        wire_proof: &WirePlonkProof<'_, P>,
         proof: &PlonkProof<P>,
     ) -> Result<()> {
         let WirePlonkProof {
@@ -66,15 +67,11 @@ impl CallPlonk for Call {
         let WirePlonkProofEvals {
             ws,
             rs,
-            qs,
-            ts,
-            ids,
             sigmas,
-            z,
             z_omega,
             w_omegas,
+            lookup,
         } = vs;
-        self.witness(*z, proof.vs.z)?;
         self.witness(*z_omega, proof.vs.z_omega)?;
         for (wire_w, w) in ws.iter().zip(proof.vs.ws) {
             self.witness(*wire_w, w)?;
@@ -82,23 +79,36 @@ impl CallPlonk for Call {
         for (wire_r, r) in rs.iter().zip(proof.vs.rs) {
             self.witness(*wire_r, r)?;
         }
-        for (wire_q, q) in qs.iter().zip(proof.vs.qs) {
-            self.witness(*wire_q, q)?;
-        }
-        for (wire_t, t) in ts.iter().zip(proof.vs.ts) {
-            self.witness(*wire_t, t)?;
-        }
-        for (wire_id, id) in ids.iter().zip(proof.vs.ids) {
-            self.witness(*wire_id, id)?;
-        }
         for (wire_sigma, sigma) in sigmas.iter().zip(proof.vs.sigmas) {
             self.witness(*wire_sigma, sigma)?;
         }
         for (wire_w_omega, w_omega) in w_omegas.iter().zip(proof.vs.w_omegas) {
             self.witness(*wire_w_omega, w_omega)?;
         }
+        ensure!(
+            lookup.is_some() == proof.vs.lookup.is_some(),
+            "The proof and its wires disagree on lookups"
+        );
+        if let (Some(wire_lookup), Some(lookup)) = (lookup, &proof.vs.lookup) {
+            let wires = [&wire_lookup.qs[..], &wire_lookup.tables, &wire_lookup.tables_omega];
+            let values = [&lookup.qs[..], &lookup.tables, &lookup.tables_omega];
+            for (wire, v) in wires.concat().into_iter().zip(values.concat()) {
+                self.witness(wire, v)?;
+            }
+            for (wire_h, h) in wire_lookup.hs.iter().zip(lookup.hs) {
+                self.witness(*wire_h, h)?;
+            }
+            self.witness(wire_lookup.h1_omega, lookup.h1_omega)?;
+            self.witness(wire_lookup.z_omega, lookup.z_omega)?;
+        }
 
-        let WirePlonkProofCommitments { ws, ts, z } = Cs;
+        let WirePlonkProofCommitments {
+            ws,
+            ts,
+            z,
+            hiding,
+            lookup,
+        } = Cs;
         self.witness_affine(*z, proof.Cs.z.into_affine())?;
         for (wire_w, w) in ws.iter().zip(proof.Cs.ws) {
             self.witness_affine(*wire_w, w.into_affine())?;
@@ -106,6 +116,39 @@ impl CallPlonk for Call {
         for (wire_t, t) in ts.iter().zip(proof.Cs.ts) {
             self.witness_affine(*wire_t, t.into_affine())?;
         }
+        ensure!(
+            hiding.is_some() == proof.Cs.hiding.is_some(),
+            "The proof and its wires disagree on hiding"
+        );
+        if let (Some(wire_hiding), Some(hiding)) = (hiding, &proof.Cs.hiding) {
+            self.witness_affine(wire_hiding.z_hi, hiding.z_hi.into_affine())?;
+            for (wire_w_hi, w_hi) in wire_hiding.ws_hi.iter().zip(hiding.ws_hi) {
+                self.witness_affine(*wire_w_hi, w_hi.into_affine())?;
+            }
+            for (wire_t_hi, t_hi) in wire_hiding.ts_hi.iter().zip(hiding.ts_hi) {
+                self.witness_affine(*wire_t_hi, t_hi.into_affine())?;
+            }
+        }
+        ensure!(
+            lookup.is_some() == proof.Cs.lookup.is_some(),
+            "The proof and its wires disagree on lookups"
+        );
+        if let (Some(wire_lookup), Some(lookup)) = (lookup, &proof.Cs.lookup) {
+            self.witness_affine(wire_lookup.z, lookup.z.into_affine())?;
+            for (wire_h, h) in wire_lookup.hs.iter().zip(lookup.hs) {
+                self.witness_affine(*wire_h, h.into_affine())?;
+            }
+            ensure!(
+                wire_lookup.hiding.is_some() == lookup.hiding.is_some(),
+                "The proof and its wires disagree on hiding the lookup"
+            );
+            if let (Some(wire_hiding), Some(hiding)) = (&wire_lookup.hiding, &lookup.hiding) {
+                self.witness_affine(wire_hiding.z_hi, hiding.z_hi.into_affine())?;
+                for (wire_h_hi, h_hi) in wire_hiding.hs_hi.iter().zip(hiding.hs_hi) {
+                    self.witness_affine(*wire_h_hi, h_hi.into_affine())?;
+                }
+            }
+        }
 
         let WirePlonkProofEvalProofs { r, r_omega } = pis;
         self.witness_eval_proof(&r, &proof.pis.r)?;
@@ -117,7 +160,7 @@ impl CallPlonk for Call {
     }
     fn public_input_plonk_proof<P: PastaConfig>(
         &mut self,
-        wire_proof: WirePlonkProof<P>,
+        wire_proof: WirePlonkProof<'_, P>,
         proof: PlonkProof<P>,
     ) -> Result<()> {
         let WirePlonkProof {
@@ -130,15 +173,11 @@ impl CallPlonk for Call {
         let WirePlonkProofEvals {
             ws,
             rs,
-            qs,
-            ts,
-            ids,
             sigmas,
-            z,
             z_omega,
             w_omegas,
+            lookup,
         } = vs;
-        self.public_input(z, proof.vs.z)?;
         self.public_input(z_omega, proof.vs.z_omega)?;
         for (wire_w, w) in ws.iter().zip(proof.vs.ws) {
             self.public_input(*wire_w, w)?;
@@ -146,23 +185,36 @@ impl CallPlonk for Call {
         for (wire_r, r) in rs.iter().zip(proof.vs.rs) {
             self.public_input(*wire_r, r)?;
         }
-        for (wire_q, q) in qs.iter().zip(proof.vs.qs) {
-            self.public_input(*wire_q, q)?;
-        }
-        for (wire_t, t) in ts.iter().zip(proof.vs.ts) {
-            self.public_input(*wire_t, t)?;
-        }
-        for (wire_id, id) in ids.iter().zip(proof.vs.ids) {
-            self.public_input(*wire_id, id)?;
-        }
         for (wire_sigma, sigma) in sigmas.iter().zip(proof.vs.sigmas) {
             self.public_input(*wire_sigma, sigma)?;
         }
         for (wire_w_omega, w_omega) in w_omegas.iter().zip(proof.vs.w_omegas) {
             self.public_input(*wire_w_omega, w_omega)?;
         }
+        ensure!(
+            lookup.is_some() == proof.vs.lookup.is_some(),
+            "The proof and its wires disagree on lookups"
+        );
+        if let (Some(wire_lookup), Some(lookup)) = (lookup, &proof.vs.lookup) {
+            let wires = [&wire_lookup.qs[..], &wire_lookup.tables, &wire_lookup.tables_omega];
+            let values = [&lookup.qs[..], &lookup.tables, &lookup.tables_omega];
+            for (wire, v) in wires.concat().into_iter().zip(values.concat()) {
+                self.public_input(wire, v)?;
+            }
+            for (wire_h, h) in wire_lookup.hs.iter().zip(lookup.hs) {
+                self.public_input(*wire_h, h)?;
+            }
+            self.public_input(wire_lookup.h1_omega, lookup.h1_omega)?;
+            self.public_input(wire_lookup.z_omega, lookup.z_omega)?;
+        }
 
-        let WirePlonkProofCommitments { ws, ts, z } = Cs;
+        let WirePlonkProofCommitments {
+            ws,
+            ts,
+            z,
+            hiding,
+            lookup,
+        } = Cs;
         self.public_input_affine(z, proof.Cs.z.into_affine())?;
         for (wire_w, w) in ws.iter().zip(proof.Cs.ws) {
             self.public_input_affine(*wire_w, w.into_affine())?;
@@ -170,6 +222,39 @@ impl CallPlonk for Call {
         for (wire_t, t) in ts.iter().zip(proof.Cs.ts) {
             self.public_input_affine(*wire_t, t.into_affine())?;
         }
+        ensure!(
+            hiding.is_some() == proof.Cs.hiding.is_some(),
+            "The proof and its wires disagree on hiding"
+        );
+        if let (Some(wire_hiding), Some(hiding)) = (hiding, &proof.Cs.hiding) {
+            self.public_input_affine(wire_hiding.z_hi, hiding.z_hi.into_affine())?;
+            for (wire_w_hi, w_hi) in wire_hiding.ws_hi.iter().zip(hiding.ws_hi) {
+                self.public_input_affine(*wire_w_hi, w_hi.into_affine())?;
+            }
+            for (wire_t_hi, t_hi) in wire_hiding.ts_hi.iter().zip(hiding.ts_hi) {
+                self.public_input_affine(*wire_t_hi, t_hi.into_affine())?;
+            }
+        }
+        ensure!(
+            lookup.is_some() == proof.Cs.lookup.is_some(),
+            "The proof and its wires disagree on lookups"
+        );
+        if let (Some(wire_lookup), Some(lookup)) = (lookup, &proof.Cs.lookup) {
+            self.public_input_affine(wire_lookup.z, lookup.z.into_affine())?;
+            for (wire_h, h) in wire_lookup.hs.iter().zip(lookup.hs) {
+                self.public_input_affine(*wire_h, h.into_affine())?;
+            }
+            ensure!(
+                wire_lookup.hiding.is_some() == lookup.hiding.is_some(),
+                "The proof and its wires disagree on hiding the lookup"
+            );
+            if let (Some(wire_hiding), Some(hiding)) = (&wire_lookup.hiding, &lookup.hiding) {
+                self.public_input_affine(wire_hiding.z_hi, hiding.z_hi.into_affine())?;
+                for (wire_h_hi, h_hi) in wire_hiding.hs_hi.iter().zip(hiding.hs_hi) {
+                    self.public_input_affine(*wire_h_hi, h_hi.into_affine())?;
+                }
+            }
+        }
 
         let WirePlonkProofEvalProofs { r, r_omega } = pis;
         self.public_input_eval_proof(&r, &proof.pis.r)?;
@@ -181,7 +266,7 @@ impl CallPlonk for Call {
     }
     fn witness_plonk_public_input<P: PastaConfig>(
         &mut self,
-        wire_plonk_public_inputs: &WirePlonkPublicInputs<P>,
+        wire_plonk_public_inputs: &WirePlonkPublicInputs<'_, P>,
         plonk_public_inputs: &PlonkPublicInputs<P>,
     ) -> Result<()> {
         assert!(
@@ -209,7 +294,7 @@ impl CallPlonk for Call {
     }
     fn public_input_plonk_public_input<P: PastaConfig>(
         &mut self,
-        wire_plonk_public_inputs: &WirePlonkPublicInputs<P>,
+        wire_plonk_public_inputs: &WirePlonkPublicInputs<'_, P>,
         plonk_public_inputs: &PlonkPublicInputs<P>,
     ) -> Result<()> {
         assert!(
@@ -237,7 +322,7 @@ impl CallPlonk for Call {
     }
     fn public_input_plonk_circuit<P: PastaConfig>(
         &mut self,
-        wire_plonk_circuit: &WirePlonkCircuit<P>,
+        wire_plonk_circuit: &WirePlonkCircuit<'_, P>,
         plonk_circuit: &PlonkCircuit<P>,
     ) -> Result<()> {
         assert!(wire_plonk_circuit.rows == plonk_circuit.rows);
@@ -247,6 +332,7 @@ impl CallPlonk for Call {
             public_input_count: _,
             omega: _,
             Cs,
+            lookup,
         } = plonk_circuit;
         let PlonkCircuitCommitments {
             qs,
@@ -265,200 +351,335 @@ impl CallPlonk for Call {
             self.public_input_affine(wire_plonk_circuit.Cs.ids[i], ids[i].into_affine())?;
             self.public_input_affine(wire_plonk_circuit.Cs.sigmas[i], sigmas[i].into_affine())?
         }
+        assert_eq!(wire_plonk_circuit.lookup.is_some(), lookup.is_some());
+        if let (Some(wire_lookup), Some(lookup)) = (&wire_plonk_circuit.lookup, lookup) {
+            let PlonkCircuitLookupCommitments { qs, tables } = lookup;
+            for (wire_q, q) in wire_lookup.qs.iter().zip(qs) {
+                self.public_input_affine(*wire_q, q.into_affine())?
+            }
+            for (wire_table, table) in wire_lookup.tables.iter().zip(tables) {
+                self.public_input_affine(*wire_table, table.into_affine())?
+            }
+        }
 
         Ok(())
     }
 }
 
 #[derive(Clone, Copy)]
-pub struct WirePlonkCircuitCommitments<P: PastaConfig> {
-    pub qs: [WireAffine<P>; Q_POLYS],
-    pub rs: [WireAffine<P>; R_POLYS],
-    pub ids: [WireAffine<P>; S_POLYS],
-    pub sigmas: [WireAffine<P>; S_POLYS],
+pub struct WirePlonkCircuitCommitments<'a, P: PastaConfig> {
+    pub qs: [WireAffine<'a, P>; Q_POLYS],
+    pub rs: [WireAffine<'a, P>; R_POLYS],
+    pub ids: [WireAffine<'a, P>; S_POLYS],
+    pub sigmas: [WireAffine<'a, P>; S_POLYS],
 }
 
 #[derive(Clone, Copy)]
-pub struct WirePlonkCircuit<P: PastaConfig> {
-    pub n: WireScalar<P>,
+pub struct WirePlonkCircuitLookupCommitments<'a, P: PastaConfig> {
+    pub qs: [WireAffine<'a, P>; 2],
+    pub tables: [WireAffine<'a, P>; L_POLYS],
+}
+
+#[derive(Clone, Copy)]
+pub struct WirePlonkCircuit<'a, P: PastaConfig> {
+    pub n: WireScalar<'a, P>,
     pub rows: usize,
-    pub mds: [[WireScalar<P>; 3]; 3],
+    pub mds: [[WireScalar<'a, P>; 3]; 3],
     pub public_input_count: usize,
-    pub omega: WireScalar<P>,
-    pub Cs: WirePlonkCircuitCommitments<P>,
+    pub omega: WireScalar<'a, P>,
+    pub Cs: WirePlonkCircuitCommitments<'a, P>,
+    pub lookup: Option<WirePlonkCircuitLookupCommitments<'a, P>>,
 }
-impl<P: PastaConfig> WirePlonkCircuit<P> {
+impl<'a, P: PastaConfig> WirePlonkCircuit<'a, P> {
     pub fn zero(rows: usize, public_input_count: usize) -> Self {
+        Self::zero_in(CircuitBuilder::current(), rows, public_input_count)
+    }
+    pub fn zero_in(builder: &'a CircuitBuilder, rows: usize, public_input_count: usize) -> Self {
         Self {
             Cs: WirePlonkCircuitCommitments {
-                qs: array::from_fn(|_| WireAffine::generator()),
-                rs: array::from_fn(|_| WireAffine::generator()),
-                ids: array::from_fn(|_| WireAffine::generator()),
-                sigmas: array::from_fn(|_| WireAffine::generator()),
+                qs: array::from_fn(|_| WireAffine::generator_in(builder)),
+                rs: array::from_fn(|_| WireAffine::generator_in(builder)),
+                ids: array::from_fn(|_| WireAffine::generator_in(builder)),
+                sigmas: array::from_fn(|_| WireAffine::generator_in(builder)),
             },
-            n: WireScalar::zero(),
+            n: WireScalar::zero_in(builder),
             rows,
             public_input_count,
-            mds: P::SCALAR_POSEIDON_MDS.map(|x| x.map(|_| WireScalar::zero())),
-            omega: WireScalar::zero(),
+            mds: P::SCALAR_POSEIDON_MDS.map(|x| x.map(|_| WireScalar::zero_in(builder))),
+            omega: WireScalar::zero_in(builder),
+            lookup: None,
         }
     }
     pub fn constant(plonk_circuit: &PlonkCircuit<P>) -> Self {
+        Self::constant_in(CircuitBuilder::current(), plonk_circuit)
+    }
+    pub fn constant_in(builder: &'a CircuitBuilder, plonk_circuit: &PlonkCircuit<P>) -> Self {
         Self {
             Cs: WirePlonkCircuitCommitments {
-                qs: array::from_fn(|i| WireAffine::constant(plonk_circuit.Cs.qs[i].into_affine())),
-                rs: array::from_fn(|i| WireAffine::constant(plonk_circuit.Cs.rs[i].into_affine())),
+                qs: array::from_fn(|i| {
+                    WireAffine::constant_in(builder, plonk_circuit.Cs.qs[i].into_affine())
+                }),
+                rs: array::from_fn(|i| {
+                    WireAffine::constant_in(builder, plonk_circuit.Cs.rs[i].into_affine())
+                }),
                 ids: array::from_fn(|i| {
-                    WireAffine::constant(plonk_circuit.Cs.ids[i].into_affine())
+                    WireAffine::constant_in(builder, plonk_circuit.Cs.ids[i].into_affine())
                 }),
                 sigmas: array::from_fn(|i| {
-                    WireAffine::constant(plonk_circuit.Cs.sigmas[i].into_affine())
+                    WireAffine::constant_in(builder, plonk_circuit.Cs.sigmas[i].into_affine())
                 }),
             },
-            n: WireScalar::constant(Scalar::<P>::from(plonk_circuit.rows as u64)),
+            n: WireScalar::constant_in(builder, Scalar::<P>::from(plonk_circuit.rows as u64)),
             rows: plonk_circuit.rows,
             public_input_count: plonk_circuit.public_input_count,
-            mds: P::SCALAR_POSEIDON_MDS.map(|x| x.map(|y| WireScalar::constant(y))),
-            omega: WireScalar::constant(plonk_circuit.omega),
+            mds: P::SCALAR_POSEIDON_MDS.map(|x| x.map(|y| WireScalar::constant_in(builder, y))),
+            omega: WireScalar::constant_in(builder, plonk_circuit.omega),
+            lookup: plonk_circuit
+                .lookup
+                .map(|lookup| WirePlonkCircuitLookupCommitments {
+                    qs: lookup
+                        .qs
+                        .map(|q| WireAffine::constant_in(builder, q.into_affine())),
+                    tables: lookup
+                        .tables
+                        .map(|t| WireAffine::constant_in(builder, t.into_affine())),
+                }),
         }
     }
     pub fn public_input(rows: usize, public_input_count: usize) -> Self {
+        Self::public_input_in(CircuitBuilder::current(), rows, public_input_count)
+    }
+    pub fn public_input_in(
+        builder: &'a CircuitBuilder,
+        rows: usize,
+        public_input_count: usize,
+    ) -> Self {
         Self {
             Cs: WirePlonkCircuitCommitments {
-                qs: array::from_fn(|i| WireAffine::public_input()),
-                rs: array::from_fn(|i| WireAffine::public_input()),
-                ids: array::from_fn(|i| WireAffine::public_input()),
-                sigmas: array::from_fn(|i| WireAffine::public_input()),
+                qs: array::from_fn(|i| WireAffine::public_input_in(builder)),
+                rs: array::from_fn(|i| WireAffine::public_input_in(builder)),
+                ids: array::from_fn(|i| WireAffine::public_input_in(builder)),
+                sigmas: array::from_fn(|i| WireAffine::public_input_in(builder)),
             },
-            n: WireScalar::constant(Scalar::<P>::from(rows as u64)),
+            n: WireScalar::constant_in(builder, Scalar::<P>::from(rows as u64)),
             rows,
             public_input_count,
-            mds: P::SCALAR_POSEIDON_MDS.map(|x| x.map(|y| WireScalar::constant(y))),
-            omega: WireScalar::constant(Domain::<P>::new(rows).unwrap().element(1)),
+            mds: P::SCALAR_POSEIDON_MDS.map(|x| x.map(|y| WireScalar::constant_in(builder, y))),
+            omega: WireScalar::constant_in(builder, Domain::<P>::new(rows).unwrap().element(1)),
+            lookup: None,
         }
     }
 }
 
 #[derive(Clone)]
-pub struct WirePlonkPublicInputs<P: PastaConfig> {
-    pub public_inputs: Vec<WireScalar<P>>,
-    pub acc_prev: WireAccumulator<P>,
+pub struct WirePlonkPublicInputs<'a, P: PastaConfig> {
+    pub public_inputs: Vec<WireScalar<'a, P>>,
+    pub acc_prev: WireAccumulator<'a, P>,
 }
 
-impl<P: PastaConfig> WirePlonkPublicInputs<P> {
+impl<'a, P: PastaConfig> WirePlonkPublicInputs<'a, P> {
     pub fn witness(rows: usize, public_input_count: usize) -> Self {
+        Self::witness_in(CircuitBuilder::current(), rows, public_input_count)
+    }
+    pub fn witness_in(builder: &'a CircuitBuilder, rows: usize, public_input_count: usize) -> Self {
         Self {
             public_inputs: (0..public_input_count)
-                .map(|_| WireScalar::witness())
+                .map(|_| WireScalar::witness_in(builder))
                 .collect(),
-            acc_prev: WireAccumulator::witness(rows),
+            acc_prev: WireAccumulator::witness_in(builder, rows),
         }
     }
     pub fn public_input(rows: usize, public_input_count: usize) -> Self {
+        Self::public_input_in(CircuitBuilder::current(), rows, public_input_count)
+    }
+    pub fn public_input_in(
+        builder: &'a CircuitBuilder,
+        rows: usize,
+        public_input_count: usize,
+    ) -> Self {
         Self {
             public_inputs: (0..public_input_count)
-                .map(|_| WireScalar::public_input())
+                .map(|_| WireScalar::public_input_in(builder))
                 .collect(),
-            acc_prev: WireAccumulator::public_input(rows),
+            acc_prev: WireAccumulator::public_input_in(builder, rows),
         }
     }
 }
 
 #[derive(Clone)]
-pub struct WirePlonkProofEvalProofs<P: PastaConfig> {
-    pub r: WireEvalProof<P>,
-    pub r_omega: WireEvalProof<P>,
+pub struct WirePlonkProofEvalProofs<'a, P: PastaConfig> {
+    pub r: WireEvalProof<'a, P>,
+    pub r_omega: WireEvalProof<'a, P>,
+}
+
+#[derive(Clone)]
+pub struct WirePlonkProofEvals<'a, P: PastaConfig> {
+    pub ws: [WireScalar<'a, P>; W_POLYS],
+    pub rs: [WireScalar<'a, P>; R_POLYS],
+    pub sigmas: [WireScalar<'a, P>; S_POLYS],
+    pub z_omega: WireScalar<'a, P>,
+    pub w_omegas: [WireScalar<'a, P>; 3],
+    pub lookup: Option<WirePlonkProofLookupEvals<'a, P>>,
+}
+
+#[derive(Clone)]
+pub struct WirePlonkProofLookupEvals<'a, P: PastaConfig> {
+    pub qs: [WireScalar<'a, P>; 2],
+    pub tables: [WireScalar<'a, P>; L_POLYS],
+    pub tables_omega: [WireScalar<'a, P>; L_POLYS],
+    pub hs: [WireScalar<'a, P>; 2],
+    pub h1_omega: WireScalar<'a, P>,
+    pub z_omega: WireScalar<'a, P>,
+}
+
+#[derive(Clone)]
+pub struct WirePlonkProofCommitments<'a, P: PastaConfig> {
+    pub ws: [WireAffine<'a, P>; W_POLYS],
+    pub ts: [WireAffine<'a, P>; T_POLYS],
+    pub z: WireAffine<'a, P>,
+    pub hiding: Option<WirePlonkProofHidingCommitments<'a, P>>,
+    pub lookup: Option<WirePlonkProofLookupCommitments<'a, P>>,
+}
+
+#[derive(Clone)]
+pub struct WirePlonkProofHidingCommitments<'a, P: PastaConfig> {
+    pub ws_hi: [WireAffine<'a, P>; W_POLYS],
+    pub z_hi: WireAffine<'a, P>,
+    pub ts_hi: [WireAffine<'a, P>; T_POLYS],
 }
 
 #[derive(Clone)]
-pub struct WirePlonkProofEvals<P: PastaConfig> {
-    pub ws: [WireScalar<P>; W_POLYS],
-    pub rs: [WireScalar<P>; R_POLYS],
-    pub qs: [WireScalar<P>; Q_POLYS],
-    pub ts: [WireScalar<P>; T_POLYS],
-    pub ids: [WireScalar<P>; S_POLYS],
-    pub sigmas: [WireScalar<P>; S_POLYS],
-    pub z: WireScalar<P>,
-    pub z_omega: WireScalar<P>,
-    pub w_omegas: [WireScalar<P>; 3],
+pub struct WirePlonkProofLookupCommitments<'a, P: PastaConfig> {
+    pub hs: [WireAffine<'a, P>; 2],
+    pub z: WireAffine<'a, P>,
+    pub hiding: Option<WirePlonkProofLookupHidingCommitments<'a, P>>,
 }
 
 #[derive(Clone)]
-pub struct WirePlonkProofCommitments<P: PastaConfig> {
-    pub ws: [WireAffine<P>; W_POLYS],
-    pub ts: [WireAffine<P>; T_POLYS],
-    pub z: WireAffine<P>,
+pub struct WirePlonkProofLookupHidingCommitments<'a, P: PastaConfig> {
+    pub hs_hi: [WireAffine<'a, P>; 2],
+    pub z_hi: WireAffine<'a, P>,
 }
 
 #[derive(Clone)]
-pub struct WirePlonkProof<P: PastaConfig> {
-    pub vs: WirePlonkProofEvals<P>,
-    pub Cs: WirePlonkProofCommitments<P>,
-    pub pis: WirePlonkProofEvalProofs<P>,
-    pub acc_next: WireAccumulator<P>,
+pub struct WirePlonkProof<'a, P: PastaConfig> {
+    pub vs: WirePlonkProofEvals<'a, P>,
+    pub Cs: WirePlonkProofCommitments<'a, P>,
+    pub pis: WirePlonkProofEvalProofs<'a, P>,
+    pub acc_next: WireAccumulator<'a, P>,
 }
-impl<P: PastaConfig> WirePlonkProof<P> {
+impl<'a, P: PastaConfig> WirePlonkProof<'a, P> {
     pub fn witness(n: usize) -> Self {
+        Self::witness_in(CircuitBuilder::current(), n)
+    }
+
+    pub fn witness_in(builder: &'a CircuitBuilder, n: usize) -> Self {
+        Self::witness_with_in(builder, n, false, false)
+    }
+
+    /// Allocates the wires of a proof made by `PlonkProof::naive_prover_hiding`
+    pub fn witness_hiding(n: usize) -> Self {
+        Self::witness_with(n, true, false)
+    }
+
+    /// Allocates the wires of a proof, which is hiding and/or for a circuit with lookup gates
+    pub fn witness_with(n: usize, hiding: bool, lookup: bool) -> Self {
+        Self::witness_with_in(CircuitBuilder::current(), n, hiding, lookup)
+    }
+
+    pub fn witness_with_in(
+        builder: &'a CircuitBuilder,
+        n: usize,
+        hiding: bool,
+        lookup: bool,
+    ) -> Self {
+        let eval_proof = if hiding {
+            WireEvalProof::witness_hiding_in
+        } else {
+            WireEvalProof::witness_in
+        };
         WirePlonkProof {
             vs: WirePlonkProofEvals {
-                ws: array::from_fn(|_| WireScalar::witness()),
-                rs: array::from_fn(|_| WireScalar::witness()),
-                qs: array::from_fn(|_| WireScalar::witness()),
-                ts: array::from_fn(|_| WireScalar::witness()),
-                ids: array::from_fn(|_| WireScalar::witness()),
-                sigmas: array::from_fn(|_| WireScalar::witness()),
-                z: WireScalar::witness(),
-                z_omega: WireScalar::witness(),
-                w_omegas: array::from_fn(|_| WireScalar::witness()),
+                ws: array::from_fn(|_| WireScalar::witness_in(builder)),
+                rs: array::from_fn(|_| WireScalar::witness_in(builder)),
+                sigmas: array::from_fn(|_| WireScalar::witness_in(builder)),
+                z_omega: WireScalar::witness_in(builder),
+                w_omegas: array::from_fn(|_| WireScalar::witness_in(builder)),
+                lookup: lookup.then(|| WirePlonkProofLookupEvals {
+                    qs: array::from_fn(|_| WireScalar::witness_in(builder)),
+                    tables: array::from_fn(|_| WireScalar::witness_in(builder)),
+                    tables_omega: array::from_fn(|_| WireScalar::witness_in(builder)),
+                    hs: array::from_fn(|_| WireScalar::witness_in(builder)),
+                    h1_omega: WireScalar::witness_in(builder),
+                    z_omega: WireScalar::witness_in(builder),
+                }),
             },
             Cs: WirePlonkProofCommitments {
-                ws: array::from_fn(|_| WireAffine::witness()),
-                ts: array::from_fn(|_| WireAffine::witness()),
-                z: WireAffine::witness(),
+                ws: array::from_fn(|_| WireAffine::witness_in(builder)),
+                ts: array::from_fn(|_| WireAffine::witness_in(builder)),
+                z: WireAffine::witness_in(builder),
+                hiding: hiding.then(|| WirePlonkProofHidingCommitments {
+                    ws_hi: array::from_fn(|_| WireAffine::witness_in(builder)),
+                    z_hi: WireAffine::witness_in(builder),
+                    ts_hi: array::from_fn(|_| WireAffine::witness_in(builder)),
+                }),
+                lookup: lookup.then(|| WirePlonkProofLookupCommitments {
+                    hs: array::from_fn(|_| WireAffine::witness_in(builder)),
+                    z: WireAffine::witness_in(builder),
+                    hiding: hiding.then(|| WirePlonkProofLookupHidingCommitments {
+                        hs_hi: array::from_fn(|_| WireAffine::witness_in(builder)),
+                        z_hi: WireAffine::witness_in(builder),
+                    }),
+                }),
             },
             pis: WirePlonkProofEvalProofs {
-                r: WireEvalProof::witness(n),
-                r_omega: WireEvalProof::witness(n),
+                r: eval_proof(builder, n),
+                r_omega: eval_proof(builder, n),
             },
-            acc_next: WireAccumulator::witness(n),
+            acc_next: WireAccumulator::witness_in(builder, n),
         }
     }
 
     pub fn public_input(n: usize) -> Self {
+        Self::public_input_in(CircuitBuilder::current(), n)
+    }
+
+    pub fn public_input_in(builder: &'a CircuitBuilder, n: usize) -> Self {
         WirePlonkProof {
             vs: WirePlonkProofEvals {
-                ws: array::from_fn(|_| WireScalar::public_input()),
-                rs: array::from_fn(|_| WireScalar::public_input()),
-                qs: array::from_fn(|_| WireScalar::public_input()),
-                ts: array::from_fn(|_| WireScalar::public_input()),
-                ids: array::from_fn(|_| WireScalar::public_input()),
-                sigmas: array::from_fn(|_| WireScalar::public_input()),
-                z: WireScalar::public_input(),
-                z_omega: WireScalar::public_input(),
-                w_omegas: array::from_fn(|_| WireScalar::public_input()),
+                ws: array::from_fn(|_| WireScalar::public_input_in(builder)),
+                rs: array::from_fn(|_| WireScalar::public_input_in(builder)),
+                sigmas: array::from_fn(|_| WireScalar::public_input_in(builder)),
+                z_omega: WireScalar::public_input_in(builder),
+                w_omegas: array::from_fn(|_| WireScalar::public_input_in(builder)),
+                lookup: None,
             },
             Cs: WirePlonkProofCommitments {
-                ws: array::from_fn(|_| WireAffine::public_input()),
-                ts: array::from_fn(|_| WireAffine::public_input()),
-                z: WireAffine::public_input(),
+                ws: array::from_fn(|_| WireAffine::public_input_in(builder)),
+                ts: array::from_fn(|_| WireAffine::public_input_in(builder)),
+                z: WireAffine::public_input_in(builder),
+                hiding: None,
+                lookup: None,
             },
             pis: WirePlonkProofEvalProofs {
-                r: WireEvalProof::public_input(n),
-                r_omega: WireEvalProof::public_input(n),
+                r: WireEvalProof::public_input_in(builder, n),
+                r_omega: WireEvalProof::public_input_in(builder, n),
             },
-            acc_next: WireAccumulator::public_input(n),
+            acc_next: WireAccumulator::public_input_in(builder, n),
         }
     }
 
     pub fn verify_succinct(
         &self,
-        circuit: WirePlonkCircuit<P>,
-        public_inputs: WirePlonkPublicInputs<P>,
-    ) -> WireBool<P> {
+        circuit: WirePlonkCircuit<'a, P>,
+        public_inputs: WirePlonkPublicInputs<'a, P>,
+    ) -> WireBool<'a, P> {
+        let builder = self.Cs.z.builder();
+        let _scope = builder.scope("plonk verifier");
         let pi = self;
         let n = circuit.n;
-        let one = WireScalar::<P>::one();
-        let mut transcript = OuterSponge::new(Protocols::PLONK);
+        let one = WireScalar::<P>::one_in(builder);
+        let mut transcript = OuterSponge::new_in(builder, Protocols::PLONK);
 
         assert!(
             public_inputs.public_inputs.len() <= circuit.public_input_count,
@@ -470,22 +691,54 @@ impl<P: PastaConfig> WirePlonkProof<P> {
         // -------------------- Round 1 --------------------
 
         transcript.absorb_g(&pi.Cs.ws);
+        if let Some(hiding) = &pi.Cs.hiding {
+            transcript.absorb_g(&hiding.ws_hi);
+        }
 
         // -------------------- Round 2 --------------------
 
+        assert_eq!(circuit.lookup.is_some(), pi.Cs.lookup.is_some());
+        assert_eq!(circuit.lookup.is_some(), pi.vs.lookup.is_some());
+
+        // θ = H(transcript)
+        let theta = pi.Cs.lookup.as_ref().map(|_| transcript.challenge());
+        if let Some(lookup) = &pi.Cs.lookup {
+            transcript.absorb_g(&lookup.hs);
+            if let Some(hiding) = &lookup.hiding {
+                transcript.absorb_g(&hiding.hs_hi);
+            }
+        }
+
         // -------------------- Round 3 --------------------
 
         // β = H(transcript)
         let beta = transcript.challenge();
         // γ = H(transcript)
         let gamma = transcript.challenge();
-        // δ = H(transcript)
+        // δ = H(transcript), ε = H(transcript)
+        let lookup_challenges = pi
+            .Cs
+            .lookup
+            .as_ref()
+            .map(|_| (transcript.challenge(), transcript.challenge()));
         transcript.absorb_g(&[pi.Cs.z]);
+        if let Some(hiding) = &pi.Cs.hiding {
+            transcript.absorb_g(&[hiding.z_hi]);
+        }
+        if let Some(lookup) = &pi.Cs.lookup {
+            transcript.absorb_g(&[lookup.z]);
+            if let Some(hiding) = &lookup.hiding {
+                transcript.absorb_g(&[hiding.z_hi]);
+            }
+        }
 
         // -------------------- Round 4 --------------------
 
         let alpha = transcript.challenge();
         transcript.absorb_g(&pi.Cs.ts);
+        if let Some(hiding) = &pi.Cs.hiding {
+            transcript.absorb_g(&hiding.ts_hi);
+        }
 
         // -------------------- Round 5 --------------------
 
@@ -493,7 +746,7 @@ impl<P: PastaConfig> WirePlonkProof<P> {
         let xi = transcript.challenge();
         let xi_n = pow_n(xi, circuit.rows);
         let xi_omega = xi * circuit.omega;
-        let ids = pi.vs.ids;
+        let ids = permutation_shifts::<P>().map(|k| WireScalar::constant_in(builder, k) * xi);
         let sigmas = pi.vs.sigmas;
 
         // f'(𝔷) = (A(𝔷) + β Sᵢ₁(𝔷) + γ) (B(𝔷) + β Sᵢ₂(𝔷) + γ) (C(𝔷) + β Sᵢ₃(𝔷) + γ)
@@ -505,38 +758,36 @@ impl<P: PastaConfig> WirePlonkProof<P> {
             g_prime *= pi.vs.ws[i] + beta * sigmas[i] + gamma;
         }
 
-        // F_GC(𝔷) = A(𝔷)Qₗ(𝔷) + B(𝔷)Qᵣ(𝔷) + C(𝔷)Qₒ(𝔷) + A(𝔷)B(𝔷)Qₘ(𝔷) + Q꜀(𝔷) + PI(𝔷)
-        let MDS = circuit.mds;
-        let poseidon_terms =
-            poseidon_constraints_generic(MDS, &pi.vs.rs, &pi.vs.ws, &pi.vs.w_omegas);
-        let affine_add_terms = affine_add_constraints_generic(pi.vs.ws.clone());
-        let affine_mul_terms =
-            affine_mul_constraints_generic(pi.vs.ws, pi.vs.w_omegas, pi.vs.rs[0]);
-        let eq = eq_generic(pi.vs.ws);
-        let rangecheck = range_check_generic(pi.vs.ws, pi.vs.w_omegas, pi.vs.rs);
-
-        let f_gc = pi.vs.ws[0] * pi.vs.qs[0]
-            + pi.vs.ws[1] * pi.vs.qs[1]
-            + pi.vs.ws[2] * pi.vs.qs[2]
-            + pi.vs.ws[0] * pi.vs.ws[1] * pi.vs.qs[3]
-            + pi.vs.qs[4]
-            + pi.vs.qs[5] * poseidon_terms
-            + pi.vs.qs[6] * affine_add_terms
-            + pi.vs.qs[7] * affine_mul_terms
-            + pi.vs.qs[8] * eq
-            + pi.vs.qs[9] * rangecheck
-            + public_input_eval_generic(&public_inputs.public_inputs, n, circuit.omega, xi, xi_n);
+        // The linearization polynomial L(X) of `plonk::Linearization`, whose evaluation at 𝔷 is
+        // v_lin and whose commitment is C_lin
+
+        // F_GC(𝔷) = Σᵢ qᵢ(𝔷) Gᵢ(𝔷) + PI(𝔷)
+        let gs = gate_terms_generic(circuit.mds, pi.vs.rs, pi.vs.ws, pi.vs.w_omegas, alpha);
+        let pi_xi =
+            public_input_eval_generic(&public_inputs.public_inputs, n, circuit.omega, xi, xi_n);
 
+        // α F_CC1(𝔷) + α² F_CC2(𝔷), without the z(𝔷) terms
         let omega = circuit.omega;
         let l1 = (omega * (xi_n - one)) / (n * (xi - omega));
-        let z_H = xi_n - one;
-        let f_cc1 = l1 * (pi.vs.z - one);
-        let f_cc2 = pi.vs.z * f_prime - pi.vs.z_omega * g_prime;
-
-        let f = f_gc + alpha * f_cc1 + (alpha * alpha) * f_cc2;
-        let t = t_reconstruct_generic(pi.vs.ts, xi_n);
-
-        let f_eq_t_zh = f.equals(t * z_H);
+        let alpha_2 = alpha * alpha;
+        let c_z = alpha * l1 + alpha_2 * f_prime;
+        let mut v_lin = alpha * l1 + alpha_2 * pi.vs.z_omega * g_prime - pi_xi;
+
+        // α³ F_PL1(𝔷) + α⁴ F_PL2(𝔷), without the z₂(𝔷) terms
+        let mut c_lookup_z = None;
+        if let (Some(vs), Some(theta), Some((delta, epsilon))) =
+            (&pi.vs.lookup, theta, lookup_challenges)
+        {
+            let f_query = lookup_query_generic(theta, vs.qs, array::from_fn(|i| pi.vs.ws[i]));
+            let t = geometric_generic(theta, vs.tables.to_vec());
+            let t_omega = geometric_generic(theta, vs.tables_omega.to_vec());
+            let hs = [vs.hs[0], vs.hs[1], vs.h1_omega];
+            let (f_prime, g_prime) =
+                plookup_terms_generic(f_query, [t, t_omega], hs, delta, epsilon);
+            let alpha_3 = alpha_2 * alpha;
+            c_lookup_z = Some(alpha_3 * l1 + (alpha_3 * alpha) * f_prime);
+            v_lin += alpha_3 * l1 + (alpha_3 * alpha) * vs.z_omega * g_prime;
+        }
 
         // let pp = PublicParams::get_pp();
         // let mut acc: Point<P> = (Affine::identity()).into();
@@ -545,28 +796,82 @@ impl<P: PastaConfig> WirePlonkProof<P> {
         // }
         // ensure!(circuit.Cs.public_input == acc);
 
-        let mut vec = Vec::new();
-        vec.extend_from_slice(&pi.vs.qs);
+        let mut vec = vec![v_lin];
         vec.extend_from_slice(&pi.vs.ws);
-        vec.extend_from_slice(&pi.vs.ts);
-        vec.push(pi.vs.z);
+        vec.extend_from_slice(&pi.vs.rs);
+        vec.extend_from_slice(&pi.vs.sigmas);
+        if let Some(vs) = &pi.vs.lookup {
+            vec.extend_from_slice(&vs.qs);
+            vec.extend_from_slice(&vs.tables);
+            vec.extend_from_slice(&vs.hs);
+        }
         let v_r = geometric_generic(zeta, vec);
 
         let mut vec = Vec::new();
         vec.extend_from_slice(&pi.vs.w_omegas);
         vec.push(pi.vs.z_omega);
+        if let Some(vs) = &pi.vs.lookup {
+            vec.extend_from_slice(&vs.tables_omega);
+            vec.push(vs.h1_omega);
+            vec.push(vs.z_omega);
+        }
         let v_r_omega = geometric_generic(zeta, vec);
 
-        let mut vec = Vec::new();
-        vec.extend_from_slice(&circuit.Cs.qs);
-        vec.extend_from_slice(&pi.Cs.ws);
-        vec.extend_from_slice(&pi.Cs.ts);
-        vec.push(pi.Cs.z);
+        // Commitments to p_lo(X) + ξⁿ p_hi(X) for the split polynomials of a hiding proof
+        let (C_ws, C_z, C_ts) = match &pi.Cs.hiding {
+            Some(hiding) => (
+                array::from_fn(|i| pi.Cs.ws[i] + hiding.ws_hi[i] * xi_n),
+                pi.Cs.z + hiding.z_hi * xi_n,
+                array::from_fn(|i| pi.Cs.ts[i] + hiding.ts_hi[i] * xi_n),
+            ),
+            None => (pi.Cs.ws, pi.Cs.z, pi.Cs.ts),
+        };
+        let lookup_Cs = circuit
+            .lookup
+            .zip(pi.Cs.lookup.as_ref())
+            .map(|(circuit_Cs, Cs)| {
+                let (C_hs, C_z) = match &Cs.hiding {
+                    Some(hiding) => (
+                        array::from_fn(|i| Cs.hs[i] + hiding.hs_hi[i] * xi_n),
+                        Cs.z + hiding.z_hi * xi_n,
+                    ),
+                    None => (Cs.hs, Cs.z),
+                };
+                (circuit_Cs, C_hs, C_z)
+            });
+
+        // Q꜀ has the factor G₄(𝔷) = 1, so it is added without a scalar multiplication
+        let minus_z_H = one - xi_n;
+        let mut C_lin = circuit.Cs.qs[4] + C_z * c_z;
+        for (i, (C_q, g)) in circuit.Cs.qs.into_iter().zip(gs).enumerate() {
+            if i != 4 {
+                C_lin += C_q * g;
+            }
+        }
+        C_lin += geometric_generic(xi_n, C_ts.to_vec()) * minus_z_H;
+        if let (Some((_, _, C_z)), Some(c_lookup_z)) = (&lookup_Cs, c_lookup_z) {
+            C_lin += *C_z * c_lookup_z;
+        }
+
+        let mut vec = vec![C_lin];
+        vec.extend_from_slice(&C_ws);
+        vec.extend_from_slice(&circuit.Cs.rs);
+        vec.extend_from_slice(&circuit.Cs.sigmas);
+        if let Some((circuit_Cs, C_hs, _)) = &lookup_Cs {
+            vec.extend_from_slice(&circuit_Cs.qs);
+            vec.extend_from_slice(&circuit_Cs.tables);
+            vec.extend_from_slice(C_hs);
+        }
         let C_r = geometric_generic(zeta, vec);
 
         let mut vec = Vec::new();
-        vec.extend_from_slice(&pi.Cs.ws[0..3]);
-        vec.push(pi.Cs.z);
+        vec.extend_from_slice(&C_ws[0..3]);
+        vec.push(C_z);
+        if let Some((circuit_Cs, C_hs, C_z)) = &lookup_Cs {
+            vec.extend_from_slice(&circuit_Cs.tables);
+            vec.push(C_hs[0]);
+            vec.push(*C_z);
+        }
         let C_r_omega = geometric_generic(zeta, vec);
 
         let instance_1 = WireInstance::new(C_r, xi, v_r, pi.pis.r.clone());
@@ -574,12 +879,10 @@ impl<P: PastaConfig> WirePlonkProof<P> {
 
         let acc_prev = public_inputs.acc_prev.clone();
 
-        let pp = WirePublicParams::new(circuit.rows);
+        let pp = WirePublicParams::new_in(builder, circuit.rows);
         let acc_next = pi.acc_next.clone();
         let qs = vec![acc_prev.instance, instance_1, instance_2];
-        let acc_ok = acc_next.verify(pp, qs);
-
-        f_eq_t_zh & acc_ok
+        acc_next.verify(pp, qs)
     }
 }
 
@@ -594,8 +897,9 @@ mod tests {
     use halo_schnorr::generate_keypair;
 
     use crate::{
+        circuit::LookupTable,
         frontend::{
-            Call, Frontend,
+            Call, CircuitBuilder, Frontend,
             plonk::{CallPlonk, WirePlonkCircuit, WirePlonkProof, WirePlonkPublicInputs},
             primitives::{WireAffine, WireScalar},
             signature::WireSchnorrSignature,
@@ -603,6 +907,34 @@ mod tests {
         plonk::PlonkProof,
     };
 
+    #[test]
+    fn proof_shape_mismatch() -> Result<()> {
+        let rng = &mut test_rng();
+        let builder = CircuitBuilder::new();
+        let x = WireScalar::<PallasConfig>::witness_in(&builder);
+        (x * x).output();
+        let mut call = builder.call();
+        call.witness(x, Fp::from(3))?;
+        let (fp_trace, _) = call.trace()?;
+        let (circuit, x, w) = fp_trace.consume();
+        let proof = PlonkProof::naive_prover(rng, circuit, &x, w);
+
+        // A proof without hiding or lookups is an error for wires that expect them, not a panic
+        for (hiding, lookup) in [(true, false), (false, true)] {
+            let builder = CircuitBuilder::new();
+            let wire_proof = WirePlonkProof::<PallasConfig>::witness_with_in(
+                &builder,
+                circuit.rows,
+                hiding,
+                lookup,
+            );
+            let mut call = builder.call();
+            assert!(call.witness_plonk_proof(&wire_proof, &proof).is_err());
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn plonk_verify_signature() -> Result<()> {
         let rng = &mut test_rng();
@@ -677,6 +1009,138 @@ mod tests {
         let (circuit, x, w) = fq_trace.consume();
         PlonkProof::naive_prover(rng, circuit, &x, w).verify(circuit, &x)?;
 
+        Ok(())
+    }
+    #[test]
+    fn plonk_verify_hiding() -> Result<()> {
+        let rng = &mut test_rng();
+
+        let x = WireScalar::<PallasConfig>::witness();
+        let y = WireScalar::<PallasConfig>::public_input();
+        (x * x + y).output();
+        Frontend::hiding();
+
+        let mut call = Call::new();
+
+        call.witness(x, Fp::rand(rng))?;
+        call.public_input(y, Fp::rand(rng))?;
+
+        let (fp_trace, fq_trace) = call.trace()?;
+        let n_fp = fp_trace.rows;
+        let n_fq = fq_trace.rows;
+
+        let (fp_circuit, fp_x, fp_w) = fp_trace.consume();
+        let pi_fp = PlonkProof::naive_prover_hiding(rng, fp_circuit, &fp_x, fp_w)?;
+        let (fq_circuit, fq_x, fq_w) = fq_trace.consume();
+        let pi_fq = PlonkProof::naive_prover_hiding(rng, fq_circuit, &fq_x, fq_w)?;
+
+        pi_fp.clone().verify_succinct(fp_circuit, &fp_x)?;
+        pi_fq.clone().verify_succinct(fq_circuit, &fq_x)?;
+
+        Frontend::reset();
+
+        let fp_wire_circuit = WirePlonkCircuit::constant(&fp_circuit);
+        let fp_wire_x = WirePlonkPublicInputs::witness(fp_circuit.rows, fp_x.public_inputs.len());
+        let fp_wire_pi = WirePlonkProof::<PallasConfig>::witness_hiding(n_fp);
+        fp_wire_pi
+            .verify_succinct(fp_wire_circuit, fp_wire_x.clone())
+            .output();
+
+        let fq_wire_circuit = WirePlonkCircuit::constant(&fq_circuit);
+        let fq_wire_x = WirePlonkPublicInputs::witness(fq_circuit.rows, fq_x.public_inputs.len());
+        let fq_wire_pi = WirePlonkProof::<VestaConfig>::witness_hiding(n_fq);
+        fq_wire_pi
+            .verify_succinct(fq_wire_circuit, fq_wire_x.clone())
+            .output();
+
+        let mut call = Call::new();
+
+        call.witness_plonk_proof(&fp_wire_pi, &pi_fp)?;
+        call.witness_plonk_public_input(&fp_wire_x, &fp_x)?;
+
+        call.witness_plonk_proof(&fq_wire_pi, &pi_fq)?;
+        call.witness_plonk_public_input(&fq_wire_x, &fq_x)?;
+
+        let (fp_trace, fq_trace) = call.trace()?;
+
+        assert_eq!(fp_trace.outputs[0], Fp::one());
+        assert_eq!(fq_trace.outputs[0], Fq::one());
+
+        let (circuit, x, w) = fp_trace.consume();
+        PlonkProof::naive_prover(rng, circuit, &x, w).verify(circuit, &x)?;
+        let (circuit, x, w) = fq_trace.consume();
+        PlonkProof::naive_prover(rng, circuit, &x, w).verify(circuit, &x)?;
+
+        Ok(())
+    }
+    #[test]
+    fn plonk_verify_lookup() -> Result<()> {
+        let rng = &mut test_rng();
+
+        let x = WireScalar::<PallasConfig>::witness();
+        let bits: Vec<_> = (0..8)
+            .map(|_| WireScalar::<PallasConfig>::witness())
+            .collect();
+        for (i, bit) in bits.iter().enumerate() {
+            let i = WireScalar::constant(Fp::from(i as u64));
+            WireScalar::lookup(LookupTable::ByteToBits, &[x, i, *bit]);
+        }
+
+        let mut call = Call::new();
+
+        let byte = 0b1011_0010u64;
+        call.witness(x, Fp::from(byte))?;
+        for (i, bit) in bits.iter().enumerate() {
+            call.witness(*bit, Fp::from((byte >> i) & 1))?;
+        }
+
+        let (fp_trace, fq_trace) = call.trace()?;
+        let n_fp = fp_trace.rows;
+        let n_fq = fq_trace.rows;
+
+        let (fp_circuit, fp_x, fp_w) = fp_trace.consume();
+        let pi_fp = PlonkProof::naive_prover(rng, fp_circuit, &fp_x, fp_w);
+        let (fq_circuit, fq_x, fq_w) = fq_trace.consume();
+        let pi_fq = PlonkProof::naive_prover(rng, fq_circuit, &fq_x, fq_w);
+
+        pi_fp.clone().verify_succinct(fp_circuit, &fp_x)?;
+        pi_fq.clone().verify_succinct(fq_circuit, &fq_x)?;
+        assert!(fp_circuit.lookup.is_some() && fq_circuit.lookup.is_none());
+
+        Frontend::reset();
+
+        let fp_wire_circuit = WirePlonkCircuit::constant(&fp_circuit);
+        let fp_wire_x = WirePlonkPublicInputs::witness(fp_circuit.rows, fp_x.public_inputs.len());
+        let fp_wire_pi = WirePlonkProof::<PallasConfig>::witness_with(n_fp, false, true);
+        fp_wire_pi
+            .verify_succinct(fp_wire_circuit, fp_wire_x.clone())
+            .output();
+
+        let fq_wire_circuit = WirePlonkCircuit::constant(&fq_circuit);
+        let fq_wire_x = WirePlonkPublicInputs::witness(fq_circuit.rows, fq_x.public_inputs.len());
+        let fq_wire_pi = WirePlonkProof::<VestaConfig>::witness(n_fq);
+        fq_wire_pi
+            .verify_succinct(fq_wire_circuit, fq_wire_x.clone())
+            .output();
+
+        let mut call = Call::new();
+
+        call.witness_plonk_proof(&fp_wire_pi, &pi_fp)?;
+        call.witness_plonk_public_input(&fp_wire_x, &fp_x)?;
+
+        call.witness_plonk_proof(&fq_wire_pi, &pi_fq)?;
+        call.witness_plonk_public_input(&fq_wire_x, &fq_x)?;
+
+        let (fp_trace, fq_trace) = call.trace()?;
+
+        assert_eq!(fp_trace.outputs[0], Fp::one());
+        assert_eq!(fq_trace.outputs[0], Fq::one());
+
+        let (circuit, x, w) = fp_trace.consume();
+        PlonkProof::naive_prover(rng, circuit, &x, w).verify(circuit, &x)?;
+        let (circuit, x, w) = fq_trace.consume();
+        PlonkProof::naive_prover(rng, circuit, &x, w).verify(circuit, &x)?;
+
         Ok(())
     }
 }
@@ -0,0 +1,131 @@
+use halo_group::{PastaConfig, Scalar};
+
+use crate::frontend::{
+    CircuitBuilder, poseidon::inner_sponge::InnerSponge, primitives::WireScalar,
+};
+
+/// Poseidon over the wires of the scalar field of P, which matches
+/// `halo_poseidon::inner_sponge::PoseidonSponge<P::OtherCurve>` natively. Any number of inputs can
+/// be absorbed, in any number of calls, before squeezing. The sponge does not pad its inputs, so
+/// `hash` should be used for inputs of varying length.
+#[derive(Clone, Debug)]
+pub struct PoseidonHash<'a, P: PastaConfig> {
+    sponge: InnerSponge<'a, P>,
+}
+impl<'a, P: PastaConfig> PoseidonHash<'a, P> {
+    pub fn new() -> Self {
+        Self::new_in(CircuitBuilder::current())
+    }
+
+    pub fn new_in(builder: &'a CircuitBuilder) -> Self {
+        Self {
+            sponge: InnerSponge::new(builder),
+        }
+    }
+
+    pub fn absorb(&mut self, xs: &[WireScalar<'a, P>]) {
+        self.sponge.absorb(xs)
+    }
+
+    pub fn squeeze(&mut self) -> WireScalar<'a, P> {
+        self.sponge.squeeze()
+    }
+
+    /// H(xs), matching `halo_poseidon::hash`. The capacity starts at the length of `xs`, so that
+    /// inputs which only differ by trailing zeros have different hashes.
+    pub fn hash(xs: &[WireScalar<'a, P>]) -> WireScalar<'a, P> {
+        let builder = xs.first().map_or(CircuitBuilder::current(), |x| x.builder);
+        Self::hash_in(builder, xs)
+    }
+
+    /// H(xs) in the circuit of `builder`, which `xs` belong to, for when `xs` may be empty
+    pub fn hash_in(builder: &'a CircuitBuilder, xs: &[WireScalar<'a, P>]) -> WireScalar<'a, P> {
+        let length = WireScalar::constant_in(builder, Scalar::<P>::from(xs.len() as u64));
+        let mut hasher = Self {
+            sponge: InnerSponge::with_capacity(length),
+        };
+        hasher.absorb(xs);
+        hasher.squeeze()
+    }
+
+    /// H(left, right), matching `halo_poseidon::hash_pair`
+    pub fn hash_pair(left: WireScalar<'a, P>, right: WireScalar<'a, P>) -> WireScalar<'a, P> {
+        Self::hash(&[left, right])
+    }
+}
+impl<P: PastaConfig> Default for PoseidonHash<'_, P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use halo_group::{Fp, PallasConfig, VestaConfig, ark_ff::UniformRand, ark_std::test_rng};
+    use halo_poseidon::inner_sponge::PoseidonSponge;
+
+    use crate::{
+        frontend::{CircuitBuilder, poseidon::hash::PoseidonHash, primitives::WireScalar},
+        plonk::PlonkProof,
+    };
+
+    #[test]
+    fn native_parity() -> Result<()> {
+        let rng = &mut test_rng();
+        // Lengths around the rate, so absorbing crosses permutations at every offset
+        let lengths = [0, 1, 2, 3, 4, 7];
+
+        let builder = CircuitBuilder::new();
+        let inputs: Vec<Vec<WireScalar<PallasConfig>>> = lengths
+            .iter()
+            .map(|n| (0..*n).map(|_| WireScalar::witness_in(&builder)).collect())
+            .collect();
+        for xs in &inputs {
+            PoseidonHash::hash_in(&builder, xs).output();
+        }
+
+        // Absorbing in parts and squeezing twice
+        let split: Vec<WireScalar<PallasConfig>> =
+            (0..5).map(|_| WireScalar::witness_in(&builder)).collect();
+        let mut hasher = PoseidonHash::new_in(&builder);
+        hasher.absorb(&split[..2]);
+        hasher.absorb(&split[2..]);
+        hasher.squeeze().output();
+        hasher.squeeze().output();
+
+        let values: Vec<Vec<Fp>> = lengths
+            .iter()
+            .map(|n| (0..*n).map(|_| Fp::rand(rng)).collect())
+            .collect();
+        let split_values: Vec<Fp> = (0..5).map(|_| Fp::rand(rng)).collect();
+
+        let mut call = builder.call();
+        for (xs, vs) in inputs.iter().zip(&values) {
+            for (x, v) in xs.iter().zip(vs) {
+                call.witness(*x, *v)?;
+            }
+        }
+        for (x, v) in split.iter().zip(&split_values) {
+            call.witness(*x, *v)?;
+        }
+        let (fp_trace, fq_trace) = call.trace()?;
+
+        let mut expected: Vec<Fp> = values
+            .iter()
+            .map(|vs| halo_poseidon::hash::<VestaConfig>(vs))
+            .collect();
+        let mut sponge = PoseidonSponge::<VestaConfig>::new();
+        sponge.absorb(&split_values);
+        expected.push(sponge.squeeze());
+        expected.push(sponge.squeeze());
+        assert_eq!(fp_trace.outputs, expected);
+
+        let (circuit, x, w) = fp_trace.consume();
+        PlonkProof::naive_prover(rng, circuit, &x, w).verify(circuit, &x)?;
+        let (circuit, x, w) = fq_trace.consume();
+        PlonkProof::naive_prover(rng, circuit, &x, w).verify(circuit, &x)?;
+
+        Ok(())
+    }
+}
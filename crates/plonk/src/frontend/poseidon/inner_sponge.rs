@@ -1,7 +1,7 @@
 use halo_group::PastaConfig;
 use halo_poseidon::{SPONGE_RATE, STATE_SIZE};
 
-use crate::frontend::{FRONTEND, primitives::WireScalar};
+use crate::frontend::{CircuitBuilder, primitives::WireScalar};
 
 #[derive(Clone, Debug)]
 enum SpongeState {
@@ -10,31 +10,40 @@ enum SpongeState {
 }
 
 #[derive(Clone, Debug)]
-pub struct InnerSponge<P: PastaConfig> {
-    state: [WireScalar<P>; STATE_SIZE],
+pub struct InnerSponge<'a, P: PastaConfig> {
+    state: [WireScalar<'a, P>; STATE_SIZE],
     sponge_state: SpongeState,
 }
-impl<P: PastaConfig> InnerSponge<P> {
-    pub(crate) fn poseidon_block_cipher(state: &mut [WireScalar<P>; STATE_SIZE]) {
-        FRONTEND.with(|frontend| {
-            let mut frontend = frontend.borrow_mut();
-            let mut wire_state = state.map(|x| x.wire);
+impl<'a, P: PastaConfig> InnerSponge<'a, P> {
+    pub(crate) fn poseidon_block_cipher(state: &mut [WireScalar<'a, P>; STATE_SIZE]) {
+        let builder = state[0].builder;
+        let wires = state.map(|x| x.wire);
+        let wires = builder.with_circuit(|circuit| {
+            let mut wire_state = wires;
             for i in 0..11 {
-                wire_state = frontend.circuit.poseidon(i, wire_state);
+                wire_state = circuit.poseidon(i, wire_state);
             }
-            wire_state = frontend.circuit.poseidon_finish(wire_state);
-            *state = wire_state.map(WireScalar::new)
-        })
+            circuit.poseidon_finish(wire_state)
+        });
+        *state = wires.map(|wire| WireScalar::new(builder, wire))
     }
 
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(builder: &'a CircuitBuilder) -> Self {
         Self {
-            state: [WireScalar::<P>::zero(); STATE_SIZE],
+            state: [WireScalar::<P>::zero_in(builder); STATE_SIZE],
             sponge_state: SpongeState::Absorbed(0),
         }
     }
 
-    pub(crate) fn absorb(&mut self, x: &[WireScalar<P>]) {
+    /// A fresh sponge whose capacity starts at `c` instead of zero
+    pub(crate) fn with_capacity(c: WireScalar<'a, P>) -> Self {
+        let mut sponge = Self::new(c.builder);
+        sponge.state[SPONGE_RATE] = c;
+        sponge
+    }
+
+    pub(crate) fn absorb(&mut self, x: &[WireScalar<'a, P>]) {
+        let _scope = self.state[0].builder.scope("sponge");
         for x in x.iter() {
             match self.sponge_state {
                 SpongeState::Absorbed(n) if n < SPONGE_RATE => {
@@ -55,7 +64,8 @@ impl<P: PastaConfig> InnerSponge<P> {
         }
     }
 
-    pub(crate) fn squeeze(&mut self) -> WireScalar<P> {
+    pub(crate) fn squeeze(&mut self) -> WireScalar<'a, P> {
+        let _scope = self.state[0].builder.scope("sponge");
         match self.sponge_state {
             SpongeState::Squeezed(n) if n < SPONGE_RATE => {
                 self.sponge_state = SpongeState::Squeezed(n + 1);
@@ -71,7 +81,7 @@ impl<P: PastaConfig> InnerSponge<P> {
     }
 
     pub(crate) fn reset(&mut self) {
-        self.state = [WireScalar::zero(); STATE_SIZE];
+        self.state = [WireScalar::zero_in(self.state[0].builder); STATE_SIZE];
         self.sponge_state = SpongeState::Absorbed(0);
     }
 }
@@ -85,7 +95,9 @@ mod tests {
     use halo_poseidon::STATE_SIZE;
 
     use crate::{
-        frontend::{Call, poseidon::inner_sponge::InnerSponge, primitives::WireScalar},
+        frontend::{
+            Call, CircuitBuilder, poseidon::inner_sponge::InnerSponge, primitives::WireScalar,
+        },
         plonk::PlonkProof,
     };
 
@@ -129,7 +141,7 @@ mod tests {
     #[test]
     fn absorb_squeeze() -> Result<()> {
         let rng = &mut test_rng();
-        let mut sponge = InnerSponge::<PallasConfig>::new();
+        let mut sponge = InnerSponge::<PallasConfig>::new(CircuitBuilder::current());
 
         let witnesses: [WireScalar<PallasConfig>; 10] = array::from_fn(|_| WireScalar::witness());
         sponge.absorb(&witnesses);
@@ -0,0 +1,203 @@
+use anyhow::{Result, ensure};
+use halo_group::{PastaConfig, PastaFE, Scalar};
+use halo_poseidon::MerklePath;
+
+use crate::frontend::{
+    Call, CircuitBuilder,
+    poseidon::hash::PoseidonHash,
+    primitives::{WireBool, WireScalar},
+};
+
+pub trait CallMerklePath {
+    fn witness_merkle_path<P: PastaConfig>(
+        &mut self,
+        wire_path: &WireMerklePath<'_, P>,
+        path: &MerklePath<P::OtherCurve>,
+    ) -> Result<()>;
+}
+impl CallMerklePath for Call {
+    fn witness_merkle_path<P: PastaConfig>(
+        &mut self,
+        wire_path: &WireMerklePath<'_, P>,
+        path: &MerklePath<P::OtherCurve>,
+    ) -> Result<()> {
+        ensure!(
+            wire_path.depth() == path.depth(),
+            "Path of depth {} given for a circuit path of depth {}",
+            path.depth(),
+            wire_path.depth()
+        );
+        self.witness(wire_path.index, Scalar::<P>::from(path.index as u64))?;
+        for (wire, sibling) in wire_path.siblings.iter().zip(&path.siblings) {
+            self.trace_builder.witness(
+                wire.wire,
+                PastaFE::from_basefield::<P::OtherCurve>(*sibling),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The path from a leaf to the root of a `halo_poseidon::MerkleTree<P::OtherCurve>` of a fixed
+/// depth, whose nodes are the scalars of P
+#[derive(Clone, Debug)]
+pub struct WireMerklePath<'a, P: PastaConfig> {
+    /// The position of the leaf, constrained to be less than 2ᵈᵉᵖᵗʰ
+    pub index: WireScalar<'a, P>,
+    /// The bits of `index`, little-endian, so bit i says if the node at height i is a right child
+    pub index_bits: Vec<WireBool<'a, P>>,
+    pub siblings: Vec<WireScalar<'a, P>>,
+}
+impl<'a, P: PastaConfig> WireMerklePath<'a, P> {
+    pub fn witness(depth: usize) -> Self {
+        Self::witness_in(CircuitBuilder::current(), depth)
+    }
+
+    pub fn witness_in(builder: &'a CircuitBuilder, depth: usize) -> Self {
+        let index = WireScalar::witness_in(builder);
+        Self {
+            index,
+            index_bits: index.to_bits(depth),
+            siblings: (0..depth)
+                .map(|_| WireScalar::witness_in(builder))
+                .collect(),
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.siblings.len()
+    }
+
+    /// The root of the tree with `leaf` at the position of this path
+    pub fn root(&self, leaf: WireScalar<'a, P>) -> WireScalar<'a, P> {
+        self.index_bits
+            .iter()
+            .zip(&self.siblings)
+            .fold(leaf, |node, (bit, sibling)| {
+                // Swapping node and sibling when the node is a right child, with a single product
+                let d = WireScalar::new(bit.builder, bit.wire) * (*sibling - node);
+                PoseidonHash::hash_pair(node + d, *sibling - d)
+            })
+    }
+
+    /// Constrains `leaf` to be at the position of this path in the tree with `root`
+    pub fn verify(&self, root: WireScalar<'a, P>, leaf: WireScalar<'a, P>) {
+        self.root(leaf).assert_eq(root)
+    }
+
+    /// Constrains `old_leaf` to be at the position of this path in the tree with `root`, and
+    /// returns the root of the tree where it is replaced by `new_leaf`
+    pub fn update(
+        &self,
+        root: WireScalar<'a, P>,
+        old_leaf: WireScalar<'a, P>,
+        new_leaf: WireScalar<'a, P>,
+    ) -> WireScalar<'a, P> {
+        self.verify(root, old_leaf);
+        self.root(new_leaf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use halo_group::{Fp, PallasConfig, VestaConfig, ark_ff::UniformRand, ark_std::test_rng};
+    use halo_poseidon::{MerklePath, MerkleTree};
+
+    use crate::{
+        frontend::{
+            Call, CircuitBuilder,
+            poseidon::merkle::{CallMerklePath, WireMerklePath},
+            primitives::WireScalar,
+        },
+        plonk::PlonkProof,
+    };
+
+    const DEPTH: usize = 4;
+
+    fn satisfied(call: Call) -> Result<bool> {
+        let rng = &mut test_rng();
+        let (fp_trace, _) = call.trace()?;
+        let (circuit, x, w) = fp_trace.consume();
+        Ok(PlonkProof::naive_prover(rng, circuit, &x, w)
+            .verify(circuit, &x)
+            .is_ok())
+    }
+
+    #[test]
+    fn inclusion_and_update() -> Result<()> {
+        let rng = &mut test_rng();
+        let leaves: Vec<Fp> = (0..11).map(|_| Fp::rand(rng)).collect();
+        let mut tree = MerkleTree::<VestaConfig>::new(DEPTH, &leaves)?;
+
+        let builder = CircuitBuilder::new();
+        let path = WireMerklePath::<PallasConfig>::witness_in(&builder, DEPTH);
+        let root = WireScalar::public_input_in(&builder);
+        let old_leaf = WireScalar::witness_in(&builder);
+        let new_leaf = WireScalar::witness_in(&builder);
+        path.update(root, old_leaf, new_leaf).output();
+
+        for i in [0, 6, 13] {
+            let old_root = tree.root();
+            let native_path = tree.path(i)?;
+            let leaf = Fp::rand(rng);
+            tree.update(i, leaf)?;
+
+            let mut call = builder.call();
+            call.witness_merkle_path(&path, &native_path)?;
+            call.public_input(root, old_root)?;
+            call.witness(old_leaf, leaves.get(i).copied().unwrap_or_default())?;
+            call.witness(new_leaf, leaf)?;
+            let (fp_trace, fq_trace) = call.trace()?;
+            assert_eq!(fp_trace.outputs[0], tree.root());
+
+            let (circuit, x, w) = fp_trace.consume();
+            PlonkProof::naive_prover(rng, circuit, &x, w).verify(circuit, &x)?;
+            let (circuit, x, w) = fq_trace.consume();
+            PlonkProof::naive_prover(rng, circuit, &x, w).verify(circuit, &x)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn wrong_paths_fail() -> Result<()> {
+        let rng = &mut test_rng();
+        let leaves: Vec<Fp> = (0..1 << DEPTH).map(|_| Fp::rand(rng)).collect();
+        let tree = MerkleTree::<VestaConfig>::new(DEPTH, &leaves)?;
+
+        let builder = CircuitBuilder::new();
+        let path = WireMerklePath::<PallasConfig>::witness_in(&builder, DEPTH);
+        let root = WireScalar::public_input_in(&builder);
+        let leaf = WireScalar::witness_in(&builder);
+        path.verify(root, leaf);
+        let call = |native_path: &MerklePath<VestaConfig>, leaf_v| -> Result<Call> {
+            let mut call = builder.call();
+            call.witness_merkle_path(&path, native_path)?;
+            call.public_input(root, tree.root())?;
+            call.witness(leaf, leaf_v)?;
+            Ok(call)
+        };
+
+        let native_path = tree.path(9)?;
+        assert!(satisfied(call(&native_path, leaves[9])?)?);
+        // The wrong leaf, and the right leaf at the wrong position
+        assert!(!satisfied(call(&native_path, leaves[8])?)?);
+        assert!(!satisfied(call(&tree.path(8)?, leaves[9])?)?);
+
+        let mut wrong_sibling = native_path.clone();
+        wrong_sibling.siblings[2] = Fp::rand(rng);
+        assert!(!satisfied(call(&wrong_sibling, leaves[9])?)?);
+
+        // An index past the leaves does not fit in the bits of the path
+        let mut wrong_index = native_path.clone();
+        wrong_index.index += 1 << DEPTH;
+        assert!(!satisfied(call(&wrong_index, leaves[9])?)?);
+
+        // Paths must have the depth of the circuit
+        let short = MerkleTree::<VestaConfig>::new(DEPTH - 1, &leaves[..8])?.path(1)?;
+        assert!(call(&short, leaves[1]).is_err());
+
+        Ok(())
+    }
+}
@@ -1,2 +1,4 @@
+pub mod hash;
 pub mod inner_sponge;
+pub mod merkle;
 pub mod outer_sponge;
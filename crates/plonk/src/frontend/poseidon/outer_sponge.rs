@@ -2,19 +2,27 @@ use halo_group::PastaConfig;
 use halo_poseidon::Protocols;
 
 use crate::frontend::{
+    CircuitBuilder,
     poseidon::inner_sponge::InnerSponge,
     primitives::{WireAffine, WireScalar},
 };
 
-pub struct OuterSponge<P: PastaConfig> {
-    sponge: InnerSponge<P::OtherCurve>,
+pub struct OuterSponge<'a, P: PastaConfig> {
+    sponge: InnerSponge<'a, P::OtherCurve>,
 }
 
-impl<P: PastaConfig> OuterSponge<P> {
+impl<'a, P: PastaConfig> OuterSponge<'a, P> {
     pub fn new(label: Protocols) -> Self {
-        let mut inner_sponge = InnerSponge::new();
-        let field_label =
-            WireScalar::<P::OtherCurve>::constant(P::OtherCurve::scalar_from_u64(label as u64));
+        Self::new_in(CircuitBuilder::current(), label)
+    }
+
+    /// A sponge in the circuit of `builder`, whose challenges are wires of it
+    pub fn new_in(builder: &'a CircuitBuilder, label: Protocols) -> Self {
+        let mut inner_sponge = InnerSponge::new(builder);
+        let field_label = WireScalar::<P::OtherCurve>::constant_in(
+            builder,
+            P::OtherCurve::scalar_from_u64(label as u64),
+        );
         inner_sponge.absorb(&[field_label]);
 
         OuterSponge {
@@ -22,19 +30,19 @@ impl<P: PastaConfig> OuterSponge<P> {
         }
     }
 
-    pub fn absorb_g(&mut self, gs: &[WireAffine<P>]) {
+    pub fn absorb_g(&mut self, gs: &[WireAffine<'a, P>]) {
         for g in gs {
             self.sponge.absorb(&[g.x, g.y]);
         }
     }
 
-    pub fn absorb_fq(&mut self, x: &[WireScalar<P::OtherCurve>]) {
+    pub fn absorb_fq(&mut self, x: &[WireScalar<'a, P::OtherCurve>]) {
         for fe in x {
             self.sponge.absorb(&[*fe])
         }
     }
 
-    pub fn absorb_fp(&mut self, x: &[WireScalar<P>]) {
+    pub fn absorb_fp(&mut self, x: &[WireScalar<'a, P>]) {
         x.iter().for_each(|x| {
             if P::SCALAR_MODULUS < P::BASE_MODULUS {
                 let v = x.fq_message_pass();
@@ -47,7 +55,7 @@ impl<P: PastaConfig> OuterSponge<P> {
         });
     }
 
-    pub fn challenge(&mut self) -> WireScalar<P> {
+    pub fn challenge(&mut self) -> WireScalar<'a, P> {
         let x: WireScalar<P::OtherCurve> = self.sponge.squeeze();
         if P::SCALAR_MODULUS < P::BASE_MODULUS {
             let (h, _) = x.fp_message_pass();
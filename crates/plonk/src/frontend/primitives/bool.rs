@@ -6,160 +6,177 @@ use std::{
 use halo_group::PastaConfig;
 
 use crate::{
-    circuit::Wire,
+    circuit::{CircuitSpec, Wire},
     frontend::{
-        FRONTEND,
+        CircuitBuilder,
         primitives::{WireAffine, WireScalar},
     },
 };
 
 #[derive(Clone, Copy, Debug)]
-pub struct WireBool<P: PastaConfig> {
+pub struct WireBool<'a, P: PastaConfig> {
     pub(crate) wire: Wire,
+    /// The builder of the circuit the wire belongs to, see `WireScalar`
+    pub(crate) builder: &'a CircuitBuilder,
     _p: PhantomData<P>,
 }
 
-impl<P: PastaConfig> WireBool<P> {
-    pub(crate) fn new(wire: Wire) -> Self {
+impl<'a, P: PastaConfig> WireBool<'a, P> {
+    pub(crate) fn new(builder: &'a CircuitBuilder, wire: Wire) -> Self {
         Self {
             wire,
+            builder,
             _p: PhantomData::default(),
         }
     }
 
+    /// Runs `f` on the circuit of the wire, and wraps the wire it returns
+    #[track_caller]
+    fn map(&self, f: impl FnOnce(&mut CircuitSpec) -> Wire) -> Self {
+        Self::new(self.builder, self.builder.with_circuit(f))
+    }
+
+    #[track_caller]
     pub fn witness() -> Self {
-        FRONTEND.with(|frontend| {
-            let mut frontend = frontend.borrow_mut();
-            Self::new(frontend.circuit.witness_bool(P::SFID))
-        })
+        Self::witness_in(CircuitBuilder::current())
+    }
+
+    #[track_caller]
+    pub fn witness_in(builder: &'a CircuitBuilder) -> Self {
+        Self::new(
+            builder,
+            builder.with_circuit(|circuit| circuit.witness_bool(P::SFID)),
+        )
     }
 
     pub fn constant(b: bool) -> Self {
-        if b { Self::t() } else { Self::f() }
+        Self::constant_in(CircuitBuilder::current(), b)
+    }
+
+    pub fn constant_in(builder: &'a CircuitBuilder, b: bool) -> Self {
+        let constants = builder.with_circuit(|circuit| if b { circuit.one } else { circuit.zero });
+        Self::new(builder, constants[P::SFID as usize])
     }
 
     pub fn f() -> Self {
-        FRONTEND.with(|frontend| {
-            let frontend = frontend.borrow();
-            WireBool::new(frontend.circuit.zero[P::SFID as usize])
-        })
+        Self::constant(false)
     }
 
     pub fn t() -> Self {
-        FRONTEND.with(|frontend| {
-            let frontend = frontend.borrow();
-            WireBool::new(frontend.circuit.one[P::SFID as usize])
-        })
+        Self::constant(true)
+    }
+
+    /// The builder of the circuit the wire belongs to, see `WireScalar::builder`
+    pub fn builder(&self) -> &'a CircuitBuilder {
+        self.builder
     }
 
+    #[track_caller]
     pub fn assert_eq(&self, other: Self) {
-        FRONTEND.with(|frontend| {
-            let mut frontend = frontend.borrow_mut();
-            frontend.circuit.assert_eq_gate(self.wire, other.wire);
-        })
+        self.builder
+            .with_circuit(|circuit| circuit.assert_eq_gate(self.wire, other.wire))
     }
 
-    pub fn message_pass(&self) -> WireBool<P::OtherCurve> {
-        FRONTEND.with(|frontend| {
-            let mut frontend = frontend.borrow_mut();
+    #[track_caller]
+    pub fn message_pass(&self) -> WireBool<'a, P::OtherCurve> {
+        let wire = self.builder.with_circuit(|circuit| {
             if P::IS_PALLAS {
-                WireBool::new(frontend.circuit.fp_bool_message_pass(self.wire))
+                circuit.fp_bool_message_pass(self.wire)
             } else {
-                WireBool::new(frontend.circuit.fq_bool_message_pass(self.wire))
+                circuit.fq_bool_message_pass(self.wire)
             }
-        })
+        });
+        WireBool::new(self.builder, wire)
     }
 
-    pub fn from_wire(wire: Wire) -> Self {
-        WireBool {
-            wire,
-            _p: PhantomData::default(),
-        }
+    pub fn from_wire(builder: &'a CircuitBuilder, wire: Wire) -> Self {
+        Self::new(builder, wire)
     }
 
-    pub fn scalar_cmp(a: WireScalar<P>, b: WireScalar<P>) {
-        FRONTEND.with(|frontend| {
-            let mut frontend = frontend.borrow_mut();
-            Self::new(frontend.circuit.eq_gate(a.wire, b.wire));
+    #[track_caller]
+    pub fn scalar_cmp(a: WireScalar<'a, P>, b: WireScalar<'a, P>) {
+        a.builder.with_circuit(|circuit| {
+            circuit.eq_gate(a.wire, b.wire);
         })
     }
 
-    pub fn scalar_ite(self, true_case: WireScalar<P>, false_case: WireScalar<P>) -> WireScalar<P> {
-        FRONTEND.with(|frontend| {
-            let mut frontend = frontend.borrow_mut();
-            let cond_times_true_case = frontend.circuit.mul_gate(self.wire, true_case.wire);
-            let one = frontend.circuit.one[P::SFID as usize];
-            let minus_cond = frontend.circuit.neg_gate(self.wire);
-            let one_minus_cond = frontend.circuit.add_gate(one, minus_cond);
-            let one_minus_cond_times_false_case =
-                frontend.circuit.mul_gate(one_minus_cond, false_case.wire);
-            let out = frontend
-                .circuit
-                .add_gate(cond_times_true_case, one_minus_cond_times_false_case);
-            WireScalar::new(out)
-        })
+    #[track_caller]
+    pub fn scalar_ite(
+        self,
+        true_case: WireScalar<'a, P>,
+        false_case: WireScalar<'a, P>,
+    ) -> WireScalar<'a, P> {
+        let out = self.builder.with_circuit(|circuit| {
+            let cond_times_true_case = circuit.mul_gate(self.wire, true_case.wire);
+            let one = circuit.one[P::SFID as usize];
+            let minus_cond = circuit.neg_gate(self.wire);
+            let one_minus_cond = circuit.add_gate(one, minus_cond);
+            let one_minus_cond_times_false_case = circuit.mul_gate(one_minus_cond, false_case.wire);
+            circuit.add_gate(cond_times_true_case, one_minus_cond_times_false_case)
+        });
+        WireScalar::new(self.builder, out)
     }
 
+    #[track_caller]
     pub fn affine_ite(
         cond: Self,
-        true_case: WireAffine<P::OtherCurve>,
-        false_case: WireAffine<P::OtherCurve>,
-    ) -> WireAffine<P::OtherCurve> {
+        true_case: WireAffine<'a, P::OtherCurve>,
+        false_case: WireAffine<'a, P::OtherCurve>,
+    ) -> WireAffine<'a, P::OtherCurve> {
         let x = cond.scalar_ite(true_case.x, false_case.x);
         let y = cond.scalar_ite(true_case.y, false_case.y);
-        WireAffine::new(x.wire, y.wire)
+        WireAffine { x, y }
     }
 
+    #[track_caller]
     pub fn output(self) {
-        FRONTEND.with(|frontend| {
-            let mut frontend = frontend.borrow_mut();
-            frontend.circuit.output_gate(self.wire)
-        })
+        self.builder
+            .with_circuit(|circuit| circuit.output_gate(self.wire))
     }
 }
-impl<P: PastaConfig> Not for WireBool<P> {
+impl<P: PastaConfig> Not for WireBool<'_, P> {
     type Output = Self;
 
+    #[track_caller]
     fn not(self) -> Self::Output {
-        FRONTEND.with(|frontend| {
-            let mut frontend = frontend.borrow_mut();
-            let one = frontend.circuit.one[P::SFID as usize];
-            frontend.circuit.add_gate(one, self.wire);
-            WireBool::new(frontend.circuit.add_gate(one, self.wire))
+        self.map(|circuit| {
+            let one = circuit.one[P::SFID as usize];
+            let minus_self = circuit.neg_gate(self.wire);
+            circuit.add_gate(one, minus_self)
         })
     }
 }
-impl<P: PastaConfig> BitAnd for WireBool<P> {
+impl<P: PastaConfig> BitAnd for WireBool<'_, P> {
     type Output = Self;
 
+    #[track_caller]
     fn bitand(self, rhs: Self) -> Self::Output {
-        FRONTEND.with(|frontend| {
-            let mut frontend = frontend.borrow_mut();
-            WireBool::new(frontend.circuit.mul_gate(self.wire, rhs.wire))
-        })
+        self.map(|circuit| circuit.mul_gate(self.wire, rhs.wire))
     }
 }
-impl<P: PastaConfig> BitAndAssign for WireBool<P> {
+impl<P: PastaConfig> BitAndAssign for WireBool<'_, P> {
+    #[track_caller]
     fn bitand_assign(&mut self, rhs: Self) {
         *self = *self & rhs
     }
 }
-impl<P: PastaConfig> BitOr for WireBool<P> {
+impl<P: PastaConfig> BitOr for WireBool<'_, P> {
     type Output = Self;
 
+    #[track_caller]
     fn bitor(self, rhs: Self) -> Self::Output {
-        FRONTEND.with(|frontend| {
-            let mut frontend = frontend.borrow_mut();
+        self.map(|circuit| {
             let a = self.wire;
             let b = rhs.wire;
-            let a_plus_b = frontend.circuit.add_gate(a, b);
-            let a_times_b = frontend.circuit.mul_gate(a, b);
-            let neg_a_times_b = frontend.circuit.neg_gate(a_times_b);
-            WireBool::new(frontend.circuit.add_gate(a_plus_b, neg_a_times_b))
+            let a_plus_b = circuit.add_gate(a, b);
+            let a_times_b = circuit.mul_gate(a, b);
+            let neg_a_times_b = circuit.neg_gate(a_times_b);
+            circuit.add_gate(a_plus_b, neg_a_times_b)
         })
     }
 }
-impl<P: PastaConfig> BitOrAssign for WireBool<P> {
+impl<P: PastaConfig> BitOrAssign for WireBool<'_, P> {
+    #[track_caller]
     fn bitor_assign(&mut self, rhs: Self) {
         *self = *self | rhs
     }
@@ -176,7 +193,7 @@ mod tests {
 
     use crate::{
         frontend::{
-            Call, FRONTEND,
+            Call, Frontend,
             primitives::{WireBool, WireScalar},
         },
         plonk::PlonkProof,
@@ -194,10 +211,7 @@ mod tests {
         c.output();
         d.output();
 
-        FRONTEND.with(|frontend| {
-            let frontend = frontend.borrow_mut();
-            println!("{:?}", frontend.circuit);
-        });
+        Frontend::with_circuit(|circuit| println!("{circuit:?}"));
 
         let mut call = Call::new();
 
@@ -222,6 +236,28 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn bool_not() -> Result<()> {
+        let rng = &mut test_rng();
+        // !x used to be 1 + x, which is 2 rather than 0 for true
+        let x = WireBool::<PallasConfig>::witness();
+        let y = WireBool::<PallasConfig>::witness();
+        (!x).output();
+        (!y).output();
+        (!!y).output();
+
+        let mut call = Call::new();
+        call.witness_scalar_bool(x, false)?;
+        call.witness_scalar_bool(y, true)?;
+        let (fp_trace, _) = call.trace()?;
+        assert_eq!(fp_trace.outputs, vec![Fp::ONE, Fp::ZERO, Fp::ONE]);
+
+        let (circuit, x, w) = fp_trace.consume();
+        PlonkProof::naive_prover(rng, circuit, &x, w).verify(circuit, &x)?;
+
+        Ok(())
+    }
+
     #[test]
     fn bool_fp_neq() -> Result<()> {
         let rng = &mut test_rng();
@@ -232,10 +268,7 @@ mod tests {
         let neq = x.equals(y);
         neq.output();
 
-        FRONTEND.with(|frontend| {
-            let frontend = frontend.borrow_mut();
-            println!("{:?}", frontend.circuit);
-        });
+        Frontend::with_circuit(|circuit| println!("{circuit:?}"));
 
         let mut call = Call::new();
 
@@ -269,10 +302,7 @@ mod tests {
         let c = x_mp & y_mp;
         c.output();
 
-        FRONTEND.with(|frontend| {
-            let frontend = frontend.borrow_mut();
-            println!("{:?}", frontend.circuit);
-        });
+        Frontend::with_circuit(|circuit| println!("{circuit:?}"));
 
         let mut call = Call::new();
 
@@ -308,10 +338,7 @@ mod tests {
         let c = x_mp & y_mp;
         c.output();
 
-        FRONTEND.with(|frontend| {
-            let frontend = frontend.borrow_mut();
-            println!("{:?}", frontend.circuit);
-        });
+        Frontend::with_circuit(|circuit| println!("{circuit:?}"));
 
         let mut call = Call::new();
 
@@ -346,10 +373,7 @@ mod tests {
         let eq = x.equals(y);
         eq.output();
 
-        FRONTEND.with(|frontend| {
-            let frontend = frontend.borrow_mut();
-            println!("{:?}", frontend.circuit);
-        });
+        Frontend::with_circuit(|circuit| println!("{circuit:?}"));
 
         let mut call = Call::new();
 
@@ -385,10 +409,7 @@ mod tests {
         let c = b.scalar_ite(x, y);
         c.output();
 
-        FRONTEND.with(|frontend| {
-            let frontend = frontend.borrow_mut();
-            println!("{:?}", frontend.circuit);
-        });
+        Frontend::with_circuit(|circuit| println!("{circuit:?}"));
 
         let mut call = Call::new();
 
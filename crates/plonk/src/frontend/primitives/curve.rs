@@ -8,58 +8,82 @@ use halo_group::{Affine, Fp, Fq, PastaConfig, PastaFE};
 use crate::{
     circuit::Wire,
     frontend::{
-        FRONTEND,
+        CircuitBuilder,
         primitives::{WireBool, WireScalar},
     },
 };
 
 #[derive(Clone, Copy, Debug)]
-pub struct WireAffine<P: PastaConfig> {
-    pub(crate) x: WireScalar<P::OtherCurve>,
-    pub(crate) y: WireScalar<P::OtherCurve>,
+pub struct WireAffine<'a, P: PastaConfig> {
+    pub(crate) x: WireScalar<'a, P::OtherCurve>,
+    pub(crate) y: WireScalar<'a, P::OtherCurve>,
 }
-impl<P: PastaConfig> WireAffine<P> {
-    pub(crate) fn new(x: Wire, y: Wire) -> Self {
+impl<'a, P: PastaConfig> WireAffine<'a, P> {
+    pub(crate) fn new(builder: &'a CircuitBuilder, x: Wire, y: Wire) -> Self {
         Self {
-            x: WireScalar::<P::OtherCurve>::new(x),
-            y: WireScalar::<P::OtherCurve>::new(y),
+            x: WireScalar::<P::OtherCurve>::new(builder, x),
+            y: WireScalar::<P::OtherCurve>::new(builder, y),
         }
     }
 
     // TODO: Need to constrain
+    #[track_caller]
     pub fn witness() -> Self {
-        FRONTEND.with(|frontend| {
-            let mut frontend = frontend.borrow_mut();
-            let x_wire = frontend.circuit.witness(P::BFID);
-            let y_wire = frontend.circuit.witness(P::BFID);
-            Self::new(x_wire, y_wire)
-        })
+        Self::witness_in(CircuitBuilder::current())
     }
 
+    #[track_caller]
     pub fn public_input() -> Self {
-        FRONTEND.with(|frontend| {
-            let mut frontend = frontend.borrow_mut();
-            let x_wire = frontend.circuit.public_input(P::BFID);
-            let y_wire = frontend.circuit.public_input(P::BFID);
-            Self::new(x_wire, y_wire)
-        })
+        Self::public_input_in(CircuitBuilder::current())
+    }
+
+    #[track_caller]
+    pub fn witness_in(builder: &'a CircuitBuilder) -> Self {
+        let (x_wire, y_wire) = builder.with_circuit(|circuit| {
+            let x_wire = circuit.witness(P::BFID);
+            let y_wire = circuit.witness(P::BFID);
+            (x_wire, y_wire)
+        });
+        Self::new(builder, x_wire, y_wire)
+    }
+
+    #[track_caller]
+    pub fn public_input_in(builder: &'a CircuitBuilder) -> Self {
+        let (x_wire, y_wire) = builder.with_circuit(|circuit| {
+            let x_wire = circuit.public_input(P::BFID);
+            let y_wire = circuit.public_input(P::BFID);
+            (x_wire, y_wire)
+        });
+        Self::new(builder, x_wire, y_wire)
     }
 
+    /// The builder of the circuit the point belongs to, see `WireScalar::builder`
+    pub fn builder(&self) -> &'a CircuitBuilder {
+        self.x.builder
+    }
+
+    #[track_caller]
     pub fn assert_eq(self, other: Self) {
         self.x.assert_eq(other.x);
         self.y.assert_eq(other.y);
     }
 
-    pub fn equals(self, other: Self) -> WireBool<P::OtherCurve> {
+    #[track_caller]
+    pub fn equals(self, other: Self) -> WireBool<'a, P::OtherCurve> {
         let x_eq = self.x.equals(other.x);
         let y_eq = self.y.equals(other.y);
         x_eq & y_eq
     }
 
+    #[track_caller]
     pub fn constant(point: Affine<P>) -> Self {
+        Self::constant_in(CircuitBuilder::current(), point)
+    }
+
+    #[track_caller]
+    pub fn constant_in(builder: &'a CircuitBuilder, point: Affine<P>) -> Self {
         assert!(point.is_on_curve());
-        FRONTEND.with(|frontend| {
-            let mut frontend = frontend.borrow_mut();
+        let (x_wire, y_wire) = builder.with_circuit(|circuit| {
             if P::IS_PALLAS {
                 let x_fe = PastaFE::new(
                     P::basefield_into_bigint(point.x),
@@ -69,9 +93,7 @@ impl<P: PastaConfig> WireAffine<P> {
                     P::basefield_into_bigint(point.y),
                     Some(halo_group::PastaFieldId::Fq),
                 );
-                let x_wire = frontend.circuit.constant(x_fe);
-                let y_wire = frontend.circuit.constant(y_fe);
-                Self::new(x_wire, y_wire)
+                (circuit.constant(x_fe), circuit.constant(y_fe))
             } else {
                 let x_fe = PastaFE::new(
                     P::basefield_into_bigint(point.x),
@@ -81,117 +103,110 @@ impl<P: PastaConfig> WireAffine<P> {
                     P::basefield_into_bigint(point.y),
                     Some(halo_group::PastaFieldId::Fp),
                 );
-                let x_wire = frontend.circuit.constant(x_fe);
-                let y_wire = frontend.circuit.constant(y_fe);
-                Self::new(x_wire, y_wire)
+                (circuit.constant(x_fe), circuit.constant(y_fe))
             }
-        })
+        });
+        Self::new(builder, x_wire, y_wire)
     }
 
     pub fn identity() -> Self {
-        FRONTEND.with(|frontend| {
-            let frontend = frontend.borrow();
-            let x_wire = frontend.circuit.zero[P::BFID as usize];
-            let y_wire = frontend.circuit.zero[P::BFID as usize];
-            Self::new(x_wire, y_wire)
-        })
+        Self::identity_in(CircuitBuilder::current())
     }
 
+    pub fn identity_in(builder: &'a CircuitBuilder) -> Self {
+        let zero = builder.with_circuit(|circuit| circuit.zero[P::BFID as usize]);
+        Self::new(builder, zero, zero)
+    }
+
+    #[track_caller]
     pub fn generator() -> Self {
-        FRONTEND.with(|frontend| {
-            let mut frontend = frontend.borrow_mut();
+        Self::generator_in(CircuitBuilder::current())
+    }
 
+    #[track_caller]
+    pub fn generator_in(builder: &'a CircuitBuilder) -> Self {
+        let (x_wire, y_wire) = builder.with_circuit(|circuit| {
             if P::IS_PALLAS {
                 let x = Fq::from_str(
                     "28948022309329048855892746252171976963363056481941560715954676764349967630336",
                 )
                 .unwrap();
                 let y = Fq::from(2u64);
-                let x_wire = frontend.circuit.constant(x.into());
-                let y_wire = frontend.circuit.constant(y.into());
-                Self::new(x_wire, y_wire)
+                (circuit.constant(x.into()), circuit.constant(y.into()))
             } else {
                 let x = Fp::from_str(
                     "28948022309329048855892746252171976963363056481941647379679742748393362948096",
                 )
                 .unwrap();
                 let y = Fp::from(2u64);
-                let x_wire = frontend.circuit.constant(x.into());
-                let y_wire = frontend.circuit.constant(y.into());
-                Self::new(x_wire, y_wire)
+                (circuit.constant(x.into()), circuit.constant(y.into()))
             }
-        })
+        });
+        Self::new(builder, x_wire, y_wire)
     }
 
+    #[track_caller]
     pub fn output(self) {
-        FRONTEND.with(|frontend| {
-            let mut frontend = frontend.borrow_mut();
-            frontend.circuit.output_gate(self.x.wire);
-            frontend.circuit.output_gate(self.y.wire);
+        self.builder().with_circuit(|circuit| {
+            circuit.output_gate(self.x.wire);
+            circuit.output_gate(self.y.wire);
         })
     }
 
+    #[track_caller]
     pub fn print(self, label: &'static str) {
-        FRONTEND.with(|frontend| {
-            let mut frontend = frontend.borrow_mut();
-            frontend.circuit.print(self.x.wire, label, " (x)");
-            frontend.circuit.print(self.y.wire, label, " (y)");
+        self.builder().with_circuit(|circuit| {
+            circuit.print(self.x.wire, label, " (x)");
+            circuit.print(self.y.wire, label, " (y)");
         })
     }
 }
 
-impl<P: PastaConfig> Add for WireAffine<P> {
-    type Output = WireAffine<P>;
+impl<P: PastaConfig> Add for WireAffine<'_, P> {
+    type Output = Self;
 
-    fn add(self, other: WireAffine<P>) -> Self::Output {
-        FRONTEND.with(|frontend| {
-            let mut frontend = frontend.borrow_mut();
-            let (x_wire, y_wire) = frontend
-                .circuit
-                .add_points((self.x.wire, self.y.wire), (other.x.wire, other.y.wire));
-            WireAffine::new(x_wire, y_wire)
-        })
+    #[track_caller]
+    fn add(self, other: Self) -> Self::Output {
+        let (x_wire, y_wire) = self.builder().with_circuit(|circuit| {
+            circuit.add_points((self.x.wire, self.y.wire), (other.x.wire, other.y.wire))
+        });
+        WireAffine::new(self.builder(), x_wire, y_wire)
     }
 }
 
-impl<P: PastaConfig> Mul<WireScalar<P>> for WireAffine<P> {
-    type Output = WireAffine<P>;
-
-    fn mul(self, other: WireScalar<P>) -> Self::Output {
-        FRONTEND.with(|frontend| {
-            let mut frontend = frontend.borrow_mut();
+impl<'a, P: PastaConfig> Mul<WireScalar<'a, P>> for WireAffine<'a, P> {
+    type Output = Self;
 
+    #[track_caller]
+    fn mul(self, other: WireScalar<'a, P>) -> Self::Output {
+        let (xs_wire, ys_wire) = self.builder().with_circuit(|circuit| {
             if P::IS_PALLAS {
-                let (h, l) = frontend.circuit.fp_message_pass(other.wire);
-                let (xs_wire, ys_wire) = frontend
-                    .circuit
-                    .scalar_mul_pallas((h, l), (self.x.wire, self.y.wire));
-                WireAffine::new(xs_wire, ys_wire)
+                let (h, l) = circuit.fp_message_pass(other.wire);
+                circuit.scalar_mul_pallas((h, l), (self.x.wire, self.y.wire))
             } else {
-                let v = frontend.circuit.fq_message_pass(other.wire);
-                let (xs_wire, ys_wire) = frontend
-                    .circuit
-                    .scalar_mul_vesta(v, (self.x.wire, self.y.wire));
-                WireAffine::new(xs_wire, ys_wire)
+                let v = circuit.fq_message_pass(other.wire);
+                circuit.scalar_mul_vesta(v, (self.x.wire, self.y.wire))
             }
-        })
+        });
+        WireAffine::new(self.builder(), xs_wire, ys_wire)
     }
 }
 
-impl<P: PastaConfig> AddAssign for WireAffine<P> {
+impl<P: PastaConfig> AddAssign for WireAffine<'_, P> {
+    #[track_caller]
     fn add_assign(&mut self, rhs: Self) {
         *self = self.clone() + rhs
     }
 }
 
-impl<P: PastaConfig> Neg for WireAffine<P> {
+impl<P: PastaConfig> Neg for WireAffine<'_, P> {
     type Output = Self;
+    #[track_caller]
     fn neg(self) -> Self::Output {
-        FRONTEND.with(|frontend| {
-            let mut frontend = frontend.borrow_mut();
-            let y_neg = frontend.circuit.neg_gate(self.y.wire);
-            WireAffine::new(self.x.wire, y_neg)
-        })
+        WireAffine {
+            x: self.x,
+            y: -self.y,
+        }
     }
 }
 
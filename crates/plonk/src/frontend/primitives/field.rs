@@ -5,61 +5,108 @@ use std::{
 
 use halo_group::{
     PastaConfig, PastaFE, PastaFieldId, Scalar,
-    ark_ff::{One, Zero},
+    ark_ff::{Field, One, PrimeField, Zero},
 };
 
 use crate::{
-    circuit::Wire,
-    frontend::{FRONTEND, primitives::bool::WireBool},
+    circuit::{CircuitSpec, LookupTable, Wire},
+    frontend::{CircuitBuilder, primitives::bool::WireBool},
+    plonk::GenericConstants,
+    utils::S_POLYS,
 };
 
 #[derive(Clone, Copy, Debug)]
-pub struct WireScalar<P: PastaConfig> {
+pub struct WireScalar<'a, P: PastaConfig> {
     pub(crate) wire: Wire,
+    /// The builder of the circuit the wire belongs to, which the gates of its operations are
+    /// added to
+    pub(crate) builder: &'a CircuitBuilder,
     _p: PhantomData<P>,
 }
 
-impl<P: PastaConfig> WireScalar<P> {
-    pub(crate) fn new(wire: Wire) -> Self {
+impl<'a, P: PastaConfig> WireScalar<'a, P> {
+    pub(crate) fn new(builder: &'a CircuitBuilder, wire: Wire) -> Self {
         Self {
             wire,
+            builder,
             _p: PhantomData::default(),
         }
     }
 
+    /// Runs `f` on the circuit of the wire, and wraps the wire it returns
+    #[track_caller]
+    fn map(&self, f: impl FnOnce(&mut CircuitSpec) -> Wire) -> Self {
+        Self::new(self.builder, self.builder.with_circuit(f))
+    }
+
     // Create a new wire using the thread-local frontend
+    #[track_caller]
     pub fn witness() -> Self {
-        FRONTEND.with(|frontend| {
-            let mut frontend = frontend.borrow_mut();
-            Self::new(frontend.circuit.witness(P::SFID))
-        })
+        Self::witness_in(CircuitBuilder::current())
     }
 
+    #[track_caller]
     pub fn public_input() -> Self {
-        FRONTEND.with(|frontend| {
-            let mut frontend = frontend.borrow_mut();
-            Self::new(frontend.circuit.public_input(P::SFID))
-        })
+        Self::public_input_in(CircuitBuilder::current())
     }
 
+    #[track_caller]
     pub fn constant(c: Scalar<P>) -> Self {
-        FRONTEND.with(|frontend| {
-            let mut frontend = frontend.borrow_mut();
-            WireScalar::new(frontend.circuit.constant(PastaFE::from_scalar::<P>(c)))
-        })
+        Self::constant_in(CircuitBuilder::current(), c)
+    }
+
+    // Create a new wire in `builder`
+    #[track_caller]
+    pub fn witness_in(builder: &'a CircuitBuilder) -> Self {
+        Self::new(
+            builder,
+            builder.with_circuit(|circuit| circuit.witness(P::SFID)),
+        )
+    }
+
+    #[track_caller]
+    pub fn public_input_in(builder: &'a CircuitBuilder) -> Self {
+        Self::new(
+            builder,
+            builder.with_circuit(|circuit| circuit.public_input(P::SFID)),
+        )
+    }
+
+    #[track_caller]
+    pub fn constant_in(builder: &'a CircuitBuilder, c: Scalar<P>) -> Self {
+        let c = PastaFE::from_scalar::<P>(c);
+        Self::new(builder, builder.with_circuit(|circuit| circuit.constant(c)))
     }
 
+    pub fn zero_in(builder: &'a CircuitBuilder) -> Self {
+        Self::new(
+            builder,
+            builder.with_circuit(|circuit| circuit.zero[P::SFID as usize]),
+        )
+    }
+
+    pub fn one_in(builder: &'a CircuitBuilder) -> Self {
+        Self::new(
+            builder,
+            builder.with_circuit(|circuit| circuit.one[P::SFID as usize]),
+        )
+    }
+
+    /// The builder of the circuit the wire belongs to, for creating wires that are combined with it
+    pub fn builder(&self) -> &'a CircuitBuilder {
+        self.builder
+    }
+
+    #[track_caller]
     pub fn inv(&self) -> Self {
-        FRONTEND.with(|frontend| {
-            let mut frontend = frontend.borrow_mut();
-            WireScalar::new(frontend.circuit.inv(self.wire))
-        })
+        self.map(|circuit| circuit.inv(self.wire))
     }
 
     // [1, self, self^2, self^3, ...]
+    #[track_caller]
     pub fn geometric_series(&self, n: usize) -> Vec<Self> {
         let mut result = Vec::with_capacity(n);
-        let mut current = WireScalar::<P>::one();
+        let mut current = WireScalar::<P>::one_in(self.builder);
         for _ in 0..n {
             result.push(current);
             current *= *self;
@@ -67,176 +114,251 @@ impl<P: PastaConfig> WireScalar<P> {
         result
     }
 
+    #[track_caller]
     pub fn square(&self) -> Self {
         *self * *self
     }
 
+    #[track_caller]
     pub fn double(&self) -> Self {
         *self + *self
     }
 
     pub fn zero() -> Self {
-        FRONTEND.with(|frontend| {
-            let frontend = frontend.borrow();
-            WireScalar::new(frontend.circuit.zero[P::SFID as usize])
-        })
+        Self::zero_in(CircuitBuilder::current())
     }
 
     pub fn one() -> Self {
-        FRONTEND.with(|frontend| {
-            let frontend = frontend.borrow();
-            WireScalar::new(frontend.circuit.one[P::SFID as usize])
-        })
+        Self::one_in(CircuitBuilder::current())
     }
 
+    #[track_caller]
     pub fn assert_eq(&self, other: Self) {
-        FRONTEND.with(|frontend| {
-            let mut frontend = frontend.borrow_mut();
-            frontend.circuit.assert_eq_gate(self.wire, other.wire);
-        })
-    }
-
-    pub fn equals(self, b: Self) -> WireBool<P> {
-        FRONTEND.with(|frontend| {
-            let mut frontend = frontend.borrow_mut();
-            WireBool::new(frontend.circuit.eq_gate(self.wire, b.wire))
-        })
-    }
-
-    pub(crate) fn fp_message_pass(self) -> (WireScalar<P::OtherCurve>, WireScalar<P::OtherCurve>) {
+        self.builder
+            .with_circuit(|circuit| circuit.assert_eq_gate(self.wire, other.wire))
+    }
+
+    #[track_caller]
+    pub fn equals(self, b: Self) -> WireBool<'a, P> {
+        WireBool::new(
+            self.builder,
+            self.builder
+                .with_circuit(|circuit| circuit.eq_gate(self.wire, b.wire)),
+        )
+    }
+
+    /// Constrains `wires` to be an entry of `table_id`, see `CircuitSpec::lookup`
+    #[track_caller]
+    pub fn lookup(table_id: LookupTable, wires: &[Self]) {
+        let builder = wires.first().expect("A lookup of no wires").builder;
+        let wires: Vec<Wire> = wires.iter().map(|x| x.wire).collect();
+        builder.with_circuit(|circuit| circuit.lookup(table_id, &wires))
+    }
+
+    /// The `n` least significant bits, which constrains the value to be less than 2ⁿ
+    #[track_caller]
+    pub fn to_bits(&self, n: usize) -> Vec<WireBool<'a, P>> {
+        assert!(
+            n < Scalar::<P>::MODULUS_BIT_SIZE as usize,
+            "{n} bits can not be recomposed without wrapping around the modulus"
+        );
+        let wires: Vec<Wire> = self
+            .builder
+            .with_circuit(|circuit| (0..n).map(|i| circuit.bit_gate(self.wire, i)).collect());
+        let bits: Vec<WireBool<P>> = wires
+            .into_iter()
+            .map(|wire| WireBool::new(self.builder, wire))
+            .collect();
+        Self::from_bits(&bits).assert_eq(*self);
+        bits
+    }
+
+    /// Σᵢ bitsᵢ 2ⁱ, with the bits in little-endian order
+    #[track_caller]
+    pub fn from_bits(bits: &[WireBool<'a, P>]) -> Self {
+        let builder = bits.first().expect("No bits to recompose").builder;
+        let bits: Vec<Wire> = bits.iter().map(|b| b.wire).collect();
+        Self::new(
+            builder,
+            builder.with_circuit(|circuit| {
+                let mut acc = circuit.zero[P::SFID as usize];
+                for (i, chunk) in bits.chunks(S_POLYS - 1).enumerate() {
+                    acc = circuit.pack_bits(acc, chunk, i * (S_POLYS - 1));
+                }
+                acc
+            }),
+        )
+    }
+
+    /// Constrains the value to be less than 2ⁿ
+    #[track_caller]
+    pub fn range_check(&self, n: usize) {
+        self.to_bits(n);
+    }
+
+    /// Whether self < other, where both are constrained to be less than 2ⁿ
+    #[track_caller]
+    pub fn lt(&self, other: Self, n: usize) -> WireBool<'a, P> {
+        self.range_check(n);
+        other.range_check(n);
+        // 2ⁿ + other - self - 1 is in [2ⁿ, 2ⁿ⁺¹) if self < other, and in [0, 2ⁿ) otherwise
+        let two_pow_n_minus_one = Scalar::<P>::from(2u64).pow([n as u64]) - Scalar::<P>::one();
+        let d = WireScalar::constant_in(self.builder, two_pow_n_minus_one) + other - *self;
+        d.to_bits(n + 1)[n]
+    }
+
+    /// Whether self ≤ other, where both are constrained to be less than 2ⁿ
+    #[track_caller]
+    pub fn le(&self, other: Self, n: usize) -> WireBool<'a, P> {
+        !self.gt(other, n)
+    }
+
+    /// Whether self > other, where both are constrained to be less than 2ⁿ
+    #[track_caller]
+    pub fn gt(&self, other: Self, n: usize) -> WireBool<'a, P> {
+        other.lt(*self, n)
+    }
+
+    #[track_caller]
+    pub(crate) fn fp_message_pass(
+        self,
+    ) -> (WireScalar<'a, P::OtherCurve>, WireScalar<'a, P::OtherCurve>) {
         assert!(self.wire.fid == PastaFieldId::Fp);
         assert!(P::IS_PALLAS);
-        FRONTEND.with(|frontend| {
-            let mut frontend = frontend.borrow_mut();
-            let (h, l) = frontend.circuit.fp_message_pass(self.wire);
-            (WireScalar::new(h), WireScalar::new(l))
-        })
+        let (h, l) = self
+            .builder
+            .with_circuit(|circuit| circuit.fp_message_pass(self.wire));
+        (
+            WireScalar::new(self.builder, h),
+            WireScalar::new(self.builder, l),
+        )
     }
 
-    pub(crate) fn fq_message_pass(self) -> WireScalar<P::OtherCurve> {
+    #[track_caller]
+    pub(crate) fn fq_message_pass(self) -> WireScalar<'a, P::OtherCurve> {
         assert!(self.wire.fid == PastaFieldId::Fq);
         assert!(!P::IS_PALLAS);
-        FRONTEND.with(|frontend| {
-            let mut frontend = frontend.borrow_mut();
-            let v = frontend.circuit.fq_message_pass(self.wire);
-            WireScalar::new(v)
-        })
+        let v = self
+            .builder
+            .with_circuit(|circuit| circuit.fq_message_pass(self.wire));
+        WireScalar::new(self.builder, v)
     }
 
-    pub fn from_wire(wire: Wire) -> Self {
-        WireScalar {
-            wire,
-            _p: PhantomData::default(),
-        }
+    pub fn from_wire(builder: &'a CircuitBuilder, wire: Wire) -> Self {
+        Self::new(builder, wire)
     }
 
+    #[track_caller]
     pub fn output(self) {
-        FRONTEND.with(|frontend| {
-            let mut frontend = frontend.borrow_mut();
-            frontend.circuit.output_gate(self.wire)
-        })
+        self.builder
+            .with_circuit(|circuit| circuit.output_gate(self.wire))
     }
 
+    #[track_caller]
     pub fn print(self, label: &'static str) {
-        FRONTEND.with(|frontend| {
-            let mut frontend = frontend.borrow_mut();
-            frontend.circuit.print(self.wire, label, "")
-        })
+        self.builder
+            .with_circuit(|circuit| circuit.print(self.wire, label, ""))
     }
 }
 
-impl<P: PastaConfig> Zero for WireScalar<P> {
+impl<P: PastaConfig> Zero for WireScalar<'_, P> {
     fn zero() -> Self {
-        FRONTEND.with(|frontend| {
-            let frontend = frontend.borrow();
-            WireScalar::new(frontend.circuit.zero[P::SFID as usize])
-        })
+        Self::zero_in(CircuitBuilder::current())
     }
 
     fn is_zero(&self) -> bool {
-        self.wire == Self::zero().wire
+        self.wire
+            == self
+                .builder
+                .with_circuit(|circuit| circuit.zero[P::SFID as usize])
     }
 }
 
-impl<P: PastaConfig> One for WireScalar<P> {
+impl<P: PastaConfig> One for WireScalar<'_, P> {
     fn one() -> Self {
-        FRONTEND.with(|frontend| {
-            let frontend = frontend.borrow();
-            WireScalar::new(frontend.circuit.one[P::SFID as usize])
-        })
+        Self::one_in(CircuitBuilder::current())
     }
 }
 
-impl<P: PastaConfig> Add for WireScalar<P> {
-    type Output = WireScalar<P>;
+impl<P: PastaConfig> GenericConstants for WireScalar<'_, P> {
+    fn zero_like(&self) -> Self {
+        Self::zero_in(self.builder)
+    }
+    fn one_like(&self) -> Self {
+        Self::one_in(self.builder)
+    }
+}
 
-    fn add(self, other: WireScalar<P>) -> Self::Output {
-        FRONTEND.with(|frontend| {
-            let mut frontend = frontend.borrow_mut();
-            WireScalar::new(frontend.circuit.add_gate(self.wire, other.wire))
-        })
+impl<P: PastaConfig> Add for WireScalar<'_, P> {
+    type Output = Self;
+
+    #[track_caller]
+    fn add(self, other: Self) -> Self::Output {
+        self.map(|circuit| circuit.add_gate(self.wire, other.wire))
     }
 }
 
-impl<P: PastaConfig> AddAssign for WireScalar<P> {
+impl<P: PastaConfig> AddAssign for WireScalar<'_, P> {
+    #[track_caller]
     fn add_assign(&mut self, rhs: Self) {
         *self = *self + rhs;
     }
 }
 
-impl<P: PastaConfig> Sub for WireScalar<P> {
-    type Output = WireScalar<P>;
+impl<P: PastaConfig> Sub for WireScalar<'_, P> {
+    type Output = Self;
 
-    fn sub(self, other: WireScalar<P>) -> Self::Output {
+    #[track_caller]
+    fn sub(self, other: Self) -> Self::Output {
         self + (-other)
     }
 }
 
-impl<P: PastaConfig> SubAssign for WireScalar<P> {
+impl<P: PastaConfig> SubAssign for WireScalar<'_, P> {
+    #[track_caller]
     fn sub_assign(&mut self, rhs: Self) {
         *self = *self - rhs;
     }
 }
 
-impl<P: PastaConfig> Mul for WireScalar<P> {
-    type Output = WireScalar<P>;
+impl<P: PastaConfig> Mul for WireScalar<'_, P> {
+    type Output = Self;
 
-    fn mul(self, other: WireScalar<P>) -> Self::Output {
-        FRONTEND.with(|frontend| {
-            let mut frontend = frontend.borrow_mut();
-            WireScalar::new(frontend.circuit.mul_gate(self.wire, other.wire))
-        })
+    #[track_caller]
+    fn mul(self, other: Self) -> Self::Output {
+        self.map(|circuit| circuit.mul_gate(self.wire, other.wire))
     }
 }
 
-impl<P: PastaConfig> MulAssign for WireScalar<P> {
+impl<P: PastaConfig> MulAssign for WireScalar<'_, P> {
+    #[track_caller]
     fn mul_assign(&mut self, rhs: Self) {
         *self = *self * rhs;
     }
 }
 
-impl<P: PastaConfig> Div for WireScalar<P> {
-    type Output = WireScalar<P>;
+impl<P: PastaConfig> Div for WireScalar<'_, P> {
+    type Output = Self;
 
-    fn div(self, other: WireScalar<P>) -> Self::Output {
+    #[track_caller]
+    fn div(self, other: Self) -> Self::Output {
         self * other.inv()
     }
 }
 
-impl<P: PastaConfig> DivAssign for WireScalar<P> {
+impl<P: PastaConfig> DivAssign for WireScalar<'_, P> {
+    #[track_caller]
     fn div_assign(&mut self, rhs: Self) {
         *self = *self / rhs;
     }
 }
 
-impl<P: PastaConfig> Neg for WireScalar<P> {
-    type Output = WireScalar<P>;
+impl<P: PastaConfig> Neg for WireScalar<'_, P> {
+    type Output = Self;
 
-    fn neg(self) -> WireScalar<P> {
-        FRONTEND.with(|frontend| {
-            let mut frontend = frontend.borrow_mut();
-            WireScalar::new(frontend.circuit.neg_gate(self.wire))
-        })
+    #[track_caller]
+    fn neg(self) -> Self {
+        self.map(|circuit| circuit.neg_gate(self.wire))
     }
 }
 
@@ -253,7 +375,7 @@ mod tests {
     };
 
     use crate::{
-        frontend::{Call, FRONTEND, primitives::WireScalar},
+        frontend::{Call, CircuitBuilder, Frontend, primitives::WireScalar},
         plonk::PlonkProof,
     };
 
@@ -278,10 +400,7 @@ mod tests {
         let d = c + z;
         d.output();
 
-        FRONTEND.with(|frontend| {
-            let frontend = frontend.borrow_mut();
-            println!("{:?}", frontend.circuit);
-        });
+        Frontend::with_circuit(|circuit| println!("{circuit:?}"));
 
         let mut call = Call::new();
 
@@ -318,10 +437,7 @@ mod tests {
         x_inv.output();
         y_neg.output();
 
-        FRONTEND.with(|frontend| {
-            let frontend = frontend.borrow_mut();
-            println!("{:?}", frontend.circuit);
-        });
+        Frontend::with_circuit(|circuit| println!("{circuit:?}"));
 
         let mut call = Call::new();
 
@@ -359,10 +475,7 @@ mod tests {
         let d = c + z;
         d.output();
 
-        FRONTEND.with(|frontend| {
-            let frontend = frontend.borrow_mut();
-            println!("{:?}", frontend.circuit);
-        });
+        Frontend::with_circuit(|circuit| println!("{circuit:?}"));
 
         let mut call = Call::new();
 
@@ -478,4 +591,77 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn fp_to_from_bits() -> Result<()> {
+        let rng = &mut test_rng();
+        let x_v = rng.next_u64() >> 24;
+        let x = WireScalar::<PallasConfig>::witness();
+        let bits = x.to_bits(40);
+        for b in &bits {
+            b.output();
+        }
+        WireScalar::from_bits(&bits[..20]).output();
+
+        let mut call = Call::new();
+        call.witness(x, Fp::from(x_v))?;
+        let (fp_trace, fq_trace) = call.trace()?;
+
+        let expected: Vec<Fp> = (0..40).map(|i| Fp::from((x_v >> i) & 1)).collect();
+        assert_eq!(fp_trace.outputs[..40], expected);
+        assert_eq!(fp_trace.outputs[40], Fp::from(x_v & ((1 << 20) - 1)));
+
+        let (circuit, x, w) = fp_trace.consume();
+        PlonkProof::naive_prover(rng, circuit, &x, w).verify(circuit, &x)?;
+        let (circuit, x, w) = fq_trace.consume();
+        PlonkProof::naive_prover(rng, circuit, &x, w).verify(circuit, &x)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn fq_range_check() -> Result<()> {
+        let rng = &mut test_rng();
+        let builder = CircuitBuilder::new();
+        let x = WireScalar::<VestaConfig>::witness_in(&builder);
+        x.range_check(16);
+
+        for (x_v, in_range) in [(0, true), ((1 << 16) - 1, true), (1 << 16, false)] {
+            let mut call = builder.call();
+            call.witness(x, Fq::from(x_v))?;
+            let (_, fq_trace) = call.trace()?;
+            let (circuit, x, w) = fq_trace.consume();
+            let verified = PlonkProof::naive_prover(rng, circuit, &x, w).verify(circuit, &x);
+            assert_eq!(verified.is_ok(), in_range, "{x_v}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn fp_comparisons() -> Result<()> {
+        let rng = &mut test_rng();
+        let builder = CircuitBuilder::new();
+        let x = WireScalar::<PallasConfig>::witness_in(&builder);
+        let y = WireScalar::<PallasConfig>::witness_in(&builder);
+        x.lt(y, 64).output();
+        x.le(y, 64).output();
+        x.gt(y, 64).output();
+
+        let z_v = rng.next_u64();
+        for (x_v, y_v) in [(z_v, z_v), (z_v / 2, z_v), (z_v, z_v / 2), (0, u64::MAX)] {
+            let mut call = builder.call();
+            call.witness(x, Fp::from(x_v))?;
+            call.witness(y, Fp::from(y_v))?;
+            let (fp_trace, _) = call.trace()?;
+
+            let expected = [x_v < y_v, x_v <= y_v, x_v > y_v].map(Fp::from);
+            assert_eq!(fp_trace.outputs, expected, "{x_v} {y_v}");
+
+            let (circuit, x, w) = fp_trace.consume();
+            PlonkProof::naive_prover(rng, circuit, &x, w).verify(circuit, &x)?;
+        }
+
+        Ok(())
+    }
 }
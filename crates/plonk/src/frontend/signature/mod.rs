@@ -4,29 +4,29 @@ use halo_poseidon::Protocols;
 use halo_schnorr::SchnorrSignature;
 
 use crate::frontend::{
-    Call,
+    Call, CircuitBuilder,
     poseidon::outer_sponge::OuterSponge,
     primitives::{WireAffine, WireBool, WireScalar},
 };
 
-pub type WirePublicKey<P> = WireAffine<P>;
+pub type WirePublicKey<'a, P> = WireAffine<'a, P>;
 
 pub trait CallSignature {
     fn witness_signature<P: PastaConfig>(
         &mut self,
-        wire_proof: WireSchnorrSignature<P>,
+        wire_proof: WireSchnorrSignature<'_, P>,
         proof: SchnorrSignature<P>,
     ) -> Result<()>;
     fn public_input_signature<P: PastaConfig>(
         &mut self,
-        wire_proof: WireSchnorrSignature<P>,
+        wire_proof: WireSchnorrSignature<'_, P>,
         proof: SchnorrSignature<P>,
     ) -> Result<()>;
 }
 impl CallSignature for Call {
     fn witness_signature<P: PastaConfig>(
         &mut self,
-        wire_signature: WireSchnorrSignature<P>,
+        wire_signature: WireSchnorrSignature<'_, P>,
         signature: SchnorrSignature<P>,
     ) -> Result<()> {
         let WireSchnorrSignature { r, s } = wire_signature;
@@ -35,7 +35,7 @@ impl CallSignature for Call {
     }
     fn public_input_signature<P: PastaConfig>(
         &mut self,
-        wire_signature: WireSchnorrSignature<P>,
+        wire_signature: WireSchnorrSignature<'_, P>,
         signature: SchnorrSignature<P>,
     ) -> Result<()> {
         let WireSchnorrSignature { r, s } = wire_signature;
@@ -46,35 +46,41 @@ impl CallSignature for Call {
 
 // Schnorr signature struct: (R, s)
 #[derive(Clone, Copy)]
-pub struct WireSchnorrSignature<P: PastaConfig> {
-    pub r: WireAffine<P>, // Commitment point R = k * G
-    pub s: WireScalar<P>, // s = k + e * x
+pub struct WireSchnorrSignature<'a, P: PastaConfig> {
+    pub r: WireAffine<'a, P>, // Commitment point R = k * G
+    pub s: WireScalar<'a, P>, // s = k + e * x
 }
-impl<P: PastaConfig> WireSchnorrSignature<P> {
+impl<'a, P: PastaConfig> WireSchnorrSignature<'a, P> {
     pub fn witness() -> Self {
+        Self::witness_in(CircuitBuilder::current())
+    }
+    pub fn witness_in(builder: &'a CircuitBuilder) -> Self {
         Self {
-            r: WireAffine::witness(),
-            s: WireScalar::witness(),
+            r: WireAffine::witness_in(builder),
+            s: WireScalar::witness_in(builder),
         }
     }
     pub fn public_input() -> Self {
+        Self::public_input_in(CircuitBuilder::current())
+    }
+    pub fn public_input_in(builder: &'a CircuitBuilder) -> Self {
         Self {
-            r: WireAffine::public_input(),
-            s: WireScalar::public_input(),
+            r: WireAffine::public_input_in(builder),
+            s: WireScalar::public_input_in(builder),
         }
     }
-    pub fn new(r: WireAffine<P>, s: WireScalar<P>) -> Self {
+    pub fn new(r: WireAffine<'a, P>, s: WireScalar<'a, P>) -> Self {
         Self { r, s }
     }
 }
 
-impl<P: PastaConfig> WireSchnorrSignature<P> {
+impl<'a, P: PastaConfig> WireSchnorrSignature<'a, P> {
     fn hash_message(
-        public_key: WireAffine<P>,
-        r: WireAffine<P>,
-        message: &[WireScalar<P::OtherCurve>],
-    ) -> WireScalar<P> {
-        let mut sponge = OuterSponge::new(Protocols::SIGNATURE);
+        public_key: WireAffine<'a, P>,
+        r: WireAffine<'a, P>,
+        message: &[WireScalar<'a, P::OtherCurve>],
+    ) -> WireScalar<'a, P> {
+        let mut sponge = OuterSponge::new_in(public_key.builder(), Protocols::SIGNATURE);
 
         // Hash P || R || m
         sponge.absorb_g(&[public_key, r]);
@@ -84,14 +90,15 @@ impl<P: PastaConfig> WireSchnorrSignature<P> {
 
     pub fn verify(
         &self,
-        pk: WireAffine<P>,
-        message: &[WireScalar<P::OtherCurve>],
-    ) -> WireBool<P::OtherCurve> {
+        pk: WireAffine<'a, P>,
+        message: &[WireScalar<'a, P::OtherCurve>],
+    ) -> WireBool<'a, P::OtherCurve> {
+        let _scope = pk.builder().scope("schnorr");
         // e = H(P || R || m)
         let e = Self::hash_message(pk, self.r, message);
 
         // s * G =? R + e * P
-        let lhs = WireAffine::generator() * self.s;
+        let lhs = WireAffine::generator_in(pk.builder()) * self.s;
         let rhs = self.r + pk * e;
         lhs.equals(rhs)
     }
@@ -101,7 +108,7 @@ impl<P: PastaConfig> WireSchnorrSignature<P> {
 mod tests {
     use anyhow::Result;
     use halo_group::{
-        Fq, PallasConfig, VestaConfig,
+        Fp, Fq, PallasConfig, PastaFieldId, VestaConfig,
         ark_ff::UniformRand,
         ark_std::{rand::Rng, test_rng},
     };
@@ -109,9 +116,9 @@ mod tests {
 
     use crate::{
         frontend::{
-            Call,
+            Call, CircuitBuilder,
             primitives::{WireAffine, WireScalar},
-            signature::WireSchnorrSignature,
+            signature::{CallSignature, WireSchnorrSignature},
         },
         plonk::PlonkProof,
     };
@@ -153,4 +160,44 @@ mod tests {
         PlonkProof::naive_prover(rng, circuit, &x, w).verify(circuit, &x)?;
         Ok(())
     }
+
+    #[test]
+    fn optimized_parity() -> Result<()> {
+        let rng = &mut test_rng();
+        let (sk_v, pk_v) = generate_keypair();
+        let message_v: Vec<Fq> = (0..5).map(|_| Fq::rand(rng)).collect();
+        let signature_v = sk_v.sign(&message_v);
+
+        // A constant key and message leave gates of constants to fold
+        let builder = CircuitBuilder::new();
+        let pk = WireAffine::constant_in(&builder, pk_v.0);
+        let message: Vec<WireScalar<VestaConfig>> = message_v
+            .iter()
+            .map(|m| WireScalar::constant_in(&builder, *m))
+            .collect();
+        let signature = WireSchnorrSignature::<PallasConfig>::witness_in(&builder);
+        signature.verify(pk, &message).output();
+        let (optimized, _) = builder.with_circuit(|circuit| circuit.optimize());
+        let row_count = builder.row_count();
+        // The Fp circuit has no constants to fold and keeps its rows, while the Fq circuit hashes
+        // the key with a permutation of constants
+        assert_eq!(optimized.used_rows(PastaFieldId::Fp), row_count[0]);
+        assert!(optimized.used_rows(PastaFieldId::Fq) < row_count[1]);
+
+        let mut wrong_signature = signature_v;
+        wrong_signature.s += Fp::from(1);
+        for (signature_v, valid) in [(signature_v, true), (wrong_signature, false)] {
+            for mut call in [builder.call(), builder.call_unoptimized()] {
+                call.witness_signature(signature, signature_v)?;
+                let (fp_trace, fq_trace) = call.trace()?;
+                assert_eq!(fq_trace.outputs, vec![Fq::from(valid)]);
+
+                let (circuit, x, w) = fp_trace.consume();
+                PlonkProof::naive_prover(rng, circuit, &x, w).verify(circuit, &x)?;
+                let (circuit, x, w) = fq_trace.consume();
+                PlonkProof::naive_prover(rng, circuit, &x, w).verify(circuit, &x)?;
+            }
+        }
+        Ok(())
+    }
 }
@@ -8,14 +8,14 @@ use std::time::Instant;
 
 use anyhow::Result;
 use halo_group::{Fq, PallasConfig, ark_ff::UniformRand, ark_std::rand::thread_rng};
-use halo_schnorr::{PublicKey, SchnorrSignature, generate_keypair};
+use halo_schnorr::{PublicKey, SchnorrSignature, batch_verify, generate_keypair};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
 use crate::{
     circuit::PlonkCircuit,
     frontend::{
         Call,
-        ivc::{CallIVCState, IVC_FP_CIRCUIT, IVC_FQ_CIRCUIT, IVCState, WireIVCState},
+        ivc::{CallIVCState, IVCState, WireIVCState},
         plonk::{CallPlonk, WirePlonkCircuit, WirePlonkProof, WirePlonkPublicInputs},
         primitives::WireAffine,
     },
@@ -46,7 +46,15 @@ fn main() -> Result<()> {
         (N as f32) / now.elapsed().as_secs_f32()
     );
 
-    // IVCState::print_ivc_circuit()?;
+    let now = Instant::now();
+    let batch = vec![(pk.clone(), msg.as_slice(), signature); N];
+    assert!(batch_verify(&batch));
+    println!(
+        "Batch verified {:?} signatures per second",
+        (N as f32) / now.elapsed().as_secs_f32()
+    );
+
+    // IVCState::preprocess(None)?;
     // Init 0
     let now = Instant::now();
     let ivc_state_0 = IVCState::init();
@@ -0,0 +1,345 @@
+#![allow(non_snake_case)]
+
+//! A versioned envelope for serialized proofs, circuits and public inputs. The header records the
+//! curve and the row count of the circuit, so bytes meant for another curve or circuit are
+//! rejected before the payload is read.
+
+use anyhow::{Context, Result, anyhow, ensure};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Validate};
+use halo_group::PastaConfig;
+
+use crate::{
+    circuit::{PlonkCircuit, PlonkPublicInputs},
+    plonk::PlonkProof,
+};
+
+/// Bumped whenever the serialized layout of a proof, circuit or public inputs changes. Version 2
+/// has the `S_POLYS` = 15 permutation columns of packed generic gates, up from 8, version 3 the
+/// high parts of the blinded chunks of t in hiding proofs, version 4 drops the evaluations of
+/// the identity permutation from proofs, and version 5 commits with the generators of the
+/// embedded public parameters past the first block, which used to overlap.
+pub const ENVELOPE_VERSION: u32 = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct EnvelopeHeader {
+    pub version: u32,
+    /// `PastaConfig::CURVE_NAME` of the curve the payload is defined over
+    pub curve: String,
+    /// The row count of the circuit the payload belongs to
+    pub rows: usize,
+}
+
+impl EnvelopeHeader {
+    pub fn new<P: PastaConfig>(rows: usize) -> Self {
+        Self {
+            version: ENVELOPE_VERSION,
+            curve: P::CURVE_NAME.to_string(),
+            rows,
+        }
+    }
+
+    /// Checks the version, the curve and the row count against the expected ones
+    pub fn check<P: PastaConfig>(&self, rows: usize) -> Result<()> {
+        ensure!(
+            self.version == ENVELOPE_VERSION,
+            "Unsupported envelope version {}, expected {ENVELOPE_VERSION}",
+            self.version
+        );
+        ensure!(
+            self.curve == P::CURVE_NAME,
+            "Envelope is for curve {}, expected {}",
+            self.curve,
+            P::CURVE_NAME
+        );
+        ensure!(
+            self.rows == rows,
+            "Envelope is for a circuit of {} rows, expected {rows}",
+            self.rows
+        );
+        Ok(())
+    }
+}
+
+/// Serializes `value` behind the header of a circuit over `P` with `rows` rows
+pub fn envelope_to_bytes<P: PastaConfig, T: CanonicalSerialize>(
+    value: &T,
+    rows: usize,
+    compress: Compress,
+) -> Result<Vec<u8>> {
+    let header = EnvelopeHeader::new::<P>(rows);
+    let mut bytes =
+        Vec::with_capacity(header.serialized_size(compress) + value.serialized_size(compress));
+    header
+        .serialize_with_mode(&mut bytes, compress)
+        .map_err(|e| anyhow!("Failed to serialize envelope header: {e}"))?;
+    value
+        .serialize_with_mode(&mut bytes, compress)
+        .map_err(|e| anyhow!("Failed to serialize envelope payload: {e}"))?;
+    Ok(bytes)
+}
+
+/// Deserializes the header and the value written by `envelope_to_bytes`. The header is checked
+/// against `rows`, or only against the version and curve if `rows` is `None`. Every point is
+/// checked to be on the curve and in the prime order subgroup.
+pub fn envelope_from_bytes<P: PastaConfig, T: CanonicalDeserialize>(
+    bytes: &[u8],
+    rows: Option<usize>,
+    compress: Compress,
+) -> Result<(EnvelopeHeader, T)> {
+    let mut reader = bytes;
+    let header = EnvelopeHeader::deserialize_with_mode(&mut reader, compress, Validate::Yes)
+        .map_err(|e| anyhow!("Failed to deserialize envelope header: {e}"))?;
+    header.check::<P>(rows.unwrap_or(header.rows))?;
+    let value = T::deserialize_with_mode(&mut reader, compress, Validate::Yes)
+        .map_err(|e| anyhow!("Failed to deserialize envelope payload: {e}"))?;
+    ensure!(
+        reader.is_empty(),
+        "{} trailing bytes after envelope payload",
+        reader.len()
+    );
+    Ok((header, value))
+}
+
+impl<P: PastaConfig> PlonkCircuit<P> {
+    pub fn to_bytes(&self, compress: Compress) -> Result<Vec<u8>> {
+        envelope_to_bytes::<P, _>(self, self.rows, compress)
+    }
+
+    pub fn from_bytes(bytes: &[u8], compress: Compress) -> Result<Self> {
+        let (header, circuit) = envelope_from_bytes::<P, Self>(bytes, None, compress)?;
+        ensure!(
+            header.rows == circuit.rows,
+            "Envelope is for a circuit of {} rows, but the circuit has {} rows",
+            header.rows,
+            circuit.rows
+        );
+        Ok(circuit)
+    }
+}
+
+impl<P: PastaConfig> PlonkPublicInputs<P> {
+    pub fn to_bytes(&self, circuit: &PlonkCircuit<P>, compress: Compress) -> Result<Vec<u8>> {
+        envelope_to_bytes::<P, _>(self, circuit.rows, compress)
+    }
+
+    /// Also checks that the previous accumulator has the shape of an opening, see
+    /// `EvalProof::check_shape`
+    pub fn from_bytes(bytes: &[u8], circuit: &PlonkCircuit<P>, compress: Compress) -> Result<Self> {
+        let (_, public_inputs) =
+            envelope_from_bytes::<P, Self>(bytes, Some(circuit.rows), compress)?;
+        let q = &public_inputs.acc_prev.q;
+        q.pi.check_shape(q.d)
+            .context("Malformed previous accumulator")?;
+        Ok(public_inputs)
+    }
+}
+
+impl<P: PastaConfig> PlonkProof<P> {
+    pub fn to_bytes(&self, circuit: &PlonkCircuit<P>, compress: Compress) -> Result<Vec<u8>> {
+        envelope_to_bytes::<P, _>(self, circuit.rows, compress)
+    }
+
+    /// Also checks that the evaluation proofs have the shape of openings of polynomials over
+    /// `circuit.rows` points, and the next accumulator that of any opening, so that a malformed
+    /// proof is rejected here instead of failing in the verifier. See `EvalProof::check_shape`.
+    pub fn from_bytes(bytes: &[u8], circuit: &PlonkCircuit<P>, compress: Compress) -> Result<Self> {
+        let (_, pi) = envelope_from_bytes::<P, Self>(bytes, Some(circuit.rows), compress)?;
+        let d = circuit.rows - 1;
+        pi.pis.r.check_shape(d).context("Malformed opening of r")?;
+        pi.pis
+            .r_omega
+            .check_shape(d)
+            .context("Malformed opening of r at ζω")?;
+        let q = &pi.acc_next.q;
+        q.pi.check_shape(q.d)
+            .context("Malformed next accumulator")?;
+        Ok(pi)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::{CircuitSpec, LookupTable, TraceBuilder};
+    use halo_group::{
+        PallasConfig, PallasScalar, VestaConfig,
+        ark_std::{rand::thread_rng, test_rng},
+    };
+
+    const MODES: [Compress; 2] = [Compress::Yes, Compress::No];
+
+    fn scalar(n: u64) -> PallasScalar {
+        PallasConfig::scalar_from_u64(n)
+    }
+
+    /// The Fp circuit, public inputs and a hiding proof of x·y = 42
+    fn proof() -> Result<(
+        PlonkCircuit<PallasConfig>,
+        PlonkPublicInputs<PallasConfig>,
+        PlonkProof<PallasConfig>,
+    )> {
+        let mut circuit = CircuitSpec::new();
+        let x = circuit.fp_public_input();
+        let y = circuit.fp_witness();
+        let xy = circuit.mul_gate(x, y);
+        circuit.output_gate(xy);
+        circuit.hiding = true;
+
+        let mut trace_builder = TraceBuilder::new(circuit);
+        trace_builder.public_input(x, scalar(7).into())?;
+        trace_builder.witness(y, scalar(6).into())?;
+        let (fp_trace, _) = trace_builder.trace(None, None)?;
+
+        let (circuit, x, w) = fp_trace.consume();
+        let pi = PlonkProof::naive_prover_hiding(&mut thread_rng(), circuit, &x, w)?;
+        Ok((circuit, x, pi))
+    }
+
+    /// Round trips all three payloads through both modes, and checks that the deserialized proof
+    /// still verifies and serializes to the same bytes
+    fn round_trip(
+        circuit: PlonkCircuit<PallasConfig>,
+        x: &PlonkPublicInputs<PallasConfig>,
+        pi: &PlonkProof<PallasConfig>,
+    ) -> Result<()> {
+        for compress in MODES {
+            let bytes = circuit.to_bytes(compress)?;
+            let circuit_de = PlonkCircuit::from_bytes(&bytes, compress)?;
+            assert_eq!(circuit, circuit_de);
+
+            let bytes = x.to_bytes(&circuit, compress)?;
+            let x_de = PlonkPublicInputs::from_bytes(&bytes, &circuit, compress)?;
+            assert_eq!(x_de.to_bytes(&circuit, compress)?, bytes);
+            assert_eq!(x.public_inputs, x_de.public_inputs);
+            assert_eq!(x.acc_prev, x_de.acc_prev);
+
+            let bytes = pi.to_bytes(&circuit, compress)?;
+            let pi_de = PlonkProof::from_bytes(&bytes, &circuit, compress)?;
+            assert_eq!(pi_de.to_bytes(&circuit, compress)?, bytes);
+            pi_de.verify(circuit_de, &x_de)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_round_trip_hiding() -> Result<()> {
+        let (circuit, x, pi) = proof()?;
+        assert!(pi.Cs.hiding.is_some() && pi.Cs.lookup.is_none());
+        round_trip(circuit, &x, &pi)?;
+
+        // The uncompressed encoding stores both coordinates of every point
+        let compressed = pi.to_bytes(&circuit, Compress::Yes)?;
+        let uncompressed = pi.to_bytes(&circuit, Compress::No)?;
+        assert!(compressed.len() < uncompressed.len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_round_trip_lookup() -> Result<()> {
+        let mut circuit = CircuitSpec::new();
+        let r = circuit.fp_witness();
+        circuit.lookup(LookupTable::Range16, &[r]);
+        circuit.hiding = true;
+
+        let mut trace_builder = TraceBuilder::new(circuit);
+        trace_builder.witness(r, scalar(0xbeef).into())?;
+        let (fp_trace, _) = trace_builder.trace(None, None)?;
+
+        let (circuit, x, w) = fp_trace.consume();
+        let pi = PlonkProof::naive_prover_hiding(&mut test_rng(), circuit, &x, w)?;
+        assert!(circuit.lookup.is_some());
+        assert!(
+            pi.Cs
+                .lookup
+                .as_ref()
+                .is_some_and(|lookup| lookup.hiding.is_some())
+        );
+        round_trip(circuit, &x, &pi)
+    }
+
+    #[test]
+    fn test_envelope_rejects() -> Result<()> {
+        let (circuit, x, pi) = proof()?;
+        let mut other_circuit = circuit;
+        other_circuit.rows *= 2;
+
+        for compress in MODES {
+            // Another circuit, another curve and another version
+            let bytes = pi.to_bytes(&circuit, compress)?;
+            assert!(PlonkProof::from_bytes(&bytes, &other_circuit, compress).is_err());
+            assert!(
+                envelope_from_bytes::<VestaConfig, PlonkProof<PallasConfig>>(
+                    &bytes, None, compress
+                )
+                .is_err()
+            );
+            let mut header = EnvelopeHeader::new::<PallasConfig>(circuit.rows);
+            header.version += 1;
+            let mut bytes_v2 = Vec::new();
+            header.serialize_with_mode(&mut bytes_v2, compress)?;
+            pi.serialize_with_mode(&mut bytes_v2, compress)?;
+            assert!(PlonkProof::from_bytes(&bytes_v2, &circuit, compress).is_err());
+
+            // Truncated and trailing bytes
+            assert!(PlonkProof::from_bytes(&bytes[..bytes.len() - 1], &circuit, compress).is_err());
+            let mut trailing = bytes.clone();
+            trailing.push(0);
+            assert!(PlonkProof::from_bytes(&trailing, &circuit, compress).is_err());
+
+            // Public inputs of the wrong circuit
+            let bytes = x.to_bytes(&other_circuit, compress)?;
+            assert!(PlonkPublicInputs::from_bytes(&bytes, &circuit, compress).is_err());
+
+            // A circuit whose header disagrees with its row count
+            let bytes =
+                envelope_to_bytes::<PallasConfig, _>(&circuit, other_circuit.rows, compress)?;
+            assert!(PlonkCircuit::<PallasConfig>::from_bytes(&bytes, compress).is_err());
+        }
+
+        // A point that is not on the curve: the first commitment of the circuit follows the
+        // header, the row and public input counts and ω
+        let mut bytes = circuit.to_bytes(Compress::No)?;
+        let offset = EnvelopeHeader::new::<PallasConfig>(circuit.rows).uncompressed_size()
+            + 2 * circuit.rows.uncompressed_size()
+            + circuit.omega.uncompressed_size();
+        bytes[offset] ^= 1;
+        assert!(PlonkCircuit::<PallasConfig>::from_bytes(&bytes, Compress::No).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_malformed_eval_proofs() -> Result<()> {
+        let (circuit, x, pi) = proof()?;
+        let tamperings: [fn(&mut PlonkProof<PallasConfig>); 4] = [
+            |pi| {
+                pi.pis.r.Ls.pop();
+            },
+            |pi| {
+                pi.pis.r_omega.Rs.pop();
+            },
+            |pi| pi.pis.r.w_prime = None,
+            |pi| {
+                pi.acc_next.q.pi.Ls.truncate(1);
+            },
+        ];
+        for tamper in tamperings {
+            let mut malformed = pi.clone();
+            tamper(&mut malformed);
+            for compress in MODES {
+                let bytes = malformed.to_bytes(&circuit, compress)?;
+                assert!(PlonkProof::from_bytes(&bytes, &circuit, compress).is_err());
+            }
+            // The verifier rejects it too, instead of panicking
+            assert!(malformed.verify(circuit, &x).is_err());
+        }
+
+        let mut x_malformed = x.clone();
+        x_malformed.acc_prev.q.pi.Ls.clear();
+        let bytes = x_malformed.to_bytes(&circuit, Compress::Yes)?;
+        assert!(PlonkPublicInputs::from_bytes(&bytes, &circuit, Compress::Yes).is_err());
+
+        Ok(())
+    }
+}
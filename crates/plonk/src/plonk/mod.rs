@@ -1,3 +1,5 @@
+mod envelope;
 mod protocol;
 
+pub use envelope::*;
 pub use protocol::*;
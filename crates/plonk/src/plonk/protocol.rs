@@ -1,18 +1,20 @@
 #![allow(non_snake_case)]
 use std::{
     array,
+    collections::{BTreeMap, HashMap},
     ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub, SubAssign},
     time::Instant,
 };
 
-use anyhow::{Result, ensure};
+use anyhow::{Context, Result, bail, ensure};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use halo_accumulation::{
     acc::{self, Accumulator},
-    pcdl::{self, EvalProof, Instance, commit},
+    pcdl::{EvalProof, Instance, commit_with_pp},
 };
 use halo_group::{
-    Domain, Evals, PastaConfig, Point, Poly, Scalar,
-    ark_ff::Field,
+    Domain, Evals, PastaConfig, Point, Poly, PublicParams, Scalar,
+    ark_ff::{Field, UniformRand},
     ark_poly::{DenseUVPolynomial, EvaluationDomain, Polynomial, univariate::DensePolynomial},
     ark_std::{One, Zero, rand::Rng},
 };
@@ -20,39 +22,80 @@ use halo_poseidon::{Protocols, Sponge};
 use log::debug;
 
 use crate::{
-    circuit::{PlonkCircuit, PlonkPublicInputs, PlonkWitness},
+    circuit::{PlonkCircuit, PlonkCircuitEvals, PlonkPublicInputs, PlonkWitness, ProvingKey},
     utils::{
-        CONSTRAINT_DEGREE_MULTIPLIER, Q_POLYS, R_POLYS, S_POLYS, T_POLYS, W_POLYS, fmt_scalar,
+        CONSTRAINT_DEGREE_MULTIPLIER, GENERIC_GATES, HIDING_MIN_ROWS, L_POLYS, LOOKUP_WIRES,
+        Q_POLYS, R_POLYS, S_POLYS, T_POLYS, W_POLYS, permutation_shifts,
     },
 };
 
-#[derive(Clone)]
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
 pub struct PlonkProofEvalProofs<P: PastaConfig> {
     pub r: EvalProof<P>,
     pub r_omega: EvalProof<P>,
 }
 
-#[derive(Clone)]
+/// The evaluations sent by the prover. The selectors, the chunks of T and z are only opened
+/// through the linearization polynomial, see `Linearization`, so their evaluations at 𝔷 are not
+/// part of the proof. Neither are those of the identity permutation, which the verifier computes,
+/// see `permutation_shifts`.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
 pub struct PlonkProofEvals<P: PastaConfig> {
     pub ws: [Scalar<P>; W_POLYS],
     pub rs: [Scalar<P>; R_POLYS],
-    pub qs: [Scalar<P>; Q_POLYS],
-    pub ts: [Scalar<P>; T_POLYS],
-    pub ids: [Scalar<P>; S_POLYS],
     pub sigmas: [Scalar<P>; S_POLYS],
-    pub z: Scalar<P>,
     pub z_omega: Scalar<P>,
     pub w_omegas: [Scalar<P>; 3],
+    pub lookup: Option<PlonkProofLookupEvals<P>>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct PlonkProofLookupEvals<P: PastaConfig> {
+    pub qs: [Scalar<P>; 2],
+    pub tables: [Scalar<P>; L_POLYS],
+    pub tables_omega: [Scalar<P>; L_POLYS],
+    pub hs: [Scalar<P>; 2],
+    pub h1_omega: Scalar<P>,
+    pub z_omega: Scalar<P>,
+}
+
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
 pub struct PlonkProofCommitments<P: PastaConfig> {
     pub ws: [Point<P>; W_POLYS],
     pub ts: [Point<P>; T_POLYS],
     pub z: Point<P>,
+    pub hiding: Option<PlonkProofHidingCommitments<P>>,
+    /// `None` if the circuit has no lookup gates
+    pub lookup: Option<PlonkProofLookupCommitments<P>>,
+}
+
+/// The blinded polynomials of a hiding proof have degree n + 2, and the blinded chunks of t degree
+/// n, so they are split as p(X) = p_lo(X) + Xⁿ p_hi(X) to stay within the degree bound of the
+/// commitment scheme. `PlonkProofCommitments` holds the commitments to p_lo and these are the ones
+/// to p_hi.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct PlonkProofHidingCommitments<P: PastaConfig> {
+    pub ws_hi: [Point<P>; W_POLYS],
+    pub z_hi: Point<P>,
+    pub ts_hi: [Point<P>; T_POLYS],
+}
+
+/// The Plookup commitments: h₁ and h₂ hold the union of the queries and the table, sorted by the
+/// table, and z is the grand product over them.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct PlonkProofLookupCommitments<P: PastaConfig> {
+    pub hs: [Point<P>; 2],
+    pub z: Point<P>,
+    pub hiding: Option<PlonkProofLookupHidingCommitments<P>>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct PlonkProofLookupHidingCommitments<P: PastaConfig> {
+    pub hs_hi: [Point<P>; 2],
+    pub z_hi: Point<P>,
+}
+
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
 pub struct PlonkProof<P: PastaConfig> {
     pub vs: PlonkProofEvals<P>,
     pub Cs: PlonkProofCommitments<P>,
@@ -66,6 +109,100 @@ impl<P: PastaConfig> PlonkProof<P> {
         circuit: PlonkCircuit<P>,
         public_inputs: &PlonkPublicInputs<P>,
         witness: PlonkWitness<P>,
+    ) -> Self {
+        let pp = PublicParams::get_pp();
+        Self::prover(rng, pp, circuit, public_inputs, witness, None, false)
+    }
+
+    /// Same as `naive_prover`, committing and opening with the public parameters `pp` instead of
+    /// the global ones
+    pub fn naive_prover_with_pp<R: Rng>(
+        rng: &mut R,
+        pp: &PublicParams<P>,
+        circuit: PlonkCircuit<P>,
+        public_inputs: &PlonkPublicInputs<P>,
+        witness: PlonkWitness<P>,
+    ) -> Self {
+        Self::prover(rng, pp, circuit, public_inputs, witness, None, false)
+    }
+
+    /// Same as `naive_prover`, but the proof is zero-knowledge: the witness polynomials and the
+    /// permutation polynomial z are blinded with random multiples of Zₕ, the chunks of t with
+    /// random terms that cancel out, and all commitments and openings made by the prover use
+    /// hiding randomness. Fails if the circuit has less than `HIDING_MIN_ROWS` rows, see
+    /// `Frontend::hiding`.
+    pub fn naive_prover_hiding<R: Rng>(
+        rng: &mut R,
+        circuit: PlonkCircuit<P>,
+        public_inputs: &PlonkPublicInputs<P>,
+        witness: PlonkWitness<P>,
+    ) -> Result<Self> {
+        let pp = PublicParams::get_pp();
+        Self::naive_prover_hiding_with_pp(rng, pp, circuit, public_inputs, witness)
+    }
+
+    /// Same as `naive_prover_hiding`, with the public parameters `pp`
+    pub fn naive_prover_hiding_with_pp<R: Rng>(
+        rng: &mut R,
+        pp: &PublicParams<P>,
+        circuit: PlonkCircuit<P>,
+        public_inputs: &PlonkPublicInputs<P>,
+        witness: PlonkWitness<P>,
+    ) -> Result<Self> {
+        ensure_hiding_rows(&circuit)?;
+        Ok(Self::prover(
+            rng,
+            pp,
+            circuit,
+            public_inputs,
+            witness,
+            None,
+            true,
+        ))
+    }
+
+    /// Same as `naive_prover`, but the selectors are not evaluated over the large domain since
+    /// `pk` already holds those evaluations. `witness` must come from a trace with `pk`, see
+    /// `Call::trace_with_params`, and `pp` must be the public parameters the key was committed
+    /// with.
+    pub fn naive_prover_with_key<R: Rng>(
+        rng: &mut R,
+        pp: &PublicParams<P>,
+        pk: &ProvingKey<P>,
+        public_inputs: &PlonkPublicInputs<P>,
+        witness: PlonkWitness<P>,
+    ) -> Self {
+        Self::prover(
+            rng,
+            pp,
+            pk.vk.circuit,
+            public_inputs,
+            witness,
+            Some(&pk.evals),
+            false,
+        )
+    }
+
+    /// Same as `naive_prover_hiding`. A hiding proof needs a large domain twice the size of the
+    /// one in `pk`, so only the selector polynomials are reused, not their evaluations.
+    pub fn naive_prover_hiding_with_key<R: Rng>(
+        rng: &mut R,
+        pp: &PublicParams<P>,
+        pk: &ProvingKey<P>,
+        public_inputs: &PlonkPublicInputs<P>,
+        witness: PlonkWitness<P>,
+    ) -> Result<Self> {
+        Self::naive_prover_hiding_with_pp(rng, pp, pk.vk.circuit, public_inputs, witness)
+    }
+
+    fn prover<R: Rng>(
+        rng: &mut R,
+        pp: &PublicParams<P>,
+        circuit: PlonkCircuit<P>,
+        public_inputs: &PlonkPublicInputs<P>,
+        witness: PlonkWitness<P>,
+        circuit_evals: Option<&PlonkCircuitEvals<P>>,
+        hiding: bool,
     ) -> Self {
         let transcript = &mut Sponge::<P>::new(Protocols::PLONK);
 
@@ -81,19 +218,37 @@ impl<P: PastaConfig> PlonkProof<P> {
         let public_inputs_evals = Evals::<P>::from_vec_and_domain(public_inputs_clone, domain);
         let public_inputs_poly = public_inputs_evals.interpolate();
 
-        let w_omega_evals = array::from_fn(|i| witness.w_evals[i].clone().shift_left());
-        let w_omegas = w_omega_evals.map(|w| w.interpolate());
+        // Witness polynomials are opened at both 𝔷 and 𝔷ω, so they get three blinding
+        // coefficients. This raises their degree to n + 2, so the gate constraints no longer fit
+        // in the large domain and we have to double it.
+        let (ws, large_domain_size) = if hiding {
+            let ws = array::from_fn(|i| blind::<R, P>(rng, &witness.polys.ws[i], 3, domain));
+            (ws, domain.size() * CONSTRAINT_DEGREE_MULTIPLIER * 2)
+        } else {
+            let ws = witness.polys.ws.clone();
+            (ws, domain.size() * CONSTRAINT_DEGREE_MULTIPLIER)
+        };
 
-        let large_domain = Domain::<P>::new(domain.size() * CONSTRAINT_DEGREE_MULTIPLIER).unwrap();
-        let q_evals: [_; Q_POLYS] = array::from_fn(|i| {
-            Evals::<P>::new(witness.polys.qs[i].evaluate_over_domain_by_ref(large_domain))
-        });
-        let w_evals: [_; W_POLYS] = array::from_fn(|i| {
-            Evals::<P>::new(witness.polys.ws[i].evaluate_over_domain_by_ref(large_domain))
-        });
-        let r_evals: [_; R_POLYS] = array::from_fn(|i| {
-            Evals::<P>::new(witness.polys.rs[i].evaluate_over_domain_by_ref(large_domain))
-        });
+        let large_domain = Domain::<P>::new(large_domain_size).unwrap();
+        let computed_evals;
+        let (q_evals, r_evals) = match circuit_evals {
+            Some(evals) => {
+                assert_eq!(evals.qs[0].domain(), large_domain);
+                (&evals.qs, &evals.rs)
+            }
+            None => {
+                let q_evals: [_; Q_POLYS] = array::from_fn(|i| {
+                    Evals::<P>::new(witness.polys.qs[i].evaluate_over_domain_by_ref(large_domain))
+                });
+                let r_evals: [_; R_POLYS] = array::from_fn(|i| {
+                    Evals::<P>::new(witness.polys.rs[i].evaluate_over_domain_by_ref(large_domain))
+                });
+                computed_evals = (q_evals, r_evals);
+                (&computed_evals.0, &computed_evals.1)
+            }
+        };
+        let w_evals: [_; W_POLYS] =
+            array::from_fn(|i| Evals::<P>::new(ws[i].evaluate_over_domain_by_ref(large_domain)));
         // let id_evals: [_; S_POLYS] = array::from_fn(|i| {
         //     Evals::<P>::new(witness.polys.ids[i].evaluate_over_domain_by_ref(large_domain))
         // });
@@ -111,15 +266,46 @@ impl<P: PastaConfig> PlonkProof<P> {
         // -------------------- Round 1 --------------------
         let r1_now = Instant::now();
 
-        let C_ws: [Point<P>; W_POLYS] = array::from_fn(|i| commit(&witness.polys.ws[i], d, None));
+        let ws_split: [_; W_POLYS] =
+            array::from_fn(|i| SplitCommitment::new(rng, pp, &ws[i], circuit.rows, hiding));
+        let C_ws: [Point<P>; W_POLYS] = ws_split.each_ref().map(|w| w.C_lo);
         transcript.absorb_g(&C_ws);
 
+        let C_ws_hi: Option<[Point<P>; W_POLYS]> =
+            hiding.then(|| ws_split.each_ref().map(|w| w.C_hi.unwrap()));
+        if let Some(C_ws_hi) = &C_ws_hi {
+            transcript.absorb_g(C_ws_hi);
+        }
+
         let r1_time = r1_now.elapsed().as_secs_f64();
         debug!("({}) Round 1 took {} s", P::SFID, r1_time);
 
         // -------------------- Round 2 --------------------
         let r2_now = Instant::now();
 
+        // Plookup: the queries f and the table t are compressed with θ, and the union of the two,
+        // sorted by t, is split alternately into h₁ and h₂
+        let mut lookup_r2 = None;
+        if let Some(polys) = &witness.polys.lookup {
+            let theta = transcript.challenge();
+            let f = lookup_query_poly::<P>(theta, &polys.qs, &ws);
+            let t = geometric_polys::<P>(theta, polys.tables.to_vec());
+            let hs = plookup_sort::<P>(&f, &t, domain);
+            let hs = if hiding {
+                hs.map(|h| blind::<R, P>(rng, &h, 3, domain))
+            } else {
+                hs
+            };
+            let hs_split = hs
+                .each_ref()
+                .map(|h| SplitCommitment::new(rng, pp, h, circuit.rows, hiding));
+            transcript.absorb_g(&hs_split.each_ref().map(|h| h.C_lo));
+            if hiding {
+                transcript.absorb_g(&hs_split.each_ref().map(|h| h.C_hi.unwrap()));
+            }
+            lookup_r2 = Some((theta, f, t, hs, hs_split));
+        }
+
         let r2_time = r2_now.elapsed().as_secs_f64();
         debug!("({}) Round 2 took {} s", P::SFID, r2_time);
 
@@ -128,39 +314,61 @@ impl<P: PastaConfig> PlonkProof<P> {
 
         let beta = transcript.challenge();
         let gamma = transcript.challenge();
-
-        let mut f_prime = &witness.polys.ws[0] + &witness.polys.ids[0] * beta + deg0::<P>(gamma);
-        let mut g_prime = &witness.polys.ws[0] + &witness.polys.sigmas[0] * beta + deg0::<P>(gamma);
-        for i in 1..S_POLYS {
-            f_prime =
-                &f_prime * (&witness.polys.ws[i] + &witness.polys.ids[i] * beta + deg0::<P>(gamma));
-            g_prime = &g_prime
-                * (&witness.polys.ws[i] + &witness.polys.sigmas[i] * beta + deg0::<P>(gamma));
+        let lookup_challenges: Option<(Scalar<P>, Scalar<P>)> = lookup_r2
+            .as_ref()
+            .map(|_| (transcript.challenge(), transcript.challenge()));
+
+        let mut f_prime = &ws[0] + &witness.polys.ids[0] * beta + deg0::<P>(gamma);
+        let mut g_prime = &ws[0] + &witness.polys.sigmas[0] * beta + deg0::<P>(gamma);
+        for (i, w) in ws.iter().enumerate().take(S_POLYS).skip(1) {
+            f_prime = &f_prime * (w + &witness.polys.ids[i] * beta + deg0::<P>(gamma));
+            g_prime = &g_prime * (w + &witness.polys.sigmas[i] * beta + deg0::<P>(gamma));
         }
-        let f_prime_evals = Evals::<P>::new(f_prime.evaluate_over_domain_by_ref(domain));
-        let g_prime_evals = Evals::<P>::new(g_prime.evaluate_over_domain_by_ref(domain));
 
         // Z
-        let mut z = vec![P::ScalarField::zero(); circuit.rows];
-        for i in 0..circuit.rows {
-            let zero_index = i;
-            let one_index = (i + 1) % circuit.rows;
-            if one_index == 1 {
-                z[zero_index] = P::ScalarField::one();
+        let z = grand_product::<P>(&f_prime, &g_prime, domain);
+        let z = if hiding {
+            blind::<R, P>(rng, &z, 3, domain)
+        } else {
+            z
+        };
+        let z_omega = shift_poly::<P>(&z, witness.omega);
+        let z_split = SplitCommitment::new(rng, pp, &z, circuit.rows, hiding);
+        transcript.absorb_g(&[z_split.C_lo]);
+        if let Some(C_z_hi) = z_split.C_hi {
+            transcript.absorb_g(&[C_z_hi]);
+        }
+
+        // Plookup grand product, z₂(ωX) g'(X) = z₂(X) f'(X) where
+        // f'(X) = (1 + δ) (ε + f(X)) (ε(1 + δ) + t(X) + δ t(ωX))
+        // g'(X) = (ε(1 + δ) + h₁(X) + δ h₂(X)) (ε(1 + δ) + h₂(X) + δ h₁(ωX))
+        let mut lookup_r3 = None;
+        if let (Some((_, f, t, hs, _)), Some((delta, epsilon))) = (&lookup_r2, lookup_challenges) {
+            let one_delta = Scalar::<P>::one() + delta;
+            let epsilon_delta = deg0::<P>(epsilon * one_delta);
+            let t_omega = shift_poly::<P>(t, witness.omega);
+            let h1_omega = shift_poly::<P>(&hs[0], witness.omega);
+            let f_prime = &(f + &deg0::<P>(epsilon))
+                * &(t + &(&t_omega * delta) + &epsilon_delta)
+                * one_delta;
+            let g_prime = &(&hs[0] + &(&hs[1] * delta) + &epsilon_delta)
+                * &(&hs[1] + &(&h1_omega * delta) + &epsilon_delta);
+
+            let z = grand_product::<P>(&f_prime, &g_prime, domain);
+            let z = if hiding {
+                blind::<R, P>(rng, &z, 3, domain)
             } else {
-                // TODO: Fix this disgusting indexing
-                let ratio = f_prime_evals[zero_index] / g_prime_evals[zero_index];
-                z[zero_index] = z[zero_index - 1] * ratio
+                z
+            };
+            let z_omega = shift_poly::<P>(&z, witness.omega);
+            let z_split = SplitCommitment::new(rng, pp, &z, circuit.rows, hiding);
+            transcript.absorb_g(&[z_split.C_lo]);
+            if let Some(C_z_hi) = z_split.C_hi {
+                transcript.absorb_g(&[C_z_hi]);
             }
+            lookup_r3 = Some((f_prime, g_prime, z, z_omega, z_split));
         }
 
-        let z = Evals::<P>::from_vec_and_domain(z, domain);
-        let z_omega = z.clone().shift_left().interpolate();
-        let z = z.interpolate();
-
-        let C_z = pcdl::commit(&z, circuit.rows - 1, None);
-        transcript.absorb_g(&[C_z]);
-
         let r3_time = r3_now.elapsed().as_secs_f64();
         debug!("({}) Round 3 took {} s", P::SFID, r3_time);
 
@@ -171,16 +379,16 @@ impl<P: PastaConfig> PlonkProof<P> {
 
         let poseidon_evals = poseidon_constraints_evals::<P>(
             P::SCALAR_POSEIDON_MDS,
-            &r_evals,
+            r_evals,
             &w_evals,
             &w_omega_evals,
         );
         let affine_add_evals = affine_add_constraints_evals(&w_evals);
         let affine_mul_evals = affine_mul_constraints_evals(&w_evals, &w_omega_evals, &r_evals[0]);
         let eq_evals = eq_constraints_evals(&w_evals);
-        let range_check_evals = range_check_constraints_evals(&w_evals, &w_omega_evals, &r_evals);
+        let range_check_evals = range_check_constraints_evals(&w_evals, &w_omega_evals, r_evals);
 
-        let f_gc_evals: Evals<P> = &w_evals[0] * &q_evals[0]
+        let mut f_gc_evals: Evals<P> = &w_evals[0] * &q_evals[0]
             + &q_evals[1] * &w_evals[1]
             + &q_evals[2] * &w_evals[2]
             + &q_evals[3] * &w_evals[0] * &w_evals[1]
@@ -192,77 +400,57 @@ impl<P: PastaConfig> PlonkProof<P> {
             + &q_evals[9] * &range_check_evals
             + &public_inputs_evals;
 
+        // The other generic gates of a row share its selectors [q_l, q_r, q_o, q_m], and are
+        // weighted by α⁵, α⁶, … so they must hold one by one rather than only in sum
+        let mut alpha_k = alpha.pow([4]);
+        for k in 1..GENERIC_GATES {
+            alpha_k *= alpha;
+            let [a, b, c] = array::from_fn(|i| &w_evals[3 * k + i]);
+            let gate = &q_evals[0] * a + &q_evals[1] * b + &q_evals[2] * c + &q_evals[3] * a * b;
+            f_gc_evals += gate.scale(&alpha_k);
+        }
+
         let f_gc = f_gc_evals.interpolate();
 
         let l1 = lagrange_basis_poly::<P>(1, domain);
-        let f_cc1 = l1 * (&z - deg0::<P>(Scalar::<P>::one()));
+        let f_cc1 = &l1 * &(&z - &deg0::<P>(Scalar::<P>::one()));
         let f_cc2 = &z * &f_prime - &z_omega * &g_prime;
 
-        // let a = witness.polys.ws[0].evaluate_over_domain_by_ref(domain);
-        // let b = witness.polys.ws[1].evaluate_over_domain_by_ref(domain);
-        // let c = witness.polys.ws[2].evaluate_over_domain_by_ref(domain);
-        // let ql = witness.polys.qs[0].evaluate_over_domain_by_ref(domain);
-        // let qr = witness.polys.qs[1].evaluate_over_domain_by_ref(domain);
-        // let qo = witness.polys.qs[2].evaluate_over_domain_by_ref(domain);
-        // let qm = witness.polys.qs[3].evaluate_over_domain_by_ref(domain);
-        // let qc = witness.polys.qs[4].evaluate_over_domain_by_ref(domain);
-        // let q5 = witness.polys.qs[5].evaluate_over_domain_by_ref(domain);
-        // let q6 = witness.polys.qs[6].evaluate_over_domain_by_ref(domain);
-        // let q7 = witness.polys.qs[7].evaluate_over_domain_by_ref(domain);
-        // let q8 = witness.polys.qs[8].evaluate_over_domain_by_ref(domain);
-        // let q9 = witness.polys.qs[9].evaluate_over_domain_by_ref(domain);
-        // let poseidon_eval = poseidon.evaluate_over_domain_by_ref(domain);
-        // let affine_add_eval = affine_add.evaluate_over_domain_by_ref(domain);
-        // let affine_mul_eval = affine_mul.evaluate_over_domain_by_ref(domain);
-        // let eq_eval = eq.evaluate_over_domain_by_ref(domain);
-        // let rangecheck_eval = rangecheck.evaluate_over_domain_by_ref(domain);
-        // let pi_eval = public_inputs_poly.evaluate_over_domain_by_ref(domain);
-        // let f_gc_eval = f_gc.evaluate_over_domain_by_ref(domain);
-        // let f_cc1_eval = f_cc1.evaluate_over_domain_by_ref(domain);
-        // let f_cc2_eval = f_cc2.evaluate_over_domain_by_ref(domain);
-        // for i in 0..circuit.rows {
-        //     println!(
-        //         "{}: {}*{} + {}*{} + {}*{} + {}*{} + {} + {}*{} + {}*{} + {}*{} + {}*{} + {}*{} + {} = {}",
-        //         P::SFID,
-        //         fmt_scalar::<P>(ql[i]),
-        //         fmt_scalar::<P>(ql[i] * a[i]),
-        //         fmt_scalar::<P>(qr[i]),
-        //         fmt_scalar::<P>(qr[i] * b[i]),
-        //         fmt_scalar::<P>(qo[i]),
-        //         fmt_scalar::<P>(qo[i] * c[i]),
-        //         fmt_scalar::<P>(qm[i]),
-        //         fmt_scalar::<P>(qm[i] * a[i] * b[i]),
-        //         fmt_scalar::<P>(qc[i]),
-        //         fmt_scalar::<P>(q5[i]),
-        //         fmt_scalar::<P>(q5[i] * poseidon_eval[i]),
-        //         fmt_scalar::<P>(q6[i]),
-        //         fmt_scalar::<P>(q6[i] * affine_add_eval[i]),
-        //         fmt_scalar::<P>(q7[i]),
-        //         fmt_scalar::<P>(q7[i] * affine_mul_eval[i]),
-        //         fmt_scalar::<P>(q8[i]),
-        //         fmt_scalar::<P>(q8[i] * eq_eval[i]),
-        //         fmt_scalar::<P>(q9[i]),
-        //         fmt_scalar::<P>(q9[i] * rangecheck_eval[i]),
-        //         fmt_scalar::<P>(pi_eval[i]),
-        //         fmt_scalar::<P>(f_gc_eval[i])
-        //     );
-
-        //     assert_eq!(Scalar::<P>::zero(), f_gc_eval[i]);
-        //     assert_eq!(Scalar::<P>::zero(), f_cc1_eval[i]);
-        //     assert_eq!(Scalar::<P>::zero(), f_cc2_eval[i]);
-        // }
-
-        let f: Poly<P> = &f_gc + &f_cc1 * alpha + &f_cc2 * alpha.pow([2]);
+        let mut f: Poly<P> = &f_gc + &f_cc1 * alpha + &f_cc2 * alpha.pow([2]);
+        if let Some((f_prime, g_prime, z, z_omega, _)) = &lookup_r3 {
+            let f_pl1 = &l1 * &(z - &deg0::<P>(Scalar::<P>::one()));
+            let f_pl2 = &(z * f_prime) - &(z_omega * g_prime);
+            f += &(&f_pl1 * alpha.pow([3]));
+            f += &(&f_pl2 * alpha.pow([4]));
+        }
         let (t, _) = f.divide_by_vanishing_poly(domain);
 
         // let ch = P::scalar_from_u64(rng.next_u64());
         // let zH = witness.domain.vanishing_polynomial();
         // assert_eq!(f.evaluate(&ch), t.evaluate(&ch) * zH.evaluate(&ch));
 
-        let ts = t_split::<P>(t.clone(), circuit.rows);
-        let C_ts: [Point<P>; T_POLYS] = array::from_fn(|i| commit(&ts[i], d, None));
+        // In a hiding proof, bᵢ Xⁿ is added to tᵢ and bᵢ is subtracted from tᵢ₊₁ for a random bᵢ,
+        // so the chunks are random but Σ Xⁿⁱ tᵢ(X) is still t(X)
+        let mut ts = t_split::<P>(t.clone(), circuit.rows);
+        if hiding {
+            for i in 0..T_POLYS - 1 {
+                let b = Scalar::<P>::rand(rng);
+                let mut coeffs = vec![Scalar::<P>::zero(); circuit.rows];
+                coeffs.push(b);
+                ts[i] += &Poly::<P>::from_coefficients_vec(coeffs);
+                ts[i + 1] -= &deg0::<P>(b);
+            }
+        }
+        let ts_split: [_; T_POLYS] = ts
+            .each_ref()
+            .map(|t| SplitCommitment::new(rng, pp, t, circuit.rows, hiding));
+        let C_ts = ts_split.each_ref().map(|t| t.C_lo);
+        let C_ts_hi = hiding.then(|| ts_split.each_ref().map(|t| t.C_hi.unwrap()));
 
         transcript.absorb_g(&C_ts);
+        if let Some(C_ts_hi) = &C_ts_hi {
+            transcript.absorb_g(C_ts_hi);
+        }
 
         let r4_time = r4_now.elapsed().as_secs_f64();
         debug!("({}) Round 4 took {} s", P::SFID, r4_time);
@@ -271,57 +459,171 @@ impl<P: PastaConfig> PlonkProof<P> {
         let r5_now = Instant::now();
 
         let zeta = transcript.challenge();
+        let xi = transcript.challenge();
+        let xi_n = pow_n(xi, circuit.rows);
 
-        let mut vec = Vec::new();
-        vec.extend_from_slice(&witness.polys.qs);
-        vec.extend_from_slice(&witness.polys.ws);
-        vec.extend_from_slice(&ts);
-        vec.push(z.clone());
+        // Since ωⁿ = 1, p_lo(X) + ξⁿ p_hi(X) agrees with p(X) at both ξ and ξω
+        let ws_xi: [Poly<P>; W_POLYS] = ws_split.each_ref().map(|w| w.combine(xi_n));
+        let z_xi = z_split.combine(xi_n);
+        let w_ws_xi: [_; W_POLYS] = ws_split.each_ref().map(|w| w.combine_hiding(xi_n));
+        let w_z_xi = z_split.combine_hiding(xi_n);
+        let ts_xi: [Poly<P>; T_POLYS] = ts_split.each_ref().map(|t| t.combine(xi_n));
+        let w_ts_xi: [_; T_POLYS] = ts_split.each_ref().map(|t| t.combine_hiding(xi_n));
+        let lookup_xi = witness
+            .polys
+            .lookup
+            .as_ref()
+            .zip(lookup_r2.as_ref())
+            .zip(lookup_r3.as_ref());
+        let lookup_xi =
+            lookup_xi.map(|((polys, (_, _, _, _, hs_split)), (_, _, _, _, z_split))| {
+                let hs_xi: [Poly<P>; 2] = hs_split.each_ref().map(|h| h.combine(xi_n));
+                let w_hs_xi: [_; 2] = hs_split.each_ref().map(|h| h.combine_hiding(xi_n));
+                let z_xi = z_split.combine(xi_n);
+                let w_z_xi = z_split.combine_hiding(xi_n);
+                (polys, hs_xi, w_hs_xi, z_xi, w_z_xi)
+            });
+
+        let xi_omega = xi * witness.omega;
+        let lookup_vs =
+            lookup_xi
+                .as_ref()
+                .map(|(polys, hs_xi, _, z_xi, _)| PlonkProofLookupEvals {
+                    qs: polys.qs.each_ref().map(|q| q.evaluate(&xi)),
+                    tables: polys.tables.each_ref().map(|t| t.evaluate(&xi)),
+                    tables_omega: polys.tables.each_ref().map(|t| t.evaluate(&xi_omega)),
+                    hs: hs_xi.each_ref().map(|h| h.evaluate(&xi)),
+                    h1_omega: hs_xi[0].evaluate(&xi_omega),
+                    z_omega: z_xi.evaluate(&xi_omega),
+                });
+        let vs = PlonkProofEvals {
+            ws: ws_xi.each_ref().map(|w| w.evaluate(&xi)),
+            rs: witness.polys.rs.each_ref().map(|r| r.evaluate(&xi)),
+            sigmas: witness.polys.sigmas.each_ref().map(|s| s.evaluate(&xi)),
+            z_omega: z_xi.evaluate(&xi_omega),
+            w_omegas: array::from_fn(|i| ws_xi[i].evaluate(&xi_omega)),
+            lookup: lookup_vs,
+        };
+
+        let challenges = LinearizationChallenges {
+            alpha,
+            beta,
+            gamma,
+            lookup: lookup_r2
+                .as_ref()
+                .zip(lookup_challenges)
+                .map(|((theta, ..), (delta, epsilon))| [*theta, delta, epsilon]),
+            xi,
+        };
+        let lin = Linearization::new(
+            &vs,
+            &public_inputs.public_inputs,
+            circuit.rows,
+            witness.omega,
+            &challenges,
+        );
+        let mut vec = witness.polys.qs.to_vec();
+        vec.push(z_xi.clone());
+        vec.extend_from_slice(&ts_xi);
+        let mut hiding_vec = vec![None; Q_POLYS];
+        hiding_vec.push(w_z_xi);
+        hiding_vec.extend_from_slice(&w_ts_xi);
+        if let Some((_, _, _, z_xi, w_z_xi)) = &lookup_xi {
+            vec.push(z_xi.clone());
+            hiding_vec.push(*w_z_xi);
+        }
+        let lin_poly = lin.poly(vec);
+        let w_lin = lin.hiding(hiding_vec);
+
+        let mut vec = vec![lin_poly];
+        vec.extend_from_slice(&ws_xi);
+        vec.extend_from_slice(&witness.polys.rs);
+        vec.extend_from_slice(&witness.polys.sigmas);
+        if let Some((polys, hs_xi, _, _, _)) = &lookup_xi {
+            vec.extend_from_slice(&polys.qs);
+            vec.extend_from_slice(&polys.tables);
+            vec.extend_from_slice(hs_xi);
+        }
         let r = geometric_polys::<P>(zeta, vec);
 
+        let mut vec = vec![w_lin];
+        vec.extend_from_slice(&w_ws_xi);
+        vec.extend_from_slice(&[None; R_POLYS + S_POLYS]);
+        if let Some((_, _, w_hs_xi, _, _)) = &lookup_xi {
+            vec.extend_from_slice(&[None; 2 + L_POLYS]);
+            vec.extend_from_slice(w_hs_xi);
+        }
+        let w_r = geometric_hiding::<P>(zeta, vec);
+
         let mut vec = Vec::new();
-        vec.extend_from_slice(&witness.polys.ws[0..3]);
-        vec.push(z.clone());
+        vec.extend_from_slice(&ws_xi[0..3]);
+        vec.push(z_xi.clone());
+        if let Some((polys, hs_xi, _, z_xi, _)) = &lookup_xi {
+            vec.extend_from_slice(&polys.tables);
+            vec.push(hs_xi[0].clone());
+            vec.push(z_xi.clone());
+        }
         let r_omega = geometric_polys::<P>(zeta, vec);
 
+        let mut vec = w_ws_xi[0..3].to_vec();
+        vec.push(w_z_xi);
+        if let Some((_, _, w_hs_xi, _, w_z_xi)) = &lookup_xi {
+            vec.extend_from_slice(&[None; L_POLYS]);
+            vec.push(w_hs_xi[0]);
+            vec.push(*w_z_xi);
+        }
+        let w_r_omega = geometric_hiding::<P>(zeta, vec);
+
         debug!(
             "({}) end of unoptimizable round 5 {} s",
             P::SFID,
             r5_now.elapsed().as_secs_f32()
         );
 
-        let xi = transcript.challenge();
         let acc_prev = public_inputs.acc_prev.clone();
-        let q_r = Instance::open(rng, r.clone(), d, &xi, None);
-        let q_r_omega = Instance::open(rng, r_omega.clone(), d, &(xi * witness.omega), None);
+        let q_r = Instance::open_with_pp(rng, pp, r, d, &xi, w_r.as_ref());
+        let q_r_omega = Instance::open_with_pp(rng, pp, r_omega, d, &xi_omega, w_r_omega.as_ref());
 
         // acc_prev.q.check().unwrap();
         // q_r.check().unwrap();
         // q_r_omega.check().unwrap();
 
-        let acc_next = acc::prover(
+        let acc_next = acc::prover_with_pp(
             rng,
+            pp,
             &[acc_prev.clone().into(), q_r.clone(), q_r_omega.clone()],
         )
         .unwrap();
 
+        let lookup_Cs = lookup_r2.as_ref().zip(lookup_r3.as_ref()).map(|(r2, r3)| {
+            let (_, _, _, _, hs_split) = r2;
+            let (_, _, _, _, z_split) = r3;
+            PlonkProofLookupCommitments {
+                hs: hs_split.each_ref().map(|h| h.C_lo),
+                z: z_split.C_lo,
+                hiding: hiding.then(|| PlonkProofLookupHidingCommitments {
+                    hs_hi: hs_split.each_ref().map(|h| h.C_hi.unwrap()),
+                    z_hi: z_split.C_hi.unwrap(),
+                }),
+            }
+        });
+
         let pi = Self {
             Cs: PlonkProofCommitments {
                 ws: C_ws,
                 ts: C_ts,
-                z: C_z,
-            },
-            vs: PlonkProofEvals {
-                ws: witness.polys.ws.map(|w| w.evaluate(&xi)),
-                rs: witness.polys.rs.map(|r| r.evaluate(&xi)),
-                qs: witness.polys.qs.map(|q| q.evaluate(&xi)),
-                ts: ts.map(|t| t.evaluate(&xi)),
-                ids: witness.polys.ids.map(|id| id.evaluate(&xi)),
-                sigmas: witness.polys.sigmas.map(|sigma| sigma.evaluate(&xi)),
-                z: z.evaluate(&xi),
-                z_omega: z.evaluate(&(xi * witness.omega)),
-                w_omegas: w_omegas.map(|w_omega| w_omega.evaluate(&xi)),
+                z: z_split.C_lo,
+                hiding: C_ws_hi
+                    .zip(z_split.C_hi)
+                    .zip(C_ts_hi)
+                    .map(|((ws_hi, z_hi), ts_hi)| PlonkProofHidingCommitments {
+                        ws_hi,
+                        z_hi,
+                        ts_hi,
+                    }),
+                lookup: lookup_Cs,
             },
+            vs,
             pis: PlonkProofEvalProofs {
                 r: q_r.pi,
                 r_omega: q_r_omega.pi,
@@ -358,11 +660,19 @@ impl<P: PastaConfig> PlonkProof<P> {
         &self,
         circuit: PlonkCircuit<P>,
         public_inputs: &PlonkPublicInputs<P>,
+    ) -> Result<()> {
+        self.verify_succinct_with_pp(PublicParams::get_pp(), circuit, public_inputs)
+    }
+
+    /// Same as `verify_succinct`, with the public parameters `pp`
+    pub fn verify_succinct_with_pp(
+        &self,
+        pp: &PublicParams<P>,
+        circuit: PlonkCircuit<P>,
+        public_inputs: &PlonkPublicInputs<P>,
     ) -> Result<()> {
         let pi = self;
         let d = circuit.rows - 1;
-        let n = P::scalar_from_u64(circuit.rows as u64);
-        let one = Scalar::<P>::one();
         let mut transcript = Sponge::new(Protocols::PLONK);
 
         ensure!(public_inputs.public_inputs.len() == circuit.public_input_count);
@@ -370,10 +680,26 @@ impl<P: PastaConfig> PlonkProof<P> {
         // -------------------- Round 1 --------------------
 
         transcript.absorb_g(&pi.Cs.ws);
+        if let Some(hiding) = &pi.Cs.hiding {
+            transcript.absorb_g(&hiding.ws_hi);
+        }
 
         // -------------------- Round 2 --------------------
 
-        // let zeta = transcript.challenge();
+        ensure!(
+            circuit.lookup.is_some() == pi.Cs.lookup.is_some()
+                && circuit.lookup.is_some() == pi.vs.lookup.is_some(),
+            "Proof and circuit disagree on the use of lookups"
+        );
+
+        // θ = H(transcript)
+        let theta = pi.Cs.lookup.as_ref().map(|_| transcript.challenge());
+        if let Some(lookup) = &pi.Cs.lookup {
+            transcript.absorb_g(&lookup.hs);
+            if let Some(hiding) = &lookup.hiding {
+                transcript.absorb_g(&hiding.hs_hi);
+            }
+        }
 
         // -------------------- Round 3 --------------------
 
@@ -381,13 +707,30 @@ impl<P: PastaConfig> PlonkProof<P> {
         let beta = transcript.challenge();
         // γ = H(transcript)
         let gamma = transcript.challenge();
-        // δ = H(transcript)
+        // δ = H(transcript), ε = H(transcript)
+        let lookup_challenges: Option<(Scalar<P>, Scalar<P>)> = pi
+            .Cs
+            .lookup
+            .as_ref()
+            .map(|_| (transcript.challenge(), transcript.challenge()));
         transcript.absorb_g(&[pi.Cs.z]);
+        if let Some(hiding) = &pi.Cs.hiding {
+            transcript.absorb_g(&[hiding.z_hi]);
+        }
+        if let Some(lookup) = &pi.Cs.lookup {
+            transcript.absorb_g(&[lookup.z]);
+            if let Some(hiding) = &lookup.hiding {
+                transcript.absorb_g(&[hiding.z_hi]);
+            }
+        }
 
         // -------------------- Round 4 --------------------
 
         let alpha = transcript.challenge();
         transcript.absorb_g(&pi.Cs.ts);
+        if let Some(hiding) = &pi.Cs.hiding {
+            transcript.absorb_g(&hiding.ts_hi);
+        }
 
         // -------------------- Round 5 --------------------
 
@@ -395,52 +738,22 @@ impl<P: PastaConfig> PlonkProof<P> {
         let xi = transcript.challenge();
         let xi_n = pow_n(xi, circuit.rows);
         let xi_omega = xi * circuit.omega;
-        let ids = pi.vs.ids;
-        let sigmas = pi.vs.sigmas;
-
-        // f'(𝔷) = (A(𝔷) + β Sᵢ₁(𝔷) + γ) (B(𝔷) + β Sᵢ₂(𝔷) + γ) (C(𝔷) + β Sᵢ₃(𝔷) + γ)
-        // g'(𝔷) = (A(𝔷)) + β S₁(𝔷)) + γ) (B(𝔷)) + β S₂(𝔷)) + γ) (C(𝔷)) + β S₃(𝔷)) + γ)
-        let mut f_prime = pi.vs.ws[0] + beta * ids[0] + gamma;
-        let mut g_prime = pi.vs.ws[0] + beta * sigmas[0] + gamma;
-        for i in 1..S_POLYS {
-            f_prime *= pi.vs.ws[i] + beta * ids[i] + gamma;
-            g_prime *= pi.vs.ws[i] + beta * sigmas[i] + gamma;
-        }
 
-        // F_GC(𝔷) = A(𝔷)Qₗ(𝔷) + B(𝔷)Qᵣ(𝔷) + C(𝔷)Qₒ(𝔷) + A(𝔷)B(𝔷)Qₘ(𝔷) + Q꜀(𝔷) + PI(𝔷)
-        let MDS = P::SCALAR_POSEIDON_MDS;
-        let poseidon_terms =
-            poseidon_constraints_generic(MDS, &pi.vs.rs, &pi.vs.ws, &pi.vs.w_omegas);
-        let affine_add_terms = affine_add_constraints_generic(pi.vs.ws.clone());
-        let affine_mul_terms =
-            affine_mul_constraints_generic(pi.vs.ws, pi.vs.w_omegas, pi.vs.rs[0]);
-        let eq = eq_generic(pi.vs.ws);
-        let rangecheck = range_check_generic(pi.vs.ws, pi.vs.w_omegas, pi.vs.rs);
-
-        let f_gc = pi.vs.ws[0] * pi.vs.qs[0]
-            + pi.vs.ws[1] * pi.vs.qs[1]
-            + pi.vs.ws[2] * pi.vs.qs[2]
-            + pi.vs.ws[0] * pi.vs.ws[1] * pi.vs.qs[3]
-            + pi.vs.qs[4]
-            + pi.vs.qs[5] * poseidon_terms
-            + pi.vs.qs[6] * affine_add_terms
-            + pi.vs.qs[7] * affine_mul_terms
-            + pi.vs.qs[8] * eq
-            + pi.vs.qs[9] * rangecheck
-            + public_input_eval_generic(&public_inputs.public_inputs, n, circuit.omega, xi, xi_n);
-
-        let omega = circuit.omega;
-        let l1 = (omega * (xi_n - one)) / (n * (xi - omega));
-        let z_H = xi_n - one;
-        let f_cc1 = l1 * (pi.vs.z - one);
-        let f_cc2 = pi.vs.z * f_prime - pi.vs.z_omega * g_prime;
-
-        let f = f_gc + alpha * f_cc1 + alpha.pow([2]) * f_cc2;
-        let t = t_reconstruct_generic(pi.vs.ts, xi_n);
-
-        ensure!(
-            f == t * z_H,
-            "T(𝔷) ≠ (F_GC(𝔷) + α F_CC1(𝔷) + α² F_CC2(𝔷) + α³ F_PL1(𝔷) + α⁴ F_PL2(𝔷)) / Zₕ(𝔷)"
+        let challenges = LinearizationChallenges {
+            alpha,
+            beta,
+            gamma,
+            lookup: theta
+                .zip(lookup_challenges)
+                .map(|(theta, (delta, epsilon))| [theta, delta, epsilon]),
+            xi,
+        };
+        let lin = Linearization::new(
+            &pi.vs,
+            &public_inputs.public_inputs,
+            circuit.rows,
+            circuit.omega,
+            &challenges,
         );
 
         // let pp = PublicParams::get_pp();
@@ -450,28 +763,77 @@ impl<P: PastaConfig> PlonkProof<P> {
         // }
         // ensure!(circuit.Cs.public_input == acc);
 
-        let mut vec = Vec::new();
-        vec.extend_from_slice(&pi.vs.qs);
+        let mut vec = vec![lin.v];
         vec.extend_from_slice(&pi.vs.ws);
-        vec.extend_from_slice(&pi.vs.ts);
-        vec.push(pi.vs.z);
+        vec.extend_from_slice(&pi.vs.rs);
+        vec.extend_from_slice(&pi.vs.sigmas);
+        if let Some(vs) = &pi.vs.lookup {
+            vec.extend_from_slice(&vs.qs);
+            vec.extend_from_slice(&vs.tables);
+            vec.extend_from_slice(&vs.hs);
+        }
         let v_r = geometric_generic(zeta, vec);
 
         let mut vec = Vec::new();
         vec.extend_from_slice(&pi.vs.w_omegas);
         vec.push(pi.vs.z_omega);
+        if let Some(vs) = &pi.vs.lookup {
+            vec.extend_from_slice(&vs.tables_omega);
+            vec.push(vs.h1_omega);
+            vec.push(vs.z_omega);
+        }
         let v_r_omega = geometric_generic(zeta, vec);
 
-        let mut vec = Vec::new();
-        vec.extend_from_slice(&circuit.Cs.qs);
-        vec.extend_from_slice(&pi.Cs.ws);
-        vec.extend_from_slice(&pi.Cs.ts);
-        vec.push(pi.Cs.z);
+        // Commitments to p_lo(X) + ξⁿ p_hi(X) for the split polynomials of a hiding proof
+        let (C_ws, C_z, C_ts) = match &pi.Cs.hiding {
+            Some(hiding) => (
+                array::from_fn(|i| pi.Cs.ws[i] + hiding.ws_hi[i] * xi_n),
+                pi.Cs.z + hiding.z_hi * xi_n,
+                array::from_fn(|i| pi.Cs.ts[i] + hiding.ts_hi[i] * xi_n),
+            ),
+            None => (pi.Cs.ws, pi.Cs.z, pi.Cs.ts),
+        };
+        let lookup_Cs = circuit
+            .lookup
+            .zip(pi.Cs.lookup.as_ref())
+            .map(|(circuit_Cs, Cs)| {
+                let (C_hs, C_z) = match &Cs.hiding {
+                    Some(hiding) => (
+                        array::from_fn(|i| Cs.hs[i] + hiding.hs_hi[i] * xi_n),
+                        Cs.z + hiding.z_hi * xi_n,
+                    ),
+                    None => (Cs.hs, Cs.z),
+                };
+                (circuit_Cs, C_hs, C_z)
+            });
+
+        let mut vec = circuit.Cs.qs.to_vec();
+        vec.push(C_z);
+        vec.extend_from_slice(&C_ts);
+        if let Some((_, _, C_z)) = &lookup_Cs {
+            vec.push(*C_z);
+        }
+        let C_lin = lin.commitment(vec);
+
+        let mut vec = vec![C_lin];
+        vec.extend_from_slice(&C_ws);
+        vec.extend_from_slice(&circuit.Cs.rs);
+        vec.extend_from_slice(&circuit.Cs.sigmas);
+        if let Some((circuit_Cs, C_hs, _)) = &lookup_Cs {
+            vec.extend_from_slice(&circuit_Cs.qs);
+            vec.extend_from_slice(&circuit_Cs.tables);
+            vec.extend_from_slice(C_hs);
+        }
         let C_r = geometric_generic(zeta, vec);
 
         let mut vec = Vec::new();
-        vec.extend_from_slice(&pi.Cs.ws[0..3]);
-        vec.push(pi.Cs.z);
+        vec.extend_from_slice(&C_ws[0..3]);
+        vec.push(C_z);
+        if let Some((circuit_Cs, C_hs, C_z)) = &lookup_Cs {
+            vec.extend_from_slice(&circuit_Cs.tables);
+            vec.push(C_hs[0]);
+            vec.push(*C_z);
+        }
         let C_r_omega = geometric_generic(zeta, vec);
 
         let instance_1 = Instance::new(C_r, d, xi, v_r, pi.pis.r.clone());
@@ -485,7 +847,7 @@ impl<P: PastaConfig> PlonkProof<P> {
 
         let acc_next = pi.acc_next.clone();
         let qs = [acc_prev.into(), instance_1, instance_2];
-        acc::verifier(&qs, acc_next)?;
+        acc::verifier_with_pp(pp, &qs, acc_next)?;
 
         Ok(())
     }
@@ -494,18 +856,348 @@ impl<P: PastaConfig> PlonkProof<P> {
         &self,
         circuit: PlonkCircuit<P>,
         public_inputs: &PlonkPublicInputs<P>,
+    ) -> Result<()> {
+        self.verify_with_pp(PublicParams::get_pp(), circuit, public_inputs)
+    }
+
+    /// Same as `verify`, with the public parameters `pp`
+    pub fn verify_with_pp(
+        &self,
+        pp: &PublicParams<P>,
+        circuit: PlonkCircuit<P>,
+        public_inputs: &PlonkPublicInputs<P>,
     ) -> Result<()> {
         let acc_next = self.acc_next.clone();
-        self.verify_succinct(circuit, public_inputs)?;
-        acc::decider(acc_next)?;
+        self.verify_succinct_with_pp(pp, circuit, public_inputs)?;
+        acc::decider_with_pp(pp, acc_next)?;
+        Ok(())
+    }
+
+    /// Verifies many proofs, possibly of different circuits, with one decider call per degree
+    /// bound instead of one per proof. Every proof is checked succinctly, then the `acc_next`
+    /// accumulators sharing a degree bound are folded into a single accumulator with
+    /// `acc::prover` and only that one is decided. If the folded accumulator is rejected, the
+    /// accumulators of the group are decided one by one to find the culprit. The error names
+    /// the index of the first proof that failed.
+    pub fn batch_verify<R: Rng>(
+        rng: &mut R,
+        proofs: &[(PlonkCircuit<P>, PlonkPublicInputs<P>, PlonkProof<P>)],
+    ) -> Result<()> {
+        Self::batch_verify_with_pp(rng, PublicParams::get_pp(), proofs)
+    }
+
+    /// Same as `batch_verify`, with the public parameters `pp`
+    pub fn batch_verify_with_pp<R: Rng>(
+        rng: &mut R,
+        pp: &PublicParams<P>,
+        proofs: &[(PlonkCircuit<P>, PlonkPublicInputs<P>, PlonkProof<P>)],
+    ) -> Result<()> {
+        let mut groups: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for (i, (circuit, public_inputs, pi)) in proofs.iter().enumerate() {
+            pi.verify_succinct_with_pp(pp, *circuit, public_inputs)
+                .with_context(|| format!("Proof {i} failed the succinct check"))?;
+            groups.entry(pi.acc_next.q.d).or_default().push(i);
+        }
+
+        for is in groups.values() {
+            let qs: Vec<Instance<P>> = is
+                .iter()
+                .map(|&i| proofs[i].2.acc_next.clone().into())
+                .collect();
+            let decided =
+                acc::prover_with_pp(rng, pp, &qs).and_then(|acc| acc::decider_with_pp(pp, acc));
+            if decided.is_err() {
+                for &i in is {
+                    acc::decider_with_pp(pp, proofs[i].2.acc_next.clone())
+                        .with_context(|| format!("Proof {i} failed the decider"))?;
+                }
+                bail!("The batched decider failed, but every proof passed on its own");
+            }
+        }
+
         Ok(())
     }
 }
 
+/// The challenges that the linearization polynomial depends on
+#[derive(Clone, Copy)]
+struct LinearizationChallenges<P: PastaConfig> {
+    alpha: Scalar<P>,
+    beta: Scalar<P>,
+    gamma: Scalar<P>,
+    /// [θ, δ, ε] if the circuit has lookup gates
+    lookup: Option<[Scalar<P>; 3]>,
+    xi: Scalar<P>,
+}
+
+/// Maller's optimization. Every term of F(X) - T(X)Zₕ(X) is a product with at most one of the
+/// qᵢ(X), z(X), z₂(X) or tⱼ(X), so evaluating everything else at 𝔷 leaves the linearization
+/// polynomial
+///
+/// L(X) = Σᵢ Gᵢ(𝔷) qᵢ(X) + (α L₁(𝔷) + α² f'(𝔷)) z(X) + (α³ L₁(𝔷) + α⁴ f'ₗ(𝔷)) z₂(X)
+///      - Zₕ(𝔷) Σⱼ 𝔷ⁿʲ tⱼ(X)
+///
+/// which is linear in polynomials the verifier has commitments to. The remaining terms are
+/// constant, so F(𝔷) = T(𝔷)Zₕ(𝔷) iff L(𝔷) = v, and opening L at 𝔷 replaces sending the
+/// evaluations of the qᵢ, tⱼ, z and z₂.
+struct Linearization<P: PastaConfig> {
+    /// The coefficients of [q₀, …, q₉, z, t₀, …, t₁₅] followed by z₂ if there are lookups
+    coeffs: Vec<Scalar<P>>,
+    /// L(𝔷)
+    v: Scalar<P>,
+}
+
+impl<P: PastaConfig> Linearization<P> {
+    fn new(
+        vs: &PlonkProofEvals<P>,
+        public_inputs: &[Scalar<P>],
+        rows: usize,
+        omega: Scalar<P>,
+        challenges: &LinearizationChallenges<P>,
+    ) -> Self {
+        let LinearizationChallenges {
+            alpha,
+            beta,
+            gamma,
+            lookup,
+            xi,
+        } = *challenges;
+        let one = Scalar::<P>::one();
+        let n = P::scalar_from_u64(rows as u64);
+        let xi_n = pow_n(xi, rows);
+        let l1 = (omega * (xi_n - one)) / (n * (xi - omega));
+        let z_H = xi_n - one;
+
+        // f'(𝔷) = (A(𝔷) + β Sᵢ₁(𝔷) + γ) (B(𝔷) + β Sᵢ₂(𝔷) + γ) (C(𝔷) + β Sᵢ₃(𝔷) + γ)
+        // g'(𝔷) = (A(𝔷)) + β S₁(𝔷)) + γ) (B(𝔷)) + β S₂(𝔷)) + γ) (C(𝔷)) + β S₃(𝔷)) + γ)
+        let ids = permutation_shifts::<P>().map(|k| k * xi);
+        let mut f_prime = vs.ws[0] + beta * ids[0] + gamma;
+        let mut g_prime = vs.ws[0] + beta * vs.sigmas[0] + gamma;
+        for i in 1..S_POLYS {
+            f_prime *= vs.ws[i] + beta * ids[i] + gamma;
+            g_prime *= vs.ws[i] + beta * vs.sigmas[i] + gamma;
+        }
+
+        // F_GC(𝔷) = Σᵢ qᵢ(𝔷) Gᵢ(𝔷) + PI(𝔷)
+        let MDS = P::SCALAR_POSEIDON_MDS;
+        let mut coeffs = gate_terms_generic(MDS, vs.rs, vs.ws, vs.w_omegas, alpha).to_vec();
+        let pi = public_input_eval_generic(public_inputs, n, omega, xi, xi_n);
+
+        // α F_CC1(𝔷) + α² F_CC2(𝔷), without the z(𝔷) terms
+        coeffs.push(alpha * l1 + alpha.pow([2]) * f_prime);
+        let mut v = alpha * l1 + alpha.pow([2]) * vs.z_omega * g_prime - pi;
+
+        // -Zₕ(𝔷) T(𝔷) = -Zₕ(𝔷) Σⱼ 𝔷ⁿʲ tⱼ(𝔷)
+        let mut acc = -z_H;
+        for _ in 0..T_POLYS {
+            coeffs.push(acc);
+            acc *= xi_n;
+        }
+
+        // α³ F_PL1(𝔷) + α⁴ F_PL2(𝔷), without the z₂(𝔷) terms
+        if let (Some(vs_lookup), Some([theta, delta, epsilon])) = (&vs.lookup, lookup) {
+            let ws = array::from_fn(|i| vs.ws[i]);
+            let f = lookup_query_generic(theta, vs_lookup.qs, ws);
+            let t = geometric_generic(theta, vs_lookup.tables.to_vec());
+            let t_omega = geometric_generic(theta, vs_lookup.tables_omega.to_vec());
+            let hs = [vs_lookup.hs[0], vs_lookup.hs[1], vs_lookup.h1_omega];
+            let (f_prime, g_prime) = plookup_terms_generic(f, [t, t_omega], hs, delta, epsilon);
+            coeffs.push(alpha.pow([3]) * l1 + alpha.pow([4]) * f_prime);
+            v += alpha.pow([3]) * l1 + alpha.pow([4]) * vs_lookup.z_omega * g_prime;
+        }
+
+        Self { coeffs, v }
+    }
+
+    /// L(X), given the polynomials in the order of `coeffs`
+    fn poly(&self, polys: Vec<Poly<P>>) -> Poly<P> {
+        assert_eq!(polys.len(), self.coeffs.len());
+        let mut result = Poly::<P>::zero();
+        for (p, c) in polys.into_iter().zip(&self.coeffs) {
+            result += &(p * *c)
+        }
+        result
+    }
+
+    /// The commitment to L(X), given the commitments in the order of `coeffs`
+    fn commitment(&self, Cs: Vec<Point<P>>) -> Point<P> {
+        assert_eq!(Cs.len(), self.coeffs.len());
+        Cs.into_iter().zip(&self.coeffs).map(|(C, c)| C * c).sum()
+    }
+
+    /// The hiding randomness of L(X), where unhidden commitments contribute zero
+    fn hiding(&self, ws: Vec<Option<Scalar<P>>>) -> Option<Scalar<P>> {
+        assert_eq!(ws.len(), self.coeffs.len());
+        if ws.iter().all(Option::is_none) {
+            return None;
+        }
+        let ws = ws.into_iter().map(Option::unwrap_or_default);
+        Some(ws.zip(&self.coeffs).map(|(w, c)| w * c).sum())
+    }
+}
+
 fn deg0<P: PastaConfig>(x: Scalar<P>) -> Poly<P> {
     Poly::<P>::from_coefficients_vec(vec![x])
 }
 
+fn ensure_hiding_rows<P: PastaConfig>(circuit: &PlonkCircuit<P>) -> Result<()> {
+    ensure!(
+        circuit.rows >= HIDING_MIN_ROWS,
+        "A hiding proof needs at least {HIDING_MIN_ROWS} rows, got {}",
+        circuit.rows
+    );
+    Ok(())
+}
+
+fn hiding_rand<R: Rng, P: PastaConfig>(rng: &mut R, hiding: bool) -> Option<Scalar<P>> {
+    hiding.then(|| Scalar::<P>::rand(rng))
+}
+
+/// p(X) + b(X)Zₕ(X) for a random b(X) of degree k - 1, leaving p unchanged on the domain
+fn blind<R: Rng, P: PastaConfig>(rng: &mut R, p: &Poly<P>, k: usize, domain: Domain<P>) -> Poly<P> {
+    let b = Poly::<P>::rand(k - 1, rng);
+    p + &b.mul_by_vanishing_poly(domain)
+}
+
+/// Splits p(X) into (p_lo(X), p_hi(X)) such that p(X) = p_lo(X) + Xⁿ p_hi(X)
+fn split_poly<P: PastaConfig>(p: &Poly<P>, n: usize) -> (Poly<P>, Poly<P>) {
+    if p.coeffs.len() <= n {
+        return (p.clone(), Poly::<P>::zero());
+    }
+    let lo = Poly::<P>::from_coefficients_slice(&p.coeffs[..n]);
+    let hi = Poly::<P>::from_coefficients_slice(&p.coeffs[n..]);
+    (lo, hi)
+}
+
+/// p(ωX)
+fn shift_poly<P: PastaConfig>(p: &Poly<P>, omega: Scalar<P>) -> Poly<P> {
+    let mut omega_i = Scalar::<P>::one();
+    let mut coeffs = p.coeffs.clone();
+    for c in coeffs.iter_mut() {
+        *c *= omega_i;
+        omega_i *= omega;
+    }
+    Poly::<P>::from_coefficients_vec(coeffs)
+}
+
+/// A polynomial p(X) = p_lo(X) + Xⁿ p_hi(X) committed to by the prover, where p_hi is only
+/// committed to in hiding mode, see `PlonkProofHidingCommitments`
+struct SplitCommitment<P: PastaConfig> {
+    lo: Poly<P>,
+    hi: Poly<P>,
+    w_lo: Option<Scalar<P>>,
+    w_hi: Option<Scalar<P>>,
+    C_lo: Point<P>,
+    C_hi: Option<Point<P>>,
+}
+
+impl<P: PastaConfig> SplitCommitment<P> {
+    fn new<R: Rng>(rng: &mut R, pp: &PublicParams<P>, p: &Poly<P>, n: usize, hiding: bool) -> Self {
+        let d = n - 1;
+        let (lo, hi) = split_poly::<P>(p, n);
+        let w_lo = hiding_rand::<R, P>(rng, hiding);
+        let w_hi = hiding_rand::<R, P>(rng, hiding);
+        let C_lo = commit_with_pp(pp, &lo, d, w_lo.as_ref());
+        let C_hi = hiding.then(|| commit_with_pp(pp, &hi, d, w_hi.as_ref()));
+        Self {
+            lo,
+            hi,
+            w_lo,
+            w_hi,
+            C_lo,
+            C_hi,
+        }
+    }
+
+    /// p_lo(X) + ξⁿ p_hi(X), which agrees with p(X) at both ξ and ξω since ωⁿ = 1
+    fn combine(&self, xi_n: Scalar<P>) -> Poly<P> {
+        &self.lo + &(&self.hi * xi_n)
+    }
+
+    fn combine_hiding(&self, xi_n: Scalar<P>) -> Option<Scalar<P>> {
+        hiding_lo_hi::<P>(self.w_lo, self.w_hi, xi_n)
+    }
+}
+
+/// The polynomial z with z(ω) = 1 and z(ωX) g'(X) = z(X) f'(X) on the domain
+fn grand_product<P: PastaConfig>(
+    f_prime: &Poly<P>,
+    g_prime: &Poly<P>,
+    domain: Domain<P>,
+) -> Poly<P> {
+    let rows = domain.size();
+    let f_prime_evals = Evals::<P>::new(f_prime.evaluate_over_domain_by_ref(domain));
+    let g_prime_evals = Evals::<P>::new(g_prime.evaluate_over_domain_by_ref(domain));
+
+    let mut z = vec![P::ScalarField::zero(); rows];
+    for i in 0..rows {
+        let zero_index = i;
+        let one_index = (i + 1) % rows;
+        if one_index == 1 {
+            z[zero_index] = P::ScalarField::one();
+        } else {
+            // TODO: Fix this disgusting indexing
+            let ratio = f_prime_evals[zero_index] / g_prime_evals[zero_index];
+            z[zero_index] = z[zero_index - 1] * ratio
+        }
+    }
+
+    Evals::<P>::from_vec_and_domain(z, domain).interpolate()
+}
+
+/// f(X) = q_K(X) (w₁(X) + θ w₂(X) + θ² w₃(X)) + θ³ q_T(X)
+fn lookup_query_poly<P: PastaConfig>(
+    theta: Scalar<P>,
+    qs: &[Poly<P>; 2],
+    ws: &[Poly<P>; W_POLYS],
+) -> Poly<P> {
+    let w = geometric_polys::<P>(theta, ws[..LOOKUP_WIRES].to_vec());
+    &(&qs[0] * &w) + &(&qs[1] * theta.pow([LOOKUP_WIRES as u64]))
+}
+
+/// Plookup's (h₁, h₂): the union of the queries f and the table t, sorted by the order of t and
+/// split alternately between the two
+fn plookup_sort<P: PastaConfig>(f: &Poly<P>, t: &Poly<P>, domain: Domain<P>) -> [Poly<P>; 2] {
+    let f_evals = f.evaluate_over_domain_by_ref(domain).evals;
+    let t_evals = t.evaluate_over_domain_by_ref(domain).evals;
+
+    let mut order = HashMap::with_capacity(t_evals.len());
+    for (i, x) in t_evals.iter().enumerate() {
+        order.entry(*x).or_insert(i);
+    }
+    // Queries missing from the table are placed last, the resulting proof will not verify
+    let mut s: Vec<Scalar<P>> = f_evals.into_iter().chain(t_evals).collect();
+    s.sort_by_key(|x| order.get(x).copied().unwrap_or(usize::MAX));
+
+    let h1 = s.iter().step_by(2).copied().collect();
+    let h2 = s.iter().skip(1).step_by(2).copied().collect();
+    [h1, h2].map(|h| Evals::<P>::from_vec_and_domain(h, domain).interpolate())
+}
+
+/// Hiding randomness of p_lo(X) + ξⁿ p_hi(X), given the randomness of p_lo and p_hi
+fn hiding_lo_hi<P: PastaConfig>(
+    w_lo: Option<Scalar<P>>,
+    w_hi: Option<Scalar<P>>,
+    xi_n: Scalar<P>,
+) -> Option<Scalar<P>> {
+    match (w_lo, w_hi) {
+        (Some(lo), Some(hi)) => Some(lo + xi_n * hi),
+        (w_lo, _) => w_lo,
+    }
+}
+
+/// Hiding randomness of a geometric combination, where unhidden commitments contribute zero
+fn geometric_hiding<P: PastaConfig>(
+    zeta: Scalar<P>,
+    ws: Vec<Option<Scalar<P>>>,
+) -> Option<Scalar<P>> {
+    if ws.iter().all(Option::is_none) {
+        return None;
+    }
+    let ws = ws.into_iter().map(Option::unwrap_or_default).collect();
+    Some(geometric_generic(zeta, ws))
+}
+
 fn t_split<P: PastaConfig>(mut t: Poly<P>, n: usize) -> [Poly<P>; T_POLYS] {
     assert!(t.degree() < T_POLYS * n, "{} < {}", t.degree(), T_POLYS * n);
     t.coeffs.resize(T_POLYS * n, Scalar::<P>::zero());
@@ -529,6 +1221,91 @@ where
     result
 }
 
+/// The constants of the `_generic` functions, made alike a value they are combined with. For
+/// wires, they are created in the circuit of that value.
+pub trait GenericConstants {
+    fn zero_like(&self) -> Self;
+    fn one_like(&self) -> Self;
+}
+impl<F: Field> GenericConstants for F {
+    fn zero_like(&self) -> Self {
+        F::zero()
+    }
+    fn one_like(&self) -> Self {
+        F::one()
+    }
+}
+
+/// f(𝔷) = q_K(𝔷) (w₁(𝔷) + θ w₂(𝔷) + θ² w₃(𝔷)) + θ³ q_T(𝔷)
+pub fn lookup_query_generic<T>(theta: T, qs: [T; 2], ws: [T; LOOKUP_WIRES]) -> T
+where
+    T: Copy + Add<Output = T> + AddAssign + Mul<Output = T> + MulAssign,
+{
+    let mut theta_l = theta;
+    for _ in 1..LOOKUP_WIRES {
+        theta_l *= theta
+    }
+    qs[0] * geometric_generic(theta, ws.to_vec()) + theta_l * qs[1]
+}
+
+/// (f'(𝔷), g'(𝔷)) of the Plookup grand product, given ts = [t(𝔷), t(𝔷ω)] and
+/// hs = [h₁(𝔷), h₂(𝔷), h₁(𝔷ω)]
+pub fn plookup_terms_generic<T>(f: T, ts: [T; 2], hs: [T; 3], delta: T, epsilon: T) -> (T, T)
+where
+    T: Copy + Add<Output = T> + Mul<Output = T> + GenericConstants,
+{
+    let one_delta = delta.one_like() + delta;
+    let epsilon_delta = epsilon * one_delta;
+    let f_prime = one_delta * (epsilon + f) * (epsilon_delta + ts[0] + delta * ts[1]);
+    let g_prime = (epsilon_delta + hs[0] + delta * hs[1]) * (epsilon_delta + hs[1] + delta * hs[2]);
+    (f_prime, g_prime)
+}
+
+/// [G₀(𝔷), …, G₉(𝔷)], the factors of the selectors in F_GC(𝔷) = Σᵢ qᵢ(𝔷) Gᵢ(𝔷) + PI(𝔷). The
+/// generic gate in position k > 0 of a row shares the selectors of the first, weighted by α⁴⁺ᵏ.
+pub fn gate_terms_generic<T>(
+    mds: [[T; 3]; 3],
+    rs: [T; R_POLYS],
+    ws: [T; W_POLYS],
+    w_omegas: [T; 3],
+    alpha: T,
+) -> [T; Q_POLYS]
+where
+    T: Copy
+        + Add<Output = T>
+        + AddAssign
+        + Sub<Output = T>
+        + SubAssign
+        + Mul<Output = T>
+        + MulAssign
+        + GenericConstants,
+{
+    let [mut l, mut r, mut o, mut m] = [ws[0], ws[1], ws[2], ws[0] * ws[1]];
+    let mut alpha_k = alpha * alpha;
+    alpha_k *= alpha_k;
+    for k in 1..GENERIC_GATES {
+        alpha_k *= alpha;
+        let [a, b, c] = [ws[3 * k], ws[3 * k + 1], ws[3 * k + 2]];
+        l += alpha_k * a;
+        r += alpha_k * b;
+        o += alpha_k * c;
+        m += alpha_k * a * b;
+    }
+
+    [
+        l,
+        r,
+        o,
+        m,
+        alpha.one_like(),
+        poseidon_constraints_generic(mds, &rs, &ws, &w_omegas),
+        affine_add_constraints_generic(ws),
+        affine_mul_constraints_generic(ws, w_omegas, rs[0]),
+        eq_generic(ws),
+        range_check_generic(ws, w_omegas, rs),
+    ]
+}
+
 pub fn pow_n<T>(mut x: T, n: usize) -> T
 where
     T: Copy + MulAssign,
@@ -570,15 +1347,14 @@ where
         + Sub<Output = T>
         + Mul<Output = T>
         + MulAssign
-        + Zero
         + Neg<Output = T>
-        + One,
+        + GenericConstants,
 {
-    let one = T::one();
+    let one = xi.one_like();
 
     // (xi_n - one) * omega_j / n * (xi - omega_j)
     let mut omega_j = omega;
-    let mut public_input_xi = T::zero();
+    let mut public_input_xi = xi.zero_like();
     for x in public_inputs {
         let l_j = ((xi_n - one) * omega_j) / (n * (xi - omega_j));
         public_input_xi += l_j * (-(*x));
@@ -704,9 +1480,9 @@ fn affine_add_constraints_evals<P: PastaConfig>(w: &[Evals<P>; W_POLYS]) -> Eval
 
 pub(crate) fn affine_add_constraints_generic<T>(w: [T; W_POLYS]) -> T
 where
-    T: Copy + Add<Output = T> + AddAssign + Sub<Output = T> + Mul<Output = T> + One,
+    T: Copy + Add<Output = T> + AddAssign + Sub<Output = T> + Mul<Output = T> + GenericConstants,
 {
-    let one = T::one();
+    let one = w[0].one_like();
     let [xp, yp, xq, yq, xr, yr, α, β, γ, δ, λ, _, _, _, _, _] = w;
 
     // (xq - xp) · ((xq - xp) · λ - (yq - yp))
@@ -850,9 +1626,9 @@ fn affine_mul_constraints_evals<P: PastaConfig>(
 
 pub(crate) fn affine_mul_constraints_generic<T>(w: [T; W_POLYS], nw: [T; 3], two_pow_i: T) -> T
 where
-    T: Copy + Add<Output = T> + AddAssign + Sub<Output = T> + Mul<Output = T> + One,
+    T: Copy + Add<Output = T> + AddAssign + Sub<Output = T> + Mul<Output = T> + GenericConstants,
 {
-    let one = T::one();
+    let one = w[0].one_like();
     let [xp, yp, a, xg, yg, b, xq, yq, xr, yr, βq, λq, αr, γr, δr, λr] = w;
 
     let xp·xp = xp * xp;
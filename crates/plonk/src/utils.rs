@@ -2,9 +2,9 @@ use std::array;
 
 use halo_group::{
     PastaConfig, Scalar,
-    ark_ff::{BigInt, BigInteger, Field},
+    ark_ff::{BigInt, BigInteger, FftField, Field},
     ark_poly::Polynomial,
-    ark_std::Zero,
+    ark_std::{One, Zero},
 };
 
 use crate::circuit::Trace;
@@ -18,14 +18,42 @@ pub const W_POLYS: usize = 16;
 pub const R_POLYS: usize = 15;
 /// How many selector polynomials in plonk
 pub const Q_POLYS: usize = 10;
-/// How many copy constraint polynomials in plonk
-pub const S_POLYS: usize = 8;
+/// How many generic gates fit in one row, each using three consecutive witness columns
+pub const GENERIC_GATES: usize = 5;
+/// How many copy constraint polynomials in plonk, covering the columns of the generic gates
+pub const S_POLYS: usize = 3 * GENERIC_GATES;
+/// How many wires a lookup gate reads, i.e. the width of the lookup tables
+pub const LOOKUP_WIRES: usize = 3;
+/// How many table polynomials in plonk, the lookup columns followed by the table id
+pub const L_POLYS: usize = LOOKUP_WIRES + 1;
+/// The shifts kⱼ = gʲ of the identity permutation of the copy constraint columns, idⱼ(X) = kⱼ X,
+/// where g generates the multiplicative group of the field. gʲ is not in the domain H for
+/// 0 < j < (p - 1) / n, so each column gets its own coset of H, and the verifier computes idⱼ(𝔷)
+/// instead of it being part of the proof.
+pub fn permutation_shifts<P: PastaConfig>() -> [Scalar<P>; S_POLYS] {
+    let mut k = Scalar::<P>::one();
+    array::from_fn(|_| {
+        let k_j = k;
+        k *= Scalar::<P>::GENERATOR;
+        k_j
+    })
+}
+
+/// The fewest rows of a trace
+pub const MIN_ROWS: usize = 4;
+/// The fewest rows of a trace with hiding proofs. Their quotient has degree 15n + 32 with the
+/// blinded copy constraints over `S_POLYS` columns, so it only fits in `T_POLYS` chunks of n
+/// coefficients from 64 rows on.
+pub const HIDING_MIN_ROWS: usize = 64;
 /// The maximum degree of the constraint polynomials in f_gc. I.e.
 /// f_gc.degree() <= n * CONSTRAINT_DEGREE_MULTIPLIER.
 pub const CONSTRAINT_DEGREE_MULTIPLIER: usize = 8;
 
 pub trait MultiAssign<T> {
     fn multi_assign<const N: usize>(&mut self, row: usize, values: [T; N]);
+
+    /// Assigns `values` to the vectors from `offset` on at `row`, the others are left as they are
+    fn multi_assign_at<const N: usize>(&mut self, row: usize, offset: usize, values: [T; N]);
 }
 impl<T, const N: usize> MultiAssign<T> for [Vec<T>; N]
 where
@@ -38,7 +66,15 @@ where
             values.len(),
             "Number of values must match number of vectors"
         );
-        for (vec, value) in self.iter_mut().zip(values) {
+        self.multi_assign_at(row, 0, values)
+    }
+
+    fn multi_assign_at<const M: usize>(&mut self, row: usize, offset: usize, values: [T; M]) {
+        assert!(
+            offset + M <= N,
+            "Number of values must fit in the vectors: {offset} + {M} > {N}"
+        );
+        for (vec, value) in self[offset..].iter_mut().zip(values) {
             assert!(
                 row < vec.len(),
                 "Error: row >= vec.len(): {row} >= {:?}",
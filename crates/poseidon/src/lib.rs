@@ -1,5 +1,9 @@
 pub mod inner_sponge;
+mod merkle;
 mod outer_sponge;
+mod params;
 
 pub use inner_sponge::{PERM_ROUNDS_FULL, SPONGE_CAPACITY, SPONGE_RATE, STATE_SIZE};
+pub use merkle::{hash, hash_pair, MerklePath, MerkleTree};
 pub use outer_sponge::{Protocols, Sponge};
+pub use params::{ParamsSponge, PoseidonParams};
@@ -0,0 +1,182 @@
+//! Binary Merkle trees over the base field of a Pasta curve, hashed with `PoseidonSponge`. A tree
+//! of depth d has 2ᵈ leaves, and the leaves that were not given are zero.
+
+use anyhow::{ensure, Result};
+use educe::Educe;
+use halo_group::{ark_ff::Zero, PastaConfig};
+
+use crate::inner_sponge::{PoseidonSponge, SPONGE_RATE};
+
+/// H(xs), absorbing all of `xs` into a fresh sponge and squeezing once. The capacity starts at the
+/// length of `xs`, so that inputs which only differ by trailing zeros have different hashes.
+pub fn hash<P: PastaConfig>(xs: &[P::BaseField]) -> P::BaseField {
+    let mut sponge = PoseidonSponge::<P>::new();
+    sponge.state[SPONGE_RATE] = P::BaseField::from(xs.len() as u64);
+    sponge.absorb(xs);
+    sponge.squeeze()
+}
+
+/// H(left, right)
+pub fn hash_pair<P: PastaConfig>(left: P::BaseField, right: P::BaseField) -> P::BaseField {
+    hash::<P>(&[left, right])
+}
+
+#[derive(Educe)]
+#[educe(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleTree<P: PastaConfig> {
+    /// The leaves followed by each layer of nodes up to the root, `layers[i]` has 2ᵈ⁻ⁱ nodes
+    layers: Vec<Vec<P::BaseField>>,
+}
+
+impl<P: PastaConfig> MerkleTree<P> {
+    pub fn new(depth: usize, leaves: &[P::BaseField]) -> Result<Self> {
+        ensure!(
+            depth < usize::BITS as usize,
+            "A depth of {depth} is too large"
+        );
+        ensure!(
+            leaves.len() <= 1 << depth,
+            "{} leaves do not fit in a tree of depth {depth}",
+            leaves.len()
+        );
+
+        let mut layer = leaves.to_vec();
+        layer.resize(1 << depth, P::BaseField::zero());
+        let mut layers = vec![layer];
+        for _ in 0..depth {
+            let layer = layers
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| hash_pair::<P>(pair[0], pair[1]))
+                .collect();
+            layers.push(layer);
+        }
+        Ok(Self { layers })
+    }
+
+    pub fn depth(&self) -> usize {
+        self.layers.len() - 1
+    }
+
+    pub fn root(&self) -> P::BaseField {
+        self.layers[self.depth()][0]
+    }
+
+    pub fn leaf(&self, index: usize) -> Option<P::BaseField> {
+        self.layers[0].get(index).copied()
+    }
+
+    /// The authentication path of the leaf at `index`
+    pub fn path(&self, index: usize) -> Result<MerklePath<P>> {
+        ensure!(index < self.layers[0].len(), "Leaf {index} is out of range");
+        let siblings = (0..self.depth())
+            .map(|i| self.layers[i][(index >> i) ^ 1])
+            .collect();
+        Ok(MerklePath { index, siblings })
+    }
+
+    /// Replaces the leaf at `index` and rehashes the nodes above it
+    pub fn update(&mut self, index: usize, leaf: P::BaseField) -> Result<()> {
+        ensure!(index < self.layers[0].len(), "Leaf {index} is out of range");
+        self.layers[0][index] = leaf;
+        for i in 0..self.depth() {
+            let j = index >> i & !1;
+            let node = hash_pair::<P>(self.layers[i][j], self.layers[i][j + 1]);
+            self.layers[i + 1][index >> (i + 1)] = node;
+        }
+        Ok(())
+    }
+}
+
+/// The siblings of the nodes from a leaf up to, but excluding, the root
+#[derive(Educe)]
+#[educe(Debug, Clone, PartialEq, Eq)]
+pub struct MerklePath<P: PastaConfig> {
+    pub index: usize,
+    pub siblings: Vec<P::BaseField>,
+}
+
+impl<P: PastaConfig> MerklePath<P> {
+    pub fn depth(&self) -> usize {
+        self.siblings.len()
+    }
+
+    /// The root of the tree with `leaf` at the position of this path
+    pub fn root(&self, leaf: P::BaseField) -> P::BaseField {
+        self.siblings
+            .iter()
+            .enumerate()
+            .fold(leaf, |node, (i, sibling)| match self.index >> i & 1 {
+                0 => hash_pair::<P>(node, *sibling),
+                _ => hash_pair::<P>(*sibling, node),
+            })
+    }
+
+    pub fn verify(&self, root: P::BaseField, leaf: P::BaseField) -> Result<()> {
+        ensure!(self.root(leaf) == root, "Leaf is not in the tree");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo_group::{ark_ff::UniformRand, Fp, VestaConfig};
+
+    use super::*;
+
+    #[test]
+    fn paths_verify() -> Result<()> {
+        let rng = &mut rand::thread_rng();
+        let leaves: Vec<Fp> = (0..6).map(|_| Fp::rand(rng)).collect();
+        let tree = MerkleTree::<VestaConfig>::new(3, &leaves)?;
+        assert_eq!(tree.depth(), 3);
+        assert_eq!(tree.leaf(7), Some(Fp::zero()));
+
+        for i in 0..8 {
+            let path = tree.path(i)?;
+            path.verify(tree.root(), tree.leaf(i).unwrap())?;
+            assert!(path.verify(tree.root(), Fp::rand(rng)).is_err());
+        }
+        assert!(tree.path(8).is_err());
+        assert!(MerkleTree::<VestaConfig>::new(2, &leaves).is_err());
+
+        // A tree of depth 1 is the hash of its two leaves
+        let tree = MerkleTree::<VestaConfig>::new(1, &leaves[..2])?;
+        assert_eq!(tree.root(), hash_pair::<VestaConfig>(leaves[0], leaves[1]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn hash_encodes_length() {
+        let rng = &mut rand::thread_rng();
+        let a = Fp::rand(rng);
+        let zero = Fp::zero();
+        assert_ne!(hash::<VestaConfig>(&[a]), hash::<VestaConfig>(&[a, zero]));
+        assert_ne!(hash::<VestaConfig>(&[]), hash::<VestaConfig>(&[zero]));
+        assert_ne!(
+            hash::<VestaConfig>(&[a, zero]),
+            hash::<VestaConfig>(&[a, zero, zero])
+        );
+    }
+
+    #[test]
+    fn update_matches_rebuild() -> Result<()> {
+        let rng = &mut rand::thread_rng();
+        let mut leaves: Vec<Fp> = (0..16).map(|_| Fp::rand(rng)).collect();
+        let mut tree = MerkleTree::<VestaConfig>::new(4, &leaves)?;
+
+        for i in [0, 5, 15] {
+            let leaf = Fp::rand(rng);
+            let path = tree.path(i)?;
+            tree.update(i, leaf)?;
+            leaves[i] = leaf;
+            assert_eq!(tree, MerkleTree::new(4, &leaves)?);
+            // The siblings do not change, so the old path computes the new root
+            assert_eq!(path.root(leaf), tree.root());
+        }
+
+        Ok(())
+    }
+}
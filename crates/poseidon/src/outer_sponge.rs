@@ -9,6 +9,7 @@ use halo_group::{
 
 use crate::inner_sponge::PoseidonSponge;
 
+#[derive(Clone)]
 pub struct Sponge<P: PastaConfig> {
     sponge: PoseidonSponge<P>,
 }
@@ -18,6 +19,10 @@ pub enum Protocols {
     ASDL = 1,
     PLONK = 2,
     SIGNATURE = 3,
+    KEYAGG = 4,
+    MUSIG = 5,
+    NONCE = 6,
+    SPLIT = 7,
 }
 
 impl<P: PastaConfig> Sponge<P> {
@@ -94,6 +99,17 @@ impl<P: PastaConfig> Sponge<P> {
         }
     }
 
+    /// A uniform scalar, unlike `challenge` which drops a bit when the scalar field is the
+    /// smaller one. Two base field elements are squeezed and their 510 bits reduced modulo the
+    /// scalar field, which leaves a negligible bias. It can not be recomputed in a circuit, so it
+    /// is only meant for values such as nonces that the verifier never derives.
+    pub fn challenge_wide(&mut self) -> P::ScalarField {
+        let bytes: Vec<u8> = (0..2)
+            .flat_map(|_| P::basefield_into_bigint(self.sponge.squeeze()).to_bytes_le())
+            .collect();
+        P::ScalarField::from_le_bytes_mod_order(&bytes)
+    }
+
     pub fn reset(&mut self) {
         self.sponge.reset()
     }
@@ -0,0 +1,446 @@
+//! Runtime Poseidon parameter sets, for hashing with other widths, round counts or constants
+//! than the fixed Kimchi instance in `inner_sponge`.
+
+use anyhow::{ensure, Result};
+use halo_group::{
+    ark_ff::{BigInteger, PrimeField},
+    PastaConfig,
+};
+
+use crate::inner_sponge::{SpongeState, PERM_ROUNDS_FULL, SPONGE_RATE, STATE_SIZE};
+
+/// A Poseidon permutation and the rate of the sponge built on it.
+///
+/// Every round applies the S-box, then the MDS matrix, then adds the round constants of that
+/// round. If `initial_ark` is set, `round_constants[0]` is added before the first round and
+/// round `r` adds `round_constants[r + 1]`, which is the layout of the Poseidon reference
+/// implementation (ARK, S-box, MDS) with a zero row appended. Kimchi adds no constants up front.
+///
+/// The first and last `full_rounds / 2` rounds apply the S-box to the whole state, the
+/// `partial_rounds` in between only to `state[0]`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PoseidonParams<F: PrimeField> {
+    pub width: usize,
+    pub rate: usize,
+    pub full_rounds: usize,
+    pub partial_rounds: usize,
+    /// The S-box is x ↦ x^alpha
+    pub alpha: u64,
+    pub initial_ark: bool,
+    pub mds: Vec<Vec<F>>,
+    pub round_constants: Vec<Vec<F>>,
+}
+
+impl<F: PrimeField> PoseidonParams<F> {
+    /// The parameters of `PoseidonSponge<P>`, which are the ones used by Mina's Kimchi.
+    pub fn kimchi<P: PastaConfig<BaseField = F>>() -> Self {
+        Self {
+            width: STATE_SIZE,
+            rate: SPONGE_RATE,
+            full_rounds: PERM_ROUNDS_FULL,
+            partial_rounds: 0,
+            alpha: 7,
+            initial_ark: false,
+            mds: P::BASE_POSEIDON_MDS
+                .iter()
+                .map(|row| row.to_vec())
+                .collect(),
+            round_constants: P::BASE_POSEIDON_ROUND_CONSTANTS
+                .iter()
+                .map(|row| row.to_vec())
+                .collect(),
+        }
+    }
+
+    /// Generates the round constants and the MDS matrix with the Grain LFSR, as described in
+    /// appendix F of the Poseidon paper. The MDS matrix is the first Cauchy matrix sampled. The
+    /// reference implementation additionally rejects matrices failing its invariant subspace
+    /// checks, so compare the result against published constants before relying on it.
+    pub fn grain(
+        width: usize,
+        rate: usize,
+        full_rounds: usize,
+        partial_rounds: usize,
+        alpha: u64,
+    ) -> Result<Self> {
+        let mut lfsr = GrainLfsr::new(
+            F::MODULUS_BIT_SIZE as u16,
+            width as u16,
+            full_rounds as u16,
+            partial_rounds as u16,
+        );
+
+        let rounds = full_rounds + partial_rounds;
+        let mut round_constants: Vec<Vec<F>> = (0..rounds)
+            .map(|_| (0..width).map(|_| lfsr.field_element()).collect())
+            .collect();
+        round_constants.push(vec![F::zero(); width]);
+
+        // Cauchy matrix 1 / (xᵢ + yⱼ) over 2·width distinct elements
+        let xys = loop {
+            let xys: Vec<F> = (0..2 * width).map(|_| lfsr.field_element()).collect();
+            if (0..xys.len()).all(|i| !xys[..i].contains(&xys[i])) {
+                break xys;
+            }
+        };
+        let (xs, ys) = xys.split_at(width);
+        let mds = xs
+            .iter()
+            .map(|x| ys.iter().map(|y| (*x + y).inverse().unwrap()).collect())
+            .collect();
+
+        let params = Self {
+            width,
+            rate,
+            full_rounds,
+            partial_rounds,
+            alpha,
+            initial_ark: true,
+            mds,
+            round_constants,
+        };
+        params.validate()?;
+        Ok(params)
+    }
+
+    pub fn rounds(&self) -> usize {
+        self.full_rounds + self.partial_rounds
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.width - self.rate
+    }
+
+    /// Checks that the dimensions of the parameters agree with each other
+    pub fn validate(&self) -> Result<()> {
+        ensure!(self.rate > 0, "The rate must be positive");
+        ensure!(self.rate < self.width, "The capacity must be positive");
+        ensure!(self.alpha > 1, "The S-box exponent must be larger than 1");
+        ensure!(
+            self.partial_rounds == 0 || self.full_rounds.is_multiple_of(2),
+            "The full rounds can not be split around the partial rounds"
+        );
+        ensure!(
+            self.mds.len() == self.width && self.mds.iter().all(|row| row.len() == self.width),
+            "The MDS matrix must be {0}×{0}",
+            self.width
+        );
+        let rows = self.rounds() + self.initial_ark as usize;
+        ensure!(
+            self.round_constants.len() == rows
+                && self
+                    .round_constants
+                    .iter()
+                    .all(|row| row.len() == self.width),
+            "Expected {rows} rows of {} round constants",
+            self.width
+        );
+        Ok(())
+    }
+
+    fn is_full_round(&self, r: usize) -> bool {
+        let half = self.full_rounds / 2;
+        self.partial_rounds == 0 || r < half || r >= half + self.partial_rounds
+    }
+
+    fn sbox(&self, x: F) -> F {
+        x.pow([self.alpha])
+    }
+
+    fn ark(state: &mut [F], round_constants: &[F]) {
+        for (s, c) in state.iter_mut().zip(round_constants) {
+            *s += c;
+        }
+    }
+
+    pub fn permute(&self, state: &mut [F]) {
+        assert_eq!(state.len(), self.width);
+        let offset = self.initial_ark as usize;
+        if self.initial_ark {
+            Self::ark(state, &self.round_constants[0]);
+        }
+        for r in 0..self.rounds() {
+            if self.is_full_round(r) {
+                for s in state.iter_mut() {
+                    *s = self.sbox(*s);
+                }
+            } else {
+                state[0] = self.sbox(state[0]);
+            }
+            let mixed: Vec<F> = self
+                .mds
+                .iter()
+                .map(|row| row.iter().zip(state.iter()).map(|(m, s)| *m * s).sum())
+                .collect();
+            state.copy_from_slice(&mixed);
+            Self::ark(state, &self.round_constants[r + offset]);
+        }
+    }
+}
+
+/// The Grain LFSR in self-shrinking mode, seeded with the parameters of a Poseidon instance
+/// over a prime field with S-box x^alpha
+struct GrainLfsr {
+    state: [bool; 80],
+}
+
+impl GrainLfsr {
+    fn new(field_bits: u16, width: u16, full_rounds: u16, partial_rounds: u16) -> Self {
+        let mut seed = Vec::with_capacity(80);
+        // (value, bit length): the field type (1 = prime field), the S-box (0 = x^alpha), n, t,
+        // R_F and R_P, followed by 30 set bits
+        for (x, len) in
+            [(1, 2), (0, 4), (field_bits, 12), (width, 12), (full_rounds, 10), (partial_rounds, 10)]
+        {
+            seed.extend((0..len).rev().map(|i| (x >> i) & 1 == 1));
+        }
+        seed.extend([true; 30]);
+
+        let mut lfsr = Self {
+            state: seed.try_into().unwrap(),
+        };
+        for _ in 0..160 {
+            lfsr.update();
+        }
+        lfsr
+    }
+
+    fn update(&mut self) -> bool {
+        let s = &self.state;
+        let bit = s[62] ^ s[51] ^ s[38] ^ s[23] ^ s[13] ^ s[0];
+        self.state.rotate_left(1);
+        self.state[79] = bit;
+        bit
+    }
+
+    fn next_bit(&mut self) -> bool {
+        loop {
+            let keep = self.update();
+            let bit = self.update();
+            if keep {
+                return bit;
+            }
+        }
+    }
+
+    /// Samples n bits, most significant first, until they are smaller than the modulus
+    fn field_element<F: PrimeField>(&mut self) -> F {
+        loop {
+            let bits: Vec<bool> = (0..F::MODULUS_BIT_SIZE).map(|_| self.next_bit()).collect();
+            if let Some(x) = F::from_bigint(F::BigInt::from_bits_be(&bits)) {
+                return x;
+            }
+        }
+    }
+}
+
+/// A duplex sponge over any `PoseidonParams`, absorbing and squeezing like `PoseidonSponge`
+#[derive(Clone)]
+pub struct ParamsSponge<'a, F: PrimeField> {
+    params: &'a PoseidonParams<F>,
+    sponge_state: SpongeState,
+    pub state: Vec<F>,
+}
+
+impl<'a, F: PrimeField> ParamsSponge<'a, F> {
+    pub fn new(params: &'a PoseidonParams<F>) -> Self {
+        Self {
+            params,
+            sponge_state: SpongeState::Absorbed(0),
+            state: vec![F::zero(); params.width],
+        }
+    }
+
+    pub fn absorb(&mut self, x: &[F]) {
+        let rate = self.params.rate;
+        for x in x.iter() {
+            match self.sponge_state {
+                SpongeState::Absorbed(n) if n < rate => {
+                    self.sponge_state = SpongeState::Absorbed(n + 1);
+                    self.state[n] += x;
+                }
+                SpongeState::Absorbed(_) => {
+                    self.params.permute(&mut self.state);
+                    self.sponge_state = SpongeState::Absorbed(1);
+                    self.state[0] += x;
+                }
+                SpongeState::Squeezed(_) => {
+                    self.sponge_state = SpongeState::Absorbed(1);
+                    self.state[0] += x;
+                }
+            }
+        }
+    }
+
+    pub fn squeeze(&mut self) -> F {
+        match self.sponge_state {
+            SpongeState::Squeezed(n) if n < self.params.rate => {
+                self.sponge_state = SpongeState::Squeezed(n + 1);
+                self.state[n]
+            }
+            SpongeState::Squeezed(_) | SpongeState::Absorbed(_) => {
+                self.params.permute(&mut self.state);
+                self.sponge_state = SpongeState::Squeezed(1);
+                self.state[0]
+            }
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.state = vec![F::zero(); self.params.width];
+        self.sponge_state = SpongeState::Absorbed(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, path::PathBuf};
+
+    use halo_group::{
+        ark_ff::{UniformRand, Zero},
+        Fp, Fq, PallasConfig, VestaConfig,
+    };
+    use serde::{de::DeserializeOwned, Deserialize};
+
+    use super::*;
+    use crate::inner_sponge::PoseidonSponge;
+
+    #[derive(Debug, Deserialize)]
+    struct HashVectors {
+        test_vectors: Vec<HashVector>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct HashVector {
+        input: Vec<String>,
+        output: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct GrainVectors {
+        params: GrainParams,
+        mds: Vec<Vec<String>>,
+        round_constants: Vec<Vec<String>>,
+        permutation_vectors: Vec<PermutationVector>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct GrainParams {
+        width: usize,
+        rate: usize,
+        full_rounds: usize,
+        partial_rounds: usize,
+        alpha: u64,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct PermutationVector {
+        input: Vec<String>,
+        output: Vec<String>,
+    }
+
+    fn from_hex<F: PrimeField>(hex: &str) -> F {
+        let bytes: Vec<u8> = hex::decode(hex).unwrap();
+        F::deserialize_uncompressed(&mut &bytes[..]).unwrap()
+    }
+
+    fn from_hexes<F: PrimeField>(hexes: &[String]) -> Vec<F> {
+        hexes.iter().map(|x| from_hex(x)).collect()
+    }
+
+    fn load<T: DeserializeOwned>(name: &str) -> T {
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("test-vectors");
+        path.push(name);
+        let file = File::open(&path).expect("couldn't open test vector file");
+        serde_json::from_reader(file).expect("couldn't deserialize test vector file")
+    }
+
+    fn grain_vectors<F: PrimeField>(name: &str) {
+        let vectors: GrainVectors = load(name);
+        let GrainParams {
+            width,
+            rate,
+            full_rounds,
+            partial_rounds,
+            alpha,
+        } = vectors.params;
+        let params =
+            PoseidonParams::<F>::grain(width, rate, full_rounds, partial_rounds, alpha).unwrap();
+
+        let mds: Vec<Vec<F>> = vectors.mds.iter().map(|row| from_hexes(row)).collect();
+        let round_constants: Vec<Vec<F>> = vectors
+            .round_constants
+            .iter()
+            .map(|row| from_hexes(row))
+            .collect();
+        assert_eq!(params.mds, mds);
+        assert_eq!(
+            params.round_constants[..round_constants.len()],
+            round_constants
+        );
+
+        for vector in vectors.permutation_vectors {
+            let mut state = from_hexes::<F>(&vector.input);
+            params.permute(&mut state);
+            assert_eq!(state, from_hexes::<F>(&vector.output));
+        }
+    }
+
+    #[test]
+    fn kimchi_vectors() {
+        let params = PoseidonParams::kimchi::<PallasConfig>();
+        params.validate().unwrap();
+
+        let vectors: HashVectors = load("kimchi-vecs.json");
+        for vector in vectors.test_vectors {
+            let mut sponge = ParamsSponge::new(&params);
+            sponge.absorb(&from_hexes::<Fq>(&vector.input));
+            assert_eq!(sponge.squeeze(), from_hex(&vector.output));
+        }
+    }
+
+    #[test]
+    fn kimchi_matches_poseidon_sponge() {
+        let rng = &mut rand::thread_rng();
+        let params = PoseidonParams::kimchi::<VestaConfig>();
+        for len in 0..7 {
+            let xs: Vec<Fp> = (0..len).map(|_| Fp::rand(rng)).collect();
+            let mut sponge = ParamsSponge::new(&params);
+            let mut expected = PoseidonSponge::<VestaConfig>::new();
+            sponge.absorb(&xs);
+            expected.absorb(&xs);
+            for _ in 0..3 {
+                assert_eq!(sponge.squeeze(), expected.squeeze());
+            }
+            sponge.absorb(&xs);
+            expected.absorb(&xs);
+            assert_eq!(sponge.squeeze(), expected.squeeze());
+        }
+    }
+
+    #[test]
+    fn grain_pallas_vectors() {
+        grain_vectors::<Fq>("grain-pallas-base-x5-3.json");
+    }
+
+    #[test]
+    fn grain_vesta_vectors() {
+        grain_vectors::<Fp>("grain-vesta-base-x5-3.json");
+    }
+
+    #[test]
+    fn grain_layout() {
+        let params = PoseidonParams::<Fq>::grain(5, 4, 8, 60, 5).unwrap();
+        assert_eq!(params.capacity(), 1);
+        assert_eq!(params.round_constants.len(), params.rounds() + 1);
+        assert!(params.round_constants[params.rounds()]
+            .iter()
+            .all(|c| c.is_zero()));
+
+        let mut broken = params.clone();
+        broken.mds.pop();
+        assert!(broken.validate().is_err());
+        assert!(PoseidonParams::<Fq>::grain(3, 3, 8, 56, 5).is_err());
+    }
+}
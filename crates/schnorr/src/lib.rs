@@ -1,23 +1,34 @@
-use halo_group::ark_std::rand::thread_rng;
-use halo_group::Affine;
+use anyhow::{anyhow, ensure, Result};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use halo_group::ark_std::rand::{rngs::StdRng, thread_rng, Rng, SeedableRng};
 use halo_group::{
     ark_ec::{short_weierstrass::Projective, AffineRepr, CurveGroup},
+    ark_ff::Zero,
     ark_std::UniformRand,
     PastaConfig, Scalar,
 };
+use halo_group::{point_dot_affine, Affine, BaseField};
 use halo_poseidon::{Protocols, Sponge};
 
+pub mod musig;
+
+// The sizes of the canonical encodings. A scalar takes 32 bytes, and a compressed point 33, as
+// the 255 bits of x and the 2 flag bits do not fit in 32.
+pub const SECRET_KEY_BYTES: usize = 32;
+pub const PUBLIC_KEY_BYTES: usize = 33;
+pub const SIGNATURE_BYTES: usize = PUBLIC_KEY_BYTES + SECRET_KEY_BYTES;
+
 // Schnorr signature struct: (R, s)
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, CanonicalSerialize, CanonicalDeserialize)]
 pub struct SchnorrSignature<P: PastaConfig> {
     pub r: Affine<P>, // Commitment point R = k * G
     pub s: Scalar<P>, // s = k + e * x
 }
 
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
 pub struct SecretKey<P: PastaConfig>(Scalar<P>);
 
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
 pub struct PublicKey<P: PastaConfig>(pub Affine<P>);
 
 // Hash function for Fiat-Shamir transform: H(P || R || m)
@@ -34,29 +45,83 @@ pub fn hash_message<P: PastaConfig>(
     sponge.challenge()
 }
 
+// Deterministic nonce, in the style of RFC6979: k = H(x || P || m), so signing never depends on
+// the quality of an RNG, and the same key only reuses a nonce for the same message. The hash is
+// reduced from twice the bits of a scalar, as even a one bit bias of k can leak the key.
+fn derive_nonce<P: PastaConfig>(
+    secret_key: Scalar<P>,
+    public_key: Affine<P>,
+    message: &[P::BaseField],
+) -> Scalar<P> {
+    let mut sponge = Sponge::new(Protocols::NONCE);
+    sponge.absorb_fr(&[secret_key]);
+    sponge.absorb_g_affine(&[public_key]);
+    sponge.absorb_fq(message);
+    sponge.challenge_wide()
+}
+
 // Generate key pair: (private_key, public_key)
 pub fn generate_keypair<P: PastaConfig>() -> (SecretKey<P>, PublicKey<P>) {
-    let mut rng = thread_rng();
-    let secret_key = SecretKey(Scalar::<P>::rand(&mut rng));
-    let public_key = PublicKey(Projective::<P>::into_affine(
-        Affine::<P>::generator() * secret_key.0,
-    ));
+    generate_keypair_from_rng(&mut thread_rng())
+}
+
+// Generate key pair with the given RNG
+pub fn generate_keypair_from_rng<P: PastaConfig, R: Rng>(
+    rng: &mut R,
+) -> (SecretKey<P>, PublicKey<P>) {
+    let mut secret_key = Scalar::<P>::rand(rng);
+    while secret_key.is_zero() {
+        secret_key = Scalar::<P>::rand(rng);
+    }
+    let secret_key = SecretKey(secret_key);
+    let public_key = secret_key.public_key();
     (secret_key, public_key)
 }
 
+// Generate the key pair determined by a 32 byte seed
+pub fn generate_keypair_from_seed<P: PastaConfig>(seed: [u8; 32]) -> (SecretKey<P>, PublicKey<P>) {
+    generate_keypair_from_rng(&mut StdRng::from_seed(seed))
+}
+
+// Deserialize a compressed, validated value that takes up exactly `len` bytes
+fn decode<T: CanonicalDeserialize>(bytes: &[u8], len: usize, name: &str) -> Result<T> {
+    ensure!(
+        bytes.len() == len,
+        "A {name} takes {len} bytes, got {}",
+        bytes.len()
+    );
+    T::deserialize_compressed(bytes).map_err(|e| anyhow!("Invalid {name}: {e}"))
+}
+
+// Serialize a compressed value into exactly N bytes
+fn encode<T: CanonicalSerialize, const N: usize>(value: &T) -> [u8; N] {
+    let mut bytes = [0u8; N];
+    value
+        .serialize_compressed(&mut bytes[..])
+        .expect("The encoding has a fixed size");
+    bytes
+}
+
 impl<P: PastaConfig> SecretKey<P> {
+    // The public key x * G belonging to this secret key
+    pub fn public_key(&self) -> PublicKey<P> {
+        PublicKey(Projective::<P>::into_affine(
+            Affine::<P>::generator() * self.0,
+        ))
+    }
+
     // Sign a message with the private key
     pub fn sign(&self, message: &[P::BaseField]) -> SchnorrSignature<P> {
         let sk = self.0;
-        let mut rng = thread_rng();
+        let pk = (Affine::<P>::generator() * sk).into_affine();
 
-        let k = Scalar::<P>::rand(&mut rng);
+        // k = H(x || P || m)
+        let k = derive_nonce::<P>(sk, pk, message);
 
         // R = k * G
         let r = (Affine::<P>::generator() * k).into_affine();
 
         // e = H(P || R || m)
-        let pk = (Affine::<P>::generator() * sk).into_affine();
         let e = hash_message::<P>(pk, r, message);
 
         // s = k + e * x
@@ -64,6 +129,17 @@ impl<P: PastaConfig> SecretKey<P> {
 
         SchnorrSignature { r, s }
     }
+
+    pub fn to_bytes(&self) -> [u8; SECRET_KEY_BYTES] {
+        encode(&self.0)
+    }
+
+    // Rejects non-canonical scalars and zero
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let sk: Scalar<P> = decode(bytes, SECRET_KEY_BYTES, "secret key")?;
+        ensure!(!sk.is_zero(), "The secret key is zero");
+        Ok(Self(sk))
+    }
 }
 
 impl<P: PastaConfig> PublicKey<P> {
@@ -77,13 +153,67 @@ impl<P: PastaConfig> PublicKey<P> {
         // s * G =? R + e * P
         Affine::generator() * signature.s == signature.r + pk * e
     }
+
+    pub fn to_bytes(&self) -> [u8; PUBLIC_KEY_BYTES] {
+        encode(&self.0)
+    }
+
+    // Rejects points that are not on the curve, and the identity
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let pk: Affine<P> = decode(bytes, PUBLIC_KEY_BYTES, "public key")?;
+        ensure!(!pk.is_zero(), "The public key is the identity");
+        Ok(Self(pk))
+    }
+}
+
+impl<P: PastaConfig> SchnorrSignature<P> {
+    // R followed by s
+    pub fn to_bytes(&self) -> [u8; SIGNATURE_BYTES] {
+        encode(self)
+    }
+
+    // Rejects an R that is not on the curve and a non-canonical s
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        decode(bytes, SIGNATURE_BYTES, "signature")
+    }
+}
+
+// A signature to verify in a batch, with its public key and message
+pub type BatchItem<'a, P> = (PublicKey<P>, &'a [BaseField<P>], SchnorrSignature<P>);
+
+// Verify many signatures at once. With random ρᵢ, the equations sᵢ * G = Rᵢ + eᵢ * Pᵢ are checked
+// as (Σ ρᵢ sᵢ) * G - Σ ρᵢ * Rᵢ - Σ ρᵢ eᵢ * Pᵢ = 0 in a single MSM, which only holds for invalid
+// signatures with negligible probability.
+pub fn batch_verify<P: PastaConfig>(signatures: &[BatchItem<P>]) -> bool {
+    let mut rng = thread_rng();
+    let n = signatures.len();
+
+    let mut scalars = Vec::with_capacity(2 * n + 1);
+    let mut points = Vec::with_capacity(2 * n + 1);
+    let mut s = Scalar::<P>::zero();
+    for (i, (pk, message, signature)) in signatures.iter().enumerate() {
+        // The first equation can be left unscaled
+        let rho = match i {
+            0 => Scalar::<P>::from(1u64),
+            _ => Scalar::<P>::rand(&mut rng),
+        };
+        let e = hash_message(pk.0, signature.r, message);
+        s += rho * signature.s;
+        scalars.extend([-rho, -rho * e]);
+        points.extend([signature.r, pk.0]);
+    }
+    scalars.push(s);
+    points.push(Affine::generator());
+
+    point_dot_affine(&scalars, &points).is_zero()
 }
 
 #[cfg(test)]
 mod tests {
     use halo_group::{
+        ark_ff::BigInteger,
         ark_std::{rand::Rng, test_rng},
-        Affine, Fp, Fq, PallasConfig,
+        Affine, Fp, Fq, PallasConfig, VestaConfig,
     };
 
     use super::*;
@@ -159,6 +289,122 @@ mod tests {
         assert!(!other_pk.verify(&message, signature));
     }
 
+    #[test]
+    fn test_deterministic_signing() {
+        let rng = &mut test_rng();
+
+        let (sk, pk) = generate_keypair_from_seed::<PallasConfig>([7; 32]);
+        let (same_sk, same_pk) = generate_keypair_from_seed::<PallasConfig>([7; 32]);
+        let (other_sk, _) = generate_keypair_from_seed::<PallasConfig>([8; 32]);
+        assert_eq!((sk.0, pk.0), (same_sk.0, same_pk.0));
+        assert_ne!(sk.0, other_sk.0);
+
+        let message = [Fq::rand(rng), Fq::rand(rng)];
+        let signature = sk.sign(&message);
+        assert!(pk.verify(&message, signature));
+        assert_eq!(signature.to_bytes(), same_sk.sign(&message).to_bytes());
+
+        // The nonce depends on both the key and the message
+        assert_ne!(signature.r, sk.sign(&[Fq::rand(rng)]).r);
+        assert_ne!(signature.r, other_sk.sign(&message).r);
+    }
+
+    // The nonces of 256 messages all fall in the lower half of the scalar field, or are all even
+    // or odd, only with probability 2^-256 if they are uniform
+    fn assert_nonces_cover_scalars<P: PastaConfig>() {
+        let (sk, pk) = generate_keypair_from_seed::<P>([9; 32]);
+        let half = P::scalar_from_bigint(P::SCALAR_MODULUS.divide_by_2_round_down()).unwrap();
+        let nonces: Vec<_> = (0..256)
+            .map(|i| derive_nonce::<P>(sk.0, pk.0, &[P::basefield_from_u64(i)]))
+            .collect();
+        assert!(nonces.iter().any(|k| *k > half));
+        assert!(nonces.iter().any(|k| P::scalar_into_bigint(*k).is_odd()));
+        assert!(nonces.iter().any(|k| P::scalar_into_bigint(*k).is_even()));
+    }
+
+    #[test]
+    fn test_nonce_range() {
+        assert_nonces_cover_scalars::<PallasConfig>();
+        assert_nonces_cover_scalars::<VestaConfig>();
+    }
+
+    #[test]
+    fn test_encodings() -> Result<()> {
+        let rng = &mut test_rng();
+
+        let (sk, pk) = generate_keypair_from_rng::<PallasConfig, _>(rng);
+        let signature = sk.sign(&[Fq::rand(rng)]);
+
+        assert_eq!(
+            SecretKey::<PallasConfig>::from_bytes(&sk.to_bytes())?.0,
+            sk.0
+        );
+        assert_eq!(
+            PublicKey::<PallasConfig>::from_bytes(&pk.to_bytes())?.0,
+            pk.0
+        );
+        let decoded = SchnorrSignature::<PallasConfig>::from_bytes(&signature.to_bytes())?;
+        assert_eq!((decoded.r, decoded.s), (signature.r, signature.s));
+
+        // Wrong lengths
+        assert!(SecretKey::<PallasConfig>::from_bytes(&sk.to_bytes()[1..]).is_err());
+        assert!(
+            PublicKey::<PallasConfig>::from_bytes(&[&pk.to_bytes()[..], &[0]].concat()).is_err()
+        );
+        assert!(SchnorrSignature::<PallasConfig>::from_bytes(&pk.to_bytes()).is_err());
+
+        // Zero, the identity and values that are not canonical
+        assert!(SecretKey::<PallasConfig>::from_bytes(&[0; 32]).is_err());
+        assert!(SecretKey::<PallasConfig>::from_bytes(&[0xff; 32]).is_err());
+        let identity = PublicKey::<PallasConfig>(Affine::zero()).to_bytes();
+        assert!(PublicKey::<PallasConfig>::from_bytes(&identity).is_err());
+        let mut bytes = signature.to_bytes();
+        bytes[PUBLIC_KEY_BYTES..].fill(0xff);
+        assert!(SchnorrSignature::<PallasConfig>::from_bytes(&bytes).is_err());
+
+        // An x coordinate without a point on the curve
+        let mut x = 0u8;
+        let off_curve = loop {
+            let mut bytes = [0; PUBLIC_KEY_BYTES];
+            bytes[0] = x;
+            if Affine::<PallasConfig>::deserialize_compressed(&bytes[..]).is_err() {
+                break bytes;
+            }
+            x += 1;
+        };
+        assert!(PublicKey::<PallasConfig>::from_bytes(&off_curve).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_batch_verify() {
+        let rng = &mut test_rng();
+
+        let messages: Vec<Vec<Fq>> = (0..8)
+            .map(|i| (0..i).map(|_| Fq::rand(rng)).collect())
+            .collect();
+        let mut batch: Vec<_> = messages
+            .iter()
+            .map(|message| {
+                let (sk, pk) = generate_keypair::<PallasConfig>();
+                (pk, message.as_slice(), sk.sign(message))
+            })
+            .collect();
+        assert!(batch_verify(&batch));
+        assert!(batch_verify::<PallasConfig>(&[]));
+
+        // A single invalid signature makes the batch fail, wherever it is
+        for i in [0, 5] {
+            let mut invalid = batch.clone();
+            invalid[i].2.s += Fp::from(1);
+            assert!(!batch_verify(&invalid));
+        }
+        let wrong_message = [Fq::rand(rng)];
+        batch[3].1 = &wrong_message;
+        assert!(!batch_verify(&batch));
+    }
+
     #[test]
     fn test_empty_message() {
         let (sk, pk) = generate_keypair::<PallasConfig>();
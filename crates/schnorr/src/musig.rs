@@ -0,0 +1,287 @@
+// MuSig2 multi-party signing, where n signers produce one signature that verifies with
+// `PublicKey::verify` under their aggregate key. Each signing session takes two rounds:
+//
+// 1. Every signer generates a `SecretNonce` and sends its `PublicNonce` to the others.
+// 2. Once all public nonces are in, every signer creates the same `SigningSession` from the
+//    `AggregateNonce` and sends its `PartialSignature`, which the session aggregates.
+//
+// The first round does not depend on the message, so it can be run ahead of time.
+
+use anyhow::{ensure, Context, Result};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use halo_group::{
+    ark_ec::{AffineRepr, CurveGroup},
+    ark_std::{rand::thread_rng, UniformRand},
+    point_dot_affine, Affine, PastaConfig, Point, Scalar,
+};
+use halo_poseidon::{Protocols, Sponge};
+
+use crate::{hash_message, PublicKey, SchnorrSignature, SecretKey};
+
+// The aggregate key X̃ = Σ aᵢ * Xᵢ of the keys L = (X₁, ..., Xₙ), with aᵢ = H(L || Xᵢ)
+#[derive(Clone, Debug)]
+pub struct KeyAggContext<P: PastaConfig> {
+    keys: Vec<PublicKey<P>>,
+    coefficients: Vec<Scalar<P>>,
+    aggregate_key: PublicKey<P>,
+}
+
+impl<P: PastaConfig> KeyAggContext<P> {
+    pub fn new(keys: Vec<PublicKey<P>>) -> Result<Self> {
+        ensure!(!keys.is_empty(), "At least one key is needed");
+
+        let mut sponge = Sponge::new(Protocols::KEYAGG);
+        let points: Vec<Affine<P>> = keys.iter().map(|pk| pk.0).collect();
+        sponge.absorb_g_affine(&points);
+        let coefficients: Vec<Scalar<P>> = points
+            .iter()
+            .map(|x| {
+                let mut sponge = sponge.clone();
+                sponge.absorb_g_affine(&[*x]);
+                sponge.challenge()
+            })
+            .collect();
+
+        let aggregate_key = point_dot_affine(&coefficients, &points).into_affine();
+        ensure!(
+            !aggregate_key.is_zero(),
+            "The aggregate key is the identity"
+        );
+
+        Ok(Self {
+            keys,
+            coefficients,
+            aggregate_key: PublicKey(aggregate_key),
+        })
+    }
+
+    pub fn keys(&self) -> &[PublicKey<P>] {
+        &self.keys
+    }
+
+    pub fn aggregate_key(&self) -> &PublicKey<P> {
+        &self.aggregate_key
+    }
+
+    // The coefficient aᵢ of a key in the aggregate key
+    fn coefficient(&self, pk: &PublicKey<P>) -> Option<Scalar<P>> {
+        let i = self.keys.iter().position(|key| key.0 == pk.0)?;
+        Some(self.coefficients[i])
+    }
+}
+
+// The nonces (k₁, k₂) of one signer for one session. They are consumed by `partial_sign`, as a
+// nonce used for two messages reveals the secret key.
+pub struct SecretNonce<P: PastaConfig> {
+    k: [Scalar<P>; 2],
+    public: PublicNonce<P>,
+}
+
+// The commitments (R₁, R₂) = (k₁ * G, k₂ * G) to the nonces of one signer
+#[derive(PartialEq, Eq, Clone, Copy, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct PublicNonce<P: PastaConfig> {
+    pub r1: Affine<P>,
+    pub r2: Affine<P>,
+}
+
+impl<P: PastaConfig> SecretNonce<P> {
+    pub fn generate() -> Self {
+        let mut rng = thread_rng();
+        let k = [Scalar::<P>::rand(&mut rng), Scalar::<P>::rand(&mut rng)];
+        let public = PublicNonce {
+            r1: (Affine::<P>::generator() * k[0]).into_affine(),
+            r2: (Affine::<P>::generator() * k[1]).into_affine(),
+        };
+        Self { k, public }
+    }
+
+    pub fn public_nonce(&self) -> PublicNonce<P> {
+        self.public
+    }
+}
+
+// The sums of the public nonces of all signers
+#[derive(PartialEq, Eq, Clone, Copy, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct AggregateNonce<P: PastaConfig> {
+    pub r1: Affine<P>,
+    pub r2: Affine<P>,
+}
+
+impl<P: PastaConfig> AggregateNonce<P> {
+    pub fn new(nonces: &[PublicNonce<P>]) -> Self {
+        let r1: Point<P> = nonces.iter().map(|nonce| nonce.r1).sum();
+        let r2: Point<P> = nonces.iter().map(|nonce| nonce.r2).sum();
+        Self {
+            r1: r1.into_affine(),
+            r2: r2.into_affine(),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct PartialSignature<P: PastaConfig>(pub Scalar<P>);
+
+// The values every signer derives for signing a message with an aggregate nonce:
+// b = H(X̃ || R₁ || R₂ || m), the nonce R = R₁ + b * R₂ and the challenge e = H(X̃ || R || m)
+#[derive(Clone, Debug)]
+pub struct SigningSession<P: PastaConfig> {
+    b: Scalar<P>,
+    r: Affine<P>,
+    e: Scalar<P>,
+}
+
+impl<P: PastaConfig> SigningSession<P> {
+    pub fn new(
+        ctx: &KeyAggContext<P>,
+        nonce: &AggregateNonce<P>,
+        message: &[P::BaseField],
+    ) -> Self {
+        let x = ctx.aggregate_key.0;
+
+        let mut sponge = Sponge::new(Protocols::MUSIG);
+        sponge.absorb_g_affine(&[x, nonce.r1, nonce.r2]);
+        sponge.absorb_fq(message);
+        let b = sponge.challenge();
+
+        let r = (nonce.r1 + nonce.r2 * b).into_affine();
+        let e = hash_message(x, r, message);
+        Self { b, r, e }
+    }
+
+    // sᵢ = k₁ + b * k₂ + e * aᵢ * xᵢ
+    pub fn partial_sign(
+        &self,
+        ctx: &KeyAggContext<P>,
+        sk: &SecretKey<P>,
+        nonce: SecretNonce<P>,
+    ) -> Result<PartialSignature<P>> {
+        let a = ctx
+            .coefficient(&sk.public_key())
+            .context("The signer is not one of the aggregated keys")?;
+        let [k1, k2] = nonce.k;
+        Ok(PartialSignature(k1 + self.b * k2 + self.e * a * sk.0))
+    }
+
+    // sᵢ * G =? R₁ᵢ + b * R₂ᵢ + e * aᵢ * Xᵢ, which identifies the signer of an invalid
+    // partial signature
+    pub fn verify_partial(
+        &self,
+        ctx: &KeyAggContext<P>,
+        pk: &PublicKey<P>,
+        nonce: &PublicNonce<P>,
+        partial: &PartialSignature<P>,
+    ) -> bool {
+        let Some(a) = ctx.coefficient(pk) else {
+            return false;
+        };
+        Affine::<P>::generator() * partial.0 == nonce.r1 + nonce.r2 * self.b + pk.0 * (self.e * a)
+    }
+
+    // (R, Σ sᵢ), which verifies under the aggregate key if every partial signature does
+    pub fn aggregate(&self, partials: &[PartialSignature<P>]) -> SchnorrSignature<P> {
+        SchnorrSignature {
+            r: self.r,
+            s: partials.iter().map(|partial| partial.0).sum(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo_group::{ark_std::test_rng, Fp, Fq, PallasConfig, VestaConfig};
+
+    use super::*;
+    use crate::generate_keypair;
+
+    // The session, public nonces and partial signatures of both rounds
+    type Rounds<P> = (
+        SigningSession<P>,
+        Vec<PublicNonce<P>>,
+        Vec<PartialSignature<P>>,
+    );
+
+    // Runs both rounds for all signers
+    fn sign<P: PastaConfig>(
+        ctx: &KeyAggContext<P>,
+        sks: &[SecretKey<P>],
+        message: &[P::BaseField],
+    ) -> Result<Rounds<P>> {
+        let nonces: Vec<SecretNonce<P>> = sks.iter().map(|_| SecretNonce::generate()).collect();
+        let public_nonces: Vec<PublicNonce<P>> =
+            nonces.iter().map(SecretNonce::public_nonce).collect();
+
+        let session = SigningSession::new(ctx, &AggregateNonce::new(&public_nonces), message);
+        let partials = sks
+            .iter()
+            .zip(nonces)
+            .map(|(sk, nonce)| session.partial_sign(ctx, sk, nonce))
+            .collect::<Result<_>>()?;
+        Ok((session, public_nonces, partials))
+    }
+
+    #[test]
+    fn test_musig_verifies() -> Result<()> {
+        let rng = &mut test_rng();
+
+        for n in [1, 2, 5] {
+            let (sks, pks): (Vec<_>, Vec<_>) =
+                (0..n).map(|_| generate_keypair::<PallasConfig>()).unzip();
+            let ctx = KeyAggContext::new(pks.clone())?;
+            let message = [Fq::rand(rng), Fq::rand(rng)];
+
+            let (session, public_nonces, partials) = sign(&ctx, &sks, &message)?;
+            for ((pk, nonce), partial) in pks.iter().zip(&public_nonces).zip(&partials) {
+                assert!(session.verify_partial(&ctx, pk, nonce, partial));
+            }
+
+            let signature = session.aggregate(&partials);
+            assert!(ctx.aggregate_key().verify(&message, signature));
+            assert!(!ctx.aggregate_key().verify(&[Fq::rand(rng)], signature));
+        }
+
+        // The other cycle of curves
+        let (sks, pks): (Vec<_>, Vec<_>) =
+            (0..3).map(|_| generate_keypair::<VestaConfig>()).unzip();
+        let ctx = KeyAggContext::new(pks)?;
+        let message = [Fp::rand(rng)];
+        let (session, _, partials) = sign(&ctx, &sks, &message)?;
+        assert!(ctx
+            .aggregate_key()
+            .verify(&message, session.aggregate(&partials)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_musig_invalid_partials() -> Result<()> {
+        let rng = &mut test_rng();
+
+        let (sks, pks): (Vec<_>, Vec<_>) =
+            (0..3).map(|_| generate_keypair::<PallasConfig>()).unzip();
+        let ctx = KeyAggContext::new(pks.clone())?;
+        let message = [Fq::rand(rng)];
+        let (session, public_nonces, mut partials) = sign(&ctx, &sks, &message)?;
+
+        // A tampered partial signature is caught, and so is the aggregate
+        partials[1].0 += Fp::from(1);
+        assert!(session.verify_partial(&ctx, &pks[0], &public_nonces[0], &partials[0]));
+        assert!(!session.verify_partial(&ctx, &pks[1], &public_nonces[1], &partials[1]));
+        assert!(!ctx
+            .aggregate_key()
+            .verify(&message, session.aggregate(&partials)));
+
+        // Keys outside of the aggregate can neither sign nor be verified against
+        let (outsider_sk, outsider_pk) = generate_keypair::<PallasConfig>();
+        assert!(session
+            .partial_sign(&ctx, &outsider_sk, SecretNonce::generate())
+            .is_err());
+        assert!(!session.verify_partial(&ctx, &outsider_pk, &public_nonces[0], &partials[0]));
+
+        // The aggregate key depends on the order of the keys
+        let reversed = KeyAggContext::new(pks.iter().rev().cloned().collect())?;
+        assert_ne!(reversed.aggregate_key().0, ctx.aggregate_key().0);
+        assert!(KeyAggContext::<PallasConfig>::new(vec![]).is_err());
+
+        Ok(())
+    }
+}